@@ -0,0 +1,91 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Human-readable previews of EIP-1559 transactions, shown to the user before signing.
+use ethers_core::{
+    types::{Eip1559TransactionRequest, NameOrAddress, U256},
+    utils::{format_units, to_checksum},
+};
+use serde::Serialize;
+use wallet_signer::calldata::{decode, Decoded};
+
+/// The preview of a transaction
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Preview {
+    pub to: Option<String>,
+    pub value: String,
+    pub chain_id: Option<u64>,
+    pub nonce: Option<String>,
+    pub gas: Option<String>,
+    pub max_fee_per_gas: Option<String>,
+    pub max_priority_fee_per_gas: Option<String>,
+    pub max_fee: Option<String>,
+    pub call: Decoded,
+}
+
+/// Formats an amount of wei in `units`.
+fn format(amount: U256, units: &str) -> String {
+    format_units(amount, units).unwrap_or_else(|_| amount.to_string())
+}
+
+/// Builds the preview of a transaction.
+pub fn preview(tx: &Eip1559TransactionRequest) -> Preview {
+    let max_fee = match (tx.gas, tx.max_fee_per_gas) {
+        (Some(gas), Some(fee)) => gas.checked_mul(fee).map(|f| format(f, "ether")),
+        _ => None,
+    };
+
+    Preview {
+        to: tx.to.as_ref().map(|to| match to {
+            NameOrAddress::Address(a) => to_checksum(a, None),
+            NameOrAddress::Name(n) => n.clone(),
+        }),
+        value: format(tx.value.unwrap_or_default(), "ether"),
+        chain_id: tx.chain_id.map(|id| id.as_u64()),
+        nonce: tx.nonce.map(|n| n.to_string()),
+        gas: tx.gas.map(|g| g.to_string()),
+        max_fee_per_gas: tx.max_fee_per_gas.map(|f| format(f, "gwei")),
+        max_priority_fee_per_gas: tx.max_priority_fee_per_gas.map(|f| format(f, "gwei")),
+        max_fee,
+        call: decode(tx.data.as_ref().map(|d| d.as_ref()).unwrap_or_default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_fees() {
+        let tx = Eip1559TransactionRequest::new()
+            .value(U256::exp10(18))
+            .gas(21_000u64)
+            .max_fee_per_gas(U256::from(30_000_000_000u64))
+            .max_priority_fee_per_gas(U256::from(1_000_000_000u64))
+            .chain_id(1u64);
+        let preview = preview(&tx);
+
+        assert_eq!(preview.value, "1.000000000000000000");
+        assert_eq!(preview.max_fee_per_gas.as_deref(), Some("30.000000000"));
+        assert_eq!(preview.max_fee.as_deref(), Some("0.000630000000000000"));
+        assert_eq!(preview.call, Decoded::Empty);
+    }
+
+    #[test]
+    fn test_preview_call() {
+        let tx = Eip1559TransactionRequest::new().data(
+            ethers_core::utils::hex::decode(
+                "a9059cbb00000000000000000000000070997970c51812dc3a010c7d01b50e0d17dc79c8\
+                 00000000000000000000000000000000000000000000000000000000000f4240",
+            )
+            .unwrap(),
+        );
+        let Decoded::Call(call) = preview(&tx).call else { panic!("not decoded") };
+        assert_eq!(
+            call.to_string(),
+            "transfer(to: 0x70997970C51812dc3A010C7d01b50e0d17dc79C8, amount: 1000000)"
+        );
+    }
+}
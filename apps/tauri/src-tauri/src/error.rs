@@ -0,0 +1,42 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use serde::{Serialize, Serializer};
+
+/// Errors returned by the commands to the webview
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{0}")]
+    MetaMask(String),
+    #[error("Vault {0} not found, list the vaults first")]
+    VaultNotFound(usize),
+    #[error("Failed to decrypt the vault, check the password")]
+    Decrypt,
+    #[error("Failed to derive accounts: {0}")]
+    Derive(String),
+    #[error("The session is locked")]
+    Locked,
+    #[error("Invalid idle timeout of {secs} seconds, expected between {min} and {max}")]
+    IdleTimeout { secs: u64, min: u64, max: u64 },
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+    #[error("Transaction {0} not found")]
+    TransactionNotFound(u64),
+    #[error("Failed to sign the transaction: {0}")]
+    Sign(String),
+    #[error("Failed to sign in: {0}")]
+    SignIn(String),
+}
+
+/// Serialize the error as its message, so that the webview receives a plain string.
+///
+/// From:
+/// https://tauri.app/v1/guides/features/command#error-handling
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.to_string().as_ref())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -1,6 +1,17 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod decode;
+mod error;
+mod metamask;
+mod session;
+mod siwe;
+mod transaction;
+
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -9,7 +20,29 @@ fn greet(name: &str) -> String {
 
 fn main() {
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![greet])
+        .manage(metamask::MetaMaskState::default())
+        .manage(session::SessionState::default())
+        .manage(transaction::TransactionState::default())
+        .setup(|app| {
+            session::spawn_auto_lock(app.handle());
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            metamask::locate_metamask,
+            metamask::list_vaults,
+            metamask::unlock_vault,
+            session::session_status,
+            session::session_accounts,
+            session::touch_session,
+            session::lock_session,
+            session::set_idle_timeout,
+            siwe::sign_in,
+            transaction::request_transaction,
+            transaction::pending_transactions,
+            transaction::approve_transaction,
+            transaction::reject_transaction
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
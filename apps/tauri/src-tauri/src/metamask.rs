@@ -0,0 +1,135 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Commands importing and unlocking a local MetaMask vault.
+///
+/// The webview only ever receives paths and addresses, the vaults stay in the managed
+/// [MetaMaskState] and the decrypted mnemonic in the [SessionState].
+use crate::{
+    error::{Error, Result},
+    session::{Account, Secret, SessionState},
+};
+use serde::Serialize;
+use std::{str, sync::Mutex};
+use tauri::{AppHandle, State};
+use wallet_metamask::{
+    mmap::Mmap,
+    source::MetaMaskSource,
+    types::{StringOrBytes, Vault},
+};
+use zeroize::Zeroizing;
+
+/// A vault found in a local MetaMask install
+#[derive(Clone, Debug, Serialize)]
+pub struct VaultInfo {
+    pub id: usize,
+    pub path: String,
+}
+
+/// A store of a local MetaMask install the vaults could not be extracted from
+#[derive(Clone, Debug, Serialize)]
+pub struct StoreError {
+    pub path: String,
+    pub error: String,
+}
+
+/// The vaults of the local MetaMask installs, and the stores that could not be read
+#[derive(Clone, Debug, Serialize)]
+pub struct VaultList {
+    pub vaults: Vec<VaultInfo>,
+    pub errors: Vec<StoreError>,
+}
+
+/// The accounts of an unlocked vault, and the keyrings of the vault it skipped
+#[derive(Clone, Debug, Serialize)]
+pub struct UnlockedVault {
+    pub accounts: Vec<Account>,
+    /// The types of the keyrings besides the first HD keyring, e.g. `Simple Key Pair`, whose
+    /// accounts are not in the session
+    pub skipped: Vec<String>,
+}
+
+/// The sources of the vaults, and the vaults found on the machine with the index of their source
+pub struct MetaMaskState {
+    sources: Vec<MetaMaskSource>,
+    vaults: Mutex<Vec<(usize, Vault)>>,
+}
+
+impl Default for MetaMaskState {
+    fn default() -> Self {
+        Self { sources: MetaMaskSource::all(), vaults: Mutex::default() }
+    }
+}
+
+/// Returns the paths of the MetaMask extension stores on the machine.
+#[tauri::command]
+pub fn locate_metamask(state: State<'_, MetaMaskState>) -> Result<Vec<String>> {
+    let mut paths = vec![];
+    for source in state.sources.iter() {
+        let files = source.locate().map_err(|e| Error::MetaMask(e.to_string()))?;
+        paths.extend(files.iter().map(|path| path.display().to_string()));
+    }
+    Ok(paths)
+}
+
+/// Extracts the vaults of the local MetaMask installs, and keeps them for [unlock_vault].
+///
+/// Most stores of an install hold no vault, so a store that fails does not fail the listing:
+/// its error is returned next to the vaults of the other stores.
+#[tauri::command]
+pub fn list_vaults(state: State<'_, MetaMaskState>) -> Result<VaultList> {
+    let mut vaults = state.vaults.lock().unwrap();
+    vaults.clear();
+
+    let mut list = VaultList { vaults: vec![], errors: vec![] };
+    for (index, source) in state.sources.iter().enumerate() {
+        for path in source.locate().map_err(|e| Error::MetaMask(e.to_string()))? {
+            let found = Mmap::open(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|data| source.extract(&data).map_err(|e| e.to_string()));
+            let path = path.display().to_string();
+            match found {
+                Ok(found) => {
+                    for vault in found {
+                        list.vaults.push(VaultInfo { id: vaults.len(), path: path.clone() });
+                        vaults.push((index, vault));
+                    }
+                }
+                Err(error) => list.errors.push(StoreError { path, error }),
+            }
+        }
+    }
+
+    Ok(list)
+}
+
+/// Decrypts the first HD keyring of a listed vault into the session, and returns the addresses of
+/// its accounts with the other keyrings of the vault, which are skipped.
+#[tauri::command]
+pub fn unlock_vault(
+    app: AppHandle,
+    state: State<'_, MetaMaskState>,
+    session: State<'_, SessionState>,
+    id: usize,
+    password: String,
+) -> Result<UnlockedVault> {
+    let password = Zeroizing::new(password);
+    let (index, vault) =
+        state.vaults.lock().unwrap().get(id).cloned().ok_or(Error::VaultNotFound(id))?;
+    let decrypted = state.sources[index].decrypt(&vault, &password).map_err(|_| Error::Decrypt)?;
+
+    // Move the mnemonic into the session, where it is zeroized once locked
+    let phrase = match decrypted.data.mnemonic {
+        StringOrBytes::String(s) => Zeroizing::new(s),
+        StringOrBytes::Bytes(b) => {
+            let b = Zeroizing::new(b);
+            Zeroizing::new(str::from_utf8(&b).map_err(|_| Error::Decrypt)?.to_string())
+        }
+    };
+    let secret = Secret::new(phrase, decrypted.data.hd_path, decrypted.data.number_of_accounts);
+    let accounts = secret.accounts()?;
+    session.unlock(&app, secret);
+
+    Ok(UnlockedVault { accounts, skipped: decrypted.skipped })
+}
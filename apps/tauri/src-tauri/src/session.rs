@@ -0,0 +1,354 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// The unlocked wallet session.
+///
+/// The session holds the key material of the unlocked vault until it is locked, either by the
+/// user, after [SessionState::idle_timeout] without activity, or by unlocking another vault.
+/// Locking drops the key material, which zeroizes it, discards the transactions pending
+/// approval, and emits [LOCKED_EVENT] to the webview.
+use crate::{
+    error::{Error, Result},
+    transaction::TransactionState,
+};
+use ethers_core::{k256::ecdsa::SigningKey, utils::to_checksum};
+use ethers_signers::{
+    coins_bip39::{English, Mnemonic},
+    Signer, Wallet,
+};
+use serde::Serialize;
+use std::{
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+use tauri::{AppHandle, Manager, State};
+use zeroize::Zeroizing;
+
+/// The event emitted to the webview when the session is locked
+pub const LOCKED_EVENT: &str = "session-locked";
+
+/// The default MetaMask HD path, used when the vault does not specify one
+const DEFAULT_HD_PATH: &str = "m/44'/60'/0'/0";
+
+/// The idle timeout used until the webview sets one
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// The shortest idle timeout, longer than the interval it is checked at
+pub const MIN_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The longest idle timeout, so that the session always locks eventually
+pub const MAX_IDLE_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often the idle timeout is checked
+const AUTO_LOCK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An account of the unlocked vault
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Account {
+    pub index: u32,
+    pub address: String,
+}
+
+/// Why the session was locked
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LockReason {
+    User,
+    Idle,
+    /// Another vault was unlocked in its place
+    Replaced,
+}
+
+/// The payload of [LOCKED_EVENT]
+#[derive(Clone, Debug, Serialize)]
+pub struct Locked {
+    pub reason: LockReason,
+}
+
+/// The state of the session, as seen by the webview
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStatus {
+    pub unlocked: bool,
+    pub idle_timeout_secs: u64,
+}
+
+/// The key material of an unlocked vault, zeroized on drop
+pub struct Secret {
+    phrase: Zeroizing<String>,
+    hd_path: String,
+    number_of_accounts: u32,
+}
+
+impl Secret {
+    pub fn new(
+        phrase: Zeroizing<String>,
+        hd_path: Option<String>,
+        number_of_accounts: Option<u32>,
+    ) -> Self {
+        Secret {
+            phrase,
+            hd_path: hd_path.unwrap_or_else(|| DEFAULT_HD_PATH.to_string()),
+            number_of_accounts: number_of_accounts.unwrap_or(1),
+        }
+    }
+
+    /// Builds the wallet of the account at `index`.
+    ///
+    /// The key is derived from the mnemonic directly rather than with a `MnemonicBuilder`,
+    /// which would keep a copy of the phrase that is not zeroized.
+    pub fn wallet(&self, index: u32) -> Result<Wallet<SigningKey>> {
+        let path = format!("{}/{}", self.hd_path, index);
+        let key = Mnemonic::<English>::new_from_phrase(&self.phrase)
+            .and_then(|mnemonic| mnemonic.derive_key(path.as_str(), None))
+            .map_err(|e| Error::Derive(e.to_string()))?;
+        let key: &SigningKey = key.as_ref();
+        Ok(Wallet::from(key.clone()))
+    }
+
+    /// Derives the accounts, following the HD path and number of accounts of the vault.
+    pub fn accounts(&self) -> Result<Vec<Account>> {
+        (0..self.number_of_accounts)
+            .map(|index| {
+                let wallet = self.wallet(index)?;
+                Ok(Account { index, address: to_checksum(&wallet.address(), None) })
+            })
+            .collect()
+    }
+}
+
+/// The unlocked key material and the time of the last activity
+pub struct Session {
+    secret: Option<Secret>,
+    last_activity: Instant,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session { secret: None, last_activity: Instant::now() }
+    }
+}
+
+impl Session {
+    /// Replaces the key material, returns whether it locked a previous one.
+    pub fn unlock(&mut self, secret: Secret) -> bool {
+        self.touch();
+        self.secret.replace(secret).is_some()
+    }
+
+    /// Drops the key material, returns whether the session was unlocked.
+    pub fn lock(&mut self) -> bool {
+        self.secret.take().is_some()
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.secret.is_some()
+    }
+
+    /// Records an activity, postponing the idle lock.
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    /// Returns whether the session is unlocked and has been idle for longer than `timeout`.
+    pub fn is_idle(&self, timeout: Duration) -> bool {
+        self.is_unlocked() && self.last_activity.elapsed() >= timeout
+    }
+
+    /// Returns the key material, recording an activity.
+    pub fn secret(&mut self) -> Result<&Secret> {
+        self.touch();
+        self.secret.as_ref().ok_or(Error::Locked)
+    }
+}
+
+/// The session managed by the app
+pub struct SessionState {
+    session: Mutex<Session>,
+    idle_timeout: Mutex<Duration>,
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        SessionState {
+            session: Mutex::new(Session::default()),
+            idle_timeout: Mutex::new(DEFAULT_IDLE_TIMEOUT),
+        }
+    }
+}
+
+impl SessionState {
+    /// Runs `f` with the session.
+    pub fn with<T>(&self, f: impl FnOnce(&mut Session) -> T) -> T {
+        f(&mut self.session.lock().unwrap())
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        *self.idle_timeout.lock().unwrap()
+    }
+
+    /// Sets the idle timeout, between [MIN_IDLE_TIMEOUT] and [MAX_IDLE_TIMEOUT].
+    pub fn set_idle_timeout(&self, timeout: Duration) -> Result<()> {
+        if !(MIN_IDLE_TIMEOUT..=MAX_IDLE_TIMEOUT).contains(&timeout) {
+            return Err(Error::IdleTimeout {
+                secs: timeout.as_secs(),
+                min: MIN_IDLE_TIMEOUT.as_secs(),
+                max: MAX_IDLE_TIMEOUT.as_secs(),
+            });
+        }
+        *self.idle_timeout.lock().unwrap() = timeout;
+        Ok(())
+    }
+
+    /// Locks the session if it has been idle for too long, returns whether it was locked.
+    pub fn lock_if_idle(&self) -> bool {
+        let timeout = self.idle_timeout();
+        self.with(|session| session.is_idle(timeout) && session.lock())
+    }
+
+    /// Unlocks the session with the key material of a vault, locking the previous vault as the
+    /// lock commands do, so that its pending transactions are not signed with the new keys.
+    pub fn unlock(&self, app: &AppHandle, secret: Secret) {
+        if self.with(|session| session.unlock(secret)) {
+            emit_locked(app, LockReason::Replaced);
+        }
+    }
+
+    fn status(&self) -> SessionStatus {
+        SessionStatus {
+            unlocked: self.with(|session| session.is_unlocked()),
+            idle_timeout_secs: self.idle_timeout().as_secs(),
+        }
+    }
+}
+
+/// Discards the transactions pending the locked session, and emits [LOCKED_EVENT] to every
+/// window.
+fn emit_locked(app: &AppHandle, reason: LockReason) {
+    app.state::<TransactionState>().clear();
+    let _ = app.emit_all(LOCKED_EVENT, Locked { reason });
+}
+
+/// Spawns the thread locking the session once it is idle.
+pub fn spawn_auto_lock(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(AUTO_LOCK_INTERVAL);
+        if app.state::<SessionState>().lock_if_idle() {
+            emit_locked(&app, LockReason::Idle);
+        }
+    });
+}
+
+/// Returns whether the session is unlocked, and its idle timeout.
+#[tauri::command]
+pub fn session_status(state: State<'_, SessionState>) -> SessionStatus {
+    state.status()
+}
+
+/// Records an activity of the user, postponing the idle lock.
+#[tauri::command]
+pub fn touch_session(state: State<'_, SessionState>) {
+    state.with(|session| session.touch());
+}
+
+/// Locks the session, zeroizing the key material.
+#[tauri::command]
+pub fn lock_session(app: AppHandle, state: State<'_, SessionState>) {
+    if state.with(|session| session.lock()) {
+        emit_locked(&app, LockReason::User);
+    }
+}
+
+/// Sets the idle timeout of the session, in seconds between [MIN_IDLE_TIMEOUT] and
+/// [MAX_IDLE_TIMEOUT].
+#[tauri::command]
+pub fn set_idle_timeout(state: State<'_, SessionState>, secs: u64) -> Result<SessionStatus> {
+    state.set_idle_timeout(Duration::from_secs(secs))?;
+    Ok(state.status())
+}
+
+/// Returns the addresses of the unlocked vault.
+#[tauri::command]
+pub fn session_accounts(state: State<'_, SessionState>) -> Result<Vec<Account>> {
+    state.with(|session| session.secret()?.accounts())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn test_secret_accounts() {
+        let secret = Secret::new(Zeroizing::new(PHRASE.to_string()), None, Some(2));
+        assert_eq!(
+            secret.accounts().unwrap(),
+            vec![
+                Account {
+                    index: 0,
+                    address: "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".to_string()
+                },
+                Account {
+                    index: 1,
+                    address: "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_session_lock() {
+        let mut session = Session::default();
+        assert!(!session.is_unlocked());
+        assert!(session.secret().is_err());
+
+        assert!(!session.unlock(Secret::new(Zeroizing::new(PHRASE.to_string()), None, None)));
+        assert!(session.is_unlocked());
+        assert!(session.secret().is_ok());
+
+        // Unlocking another vault locks the previous one
+        assert!(session.unlock(Secret::new(Zeroizing::new(PHRASE.to_string()), None, None)));
+
+        assert!(session.lock());
+        assert!(!session.lock());
+        assert!(session.secret().is_err());
+    }
+
+    #[test]
+    fn test_session_idle() {
+        let state = SessionState::default();
+        *state.idle_timeout.lock().unwrap() = Duration::from_millis(10);
+
+        // A locked session is never idle
+        assert!(!state.lock_if_idle());
+
+        state.with(|session| {
+            session.unlock(Secret::new(Zeroizing::new(PHRASE.to_string()), None, None))
+        });
+        assert!(!state.lock_if_idle());
+
+        thread::sleep(Duration::from_millis(20));
+        assert!(state.lock_if_idle());
+        assert!(!state.status().unlocked);
+    }
+
+    #[test]
+    fn test_set_idle_timeout() {
+        let state = SessionState::default();
+        for secs in [0, 9, MAX_IDLE_TIMEOUT.as_secs() + 1, u64::MAX] {
+            assert!(matches!(
+                state.set_idle_timeout(Duration::from_secs(secs)),
+                Err(Error::IdleTimeout { .. })
+            ));
+        }
+        assert_eq!(state.idle_timeout(), DEFAULT_IDLE_TIMEOUT);
+
+        for timeout in [MIN_IDLE_TIMEOUT, MAX_IDLE_TIMEOUT] {
+            assert!(state.set_idle_timeout(timeout).is_ok());
+            assert_eq!(state.status().idle_timeout_secs, timeout.as_secs());
+        }
+    }
+}
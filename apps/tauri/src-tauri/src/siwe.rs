@@ -0,0 +1,82 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Commands for Sign-In with Ethereum.
+///
+/// A message is signed only for the origin that requests it, within its validity window, and
+/// with the session account it names.
+use crate::{
+    error::{Error, Result},
+    session::SessionState,
+};
+use ethers_core::utils::hex;
+use ethers_signers::Signer;
+use tauri::State;
+use wallet_signer::siwe::{Message, VerifyOptions};
+
+/// Checks that a message is for the origin that requests it, e.g. `https://example.com`.
+fn check_origin(message: &Message, origin: &str) -> Result<()> {
+    let (scheme, authority) = origin
+        .split_once("://")
+        .ok_or_else(|| Error::SignIn(format!("invalid origin {}", origin)))?;
+    // The messages without a scheme are for HTTPS, as in EIP-4361
+    if message.scheme.as_deref().unwrap_or("https") != scheme {
+        return Err(Error::SignIn(format!("the message is not for {}", origin)));
+    }
+    let options = VerifyOptions { domain: Some(authority.to_string()), ..Default::default() };
+    message.validate(&options).map_err(|e| Error::SignIn(e.to_string()))
+}
+
+/// Signs a Sign-In with Ethereum message requested by an origin with a session account.
+#[tauri::command]
+pub fn sign_in(
+    session: State<'_, SessionState>,
+    account: u32,
+    message: String,
+    origin: String,
+) -> Result<String> {
+    let message: Message = message.parse().map_err(|e| Error::SignIn(format!("{}", e)))?;
+    check_origin(&message, &origin)?;
+
+    let wallet = session.with(|session| session.secret()?.wallet(account))?;
+    if wallet.address() != message.address {
+        return Err(Error::SignIn("the message is for another account".to_string()));
+    }
+    let signature = message.sign(wallet.signer()).map_err(|e| Error::Sign(e.to_string()))?;
+    Ok(format!("0x{}", hex::encode(signature)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "https://example.com wants you to sign in with your Ethereum account:
+0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2
+
+
+URI: https://example.com/login
+Version: 1
+Chain ID: 1
+Nonce: 32891756
+Issued At: 2021-09-30T16:25:24Z";
+
+    #[test]
+    fn test_check_origin() {
+        let message: Message = MESSAGE.parse().unwrap();
+        assert!(check_origin(&message, "https://example.com").is_ok());
+        assert!(check_origin(&message, "http://example.com").is_err());
+        assert!(check_origin(&message, "https://evil.example").is_err());
+        assert!(check_origin(&message, "example.com").is_err());
+
+        // Without a scheme, the message is only for HTTPS
+        let message: Message = MESSAGE.strip_prefix("https://").unwrap().parse().unwrap();
+        assert!(check_origin(&message, "https://example.com").is_ok());
+        assert!(check_origin(&message, "http://example.com").is_err());
+
+        // Expired, though from the right origin
+        let expired = format!("{}\nExpiration Time: 2021-09-30T17:25:24Z", MESSAGE);
+        let message: Message = expired.parse().unwrap();
+        assert!(check_origin(&message, "https://example.com").is_err());
+    }
+}
@@ -0,0 +1,265 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Commands for the transaction approval flow.
+///
+/// A transaction request is decoded, bound to the address of its account in the unlocked
+/// vault, and kept pending until the user approves it, at which point it is signed with the
+/// session key of that address only, or rejects it. Every new request is announced to the
+/// webview with [REQUESTED_EVENT].
+use crate::{
+    decode::{preview, Preview},
+    error::{Error, Result},
+    session::SessionState,
+};
+use ethers_core::{
+    types::{transaction::eip2718::TypedTransaction, Address, Eip1559TransactionRequest},
+    utils::{keccak256, to_checksum},
+};
+use ethers_signers::Signer;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Mutex};
+use tauri::{AppHandle, Manager, State};
+
+/// The event emitted to the webview when a transaction awaits approval
+pub const REQUESTED_EVENT: &str = "transaction-requested";
+
+/// An unsigned transaction, and the session account signing it
+#[derive(Clone, Debug, Deserialize)]
+pub struct TransactionRequest {
+    pub account: u32,
+    #[serde(flatten)]
+    pub tx: Eip1559TransactionRequest,
+}
+
+/// A transaction request, and the address of its account when it was requested
+#[derive(Clone, Debug)]
+struct Bound {
+    request: TransactionRequest,
+    address: Address,
+}
+
+/// A transaction awaiting approval
+#[derive(Clone, Debug, Serialize)]
+pub struct Pending {
+    pub id: u64,
+    pub account: u32,
+    /// The address signing the transaction
+    pub address: String,
+    pub preview: Preview,
+}
+
+impl Pending {
+    fn new(id: u64, bound: &Bound) -> Self {
+        Pending {
+            id,
+            account: bound.request.account,
+            address: to_checksum(&bound.address, None),
+            preview: preview(&bound.request.tx),
+        }
+    }
+}
+
+/// A signed transaction, ready to be broadcast
+#[derive(Clone, Debug, Serialize)]
+pub struct Signed {
+    pub raw: String,
+    pub hash: String,
+}
+
+/// The transactions awaiting approval
+#[derive(Default)]
+pub struct TransactionState {
+    next_id: Mutex<u64>,
+    pending: Mutex<BTreeMap<u64, Bound>>,
+}
+
+impl TransactionState {
+    /// Binds a transaction request to the address of its account in the unlocked vault, and
+    /// keeps it pending.
+    pub fn request(&self, session: &SessionState, request: TransactionRequest) -> Result<Pending> {
+        check_signable(&request.tx)?;
+        let address = session.with(|session| session.secret()?.wallet(request.account))?.address();
+        match request.tx.from {
+            Some(from) if from != address => {
+                return Err(Error::InvalidTransaction(format!(
+                    "from {} is not the address of account {}",
+                    to_checksum(&from, None),
+                    request.account
+                )))
+            }
+            _ => {}
+        }
+
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id
+        };
+        let bound = Bound { request, address };
+        let pending = Pending::new(id, &bound);
+        self.pending.lock().unwrap().insert(id, bound);
+        Ok(pending)
+    }
+
+    /// Signs a pending transaction with the key of the address it was requested for. It stays
+    /// pending until it is signed, e.g. while the session is locked.
+    pub fn approve(&self, session: &SessionState, id: u64) -> Result<Signed> {
+        let Bound { request, address } =
+            self.pending.lock().unwrap().get(&id).cloned().ok_or(Error::TransactionNotFound(id))?;
+
+        let wallet = session.with(|session| session.secret()?.wallet(request.account))?;
+        if wallet.address() != address {
+            return Err(Error::InvalidTransaction(format!(
+                "account {} of the unlocked vault is not {}",
+                request.account,
+                to_checksum(&address, None)
+            )));
+        }
+        let tx: TypedTransaction = request.tx.from(address).into();
+        let signature =
+            wallet.sign_transaction_sync(&tx).map_err(|e| Error::Sign(e.to_string()))?;
+        self.pending.lock().unwrap().remove(&id);
+
+        let raw = tx.rlp_signed(&signature);
+        Ok(Signed {
+            raw: format!("0x{}", ethers_core::utils::hex::encode(&raw)),
+            hash: format!("0x{}", ethers_core::utils::hex::encode(keccak256(&raw))),
+        })
+    }
+
+    /// Discards every pending transaction, once the session that would sign them is locked.
+    pub fn clear(&self) {
+        self.pending.lock().unwrap().clear();
+    }
+}
+
+/// Checks that a transaction has every field needed to sign it offline.
+fn check_signable(tx: &Eip1559TransactionRequest) -> Result<()> {
+    let missing = [
+        ("chainId", tx.chain_id.is_none()),
+        ("nonce", tx.nonce.is_none()),
+        ("gas", tx.gas.is_none()),
+        ("maxFeePerGas", tx.max_fee_per_gas.is_none()),
+        ("maxPriorityFeePerGas", tx.max_priority_fee_per_gas.is_none()),
+    ];
+    match missing.iter().find(|(_, missing)| *missing) {
+        Some((field, _)) => Err(Error::InvalidTransaction(format!("missing {}", field))),
+        None => Ok(()),
+    }
+}
+
+/// Decodes a transaction request of the unlocked vault and keeps it pending until approved or
+/// rejected.
+#[tauri::command]
+pub fn request_transaction(
+    app: AppHandle,
+    state: State<'_, TransactionState>,
+    session: State<'_, SessionState>,
+    request: TransactionRequest,
+) -> Result<Pending> {
+    let pending = state.request(&session, request)?;
+    let _ = app.emit_all(REQUESTED_EVENT, pending.clone());
+    Ok(pending)
+}
+
+/// Returns the transactions awaiting approval.
+#[tauri::command]
+pub fn pending_transactions(state: State<'_, TransactionState>) -> Vec<Pending> {
+    state.pending.lock().unwrap().iter().map(|(id, bound)| Pending::new(*id, bound)).collect()
+}
+
+/// Signs an approved transaction with the session key.
+#[tauri::command]
+pub fn approve_transaction(
+    state: State<'_, TransactionState>,
+    session: State<'_, SessionState>,
+    id: u64,
+) -> Result<Signed> {
+    state.approve(&session, id)
+}
+
+/// Discards a transaction awaiting approval.
+#[tauri::command]
+pub fn reject_transaction(state: State<'_, TransactionState>, id: u64) -> Result<()> {
+    state.pending.lock().unwrap().remove(&id).map(|_| ()).ok_or(Error::TransactionNotFound(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Secret;
+    use zeroize::Zeroizing;
+
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn test_request_deserialize() {
+        let request: TransactionRequest = serde_json::from_str(
+            r#"{
+                "account": 1,
+                "to": "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+                "value": "0xde0b6b3a7640000",
+                "nonce": "0x0",
+                "gas": "0x5208",
+                "maxFeePerGas": "0x6fc23ac00",
+                "maxPriorityFeePerGas": "0x3b9aca00",
+                "chainId": "0x1"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(request.account, 1);
+        assert!(check_signable(&request.tx).is_ok());
+        assert!(check_signable(&Eip1559TransactionRequest::new()).is_err());
+    }
+
+    #[test]
+    fn test_approve() {
+        let state = TransactionState::default();
+        let session = SessionState::default();
+        let tx = Eip1559TransactionRequest::new()
+            .to(Address::zero())
+            .chain_id(1)
+            .nonce(0)
+            .gas(21000)
+            .max_fee_per_gas(1)
+            .max_priority_fee_per_gas(1);
+        let request = |account: u32, tx: Eip1559TransactionRequest| {
+            state.request(&session, TransactionRequest { account, tx })
+        };
+        let unlock = |phrase: &str| {
+            session.with(|session| {
+                session.unlock(Secret::new(Zeroizing::new(phrase.to_string()), None, Some(2)))
+            })
+        };
+
+        // Requested for an account of the unlocked vault only
+        assert!(matches!(request(0, tx.clone()), Err(Error::Locked)));
+        unlock(PHRASE);
+        let other: Address = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8".parse().unwrap();
+        assert!(matches!(request(0, tx.clone().from(other)), Err(Error::InvalidTransaction(_))));
+        let pending = request(1, tx.clone().from(other)).unwrap();
+        assert_eq!(pending.address, "0x70997970C51812dc3A010C7d01b50e0d17dc79C8");
+        assert!(state.approve(&session, pending.id).is_ok());
+        assert!(matches!(state.approve(&session, pending.id), Err(Error::TransactionNotFound(_))));
+
+        // Kept pending while the session is locked
+        let pending = request(0, tx.clone()).unwrap();
+        session.with(|session| session.lock());
+        assert!(matches!(state.approve(&session, pending.id), Err(Error::Locked)));
+        assert!(state.pending.lock().unwrap().contains_key(&pending.id));
+
+        // Not signed with the same account of another vault
+        unlock("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about");
+        assert!(matches!(state.approve(&session, pending.id), Err(Error::InvalidTransaction(_))));
+        assert!(state.pending.lock().unwrap().contains_key(&pending.id));
+        unlock(PHRASE);
+        assert!(state.approve(&session, pending.id).is_ok());
+
+        request(0, tx).unwrap();
+        state.clear();
+        assert!(state.pending.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,133 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use clap::{Args, Parser, Subcommand};
+use ethers_core::{types::H256, utils::hex};
+use std::thread;
+use tracing::info;
+use wallet_signer::address::{checksum, validate, vanity_key, vanity_salt, Pattern, Progress};
+
+/// Start the address command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[command(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Print the checksummed address
+    Checksum(AddressArgs),
+    /// Check the format and the checksum of an address
+    Validate(AddressArgs),
+    /// Generate a private key, or mine a CREATE2 salt, for an address matching a pattern
+    Vanity(VanityArgs),
+}
+
+#[derive(Debug, Args)]
+struct AddressArgs {
+    address: String,
+
+    /// The chain of an EIP-1191 checksum, instead of the EIP-55 one
+    #[arg(short, long)]
+    chain_id: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+struct VanityArgs {
+    /// The hex characters the address starts with
+    #[arg(short, long, default_value = "")]
+    prefix: String,
+
+    /// The hex characters the address ends with
+    #[arg(short, long, default_value = "")]
+    suffix: String,
+
+    /// Match the case of the checksummed address
+    #[arg(long)]
+    case_sensitive: bool,
+
+    /// The CREATE2 deployer, to mine a salt instead of a private key
+    #[arg(long, requires = "init_code_hash")]
+    deployer: Option<String>,
+
+    /// The keccak256 hash of the init code of the CREATE2 contract
+    #[arg(long, requires = "deployer")]
+    init_code_hash: Option<H256>,
+
+    /// The number of threads, defaults to the number of CPUs
+    #[arg(short, long)]
+    threads: Option<usize>,
+}
+
+impl Command {
+    pub async fn run(&self) -> eyre::Result<()> {
+        match &self.command {
+            Subcommands::Checksum(args) => {
+                let address = validate(&args.address.to_lowercase(), None)?;
+                println!("{}", checksum(&address, args.chain_id));
+            }
+            Subcommands::Validate(args) => {
+                validate(&args.address, args.chain_id)?;
+                info!("Valid address");
+            }
+            Subcommands::Vanity(args) => {
+                let pattern = Pattern::new(&args.prefix, &args.suffix, args.case_sensitive)?;
+                let threads = args
+                    .threads
+                    .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+                info!("Expecting {} attempts on {} threads", pattern.difficulty(), threads);
+                let progress = |p: Progress| info!("Tried {} addresses", p.attempts);
+                match (&args.deployer, args.init_code_hash) {
+                    (Some(deployer), Some(init_code_hash)) => {
+                        let deployer = validate(deployer, None)?;
+                        let (salt, address) =
+                            vanity_salt(&pattern, deployer, init_code_hash, threads, progress);
+                        println!("Address: {}", checksum(&address, None));
+                        println!("Salt: {:?}", salt);
+                    }
+                    _ => {
+                        let (key, address) = vanity_key(&pattern, threads, progress);
+                        println!("Address: {}", checksum(&address, None));
+                        println!("Private key: 0x{}", hex::encode(key.to_bytes()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_test::traced_test;
+
+    const ADDRESS: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    #[test]
+    fn test_address_parse() {
+        let command = Command::parse_from(["address", "checksum", ADDRESS, "-c", "30"]);
+        let Subcommands::Checksum(args) = command.command else { panic!("expected checksum") };
+        assert_eq!(args.chain_id, Some(30));
+
+        assert!(Command::try_parse_from(["address", "vanity", "--deployer", ADDRESS]).is_err());
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_address_run() {
+        let command = Command::parse_from(["address", "validate", ADDRESS]);
+        assert!(command.run().await.is_ok());
+        assert!(logs_contain("Valid address"));
+
+        let invalid = ADDRESS.replace('a', "A");
+        let command = Command::parse_from(["address", "validate", &invalid]);
+        assert!(command.run().await.is_err());
+
+        let command = Command::parse_from(["address", "vanity", "-p", "f", "-t", "1"]);
+        assert!(command.run().await.is_ok());
+        assert!(logs_contain("Expecting 16 attempts on 1 threads"));
+    }
+}
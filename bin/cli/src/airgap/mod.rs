@@ -0,0 +1,343 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// An air-gapped signer for MetaMask and the other ERC-4527 wallets, on an offline machine.
+///
+/// The wallet connects to the `crypto-hdkey` of the accounts, then shows each request as an
+/// animated QR code of `eth-sign-request` parts, and scans back the `eth-signature`.
+///
+/// From:
+/// https://eips.ethereum.org/EIPS/eip-4527
+use crate::paper::{document::qr_code, render::ansi};
+use clap::{Args, Parser, Subcommand};
+use ethers_core::{
+    types::transaction::eip2718::TypedTransaction,
+    utils::{format_ether, hex, rlp::Rlp, to_checksum},
+};
+use inquire::{Confirm, Password, PasswordDisplayMode};
+use qrcodegen::QrCodeEcc;
+use std::{
+    io::{self, BufRead},
+    thread,
+    time::Duration,
+};
+use tracing::info;
+use wallet_signer::{
+    calldata::{self, Decoded},
+    erc4527::{CryptoHdKey, DataType, EthSignRequest, ETH_SIGNATURE},
+    mnemonic::{master_key, validate, Language},
+    ur::{Decoder, Encoder},
+};
+use zeroize::Zeroizing;
+
+/// The note of a `crypto-hdkey` of the standard BIP-44 accounts
+const STANDARD_ACCOUNT: &str = "account.standard";
+
+/// Start the airgap command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[command(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Print the crypto-hdkey UR that connects a wallet to the accounts of a mnemonic
+    Account(AccountArgs),
+    /// Sign an eth-sign-request UR, and print the eth-signature UR
+    Sign(SignArgs),
+}
+
+#[derive(Debug, Args)]
+struct AccountArgs {
+    /// Prompt for the passphrase of the prompted mnemonic
+    #[arg(short, long)]
+    passphrase: bool,
+
+    /// The path of the extended public key, the accounts at its children `0/*`
+    #[arg(long, default_value = "m/44'/60'/0'")]
+    hd_path: String,
+
+    /// The name of the signer shown by the wallet
+    #[arg(long, default_value = "wallet-rs")]
+    name: String,
+
+    /// Show the UR as a QR code
+    #[arg(long)]
+    qr: bool,
+}
+
+#[derive(Debug, Args)]
+struct SignArgs {
+    /// The parts of the eth-sign-request UR, read one per line from stdin when omitted
+    parts: Vec<String>,
+
+    /// Prompt for the passphrase of the prompted mnemonic
+    #[arg(short, long)]
+    passphrase: bool,
+
+    /// Sign without asking for confirmation
+    #[arg(short, long)]
+    yes: bool,
+
+    /// The longest fragment of the parts of the signature
+    #[arg(long, default_value_t = 200)]
+    fragment_len: usize,
+
+    /// Show the parts of the signature as an animated QR code
+    #[arg(long)]
+    qr: bool,
+
+    /// The milliseconds each frame of the animated QR code is shown
+    #[arg(long, default_value_t = 250)]
+    interval: u64,
+}
+
+/// Prompts for a secret without echoing it.
+fn prompt(message: &str) -> eyre::Result<String> {
+    Ok(Password::new(message)
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .without_confirmation()
+        .prompt()?)
+}
+
+/// The prompted mnemonic, its language and its passphrase
+struct Secrets {
+    phrase: Zeroizing<String>,
+    language: Language,
+    passphrase: Option<Zeroizing<String>>,
+}
+
+impl Secrets {
+    /// Prompts for a mnemonic, finding its language, and for its passphrase if asked to.
+    fn read(
+        prompt: &mut dyn FnMut(&str) -> eyre::Result<String>,
+        ask_passphrase: bool,
+    ) -> eyre::Result<Self> {
+        let phrase = Zeroizing::new(prompt("Your mnemonic:")?);
+        let language = Language::ALL
+            .into_iter()
+            .find(|language| validate(&phrase, *language).is_ok())
+            .ok_or_else(|| eyre::eyre!("Invalid mnemonic"))?;
+        let passphrase = match ask_passphrase {
+            true => Some(Zeroizing::new(prompt("The passphrase of the mnemonic:")?)),
+            false => None,
+        };
+        Ok(Secrets { phrase, language, passphrase })
+    }
+
+    fn passphrase(&self) -> Option<&str> {
+        self.passphrase.as_deref().map(String::as_str)
+    }
+}
+
+/// Shows the parts of a UR, as text or an animated QR code that cycles through them.
+fn show(encoder: &mut Encoder, qr: bool, interval: u64) -> eyre::Result<()> {
+    if !qr {
+        for _ in 0..encoder.fragment_count() {
+            println!("{}", encoder.next_part());
+        }
+        return Ok(());
+    }
+    if encoder.is_single_part() {
+        print!("{}", ansi(&qr_code(&encoder.next_part().to_uppercase(), QrCodeEcc::Low)?));
+        return Ok(());
+    }
+
+    // Ten rounds of frames, past the fragments into their mixes
+    for _ in 0..10 * encoder.fragment_count() {
+        let code = qr_code(&encoder.next_part().to_uppercase(), QrCodeEcc::Low)?;
+        print!("\x1b[2J\x1b[H{}", ansi(&code));
+        thread::sleep(Duration::from_millis(interval));
+    }
+    Ok(())
+}
+
+/// Receives the parts of a UR until it is complete.
+fn receive(parts: &[String]) -> eyre::Result<Decoder> {
+    let mut decoder = Decoder::default();
+    let mut lines: Box<dyn Iterator<Item = io::Result<String>>> = match parts.is_empty() {
+        true => Box::new(io::stdin().lock().lines()),
+        false => Box::new(parts.iter().cloned().map(Ok)),
+    };
+    while !decoder.is_complete() {
+        let Some(line) = lines.next() else {
+            eyre::bail!("Missing parts, {:.0}% received", decoder.progress() * 100.0);
+        };
+        let line = line?;
+        if !line.trim().is_empty() {
+            decoder.receive(&line)?;
+        }
+    }
+    Ok(decoder)
+}
+
+/// Prints what a request signs.
+fn print_request(request: &EthSignRequest) {
+    println!("Path: {}", request.derivation_path);
+    if let Some(address) = request.address {
+        println!("Address: {}", to_checksum(&address, None));
+    }
+    if let Some(chain_id) = request.chain_id {
+        println!("Chain ID: {}", chain_id);
+    }
+    if let Some(origin) = &request.origin {
+        println!("Requested by: {}", origin);
+    }
+    match request.data_type {
+        DataType::PersonalMessage => match std::str::from_utf8(&request.sign_data) {
+            Ok(message) => println!("Message: {}", message),
+            Err(_) => println!("Message: 0x{}", hex::encode(&request.sign_data)),
+        },
+        DataType::TypedData => {
+            println!("Typed data: {}", String::from_utf8_lossy(&request.sign_data))
+        }
+        DataType::Transaction | DataType::TypedTransaction => {
+            match Rlp::new(&request.sign_data).as_val::<TypedTransaction>() {
+                Ok(transaction) => {
+                    if let Some(to) = transaction.to().and_then(|to| to.as_address()) {
+                        println!("To: {}", to_checksum(to, None));
+                    }
+                    let value = transaction.value().copied().unwrap_or_default();
+                    println!("Value: {} ETH", format_ether(value));
+                    let data = transaction.data().map(|data| data.to_vec()).unwrap_or_default();
+                    match calldata::decode(&data) {
+                        Decoded::Empty => {}
+                        Decoded::Call(call) => println!("Call: {}", call.signature),
+                        Decoded::Raw { .. } => println!("Data: 0x{}", hex::encode(&data)),
+                    }
+                }
+                Err(e) => println!("Undecodable transaction: {}", e),
+            }
+        }
+    }
+}
+
+impl Command {
+    pub async fn run(&self) -> eyre::Result<()> {
+        self.run_with(&mut prompt)
+    }
+
+    /// Runs the command, prompting for the secrets rather than taking them from the arguments.
+    fn run_with(&self, prompt: &mut dyn FnMut(&str) -> eyre::Result<String>) -> eyre::Result<()> {
+        match &self.command {
+            Subcommands::Account(args) => {
+                let secrets = Secrets::read(prompt, args.passphrase)?;
+                let master = master_key(&secrets.phrase, secrets.language, secrets.passphrase())?;
+                let mut hdkey = CryptoHdKey::from_master(&master, &args.hd_path, "0/*")?;
+                hdkey.name = Some(args.name.clone());
+                hdkey.note = Some(STANDARD_ACCOUNT.to_string());
+                let ur = hdkey.to_ur();
+                info!("Connect the wallet to the accounts at {}/0/*", args.hd_path);
+                match args.qr {
+                    true => print!("{}", ansi(&qr_code(&ur.to_uppercase(), QrCodeEcc::Low)?)),
+                    false => println!("{}", ur),
+                }
+            }
+            Subcommands::Sign(args) => {
+                let decoder = receive(&args.parts)?;
+                let request = EthSignRequest::from_ur(
+                    decoder.ur_type().unwrap_or_default(),
+                    decoder.message().unwrap_or_default(),
+                )?;
+                print_request(&request);
+                if !args.yes && !Confirm::new("Sign?").with_default(false).prompt()? {
+                    eyre::bail!("Signing cancelled");
+                }
+
+                let secrets = Secrets::read(prompt, args.passphrase)?;
+                let master = master_key(&secrets.phrase, secrets.language, secrets.passphrase())?;
+                let mut signature = request.sign(&master)?;
+                signature.origin = Some("wallet-rs".to_string());
+                info!("Signed the request, scan the signature back into the wallet");
+                let mut encoder =
+                    Encoder::new(ETH_SIGNATURE, &signature.to_cbor(), args.fragment_len)?;
+                show(&mut encoder, args.qr, args.interval)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_test::traced_test;
+    use wallet_signer::erc4527::{KeyPath, ETH_SIGN_REQUEST};
+
+    const PHRASE: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_airgap_parse() {
+        let command = Command::parse_from(["airgap", "sign", "ur:a/b", "ur:c/d", "-y"]);
+        let Subcommands::Sign(args) = command.command else { panic!("expected sign") };
+        assert_eq!(args.parts, ["ur:a/b", "ur:c/d"]);
+        assert!(args.yes && !args.qr);
+        assert_eq!(args.fragment_len, 200);
+
+        let command = Command::parse_from(["airgap", "account"]);
+        let Subcommands::Account(args) = command.command else { panic!("expected account") };
+        assert_eq!(args.hd_path, "m/44'/60'/0'");
+    }
+
+    /// Returns a prompt answering the mnemonic, and recording the prompts.
+    fn answer<'a>(
+        phrase: &'a str,
+        prompts: &'a mut Vec<String>,
+    ) -> impl FnMut(&str) -> eyre::Result<String> + 'a {
+        move |message| {
+            prompts.push(message.to_string());
+            match message {
+                "Your mnemonic:" => Ok(phrase.to_string()),
+                _ => Ok("passphrase".to_string()),
+            }
+        }
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_airgap_run() -> eyre::Result<()> {
+        let mut prompts = vec![];
+        Command::parse_from(["airgap", "account"]).run_with(&mut answer(PHRASE, &mut prompts))?;
+        assert!(logs_contain("Connect the wallet to the accounts at m/44'/60'/0'/0/*"));
+        assert_eq!(prompts, ["Your mnemonic:"]);
+
+        // The passphrase is prompted for, never an argument
+        let mut prompts = vec![];
+        let command = Command::parse_from(["airgap", "account", "-p"]);
+        command.run_with(&mut answer(PHRASE, &mut prompts))?;
+        assert_eq!(prompts, ["Your mnemonic:", "The passphrase of the mnemonic:"]);
+        assert!(Command::try_parse_from(["airgap", "account", "-m", PHRASE]).is_err());
+        assert!(Command::try_parse_from(["airgap", "account", "-p", "passphrase"]).is_err());
+
+        // A long message, sent as a multipart UR
+        let request = EthSignRequest {
+            request_id: Some([1; 16]),
+            sign_data: "Sign in to wallet-rs. ".repeat(20).into_bytes(),
+            data_type: DataType::PersonalMessage,
+            chain_id: Some(1),
+            derivation_path: KeyPath::parse("m/44'/60'/0'/0/0", Some(0x73c5da0a))?,
+            address: None,
+            origin: Some("MetaMask".to_string()),
+        };
+        let mut encoder = Encoder::new(ETH_SIGN_REQUEST, &request.to_cbor(), 100)?;
+        assert!(!encoder.is_single_part());
+        let mut args = vec!["airgap".to_string(), "sign".to_string()];
+        args.extend((0..encoder.fragment_count()).map(|_| encoder.next_part()));
+        args.push("-y".to_string());
+        let mut prompts = vec![];
+        Command::parse_from(&args).run_with(&mut answer(PHRASE, &mut prompts))?;
+        assert!(logs_contain("Signed the request"));
+
+        // Missing parts, and the mnemonic of another fingerprint
+        let mut prompts = vec![];
+        let first: Vec<&String> = args[..3].iter().chain(&args[args.len() - 1..]).collect();
+        assert!(Command::parse_from(first).run_with(&mut answer(PHRASE, &mut prompts)).is_err());
+        assert!(prompts.is_empty());
+        let other = "test test test test test test test test test test test junk";
+        assert!(Command::parse_from(&args).run_with(&mut answer(other, &mut prompts)).is_err());
+        Ok(())
+    }
+}
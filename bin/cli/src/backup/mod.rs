@@ -0,0 +1,278 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use clap::{ArgAction, Args, Parser, Subcommand};
+use inquire::{Password, PasswordDisplayMode};
+use std::{fs, path::PathBuf};
+use tracing::info;
+use wallet_signer::backup::{create, inspect, restore, KdfParams, Keyring, KeyringKind, Label};
+use zeroize::Zeroizing;
+
+/// Start the backup command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[command(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Encrypt a mnemonic and private keys into a backup file
+    Create(CreateArgs),
+    /// Print the metadata of a backup, without decrypting it
+    Inspect(InspectArgs),
+    /// Decrypt the keyrings of a backup
+    Restore(RestoreArgs),
+}
+
+#[derive(Debug, Args)]
+struct CreateArgs {
+    /// The path of the backup file
+    path: PathBuf,
+
+    /// Back up a mnemonic, prompted for, the default when no private key is backed up
+    #[arg(short, long)]
+    mnemonic: bool,
+
+    /// The HD path of the accounts of the mnemonic, without their index
+    #[arg(long)]
+    hd_path: Option<String>,
+
+    /// The number of accounts of the mnemonic
+    #[arg(short = 'n', long)]
+    accounts: Option<u32>,
+
+    /// The label of an account of the mnemonic, as `index=name`, repeated for every account
+    #[arg(short, long = "label", value_parser = parse_label)]
+    labels: Vec<Label>,
+
+    /// Back up a hex private key, prompted for, repeated for every key
+    #[arg(short = 'k', long = "private-key", action = ArgAction::Count)]
+    private_keys: u8,
+
+    /// The memory cost of the key derivation, in KiB
+    #[arg(long, default_value_t = KdfParams::default().memory)]
+    memory: u32,
+
+    /// The iterations of the key derivation
+    #[arg(long, default_value_t = KdfParams::default().iterations)]
+    iterations: u32,
+
+    /// Overwrite the backup file if it exists
+    #[arg(short, long)]
+    force: bool,
+}
+
+#[derive(Debug, Args)]
+struct InspectArgs {
+    /// The path of the backup file
+    path: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct RestoreArgs {
+    /// The path of the backup file
+    path: PathBuf,
+}
+
+/// Parses the label of an account, e.g. `0=Savings`.
+fn parse_label(s: &str) -> Result<Label, String> {
+    let (index, name) = s.split_once('=').ok_or("expected index=name, e.g. 0=Savings")?;
+    Ok(Label {
+        index: index.parse().map_err(|e| format!("invalid index: {}", e))?,
+        name: name.to_string(),
+    })
+}
+
+/// Prompts for a secret without echoing it, confirming it if asked to.
+fn prompt(message: &str, confirmation: bool) -> eyre::Result<String> {
+    let prompt = Password::new(message).with_display_mode(PasswordDisplayMode::Masked);
+    match confirmation {
+        true => Ok(prompt.prompt()?),
+        false => Ok(prompt.without_confirmation().prompt()?),
+    }
+}
+
+/// Prints a restored keyring.
+fn print_keyring(index: usize, keyring: &Keyring) {
+    let kind = match keyring.kind {
+        KeyringKind::Mnemonic => "mnemonic",
+        KeyringKind::PrivateKey => "private key",
+    };
+    match &keyring.source {
+        Some(source) => println!("Keyring {}, a {} from {}", index + 1, kind, source),
+        None => println!("Keyring {}, a {}", index + 1, kind),
+    }
+    println!("{}", keyring.secret);
+    if let Some(hd_path) = &keyring.hd_path {
+        println!("HD path: {}", hd_path);
+    }
+    if let Some(accounts) = keyring.number_of_accounts {
+        println!("Accounts: {}", accounts);
+    }
+    if let Some(passphrase) = &keyring.passphrase {
+        println!("Passphrase: {}", passphrase);
+    }
+    for label in &keyring.labels {
+        println!("Account {}: {}", label.index, label.name);
+    }
+    println!();
+}
+
+impl Command {
+    pub async fn run(&self) -> eyre::Result<()> {
+        self.run_with(&mut prompt)
+    }
+
+    /// Runs the command, prompting for the secrets and passwords rather than reading them from
+    /// the arguments, which other users and the shell history could read.
+    fn run_with(
+        &self,
+        prompt: &mut dyn FnMut(&str, bool) -> eyre::Result<String>,
+    ) -> eyre::Result<()> {
+        match &self.command {
+            Subcommands::Create(args) => {
+                if args.path.exists() && !args.force {
+                    eyre::bail!("{} exists, use --force to overwrite it", args.path.display());
+                }
+
+                // The mnemonic, then the private keys
+                let mut keyrings = vec![];
+                if args.mnemonic || args.private_keys == 0 {
+                    let mnemonic = Zeroizing::new(prompt("Your mnemonic:", false)?);
+                    let mut keyring =
+                        Keyring::mnemonic(mnemonic.trim(), args.hd_path.as_deref(), args.accounts);
+                    keyring.labels = args.labels.clone();
+                    keyrings.push(keyring);
+                }
+                for index in 1..=args.private_keys {
+                    let key = Zeroizing::new(prompt(&format!("Private key {}:", index), false)?);
+                    keyrings.push(Keyring::private_key(key.trim()));
+                }
+
+                let password = Zeroizing::new(prompt("The password of the backup:", true)?);
+                let params = KdfParams {
+                    memory: args.memory,
+                    iterations: args.iterations,
+                    ..KdfParams::default()
+                };
+                fs::write(&args.path, create(&keyrings, &password, params)?)?;
+                info!("Backed up {} keyrings to {}", keyrings.len(), args.path.display());
+            }
+            Subcommands::Inspect(args) => {
+                let header = inspect(&fs::read_to_string(&args.path)?)?;
+                println!("Version: {}", header.version);
+                println!("Created at: {}", header.created_at);
+                println!("Keyrings: {}", header.keyrings);
+                println!(
+                    "Key derivation: {}, {} KiB, {} iterations, {} lanes",
+                    header.kdf.algorithm,
+                    header.kdf.memory,
+                    header.kdf.iterations,
+                    header.kdf.parallelism
+                );
+                println!("Cipher: {}", header.cipher.algorithm);
+            }
+            Subcommands::Restore(args) => {
+                let backup = fs::read_to_string(&args.path)?;
+                let password = Zeroizing::new(prompt("The password of the backup:", false)?);
+                let keyrings = restore(&backup, &password)?;
+                info!("Restored {} keyrings", keyrings.len());
+                for (index, keyring) in keyrings.iter().enumerate() {
+                    print_keyring(index, keyring);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use tracing_test::traced_test;
+
+    const PHRASE: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_backup_parse() {
+        let command =
+            Command::parse_from(["backup", "create", "a.json", "-l", "0=Main", "-l", "3=x=y"]);
+        let Subcommands::Create(args) = command.command else { panic!("expected create") };
+        assert_eq!(
+            args.labels,
+            vec![
+                Label { index: 0, name: "Main".to_string() },
+                Label { index: 3, name: "x=y".to_string() }
+            ]
+        );
+        assert_eq!(args.memory, KdfParams::default().memory);
+        assert!(Command::try_parse_from(["backup", "create", "a.json", "-l", "Main"]).is_err());
+    }
+
+    /// Returns a prompt answering the messages with the answers given, in order.
+    fn answers(answers: &[(&str, &str)]) -> impl FnMut(&str, bool) -> eyre::Result<String> {
+        let mut answers: Vec<(String, String)> =
+            answers.iter().rev().map(|(m, a)| (m.to_string(), a.to_string())).collect();
+        move |message, _| match answers.pop() {
+            Some((expected, answer)) if expected == message => Ok(answer),
+            _ => eyre::bail!("unexpected prompt {}", message),
+        }
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_backup_run() -> eyre::Result<()> {
+        let path = env::temp_dir().join(format!("wallet-rs-backup-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let path_arg = path.to_str().unwrap();
+        let create = [
+            "backup",
+            "create",
+            path_arg,
+            "-m",
+            "-n",
+            "2",
+            "-l",
+            "1=Savings",
+            "-k",
+            "--memory",
+            "64",
+            "--iterations",
+            "1",
+        ];
+        let secrets = [
+            ("Your mnemonic:", PHRASE),
+            (
+                "Private key 1:",
+                "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+            ),
+            ("The password of the backup:", "password"),
+        ];
+        Command::parse_from(create).run_with(&mut answers(&secrets))?;
+        assert!(logs_contain("Backed up 2 keyrings"));
+
+        // An existing backup is not overwritten
+        assert!(Command::parse_from(create).run_with(&mut answers(&secrets)).is_err());
+
+        let header = inspect(&fs::read_to_string(&path)?)?;
+        assert_eq!((header.keyrings, header.kdf.memory), (2, 64));
+        Command::parse_from(["backup", "inspect", path_arg]).run().await?;
+
+        let restore = Command::parse_from(["backup", "restore", path_arg]);
+        restore.run_with(&mut answers(&[("The password of the backup:", "password")]))?;
+        assert!(logs_contain("Restored 2 keyrings"));
+        let mut wrong = answers(&[("The password of the backup:", "wrong")]);
+        assert!(restore.run_with(&mut wrong).is_err());
+
+        // The secrets are not arguments
+        assert!(Command::try_parse_from(["backup", "create", path_arg, "-m", PHRASE]).is_err());
+        assert!(Command::try_parse_from(["backup", "restore", path_arg, "-p", "x"]).is_err());
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
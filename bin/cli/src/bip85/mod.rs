@@ -0,0 +1,133 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use clap::{Parser, Subcommand};
+use inquire::{Password, PasswordDisplayMode};
+use tracing::info;
+use wallet_signer::{
+    bip85::{derive, parse_xprv, Application},
+    mnemonic::{master_key, validate, Language},
+};
+
+/// Start the bip85 command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[command(subcommand)]
+    application: Applications,
+
+    /// The root extended private key, instead of the entropy of a prompted mnemonic
+    #[arg(long, global = true)]
+    xprv: Option<String>,
+
+    /// The language of the prompted mnemonic
+    #[arg(long, global = true, default_value_t = Language::English)]
+    root_language: Language,
+
+    /// The BIP-39 passphrase of the prompted mnemonic
+    #[arg(long, global = true)]
+    passphrase: Option<String>,
+
+    /// The index of the child
+    #[arg(short, long, global = true, default_value_t = 0)]
+    index: u32,
+}
+
+#[derive(Debug, Subcommand)]
+enum Applications {
+    /// Derive a child BIP-39 mnemonic
+    Mnemonic {
+        /// The number of words: 12, 15, 18, 21 or 24
+        #[arg(short, long, default_value_t = 12)]
+        words: usize,
+
+        /// The language of the child mnemonic
+        #[arg(short, long, default_value_t = Language::English)]
+        language: Language,
+    },
+    /// Derive a child private key in the wallet import format
+    Wif,
+    /// Derive a child extended private key
+    Xprv,
+    /// Derive child hex entropy
+    Hex {
+        /// The number of bytes, from 16 to 64
+        #[arg(short, long, default_value_t = 32)]
+        bytes: usize,
+    },
+    /// Derive a child password
+    Password {
+        /// The number of characters, from 20 to 86, or 10 to 80 in base85
+        #[arg(short, long, default_value_t = 21)]
+        length: usize,
+
+        /// Use the base85 alphabet instead of base64
+        #[arg(long)]
+        base85: bool,
+    },
+}
+
+impl Applications {
+    fn application(&self) -> Application {
+        match *self {
+            Applications::Mnemonic { words, language } => Application::Bip39 { language, words },
+            Applications::Wif => Application::Wif,
+            Applications::Xprv => Application::Xprv,
+            Applications::Hex { bytes } => Application::Hex { bytes },
+            Applications::Password { length, base85: false } => Application::Base64 { length },
+            Applications::Password { length, base85: true } => Application::Base85 { length },
+        }
+    }
+}
+
+impl Command {
+    pub async fn run(&self) -> eyre::Result<()> {
+        let root = match &self.xprv {
+            Some(xprv) => parse_xprv(xprv)?,
+            None => {
+                let phrase = Password::new("Your mnemonic:")
+                    .with_display_mode(PasswordDisplayMode::Masked)
+                    .without_confirmation()
+                    .prompt()?;
+                validate(&phrase, self.root_language)?;
+                master_key(&phrase, self.root_language, self.passphrase.as_deref())?
+            }
+        };
+        let application = self.application.application();
+        info!("Deriving the {} child at index {}", application, self.index);
+        println!("{}", derive(&root, application, self.index)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_test::traced_test;
+
+    const ROOT: &str = "xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb";
+
+    #[test]
+    fn test_bip85_parse() {
+        let command = Command::parse_from(["bip85", "mnemonic", "-w", "24", "-i", "3"]);
+        assert_eq!(command.index, 3);
+        assert_eq!(
+            command.application.application(),
+            Application::Bip39 { language: Language::English, words: 24 }
+        );
+
+        let command = Command::parse_from(["bip85", "password", "--base85", "-l", "12"]);
+        assert_eq!(command.application.application(), Application::Base85 { length: 12 });
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_bip85_run() {
+        let command = Command::parse_from(["bip85", "--xprv", ROOT, "hex", "-b", "16"]);
+        assert!(command.run().await.is_ok());
+        assert!(logs_contain("Deriving the 16 bytes hex child at index 0"));
+
+        let command = Command::parse_from(["bip85", "--xprv", ROOT, "hex", "-b", "8"]);
+        assert!(command.run().await.is_err());
+    }
+}
@@ -5,7 +5,9 @@
 /// Main entry point for the wallet cli.
 /// Structue of the CLI is extremely influenced from reth.
 /// https://github.com/paradigmxyz/reth/tree/main/bin/reth
-use crate::metamask;
+use crate::{
+    address, airgap, backup, bip85, extension, import, metamask, mnemonic, paper, siwe, slip39,
+};
 use clap::{ArgAction, Args, Parser, Subcommand};
 use tracing::{metadata::LevelFilter, Level};
 use tracing_subscriber::{filter::Directive, EnvFilter};
@@ -23,6 +25,16 @@ pub async fn run() -> eyre::Result<()> {
     // Run the chosen command
     match opt.command {
         Commands::Metamask(m) => m.run().await,
+        Commands::Mnemonic(m) => m.run().await,
+        Commands::Slip39(m) => m.run().await,
+        Commands::Bip85(m) => m.run().await,
+        Commands::Address(m) => m.run().await,
+        Commands::Import(m) => m.run().await,
+        Commands::Extension(m) => m.run().await,
+        Commands::Backup(m) => m.run().await,
+        Commands::Paper(m) => m.run().await,
+        Commands::Airgap(m) => m.run().await,
+        Commands::Siwe(m) => m.run().await,
     }
 }
 
@@ -32,6 +44,29 @@ pub enum Commands {
     /// Run the metamask command utilities
     #[command(name = "metamask")]
     Metamask(metamask::Command),
+    /// Generate, validate and derive BIP-39 mnemonics
+    #[command(name = "mnemonic")]
+    Mnemonic(mnemonic::Command),
+    /// Split and combine SLIP-39 shares of a seed
+    #[command(name = "slip39")]
+    Slip39(slip39::Command),
+    /// Derive child mnemonics, keys and passwords of a seed with BIP-85
+    #[command(name = "bip85")]
+    Bip85(bip85::Command),
+    /// Checksum, validate and generate vanity addresses
+    Address(address::Command),
+    /// Import an account from a keystore, a presale wallet, a hex or WIF private key
+    Import(import::Command),
+    /// Decrypt the vaults of the Rabby and Brave wallets
+    Extension(extension::Command),
+    /// Create, inspect and restore encrypted backups of keyrings
+    Backup(backup::Command),
+    /// Print a mnemonic and its accounts as a paper wallet with QR codes
+    Paper(paper::Command),
+    /// Sign the ERC-4527 QR code requests of MetaMask and other wallets on an offline machine
+    Airgap(airgap::Command),
+    /// Sign and verify Sign-In with Ethereum messages
+    Siwe(siwe::Command),
 }
 
 #[derive(Parser)]
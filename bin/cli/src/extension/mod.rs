@@ -0,0 +1,114 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use clap::Parser;
+use inquire::{Password, PasswordDisplayMode};
+use std::path::PathBuf;
+use tracing::{error, info};
+use wallet_extensions::{
+    types::{EncryptedVault, Error, Secret},
+    wallet::{extract_all_vaults, extract_vaults_from_file, Wallet},
+};
+
+/// Start the extension command
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The wallet to decrypt: rabby or brave, both of them when omitted
+    #[arg(short, long)]
+    wallet: Option<Wallet>,
+
+    /// A storage file of the wallet instead of the browser profiles, requires the wallet
+    #[arg(short, long, requires = "wallet")]
+    file: Option<PathBuf>,
+
+    /// The number of accounts to derive from each mnemonic
+    #[arg(short, long, default_value_t = 1)]
+    count: u32,
+
+    /// Output the decrypted mnemonics and private keys to stdout
+    #[arg(short, long)]
+    output: bool,
+}
+
+impl Command {
+    pub async fn run(&self) -> eyre::Result<()> {
+        // Find the vaults in the file or in the browser profiles
+        let vaults = match (&self.file, self.wallet) {
+            (Some(file), Some(wallet)) => extract_vaults_from_file(wallet, file)?,
+            _ => extract_all_vaults()
+                .into_iter()
+                .filter(|vault| self.wallet.map_or(true, |wallet| vault.wallet == wallet))
+                .collect(),
+        };
+        info!("Found {} vaults", vaults.len());
+        if vaults.is_empty() {
+            return Ok(());
+        }
+
+        // The password is prompted for rather than passed as an argument, which other users and
+        // the shell history could read
+        let password = Password::new("The password of the vaults:")
+            .with_display_mode(PasswordDisplayMode::Masked)
+            .without_confirmation()
+            .prompt()?;
+        self.decrypt(vaults, &password)
+    }
+
+    /// Decrypts every vault with a password and logs their accounts.
+    fn decrypt(&self, vaults: Vec<EncryptedVault>, password: &str) -> eyre::Result<()> {
+        // Decrypt every vault, the wallets may have different passwords
+        for vault in vaults {
+            let secrets = match vault.decrypt(password) {
+                Ok(secrets) => secrets,
+                Err(Error::Password) => {
+                    error!("Wrong password for the {} vault", vault.wallet);
+                    continue;
+                }
+                Err(e) => {
+                    error!("Failed to decrypt the {} vault: {}", vault.wallet, e);
+                    continue;
+                }
+            };
+            for secret in secrets {
+                for account in secret.accounts(self.count)? {
+                    info!("Decrypted {} from {}", account, vault.wallet);
+                }
+                if self.output {
+                    match secret {
+                        Secret::Mnemonic { phrase, .. } => println!("{}", phrase),
+                        Secret::PrivateKey(key) => println!("0x{}", key.trim_start_matches("0x")),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_test::traced_test;
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_extension_decrypt() {
+        let fixture = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../crates/extensions/tests/fixtures/rabby/000003.log"
+        );
+        let command = Command::parse_from(["extension", "-w", "rabby", "-f", fixture]);
+        let vaults = extract_vaults_from_file(Wallet::Rabby, fixture.as_ref()).unwrap();
+        assert_eq!(vaults.len(), 1);
+        assert!(command.decrypt(vaults.clone(), "correct horse battery staple").is_ok());
+        assert!(logs_contain("Decrypted 0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"));
+        assert!(logs_contain("Decrypted 0x70997970C51812dc3A010C7d01b50e0d17dc79C8 (private key)"));
+
+        assert!(command.decrypt(vaults, "x").is_ok());
+        assert!(logs_contain("Wrong password for the rabby vault"));
+
+        // The password is no longer an argument
+        assert!(Command::try_parse_from(["extension", "-p", "x"]).is_err());
+    }
+}
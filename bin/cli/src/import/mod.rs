@@ -0,0 +1,101 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use clap::Parser;
+use eth_keystore::encrypt_key;
+use ethers_core::utils::hex;
+use inquire::{Password, PasswordDisplayMode};
+use std::{fs, path::Path};
+use tracing::info;
+use wallet_signer::account::{Account, Error};
+
+/// Start the import command
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// A keystore or presale wallet file, or a hex or WIF private key, prompted for when omitted
+    input: Option<String>,
+
+    /// The password of the keystore or the presale wallet, prompted for when needed
+    #[arg(short, long)]
+    password: Option<String>,
+
+    /// Output the private key to stdout
+    #[arg(short, long)]
+    output: bool,
+
+    /// The directory of a new keystore file, encrypted with the password, to export the account to
+    #[arg(short, long)]
+    keystore: Option<String>,
+}
+
+/// Prompts for a secret without echoing it.
+fn prompt(message: &str) -> eyre::Result<String> {
+    Ok(Password::new(message)
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .without_confirmation()
+        .prompt()?)
+}
+
+impl Command {
+    pub async fn run(&self) -> eyre::Result<()> {
+        // Read the input, from a file if it is a path
+        let input = match &self.input {
+            Some(input) if Path::new(input).is_file() => fs::read_to_string(input)?,
+            Some(input) => input.clone(),
+            None => prompt("Your keystore or private key:")?,
+        };
+
+        // Import the account, prompting for the password if needed
+        let account = match Account::import(&input, self.password.as_deref()) {
+            Err(Error::PasswordRequired(source)) => {
+                let password = prompt(&format!("The password of the {}:", source))?;
+                Account::import(&input, Some(&password))?
+            }
+            account => account?,
+        };
+        info!("Imported {}", account);
+
+        if self.output {
+            println!("0x{}", hex::encode(account.signing_key().to_bytes()));
+        }
+
+        if let Some(keystore) = &self.keystore {
+            let password = match &self.password {
+                Some(password) => password.clone(),
+                None => prompt("The password of the new keystore:")?,
+            };
+            let mut rng = rand::thread_rng();
+            let name =
+                encrypt_key(keystore, &mut rng, account.signing_key().to_bytes(), password, None)?;
+            info!("Exported the account to {}", Path::new(keystore).join(name).display());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_test::traced_test;
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_import_run() {
+        let fixture = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../crates/signer/tests/fixtures/import/keystore-scrypt.json"
+        );
+        let command = Command::parse_from(["import", fixture, "-p", "testpassword"]);
+        assert!(command.run().await.is_ok());
+        assert!(logs_contain("Imported 0x008AeEda4D805471dF9b2A5B0f38A0C3bCBA786b (keystore)"));
+
+        let wif = "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98617";
+        let command = Command::parse_from(["import", wif, "-o"]);
+        assert!(command.run().await.is_ok());
+        assert!(logs_contain("(WIF key)"));
+
+        let command = Command::parse_from(["import", "0x1234"]);
+        assert!(command.run().await.is_err());
+    }
+}
@@ -2,5 +2,15 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+pub mod address;
+pub mod airgap;
+pub mod backup;
+pub mod bip85;
 pub mod cli;
+pub mod extension;
+pub mod import;
 pub mod metamask;
+pub mod mnemonic;
+pub mod paper;
+pub mod siwe;
+pub mod slip39;
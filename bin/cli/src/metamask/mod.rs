@@ -2,18 +2,36 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use clap::Parser;
+mod recover;
+
+use crate::paper::{
+    document::{PaperWallet, WordLayout},
+    export,
+};
+use clap::{Parser, Subcommand};
 use eth_keystore::encrypt_key;
-use ethers_signers::{coins_bip39::English, MnemonicBuilder};
+use inquire::{Password, PasswordDisplayMode};
+use recover::RecoverArgs;
+use std::{fs, path::Path};
 use tracing::{debug, error, info};
 use wallet_metamask::{
-    interactive::{extract_all_vaults, get_password},
-    vault::decrypt_vault,
+    source::{Found, MetaMaskSource},
+    types::Vault,
+};
+use wallet_signer::{
+    account::Account,
+    backup::{create, KdfParams, Keyring},
+    mnemonic::{Language, DEFAULT_HD_PATH},
 };
+use zeroize::Zeroizing;
 
 /// Start the metamask command
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Command {
+    #[command(subcommand)]
+    command: Option<Subcommands>,
+
     /// Output the decrypted mnemonic to stdout
     #[arg(short, long)]
     output: bool,
@@ -22,20 +40,67 @@ pub struct Command {
     #[arg(short, long)]
     keystore: Option<String>,
 
+    /// The path to an encrypted backup of the keyring, if you want to export it with its metadata
+    #[arg(short, long)]
+    backup: Option<String>,
+
+    /// The path to a paper wallet of the mnemonic and the accounts, an SVG or a PDF file
+    #[arg(long)]
+    paper: Option<String>,
+
+    /// How the mnemonic is printed on the paper wallet
+    #[arg(short, long, value_enum, default_value_t = WordLayout::Words)]
+    layout: WordLayout,
+
+    /// Print the QR code of the mnemonic in the terminal
+    #[arg(long)]
+    qr: bool,
+
     /// Flag to test running the command
     #[arg(short, long)]
     test: bool,
 }
 
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Recover the mnemonic of a wallet with unreadable (`?`), misspelled, missing or swapped
+    /// words
+    Recover(RecoverArgs),
+}
+
+/// Prompts for a password without echoing it, confirming it if asked to.
+fn prompt(message: &str, confirmation: bool) -> eyre::Result<String> {
+    let prompt = Password::new(message).with_display_mode(PasswordDisplayMode::Masked);
+    match confirmation {
+        true => Ok(prompt.prompt()?),
+        false => Ok(prompt.without_confirmation().prompt()?),
+    }
+}
+
 impl Command {
     pub async fn run(&self) -> eyre::Result<()> {
-        // Get the vaults and the password
-        let vaults = extract_all_vaults();
+        if let Some(Subcommands::Recover(args)) = &self.command {
+            return args.run();
+        }
+        self.run_with(&MetaMaskSource::all(), &mut prompt)
+    }
 
-        // Print the error and exit if there was an error
-        if let Err(e) = vaults {
-            error!("Failed to extract vaults: {:?}", e);
-            return Ok(());
+    /// Runs the command against the given vault sources, prompting for the passwords.
+    fn run_with(
+        &self,
+        sources: &[MetaMaskSource],
+        prompt: &mut dyn FnMut(&str, bool) -> eyre::Result<String>,
+    ) -> eyre::Result<()> {
+        // Get the vaults of every source
+        let mut vaults: Vec<(&MetaMaskSource, Found<Vault>)> = vec![];
+        for source in sources {
+            match source.vaults() {
+                Ok(found) => vaults.extend(found.into_iter().map(|found| (source, found))),
+                Err(e) => {
+                    error!("Failed to extract vaults: {:?}", e);
+                    return Ok(());
+                }
+            }
         }
 
         // Exit if this is a test run
@@ -44,9 +109,6 @@ impl Command {
             return Ok(());
         }
 
-        // Unwrap the vaults
-        let vaults = vaults.unwrap();
-
         // Print the number of vaults
         info!("Found {} vaults", vaults.len());
 
@@ -57,43 +119,62 @@ impl Command {
         }
 
         // Get the first vault and the password
-        let vault = vaults[0].clone();
-        let pwd = get_password().unwrap();
+        let (source, found) = &vaults[0];
+        info!("Decrypting the {} vault of {:?}", source.name, found.path);
+        let pwd = Zeroizing::new(prompt("Your metamask password:", false)?);
 
         // Attempt to decrypt the vault
-        let res = decrypt_vault(&vault, &pwd);
+        let res = source.decrypt(&found.vault, &pwd);
 
         // Print the result
-        if res.is_ok() {
+        if let Ok(decrypted) = res {
             debug!("Decrypted vault");
 
             // Print the mnemonic
             if self.output {
-                print!("{}", &res.unwrap().data.mnemonic);
+                print!("{}", &decrypted.data.mnemonic);
                 return Ok(());
             }
 
-            if self.keystore.is_some() {
-                // Get the mnemonic and index
-                let index = 0u32;
-                let phrase = &res.unwrap().data.mnemonic.to_string();
-
-                // Build the wallet with the mnemonic
-                let wallet = MnemonicBuilder::<English>::default()
-                    .phrase(phrase.as_str())
-                    .index(index)
-                    .unwrap()
-                    .build()
-                    .unwrap();
-
-                // Encrypt the wallet
-                let pk = wallet.signer();
+            // Get the mnemonic and the path of the first account
+            let index = 0u32;
+            let data = &decrypted.data;
+            let phrase = data.mnemonic.to_string();
+            let path = format!("{}/{}", data.hd_path.as_deref().unwrap_or(DEFAULT_HD_PATH), index);
+
+            // Derive the account of the vault
+            let account = Account::from_mnemonic(&phrase, Language::English, None, &path)?;
+            info!("Decrypted {}", account);
+
+            let mut keystore_json = None;
+            if let Some(keystore) = &self.keystore {
+                // Encrypt the account
+                info!("Exporting {}", account);
+                let pk = account.signing_key();
                 let mut rng = rand::thread_rng();
-                let _ =
-                    encrypt_key(self.keystore.clone().unwrap(), &mut rng, pk.to_bytes(), pwd, None);
+                let name = encrypt_key(keystore, &mut rng, pk.to_bytes(), &pwd, None)?;
+                keystore_json = Some(fs::read_to_string(Path::new(keystore).join(name))?);
+            }
+
+            if let Some(backup) = &self.backup {
+                // Encrypt the keyring with its derivation metadata, under a password of its own
+                // rather than the one of the vault
+                let mut keyring =
+                    Keyring::mnemonic(&phrase, data.hd_path.as_deref(), data.number_of_accounts);
+                keyring.source = Some(source.name.to_string());
+                let password = Zeroizing::new(prompt("The password of the backup:", true)?);
+                fs::write(backup, create(&[keyring], &password, KdfParams::default())?)?;
+                info!("Backed up the keyring to {}", backup);
+            }
+
+            if self.paper.is_some() || self.qr {
+                // Print the accounts of the keyring
+                let mut wallet = PaperWallet::from_decrypted(&decrypted, self.layout)?;
+                wallet.keystore = keystore_json;
+                export(&wallet, self.paper.as_deref(), self.qr)?;
             }
         } else {
-            error!("Failed to decrypt vault: {:?}", res);
+            error!("Failed to decrypt vault: {:?}", res.err());
         }
         Ok(())
     }
@@ -102,21 +183,182 @@ impl Command {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::PathBuf;
     use tracing_test::traced_test;
+    use wallet_metamask::{
+        interactive::{
+            ChromeLocator, DirEntry, FirefoxLocator, MockEnvironment, Os, CHROME_EXTENSION_ID,
+        },
+        source::{MockVaultDecryptor, MockVaultLocator, VaultSource},
+        vault::{ChromeExtractor, FirefoxExtractor, MetaMaskDecryptor},
+    };
 
     #[traced_test]
     #[tokio::test]
     async fn test_metamask_run() {
-        // Set up test input
-        let command = Command { output: false, keystore: None, test: true };
+        let fixture = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../crates/metamask/tests/fixtures/chromium-108.0_5359.98_4.10.24.2/000003.log"
+        );
+        let store = format!(
+            "/home/satoshi/.config/chromium/Default/Local Extension Settings/{}",
+            CHROME_EXTENSION_ID
+        );
 
-        // Run the command
-        let res = command.run().await;
+        // A Linux machine with MetaMask in the default profile of Chromium
+        let source = || {
+            let store = PathBuf::from(&store);
+            let mut env = MockEnvironment::new();
+            env.expect_os().return_const(Os::Linux);
+            env.expect_var_os().returning(|key| (key == "HOME").then(|| "/home/satoshi".into()));
+            env.expect_read_dir().returning(move |dir| {
+                let entry = |path: &str, is_dir| vec![DirEntry { path: path.into(), is_dir }];
+                match dir.to_str().unwrap_or_default() {
+                    "/home/satoshi/.config/chromium" => {
+                        entry("/home/satoshi/.config/chromium/Default", true)
+                    }
+                    _ if dir == store => entry(fixture, false),
+                    _ => vec![],
+                }
+            });
+            VaultSource::new(
+                "MetaMask Chrome",
+                Box::new(ChromeLocator { user_data_dirs: vec![], env }),
+                Box::new(ChromeExtractor),
+                Box::new(MetaMaskDecryptor),
+            )
+        };
 
-        // Check that the command ran successfully
-        assert!(res.is_ok());
+        // A test run stops before prompting
+        let command = Command::parse_from(["metamask", "-t"]);
+        assert!(command.run_with(&[source()], &mut |_, _| eyre::bail!("no prompt")).is_ok());
+        assert!(logs_contain("Cargo test, exiting"));
+        assert!(!logs_contain("Found 1 vaults"));
+
+        let password = "JooXegoodowu8mohf2ietah5kohgah5";
+        let command = Command::parse_from(["metamask"]);
+        assert!(command.run_with(&[source()], &mut |_, _| Ok(password.to_string())).is_ok());
+        assert!(logs_contain("Found 1 vaults"));
+        assert!(logs_contain("Decrypting the MetaMask Chrome vault of"));
+        assert!(logs_contain("Decrypted 0x"));
+        assert!(!logs_contain("Failed to"));
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_metamask_run_without_vaults() {
+        // A Linux machine without any browser profile
+        let environment = || {
+            let mut env = MockEnvironment::new();
+            env.expect_os().return_const(Os::Linux);
+            env.expect_var_os().returning(|key| (key == "HOME").then(|| "/home/satoshi".into()));
+            env.expect_read_dir().returning(|_| vec![]);
+            env.expect_read_to_string().returning(|_| None);
+            env
+        };
+        let sources = [
+            VaultSource::new(
+                "MetaMask Chrome",
+                Box::new(ChromeLocator { user_data_dirs: vec![], env: environment() }),
+                Box::new(ChromeExtractor),
+                Box::new(MetaMaskDecryptor),
+            ),
+            VaultSource::new(
+                "MetaMask Firefox",
+                Box::new(FirefoxLocator { profiles_dir: None, env: environment() }),
+                Box::new(FirefoxExtractor),
+                Box::new(MetaMaskDecryptor),
+            ),
+        ];
+
+        let command = Command {
+            command: None,
+            output: false,
+            keystore: None,
+            backup: None,
+            paper: None,
+            layout: WordLayout::Words,
+            qr: false,
+            test: false,
+        };
+        assert!(command.run_with(&sources, &mut |_, _| eyre::bail!("no prompt")).is_ok());
+        assert!(logs_contain("Found 0 vaults"));
+        assert!(logs_contain("No vaults found"));
+        assert!(!logs_contain("Failed to extract"));
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_metamask_run_with() {
+        let fixture = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../crates/metamask/tests/fixtures/chromium-108.0_5359.98_4.10.24.2/000003.log"
+        );
+
+        // A locator finding the fixture, with the MetaMask extractor and decryptor
+        let mut locator = MockVaultLocator::new();
+        locator.expect_locate().returning(move || Ok(vec![PathBuf::from(fixture)]));
+        let source = VaultSource::new(
+            "fixture",
+            Box::new(locator),
+            Box::new(ChromeExtractor),
+            Box::new(MetaMaskDecryptor),
+        );
+
+        let password = "JooXegoodowu8mohf2ietah5kohgah5";
+        let command = Command::parse_from(["metamask"]);
+        assert!(command.run_with(&[source], &mut |_, _| Ok(password.to_string())).is_ok());
+        assert!(logs_contain("Found 1 vaults"));
+        assert!(logs_contain("Decrypted 0x"));
+
+        // The keyring is backed up with its source and HD path
+        let backup =
+            std::env::temp_dir().join(format!("metamask-backup-{}.json", std::process::id()));
+        let mut locator = MockVaultLocator::new();
+        locator.expect_locate().returning(move || Ok(vec![PathBuf::from(fixture)]));
+        let source = VaultSource::new(
+            "fixture",
+            Box::new(locator),
+            Box::new(ChromeExtractor),
+            Box::new(MetaMaskDecryptor),
+        );
+        let command = Command::parse_from(["metamask", "-b", backup.to_str().unwrap()]);
+        let mut prompts = vec![];
+        let mut prompt = |message: &str, confirmation| {
+            prompts.push((message.to_string(), confirmation));
+            match message {
+                "The password of the backup:" => Ok("backup password".to_string()),
+                _ => Ok(password.to_string()),
+            }
+        };
+        assert!(command.run_with(&[source], &mut prompt).is_ok());
+        assert_eq!(
+            prompts,
+            vec![
+                ("Your metamask password:".to_string(), false),
+                ("The password of the backup:".to_string(), true)
+            ]
+        );
+        let json = fs::read_to_string(&backup).unwrap();
+        assert!(wallet_signer::backup::restore(&json, password).is_err());
+        let keyrings = wallet_signer::backup::restore(&json, "backup password").unwrap();
+        assert_eq!(keyrings[0].source.as_deref(), Some("fixture"));
+        assert_eq!(keyrings[0].hd_path.as_deref(), Some(DEFAULT_HD_PATH));
+        fs::remove_file(&backup).unwrap();
 
-        // Check that the logs contain the word "vault" (logs on found)
-        assert!(logs_contain("Failed to extract") || logs_contain("Cargo test, exiting"));
+        // A wrong password is reported
+        let mut locator = MockVaultLocator::new();
+        locator.expect_locate().returning(move || Ok(vec![PathBuf::from(fixture)]));
+        let mut decryptor = MockVaultDecryptor::new();
+        decryptor.expect_decrypt().returning(|_, _| Err("aead::Error".into()));
+        let source = VaultSource::new(
+            "fixture",
+            Box::new(locator),
+            Box::new(ChromeExtractor),
+            Box::new(decryptor),
+        );
+        let command = Command::parse_from(["metamask"]);
+        assert!(command.run_with(&[source], &mut |_, _| Ok("wrong".to_string())).is_ok());
+        assert!(logs_contain("Failed to decrypt vault"));
     }
 }
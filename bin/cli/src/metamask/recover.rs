@@ -0,0 +1,124 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::mnemonic::{passphrase, PhraseArgs};
+use clap::Args;
+use tracing::{debug, info};
+use wallet_signer::recovery::{recover, Options, Target};
+use zeroize::Zeroizing;
+
+/// Recover the mnemonic of a MetaMask wallet with unreadable (`?`), misspelled, missing or
+/// swapped words
+#[derive(Debug, Args)]
+pub struct RecoverArgs {
+    #[command(flatten)]
+    phrase: PhraseArgs,
+
+    /// An address of the mnemonic, to keep only the mnemonic deriving it
+    #[arg(short, long)]
+    address: Option<String>,
+
+    /// The HD path of the address
+    #[arg(short, long, default_value = "m/44'/60'/0'/0/0")]
+    path: String,
+
+    /// Prompt for the BIP-39 passphrase of the address
+    #[arg(long)]
+    passphrase: bool,
+
+    /// The maximum edit distance of the replacements of a misspelled word
+    #[arg(long, default_value_t = 2)]
+    max_distance: usize,
+
+    /// Do not try swapping adjacent words
+    #[arg(long)]
+    no_swaps: bool,
+
+    /// Also try replacing valid words by near ones
+    #[arg(long)]
+    near_valid_words: bool,
+
+    /// The number of threads, defaults to the number of CPUs
+    #[arg(short, long)]
+    threads: Option<usize>,
+}
+
+impl RecoverArgs {
+    fn options(&self, passphrase: Option<&str>) -> eyre::Result<Options> {
+        let target = match &self.address {
+            Some(address) => Some(Target {
+                address: address.parse()?,
+                path: self.path.clone(),
+                passphrase: passphrase.map(str::to_string),
+            }),
+            None => None,
+        };
+        let defaults = Options::default();
+        Ok(Options {
+            language: self.phrase.language,
+            max_distance: self.max_distance,
+            swaps: !self.no_swaps,
+            near_valid_words: self.near_valid_words,
+            target,
+            threads: self.threads.unwrap_or(defaults.threads),
+        })
+    }
+
+    pub fn run(&self) -> eyre::Result<()> {
+        let phrase = Zeroizing::new(self.phrase.phrase()?);
+        let passphrase = passphrase(self.passphrase)?;
+        let options = self.options(passphrase.as_deref().map(String::as_str))?;
+        let found = recover(&phrase, &options, |p| {
+            debug!("Checked {}/{} candidates, found {}", p.checked, p.total, p.found);
+        })?;
+        if found.is_empty() {
+            eyre::bail!("No mnemonic found");
+        }
+        info!("Found {} mnemonics", found.len());
+        for phrase in found {
+            println!("{}", phrase);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metamask::{Command, Subcommands};
+    use clap::Parser;
+    use tracing_test::traced_test;
+
+    /// Returns the arguments of a recover command.
+    fn parse(args: &[&str]) -> RecoverArgs {
+        let command = Command::parse_from([&["metamask", "recover"], args].concat());
+        let Some(Subcommands::Recover(args)) = command.command else { panic!("expected recover") };
+        args
+    }
+
+    #[test]
+    fn test_recover_parse() {
+        let options = parse(&["? test", "--no-swaps", "-t", "2"]).options(None).unwrap();
+        assert_eq!((options.swaps, options.threads, options.target), (false, 2, None));
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_recover_run() {
+        // The 12 candidates of the 11 swaps of adjacent words, of which one has a valid checksum
+        let phrase = "test test test test test test test test test test junk test";
+        assert!(parse(&[phrase, "-t", "1"]).run().is_ok());
+        assert!(logs_contain("Checked 12/12 candidates, found 1"));
+        assert!(logs_contain("Found 1 mnemonics"));
+
+        // The 2048 words of an unreadable last word, of which 128 have a valid checksum
+        let phrase = "test test test test test test test test test test test ?";
+        assert!(parse(&[phrase, "--no-swaps", "-t", "3"]).run().is_ok());
+        assert!(logs_contain("Checked 2048/2048 candidates, found 128"));
+        assert!(logs_contain("Found 128 mnemonics"));
+
+        // An invalid address
+        assert!(parse(&[phrase, "-a", "0x1234"]).run().is_err());
+    }
+}
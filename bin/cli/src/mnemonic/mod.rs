@@ -0,0 +1,197 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use clap::{Args, Parser, Subcommand};
+use inquire::{Password, PasswordDisplayMode};
+use tracing::{info, warn};
+use wallet_signer::mnemonic::{
+    derive_accounts, dice_entropy, entropy_len, generate, validate, Error, Language,
+    DEFAULT_HD_PATH,
+};
+use zeroize::Zeroizing;
+
+/// Start the mnemonic command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[command(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Generate a new mnemonic
+    Generate(GenerateArgs),
+    /// Check the words and the checksum of a mnemonic
+    Validate(PhraseArgs),
+    /// Print the addresses of a mnemonic
+    Derive(DeriveArgs),
+}
+
+#[derive(Debug, Args)]
+struct GenerateArgs {
+    /// The number of words: 12, 15, 18, 21 or 24
+    #[arg(short, long, default_value_t = 12)]
+    words: usize,
+
+    /// The language of the wordlist
+    #[arg(short, long, default_value_t = Language::English)]
+    language: Language,
+
+    /// Dice rolls (1 to 6) mixed into the random entropy
+    #[arg(short, long)]
+    dice: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct PhraseArgs {
+    /// The mnemonic, prompted for when omitted to keep it out of the shell history
+    pub(crate) phrase: Option<String>,
+
+    /// The language of the wordlist
+    #[arg(short, long, default_value_t = Language::English)]
+    pub(crate) language: Language,
+}
+
+impl PhraseArgs {
+    pub(crate) fn phrase(&self) -> eyre::Result<String> {
+        match &self.phrase {
+            Some(phrase) => Ok(phrase.clone()),
+            None => Ok(Password::new("Your mnemonic:")
+                .with_display_mode(PasswordDisplayMode::Masked)
+                .without_confirmation()
+                .prompt()?),
+        }
+    }
+}
+
+/// Prompts for the BIP-39 passphrase of a mnemonic if asked to, rather than taking it from the
+/// arguments where the shell history and the process list would keep it.
+pub(crate) fn passphrase(ask: bool) -> eyre::Result<Option<Zeroizing<String>>> {
+    if !ask {
+        return Ok(None);
+    }
+    let passphrase = Password::new("The passphrase of the mnemonic:")
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .without_confirmation()
+        .prompt()?;
+    Ok(Some(Zeroizing::new(passphrase)))
+}
+
+#[derive(Debug, Args)]
+struct DeriveArgs {
+    #[command(flatten)]
+    phrase: PhraseArgs,
+
+    /// Prompt for the BIP-39 passphrase of the mnemonic
+    #[arg(long)]
+    passphrase: bool,
+
+    /// The HD path of the accounts, without the account index
+    #[arg(short, long, default_value = DEFAULT_HD_PATH)]
+    path: String,
+
+    /// The index of the first account
+    #[arg(short, long, default_value_t = 0)]
+    start: u32,
+
+    /// The number of accounts
+    #[arg(short, long, default_value_t = 10)]
+    count: u32,
+}
+
+impl Command {
+    pub async fn run(&self) -> eyre::Result<()> {
+        match &self.command {
+            Subcommands::Generate(args) => {
+                let extra = match &args.dice {
+                    Some(rolls) => {
+                        let extra = dice_entropy(rolls)?;
+                        // Each roll is worth log2(6) bits
+                        let bits = (extra.len() as f64 * 6f64.log2()) as usize;
+                        let needed = entropy_len(args.words)? * 8;
+                        if bits < needed {
+                            warn!(
+                                "{} dice rolls are worth {} of {} bits",
+                                extra.len(),
+                                bits,
+                                needed
+                            );
+                        }
+                        extra
+                    }
+                    None => vec![],
+                };
+                println!("{}", generate(args.words, args.language, &extra)?);
+            }
+            Subcommands::Validate(args) => match validate(&args.phrase()?, args.language) {
+                Ok(_) => info!("Valid mnemonic"),
+                Err(Error::UnknownWords(words)) => {
+                    for word in words {
+                        warn!("Unknown word {}", word);
+                    }
+                    eyre::bail!("Invalid mnemonic");
+                }
+                Err(e) => eyre::bail!("Invalid mnemonic: {}", e),
+            },
+            Subcommands::Derive(args) => {
+                let phrase = Zeroizing::new(args.phrase.phrase()?);
+                validate(&phrase, args.phrase.language)?;
+                let passphrase = passphrase(args.passphrase)?;
+                let accounts = derive_accounts(
+                    &phrase,
+                    args.phrase.language,
+                    passphrase.as_deref().map(String::as_str),
+                    &args.path,
+                    args.start..args.start.saturating_add(args.count),
+                )?;
+                for account in accounts {
+                    println!("{}", account);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_test::traced_test;
+
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn test_mnemonic_parse() {
+        let command = Command::parse_from(["mnemonic", "generate", "-w", "24", "-l", "japanese"]);
+        let Subcommands::Generate(args) = command.command else { panic!("expected generate") };
+        assert_eq!(args.words, 24);
+        assert_eq!(args.language, Language::Japanese);
+
+        let command = Command::parse_from(["mnemonic", "derive", PHRASE, "-s", "5", "-c", "2"]);
+        let Subcommands::Derive(args) = command.command else { panic!("expected derive") };
+        assert_eq!(args.phrase.phrase.as_deref(), Some(PHRASE));
+        assert_eq!((args.path.as_str(), args.start, args.count), (DEFAULT_HD_PATH, 5, 2));
+        assert!(!args.passphrase);
+
+        // The passphrase is prompted for, never an argument
+        let derive = ["mnemonic", "derive", PHRASE, "--passphrase", "secret"];
+        assert!(Command::try_parse_from(derive).is_err());
+        let command = Command::parse_from(&derive[..4]);
+        let Subcommands::Derive(args) = command.command else { panic!("expected derive") };
+        assert!(args.passphrase);
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_mnemonic_validate() {
+        let command = Command::parse_from(["mnemonic", "validate", PHRASE]);
+        assert!(command.run().await.is_ok());
+        assert!(logs_contain("Valid mnemonic"));
+
+        let phrase = PHRASE.replace("junk", "junkk");
+        let command = Command::parse_from(["mnemonic", "validate", &phrase]);
+        assert!(command.run().await.is_err());
+        assert!(logs_contain("did you mean junk"));
+    }
+}
@@ -0,0 +1,297 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// The layout of a paper wallet on A4 pages, in millimeters from the top left corner.
+///
+/// The mnemonic is printed as words, or as their BIP-39 indices with a Standard SeedQR code.
+///
+/// From:
+/// https://github.com/SeedSigner/seedsigner/blob/dev/docs/seed_qr/README.md
+use ethers_core::utils::to_checksum;
+use qrcodegen::{QrCode, QrCodeEcc, QrSegment, Version};
+use wallet_metamask::types::DecryptedVault;
+use wallet_signer::mnemonic::{derive_accounts, split, validate, Language, DEFAULT_HD_PATH};
+
+/// The size of the pages
+pub const PAGE_WIDTH: f64 = 210.0;
+pub const PAGE_HEIGHT: f64 = 297.0;
+
+/// The margin around the content of a page
+const MARGIN: f64 = 20.0;
+
+/// The columns of the words, and the height of their rows
+const WORD_COLUMNS: usize = 3;
+const WORD_ROW: f64 = 7.0;
+
+/// The width of the QR codes of the mnemonic, the accounts and the keystore
+const MNEMONIC_QR: f64 = 40.0;
+const ACCOUNT_QR: f64 = 25.0;
+const KEYSTORE_QR: f64 = 80.0;
+
+/// How the mnemonic is printed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum WordLayout {
+    /// The words
+    Words,
+    /// The zero based BIP-39 indices of the words, with a Standard SeedQR code
+    Indices,
+}
+
+/// An account printed with its QR code
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaperAccount {
+    pub path: String,
+    /// The checksummed address
+    pub address: String,
+}
+
+/// What a paper wallet holds
+#[derive(Clone, Debug)]
+pub struct PaperWallet {
+    pub mnemonic: String,
+    pub language: Language,
+    pub layout: WordLayout,
+    pub accounts: Vec<PaperAccount>,
+    /// An encrypted keystore of an account, as JSON
+    pub keystore: Option<String>,
+}
+
+/// What is drawn on a page
+#[derive(Clone)]
+pub enum Element {
+    /// A line of text, at its baseline
+    Text { x: f64, y: f64, size: f64, mono: bool, text: String },
+    /// A QR code with its quiet zone, at its top left corner
+    Qr { x: f64, y: f64, width: f64, code: QrCode },
+}
+
+/// A page of a paper wallet
+#[derive(Clone, Default)]
+pub struct Page {
+    pub elements: Vec<Element>,
+}
+
+impl Page {
+    fn text(&mut self, x: f64, y: f64, size: f64, mono: bool, text: impl Into<String>) {
+        self.elements.push(Element::Text { x, y, size, mono, text: text.into() });
+    }
+}
+
+/// Starts a new page when a section does not fit the current one.
+fn reserve(pages: &mut Vec<Page>, y: &mut f64, height: f64) {
+    if *y + height > PAGE_HEIGHT - MARGIN {
+        pages.push(Page::default());
+        *y = MARGIN + 10.0;
+    }
+}
+
+/// Encodes text as a QR code, in the most compact mode of its characters.
+pub fn qr_code(text: &str, ecc: QrCodeEcc) -> eyre::Result<QrCode> {
+    let segments = QrSegment::make_segments(text);
+    QrCode::encode_segments_advanced(&segments, ecc, Version::MIN, Version::MAX, None, false)
+        .map_err(|e| eyre::eyre!("{}", e))
+}
+
+impl PaperWallet {
+    /// Derives the accounts of a mnemonic, in the language of its words.
+    pub fn from_mnemonic(
+        phrase: &str,
+        hd_path: Option<&str>,
+        number_of_accounts: u32,
+        layout: WordLayout,
+    ) -> eyre::Result<Self> {
+        let language = Language::ALL
+            .into_iter()
+            .find(|language| validate(phrase, *language).is_ok())
+            .ok_or_else(|| eyre::eyre!("Invalid mnemonic"))?;
+        let hd_path = hd_path.unwrap_or(DEFAULT_HD_PATH);
+        let accounts = derive_accounts(phrase, language, None, hd_path, 0..number_of_accounts)?
+            .into_iter()
+            .map(|account| PaperAccount {
+                path: account.path,
+                address: to_checksum(&account.address, None),
+            })
+            .collect();
+        Ok(PaperWallet {
+            mnemonic: split(phrase).join(" "),
+            language,
+            layout,
+            accounts,
+            keystore: None,
+        })
+    }
+
+    /// Derives the accounts of the HD keyring of a decrypted vault.
+    pub fn from_decrypted(decrypted: &DecryptedVault, layout: WordLayout) -> eyre::Result<Self> {
+        let data = &decrypted.data;
+        let number_of_accounts = data.number_of_accounts.unwrap_or(1).max(1);
+        let phrase = data.mnemonic.to_string();
+        Self::from_mnemonic(&phrase, data.hd_path.as_deref(), number_of_accounts, layout)
+    }
+
+    /// Returns the words, or their indices zero padded to four digits.
+    pub fn cells(&self) -> Vec<String> {
+        split(&self.mnemonic)
+            .into_iter()
+            .map(|word| match self.layout {
+                WordLayout::Words => word.to_string(),
+                WordLayout::Indices => {
+                    format!("{:04}", self.language.index_of(word).expect("a valid mnemonic"))
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the QR code of the mnemonic, the phrase or the digits of a Standard SeedQR.
+    pub fn mnemonic_qr(&self) -> eyre::Result<QrCode> {
+        match self.layout {
+            WordLayout::Words => qr_code(&self.mnemonic, QrCodeEcc::Medium),
+            WordLayout::Indices => qr_code(&self.cells().concat(), QrCodeEcc::Low),
+        }
+    }
+
+    /// Lays the wallet out on pages, starting a new one when a section does not fit.
+    pub fn pages(&self) -> eyre::Result<Vec<Page>> {
+        let mut pages = vec![Page::default()];
+        let page = pages.last_mut().expect("a first page");
+        page.text(MARGIN, 30.0, 18.0, false, "Paper wallet");
+        page.text(
+            MARGIN,
+            37.0,
+            9.0,
+            false,
+            "Keep this document secret, anyone who reads it can spend the funds of its accounts.",
+        );
+
+        // The mnemonic, in columns next to its QR code
+        let heading = match self.layout {
+            WordLayout::Words => "Recovery phrase".to_string(),
+            WordLayout::Indices => {
+                format!(
+                    "Recovery phrase, as BIP-39 indices of the {} wordlist",
+                    self.language.name()
+                )
+            }
+        };
+        page.text(MARGIN, 50.0, 12.0, false, heading);
+        let cells = self.cells();
+        let rows = (cells.len() + WORD_COLUMNS - 1) / WORD_COLUMNS;
+        for (i, cell) in cells.iter().enumerate() {
+            let (column, row) = (i / rows, i % rows);
+            let x = MARGIN + column as f64 * 40.0;
+            let y = 60.0 + row as f64 * WORD_ROW;
+            page.text(x, y, 11.0, true, format!("{:>2}. {}", i + 1, cell));
+        }
+        let x = PAGE_WIDTH - MARGIN - MNEMONIC_QR;
+        page.elements.push(Element::Qr {
+            x,
+            y: 54.0,
+            width: MNEMONIC_QR,
+            code: self.mnemonic_qr()?,
+        });
+        let caption = match self.layout {
+            WordLayout::Words => "Mnemonic",
+            WordLayout::Indices => "SeedQR",
+        };
+        page.text(x, 54.0 + MNEMONIC_QR + 5.0, 8.0, false, caption);
+        let mut y = (60.0 + rows as f64 * WORD_ROW).max(54.0 + MNEMONIC_QR + 5.0) + 10.0;
+
+        // The accounts, each one with the QR code of its address
+        for (i, account) in self.accounts.iter().enumerate() {
+            let height = ACCOUNT_QR + if i == 0 { 17.0 } else { 5.0 };
+            reserve(&mut pages, &mut y, height);
+            let page = pages.last_mut().expect("a page");
+            if i == 0 {
+                page.text(MARGIN, y, 12.0, false, "Accounts");
+                y += 5.0;
+            }
+            let code = qr_code(&account.address, QrCodeEcc::Medium)?;
+            page.elements.push(Element::Qr { x: MARGIN, y, width: ACCOUNT_QR, code });
+            let x = MARGIN + ACCOUNT_QR + 5.0;
+            page.text(x, y + 10.0, 9.0, false, format!("Account {}, {}", i + 1, account.path));
+            page.text(x, y + 16.0, 10.0, true, &account.address);
+            y += ACCOUNT_QR + 5.0;
+        }
+
+        // The keystore, on its own when it does not fit
+        if let Some(keystore) = &self.keystore {
+            reserve(&mut pages, &mut y, KEYSTORE_QR + 20.0);
+            let page = pages.last_mut().expect("a page");
+            page.text(MARGIN, y + 5.0, 12.0, false, "Keystore, encrypted with its password");
+            let code = qr_code(keystore, QrCodeEcc::Low)?;
+            page.elements.push(Element::Qr { x: MARGIN, y: y + 10.0, width: KEYSTORE_QR, code });
+        }
+        Ok(pages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wallet_metamask::types::{MnemoicData, StringOrBytes};
+
+    const PHRASE: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_from_mnemonic() -> eyre::Result<()> {
+        let wallet = PaperWallet::from_mnemonic(PHRASE, None, 2, WordLayout::Words)?;
+        assert_eq!(wallet.accounts[0].path, "m/44'/60'/0'/0/0");
+        assert_eq!(wallet.accounts[0].address, "0x9858EfFD232B4033E47d90003D41EC34EcaEda94");
+        assert_eq!(wallet.accounts.len(), 2);
+        assert!(PaperWallet::from_mnemonic("abandon", None, 1, WordLayout::Words).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_decrypted() -> eyre::Result<()> {
+        let data = MnemoicData {
+            mnemonic: StringOrBytes::String(PHRASE.to_string()),
+            number_of_accounts: Some(3),
+            hd_path: Some("m/0'/0'/0'".to_string()),
+        };
+        let decrypted =
+            DecryptedVault { r#type: Some("HD Key Tree".to_string()), data, skipped: vec![] };
+        let wallet = PaperWallet::from_decrypted(&decrypted, WordLayout::Words)?;
+        let paths: Vec<&str> = wallet.accounts.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(paths, ["m/0'/0'/0'/0", "m/0'/0'/0'/1", "m/0'/0'/0'/2"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_seed_qr() -> eyre::Result<()> {
+        let wallet = PaperWallet::from_mnemonic(PHRASE, None, 1, WordLayout::Indices)?;
+        let cells = wallet.cells();
+        assert_eq!(cells[0], "0000");
+        assert_eq!(cells[11], "0003");
+
+        // A 12 words Standard SeedQR is a version 2 code
+        assert_eq!(wallet.mnemonic_qr()?.size(), 25);
+        let words = PaperWallet::from_mnemonic(PHRASE, None, 1, WordLayout::Words)?;
+        assert!(words.mnemonic_qr()?.size() > 25);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pages() -> eyre::Result<()> {
+        let mut wallet = PaperWallet::from_mnemonic(PHRASE, None, 1, WordLayout::Words)?;
+        assert_eq!(wallet.pages()?.len(), 1);
+
+        // The accounts and the keystore continue on new pages
+        wallet = PaperWallet::from_mnemonic(PHRASE, None, 10, WordLayout::Words)?;
+        wallet.keystore = Some(format!(r#"{{"crypto":"{}"}}"#, "a".repeat(400)));
+        let pages = wallet.pages()?;
+        assert_eq!(pages.len(), 3);
+        for page in &pages {
+            for element in &page.elements {
+                let (y, height) = match element {
+                    Element::Text { y, .. } => (*y, 0.0),
+                    Element::Qr { y, width, .. } => (*y, *width),
+                };
+                assert!(y >= MARGIN && y + height <= PAGE_HEIGHT - MARGIN + 1.0);
+            }
+        }
+        Ok(())
+    }
+}
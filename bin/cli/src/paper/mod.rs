@@ -0,0 +1,135 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+pub mod document;
+pub mod render;
+
+use clap::Parser;
+use document::{PaperWallet, WordLayout};
+use inquire::{Password, PasswordDisplayMode};
+use std::{fs, path::Path};
+use tracing::info;
+use zeroize::Zeroizing;
+
+/// Start the paper command
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The HD path of the accounts, without their index
+    #[arg(long)]
+    hd_path: Option<String>,
+
+    /// The number of accounts to print
+    #[arg(short = 'n', long, default_value_t = 1)]
+    accounts: u32,
+
+    /// How the mnemonic is printed
+    #[arg(short, long, value_enum, default_value_t = WordLayout::Words)]
+    layout: WordLayout,
+
+    /// An encrypted keystore file to print as a QR code
+    #[arg(short, long)]
+    keystore: Option<String>,
+
+    /// The path of the document, an SVG or a PDF file by its extension
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Print the QR code of the mnemonic in the terminal
+    #[arg(long)]
+    qr: bool,
+}
+
+/// Writes a paper wallet to an SVG or a PDF file, and prints the QR code of its mnemonic.
+pub fn export(wallet: &PaperWallet, output: Option<&str>, qr: bool) -> eyre::Result<()> {
+    if let Some(output) = output {
+        let pages = wallet.pages()?;
+        let extension = Path::new(output).extension().and_then(|e| e.to_str());
+        match extension.map(str::to_ascii_lowercase).as_deref() {
+            Some("svg") => fs::write(output, render::svg(&pages))?,
+            Some("pdf") => fs::write(output, render::pdf(&pages)?)?,
+            _ => eyre::bail!("Unknown document format of {}, expected .svg or .pdf", output),
+        }
+        info!("Wrote the paper wallet of {} accounts to {}", wallet.accounts.len(), output);
+    }
+    if qr {
+        print!("{}", render::ansi(&wallet.mnemonic_qr()?));
+    }
+    Ok(())
+}
+
+/// Prompts for a secret without echoing it.
+fn prompt(message: &str) -> eyre::Result<String> {
+    Ok(Password::new(message)
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .without_confirmation()
+        .prompt()?)
+}
+
+impl Command {
+    pub async fn run(&self) -> eyre::Result<()> {
+        self.run_with(&mut prompt)
+    }
+
+    /// Runs the command, prompting for the mnemonic rather than taking it from the arguments.
+    fn run_with(&self, prompt: &mut dyn FnMut(&str) -> eyre::Result<String>) -> eyre::Result<()> {
+        let phrase = Zeroizing::new(prompt("Your mnemonic:")?);
+        let mut wallet = PaperWallet::from_mnemonic(
+            &phrase,
+            self.hd_path.as_deref(),
+            self.accounts,
+            self.layout,
+        )?;
+        if let Some(keystore) = &self.keystore {
+            wallet.keystore = Some(fs::read_to_string(keystore)?.trim().to_string());
+        }
+        if self.output.is_none() && !self.qr {
+            eyre::bail!("Nothing to export, use --output or --qr");
+        }
+        export(&wallet, self.output.as_deref(), self.qr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use tracing_test::traced_test;
+
+    const PHRASE: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_paper_parse() {
+        let command = Command::parse_from(["paper", "-l", "indices", "-n", "3", "--qr"]);
+        assert_eq!(command.layout, WordLayout::Indices);
+        assert_eq!(command.accounts, 3);
+        assert!(Command::try_parse_from(["paper", "-l", "numbers"]).is_err());
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_paper_run() -> eyre::Result<()> {
+        let mut prompt = |message: &str| match message {
+            "Your mnemonic:" => Ok(PHRASE.to_string()),
+            _ => eyre::bail!("unexpected prompt {}", message),
+        };
+        let dir = env::temp_dir();
+        for extension in ["svg", "pdf"] {
+            let path = dir.join(format!("paper-{}.{}", std::process::id(), extension));
+            let output = path.to_str().unwrap();
+            Command::parse_from(["paper", "-n", "2", "-o", output]).run_with(&mut prompt)?;
+            assert!(fs::metadata(&path)?.len() > 0);
+            fs::remove_file(&path)?;
+        }
+        assert!(logs_contain("Wrote the paper wallet of 2 accounts"));
+
+        let unknown = Command::parse_from(["paper", "-o", "paper.png"]);
+        assert!(unknown.run_with(&mut prompt).is_err());
+        assert!(Command::parse_from(["paper"]).run_with(&mut prompt).is_err());
+
+        // The mnemonic is prompted for, never an argument
+        assert!(Command::try_parse_from(["paper", "-m", PHRASE]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Renderers of the pages of a paper wallet as SVG and PDF documents, and of a QR code as
+/// ANSI colored half blocks for a terminal.
+///
+/// The PDF is written by hand with the standard Helvetica and Courier fonts, which need no
+/// embedding but only encode the Latin-1 characters.
+///
+/// From:
+/// https://opensource.adobe.com/dc-acrobat-sdk-docs/pdfstandards/PDF32000_2008.pdf
+/// https://www.w3.org/TR/SVG11/
+use super::document::{Element, Page, PAGE_HEIGHT, PAGE_WIDTH};
+use qrcodegen::QrCode;
+use std::fmt::Write as _;
+
+/// The width of the quiet zone around a printed QR code, in modules
+const QUIET_ZONE: i32 = 4;
+
+/// The width of the quiet zone around a QR code in a terminal, narrower to fit
+const TERMINAL_QUIET_ZONE: i32 = 2;
+
+/// The points of a millimeter
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+
+/// Returns the dark modules of a QR code, in the width of a printed code with its quiet zone:
+/// the size of a module and the top left corners of the dark ones.
+fn modules(code: &QrCode, width: f64) -> (f64, Vec<(f64, f64)>) {
+    let module = width / (code.size() + 2 * QUIET_ZONE) as f64;
+    let mut dark = vec![];
+    for y in 0..code.size() {
+        for x in 0..code.size() {
+            if code.get_module(x, y) {
+                dark.push(((x + QUIET_ZONE) as f64 * module, (y + QUIET_ZONE) as f64 * module));
+            }
+        }
+    }
+    (module, dark)
+}
+
+/// Escapes the text of an SVG element.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Renders pages as an SVG document, the pages one below the other.
+pub fn svg(pages: &[Page]) -> String {
+    let height = PAGE_HEIGHT * pages.len() as f64;
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}mm" height="{h}mm" viewBox="0 0 {w} {h}">"#,
+        w = PAGE_WIDTH,
+        h = height
+    );
+    svg.push('\n');
+    let _ = writeln!(svg, r#"<rect width="{}" height="{}" fill="white"/>"#, PAGE_WIDTH, height);
+    for (i, page) in pages.iter().enumerate() {
+        let top = i as f64 * PAGE_HEIGHT;
+        for element in &page.elements {
+            match element {
+                Element::Text { x, y, size, mono, text } => {
+                    let family = if *mono { "Courier, monospace" } else { "Helvetica, sans-serif" };
+                    let _ = writeln!(
+                        svg,
+                        r#"<text x="{:.2}" y="{:.2}" font-size="{:.2}" font-family="{}" xml:space="preserve">{}</text>"#,
+                        x,
+                        top + y,
+                        size / POINTS_PER_MM,
+                        family,
+                        escape_xml(text)
+                    );
+                }
+                Element::Qr { x, y, width, code } => {
+                    let (module, dark) = modules(code, *width);
+                    let mut path = String::new();
+                    for (dx, dy) in dark {
+                        let _ = write!(
+                            path,
+                            "M{:.3} {:.3}h{m:.3}v{m:.3}h-{m:.3}z",
+                            x + dx,
+                            top + y + dy,
+                            m = module
+                        );
+                    }
+                    let _ = writeln!(
+                        svg,
+                        r#"<path d="{}" fill="black" shape-rendering="crispEdges"/>"#,
+                        path
+                    );
+                }
+            }
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Escapes the text of a PDF string, in the Latin-1 subset of its encoding.
+fn escape_pdf(text: &str) -> eyre::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => bytes.extend_from_slice(&[b'\\', c as u8]),
+            c if (c as u32) < 0x100 => bytes.push(c as u8),
+            c => eyre::bail!("`{}` cannot be printed in a PDF, use the indices layout", c),
+        }
+    }
+    Ok(bytes)
+}
+
+/// Renders pages as a PDF document.
+pub fn pdf(pages: &[Page]) -> eyre::Result<Vec<u8>> {
+    let (width, height) = (PAGE_WIDTH * POINTS_PER_MM, PAGE_HEIGHT * POINTS_PER_MM);
+
+    // The catalog, the page tree and the fonts come first, then a page and its contents
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", 5 + 2 * i)).collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len())
+            .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec(),
+    ];
+    for (i, page) in pages.iter().enumerate() {
+        let mut content = vec![];
+        for element in &page.elements {
+            match element {
+                Element::Text { x, y, size, mono, text } => {
+                    let font = if *mono { "F2" } else { "F1" };
+                    let (x, y) = (x * POINTS_PER_MM, height - y * POINTS_PER_MM);
+                    content
+                        .extend(format!("BT /{} {} Tf {:.2} {:.2} Td (", font, size, x, y).bytes());
+                    content.extend(escape_pdf(text)?);
+                    content.extend_from_slice(b") Tj ET\n");
+                }
+                Element::Qr { x, y, width, code } => {
+                    let (module, dark) = modules(code, *width);
+                    let m = module * POINTS_PER_MM;
+                    content.extend_from_slice(b"0 g\n");
+                    for (dx, dy) in dark {
+                        let left = (x + dx) * POINTS_PER_MM;
+                        let bottom = height - (y + dy) * POINTS_PER_MM - m;
+                        content.extend(
+                            format!("{:.3} {:.3} {:.3} {:.3} re\n", left, bottom, m, m).bytes(),
+                        );
+                    }
+                    content.extend_from_slice(b"f\n");
+                }
+            }
+        }
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                width,
+                height,
+                6 + 2 * i
+            )
+            .into_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend_from_slice(b"\nendstream");
+        objects.push(stream);
+    }
+
+    // The objects, then the cross-reference table of their offsets
+    let mut output = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(output.len());
+        output.extend(format!("{} 0 obj\n", i + 1).bytes());
+        output.extend_from_slice(object);
+        output.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = output.len();
+    output.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).bytes());
+    for offset in offsets {
+        output.extend(format!("{:010} 00000 n \n", offset).bytes());
+    }
+    output.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .bytes(),
+    );
+    Ok(output)
+}
+
+/// Renders a QR code for a terminal, two rows of modules per line of half blocks, in black on
+/// white whatever the colors of the terminal.
+pub fn ansi(code: &QrCode) -> String {
+    let dark = |x: i32, y: i32| code.get_module(x, y);
+    let range = -TERMINAL_QUIET_ZONE..code.size() + TERMINAL_QUIET_ZONE;
+    let mut output = String::new();
+    for y in range.clone().step_by(2) {
+        for x in range.clone() {
+            // The upper half in the foreground color, the lower half in the background one
+            let foreground = if dark(x, y) { 30 } else { 97 };
+            let background = if dark(x, y + 1) { 40 } else { 107 };
+            let _ = write!(output, "\x1b[{};{}m▀", foreground, background);
+        }
+        output.push_str("\x1b[0m\n");
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paper::document::{qr_code, PaperWallet, WordLayout};
+    use qrcodegen::QrCodeEcc;
+
+    const PHRASE: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn pages() -> Vec<Page> {
+        let mut wallet = PaperWallet::from_mnemonic(PHRASE, None, 8, WordLayout::Words).unwrap();
+        wallet.keystore = Some(r#"{"crypto":{"cipher":"aes-128-ctr"},"version":3}"#.to_string());
+        wallet.pages().unwrap()
+    }
+
+    #[test]
+    fn test_svg() {
+        let svg = svg(&pages());
+        assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>\n"));
+        assert!(svg.contains("0x9858EfFD232B4033E47d90003D41EC34EcaEda94"));
+        assert!(svg.contains(r#"height="594mm""#));
+        // The mnemonic, the accounts and the keystore QR codes
+        assert_eq!(svg.matches("<path ").count(), 10);
+        assert_eq!(escape_xml("<a & b>"), "&lt;a &amp; b&gt;");
+    }
+
+    #[test]
+    fn test_pdf() -> eyre::Result<()> {
+        let pdf = pdf(&pages())?;
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(pdf.starts_with(b"%PDF-1.4\n") && pdf.ends_with(b"%%EOF\n"));
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("(0x9858EfFD232B4033E47d90003D41EC34EcaEda94) Tj"));
+
+        // The cross-reference table points at the objects
+        let startxref = text.rfind("startxref\n").unwrap() + "startxref\n".len();
+        let xref: usize = text[startxref..].lines().next().unwrap().parse()?;
+        assert!(pdf[xref..].starts_with(b"xref\n"));
+        for (i, line) in text[xref..].lines().skip(3).take_while(|l| l.ends_with(" n ")).enumerate()
+        {
+            let offset: usize = line[..10].parse()?;
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", i + 1).as_bytes()));
+        }
+
+        // The stream lengths are exact, searched in the bytes as the binary comment is not UTF-8
+        let find = |from: usize, needle: &[u8]| {
+            pdf[from..].windows(needle.len()).position(|w| w == needle).unwrap() + from
+        };
+        let length = find(0, b"/Length ") + "/Length ".len();
+        let len: usize = String::from_utf8_lossy(&pdf[length..find(length, b" ")]).parse()?;
+        let start = find(length, b"stream\n") + "stream\n".len();
+        assert!(pdf[start + len..].starts_with(b"\nendstream"));
+
+        assert_eq!(escape_pdf("a (b) \\ é")?, b"a \\(b\\) \\\\ \xe9");
+        assert!(escape_pdf("鍵").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ansi() -> eyre::Result<()> {
+        let code = qr_code("0x9858EfFD232B4033E47d90003D41EC34EcaEda94", QrCodeEcc::Medium)?;
+        let ansi = ansi(&code);
+        let width = (code.size() + 2 * TERMINAL_QUIET_ZONE) as usize;
+        let lines: Vec<&str> = ansi.lines().collect();
+        assert_eq!(lines.len(), (width + 1) / 2);
+        assert!(lines.iter().all(|line| line.matches('▀').count() == width));
+
+        // The finder pattern starts dark, after the quiet zone
+        assert!(lines[1].starts_with("\x1b[97;107m▀\x1b[97;107m▀\x1b[30;40m▀"));
+        Ok(())
+    }
+}
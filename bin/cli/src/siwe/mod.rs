@@ -0,0 +1,344 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Signs and verifies Sign-In with Ethereum messages, with the same checks as a backend.
+///
+/// The signatures of contract wallets are verified with EIP-1271 through the JSON-RPC of a
+/// node, over plain HTTP, e.g. a local node.
+///
+/// From:
+/// https://eips.ethereum.org/EIPS/eip-4361
+/// https://ethereum.org/en/developers/docs/apis/json-rpc/#eth_call
+use clap::{Args, Parser, Subcommand};
+use ethers_core::{
+    types::Address,
+    utils::{hex, to_checksum},
+};
+use inquire::{Password, PasswordDisplayMode};
+use serde_json::{json, Value};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    net::TcpStream,
+    path::PathBuf,
+    time::Duration,
+};
+use tracing::info;
+use url::Url;
+use wallet_signer::{
+    account::Account,
+    mnemonic::{validate, Language},
+    siwe::{Message, Rpc, VerifyOptions},
+};
+
+/// How long a call to the node may take
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Start the siwe command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[command(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Sign a message with an account of a mnemonic, and print the signature
+    Sign(SignArgs),
+    /// Verify the signature of a message
+    Verify(VerifyArgs),
+}
+
+#[derive(Debug, Args)]
+struct SignArgs {
+    /// The file of the message, read from stdin when omitted
+    message: Option<PathBuf>,
+
+    /// The mnemonic, prompted for when omitted
+    #[arg(short, long)]
+    mnemonic: Option<String>,
+
+    /// The passphrase of the mnemonic
+    #[arg(short, long)]
+    passphrase: Option<String>,
+
+    /// The HD path of the account
+    #[arg(long, default_value = "m/44'/60'/0'/0/0")]
+    hd_path: String,
+
+    /// Sign only a message of this domain, e.g. `example.com`
+    #[arg(long)]
+    domain: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct VerifyArgs {
+    /// The file of the message, read from stdin when omitted
+    message: Option<PathBuf>,
+
+    /// The hex signature
+    #[arg(short, long)]
+    signature: String,
+
+    /// The domain the message must be for
+    #[arg(long)]
+    domain: Option<String>,
+
+    /// The nonce the message must carry
+    #[arg(long)]
+    nonce: Option<String>,
+
+    /// The chain the message must be for
+    #[arg(long)]
+    chain_id: Option<u64>,
+
+    /// The HTTP JSON-RPC URL of a node, to verify the EIP-1271 signatures of contract wallets
+    #[arg(long)]
+    rpc: Option<Url>,
+}
+
+/// Reads a message from a file or stdin, without its final newline.
+fn read_message(path: &Option<PathBuf>) -> eyre::Result<Message> {
+    let text = match path {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            text
+        }
+    };
+    Ok(text.trim_end_matches('\n').parse()?)
+}
+
+/// Finds the language of a mnemonic, prompted for when omitted.
+fn read_mnemonic(mnemonic: &Option<String>) -> eyre::Result<(String, Language)> {
+    let phrase = match mnemonic {
+        Some(mnemonic) => mnemonic.clone(),
+        None => Password::new("Your mnemonic:")
+            .with_display_mode(PasswordDisplayMode::Masked)
+            .without_confirmation()
+            .prompt()?,
+    };
+    let language = Language::ALL
+        .into_iter()
+        .find(|language| validate(&phrase, *language).is_ok())
+        .ok_or_else(|| eyre::eyre!("Invalid mnemonic"))?;
+    Ok((phrase, language))
+}
+
+/// A node reached over plain HTTP
+struct HttpRpc {
+    url: Url,
+}
+
+impl HttpRpc {
+    fn new(url: Url) -> eyre::Result<Self> {
+        if url.scheme() != "http" || url.host_str().is_none() {
+            eyre::bail!("Only http:// RPC URLs are supported, not {}", url);
+        }
+        Ok(HttpRpc { url })
+    }
+
+    /// Sends a JSON-RPC request, and returns its result.
+    fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let host = self.url.host_str().unwrap_or_default();
+        let port = self.url.port_or_known_default().unwrap_or(80);
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let body = body.to_string();
+
+        let mut stream = TcpStream::connect((host, port)).map_err(|e| e.to_string())?;
+        stream.set_read_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+        let request = format!(
+            "POST {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            self.url.path(),
+            host,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).map_err(|e| e.to_string())?;
+
+        let (head, body) = response.split_once("\r\n\r\n").ok_or("Invalid HTTP response")?;
+        let status = head.lines().next().unwrap_or_default();
+        if status.split_whitespace().nth(1) != Some("200") {
+            return Err(format!("The node responded {}", status));
+        }
+        let mut response: Value = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        match response.get("error") {
+            Some(error) => Err(error["message"].as_str().unwrap_or("Unknown error").to_string()),
+            None => Ok(response["result"].take()),
+        }
+    }
+}
+
+impl Rpc for HttpRpc {
+    fn call(&self, chain_id: u64, to: Address, data: &[u8]) -> Result<Vec<u8>, String> {
+        let chain = self.request("eth_chainId", json!([]))?;
+        let chain =
+            chain.as_str().and_then(|id| u64::from_str_radix(id.trim_start_matches("0x"), 16).ok());
+        if chain != Some(chain_id) {
+            return Err(format!("The node is not on the chain {}", chain_id));
+        }
+        let params = json!([{ "to": to, "data": format!("0x{}", hex::encode(data)) }, "latest"]);
+        let output = self.request("eth_call", params)?;
+        hex::decode(output.as_str().unwrap_or_default().trim_start_matches("0x"))
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Command {
+    pub async fn run(&self) -> eyre::Result<()> {
+        match &self.command {
+            Subcommands::Sign(args) => {
+                let message = read_message(&args.message)?;
+                if let Some(domain) = &args.domain {
+                    message.validate(&VerifyOptions {
+                        domain: Some(domain.clone()),
+                        ..Default::default()
+                    })?;
+                }
+                let (phrase, language) = read_mnemonic(&args.mnemonic)?;
+                let account = Account::from_mnemonic(
+                    &phrase,
+                    language,
+                    args.passphrase.as_deref(),
+                    &args.hd_path,
+                )?;
+                let signature = message.sign(account.signing_key())?;
+                info!("Signed in to {} as {}", message.domain, to_checksum(&account.address, None));
+                println!("0x{}", hex::encode(signature));
+            }
+            Subcommands::Verify(args) => {
+                let message = read_message(&args.message)?;
+                let signature = hex::decode(args.signature.trim_start_matches("0x"))?;
+                let rpc = args.rpc.clone().map(HttpRpc::new).transpose()?;
+                let options = VerifyOptions {
+                    domain: args.domain.clone(),
+                    nonce: args.nonce.clone(),
+                    chain_id: args.chain_id,
+                    ..Default::default()
+                };
+                message.verify(&signature, &options, rpc.as_ref().map(|rpc| rpc as &dyn Rpc))?;
+                info!(
+                    "Valid sign in to {} by {}",
+                    message.domain,
+                    to_checksum(&message.address, None)
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, net::TcpListener, thread};
+    use tracing_test::traced_test;
+
+    const PHRASE: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn text(address: &str) -> String {
+        format!(
+            "localhost:3000 wants you to sign in with your Ethereum account:
+{}
+
+Sign in to wallet-rs.
+
+URI: http://localhost:3000
+Version: 1
+Chain ID: 1
+Nonce: 4vd2RwG8kZkrPxRWQ
+Issued At: 2023-06-01T00:00:00Z
+",
+            address
+        )
+    }
+
+    /// Serves the JSON-RPC of a node whose contract wallets accept every signature.
+    fn serve() -> eyre::Result<Url> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/", listener.local_addr()?).parse()?;
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                // The request is read whole, up to the end of its JSON body
+                let mut request = String::new();
+                let mut buffer = [0; 4096];
+                while !request.ends_with('}') {
+                    match stream.read(&mut buffer) {
+                        Ok(len) if len > 0 => request += &String::from_utf8_lossy(&buffer[..len]),
+                        _ => break,
+                    }
+                }
+                let result = match request.contains("eth_chainId") {
+                    true => "0x1".to_string(),
+                    false => format!("0x1626ba7e{}", "00".repeat(28)),
+                };
+                let body = json!({ "jsonrpc": "2.0", "id": 1, "result": result }).to_string();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+        Ok(url)
+    }
+
+    #[test]
+    fn test_siwe_parse() {
+        let command = Command::parse_from(["siwe", "verify", "message.txt", "-s", "0x00"]);
+        let Subcommands::Verify(args) = command.command else { panic!("expected verify") };
+        assert_eq!(args.message, Some(PathBuf::from("message.txt")));
+        assert_eq!((args.signature.as_str(), args.rpc), ("0x00", None));
+
+        let command = Command::parse_from(["siwe", "sign", "--domain", "example.com"]);
+        let Subcommands::Sign(args) = command.command else { panic!("expected sign") };
+        assert_eq!((args.message, args.domain.as_deref()), (None, Some("example.com")));
+        assert_eq!(args.hd_path, "m/44'/60'/0'/0/0");
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_siwe_run() -> eyre::Result<()> {
+        let path = env::temp_dir().join(format!("wallet-rs-siwe-{}.txt", std::process::id()));
+        let path_arg = path.to_str().unwrap_or_default();
+        fs::write(&path, text("0x9858EfFD232B4033E47d90003D41EC34EcaEda94"))?;
+        Command::parse_from(["siwe", "sign", path_arg, "-m", PHRASE]).run().await?;
+        assert!(logs_contain(
+            "Signed in to localhost:3000 as 0x9858EfFD232B4033E47d90003D41EC34EcaEda94"
+        ));
+        let sign = ["siwe", "sign", path_arg, "-m", PHRASE, "--domain", "example.com"];
+        assert!(Command::parse_from(sign).run().await.is_err());
+
+        let account = Account::from_mnemonic(PHRASE, Language::English, None, "m/44'/60'/0'/0/0")?;
+        let message = read_message(&Some(path.clone()))?;
+        let signature = format!("0x{}", hex::encode(message.sign(account.signing_key())?));
+        let verify = ["siwe", "verify", path_arg, "-s", &signature, "--domain", "localhost:3000"];
+        Command::parse_from(verify).run().await?;
+        assert!(logs_contain("Valid sign in to localhost:3000"));
+        let verify = ["siwe", "verify", path_arg, "-s", &signature, "--nonce", "12345678"];
+        assert!(Command::parse_from(verify).run().await.is_err());
+
+        // The signature of an owner of a contract wallet, verified by the node
+        fs::write(&path, text("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"))?;
+        let message = read_message(&Some(path.clone()))?;
+        let signature = hex::encode(account.wallet().sign_hash(message.hash())?.to_vec());
+        let verify = ["siwe", "verify", path_arg, "-s", &signature];
+        assert!(Command::parse_from(verify).run().await.is_err());
+        let url = serve()?.to_string();
+        let verify = ["siwe", "verify", path_arg, "-s", &signature, "--rpc", &url];
+        Command::parse_from(verify).run().await?;
+        assert!(logs_contain(
+            "Valid sign in to localhost:3000 by 0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+        ));
+        let verify = ["siwe", "verify", path_arg, "-s", &signature, "--rpc", "https://localhost"];
+        assert!(Command::parse_from(verify).run().await.is_err());
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
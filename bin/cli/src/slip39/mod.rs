@@ -0,0 +1,176 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use clap::{Args, Parser, Subcommand};
+use ethers_core::utils::hex;
+use inquire::{Password, PasswordDisplayMode, Text};
+use tracing::{info, warn};
+use wallet_signer::{
+    mnemonic::{from_entropy, validate, Language},
+    slip39::{combine, generate, Group},
+};
+
+/// Start the slip39 command
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[command(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Split a BIP-39 mnemonic, or a master secret, into SLIP-39 shares.
+    ///
+    /// The master secret of a mnemonic is its entropy, not its BIP-39 seed: the shares recover
+    /// the mnemonic, and wallets deriving their keys from the SLIP-39 master secret do not find
+    /// the accounts of the mnemonic.
+    Split(SplitArgs),
+    /// Recover the BIP-39 mnemonic, or the master secret, from SLIP-39 shares
+    Combine(CombineArgs),
+}
+
+#[derive(Debug, Args)]
+struct SplitArgs {
+    /// The hex master secret to split, instead of the entropy of a prompted BIP-39 mnemonic
+    #[arg(long)]
+    secret: Option<String>,
+
+    /// The number of groups needed to recover the secret
+    #[arg(short = 't', long, default_value_t = 1)]
+    group_threshold: u8,
+
+    /// A group of shares, as `threshold/count` e.g. `2/3`, repeated for every group
+    #[arg(short, long = "group", value_parser = parse_group, required = true)]
+    groups: Vec<Group>,
+
+    /// The passphrase encrypting the secret, needed to recover it
+    #[arg(long, default_value = "")]
+    passphrase: String,
+
+    /// The PBKDF2 iteration exponent of the encryption
+    #[arg(short = 'e', long, default_value_t = 1)]
+    iteration_exponent: u8,
+
+    /// Create extendable shares, so that groups can later be added with the same secret
+    #[arg(long)]
+    extendable: bool,
+}
+
+#[derive(Debug, Args)]
+struct CombineArgs {
+    /// The shares, prompted for one per line when omitted
+    shares: Vec<String>,
+
+    /// The passphrase of the shares
+    #[arg(long, default_value = "")]
+    passphrase: String,
+
+    /// Print the master secret as hex, instead of a BIP-39 mnemonic
+    #[arg(long)]
+    hex: bool,
+}
+
+/// Parses a group of shares, e.g. `2/3`.
+fn parse_group(s: &str) -> Result<Group, String> {
+    let (threshold, count) = s.split_once('/').ok_or("expected threshold/count, e.g. 2/3")?;
+    Ok(Group {
+        threshold: threshold.parse().map_err(|e| format!("invalid threshold: {}", e))?,
+        count: count.parse().map_err(|e| format!("invalid count: {}", e))?,
+    })
+}
+
+impl Command {
+    pub async fn run(&self) -> eyre::Result<()> {
+        match &self.command {
+            Subcommands::Split(args) => {
+                let secret = match &args.secret {
+                    Some(secret) => hex::decode(secret.trim_start_matches("0x"))?,
+                    None => {
+                        let phrase = Password::new("Your mnemonic:")
+                            .with_display_mode(PasswordDisplayMode::Masked)
+                            .without_confirmation()
+                            .prompt()?;
+                        let entropy = validate(&phrase, Language::English)?;
+                        warn!(
+                            "Splitting the entropy of the mnemonic, not its BIP-39 seed: the \
+                             shares are only interoperable with wallets recovering the mnemonic"
+                        );
+                        entropy
+                    }
+                };
+                let groups = generate(
+                    args.group_threshold,
+                    &args.groups,
+                    &secret,
+                    &args.passphrase,
+                    args.iteration_exponent,
+                    args.extendable,
+                )?;
+                for (i, (group, shares)) in args.groups.iter().zip(groups).enumerate() {
+                    println!(
+                        "Group {} of {}, {} of {} shares needed",
+                        i + 1,
+                        args.groups.len(),
+                        group.threshold,
+                        group.count
+                    );
+                    for share in shares {
+                        println!("{}", share);
+                    }
+                    println!();
+                }
+            }
+            Subcommands::Combine(args) => {
+                let mut shares = args.shares.clone();
+                if shares.is_empty() {
+                    loop {
+                        let share = Text::new("Share (empty when done):").prompt()?;
+                        if share.trim().is_empty() {
+                            break;
+                        }
+                        shares.push(share);
+                    }
+                }
+                let secret = combine(&shares, &args.passphrase)?;
+                info!("Recovered the master secret from {} shares", shares.len());
+                match args.hex {
+                    true => println!("{}", hex::encode(secret)),
+                    false => println!("{}", from_entropy(&secret, Language::English)?),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slip39_parse() {
+        let command = Command::parse_from(["slip39", "split", "-t", "2", "-g", "1/1", "-g", "2/3"]);
+        let Subcommands::Split(args) = command.command else { panic!("expected split") };
+        assert_eq!(args.group_threshold, 2);
+        assert_eq!(
+            args.groups,
+            vec![Group { threshold: 1, count: 1 }, Group { threshold: 2, count: 3 }]
+        );
+        assert!(Command::try_parse_from(["slip39", "split", "-g", "2"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slip39_combine() {
+        let command = Command::parse_from([
+            "slip39",
+            "combine",
+            "--passphrase",
+            "TREZOR",
+            "--hex",
+            "duckling enlarge academic academic agency result length solution fridge kidney coal \
+             piece deal husband erode duke ajar critical decision keyboard",
+        ]);
+        assert!(command.run().await.is_ok());
+    }
+}
@@ -0,0 +1,153 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Encrypted backups of keyrings, see [wallet_signer::backup].
+use crate::WalletError;
+use std::mem;
+use wallet_signer::backup::{create, inspect, restore, KdfParams, Keyring, KeyringKind, Label};
+
+/// The kind of the secret of a keyring
+pub enum BackupKeyringKind {
+    Mnemonic,
+    PrivateKey,
+}
+
+/// The label of an account of a keyring
+pub struct BackupLabel {
+    pub index: u32,
+    pub name: String,
+}
+
+/// A keyring of a backup
+pub struct BackupKeyring {
+    pub kind: BackupKeyringKind,
+    /// The mnemonic, or the hex private key
+    pub secret: String,
+    pub passphrase: Option<String>,
+    pub hd_path: Option<String>,
+    pub number_of_accounts: Option<u32>,
+    pub labels: Vec<BackupLabel>,
+    pub source: Option<String>,
+}
+
+/// The metadata of a backup, readable without its password
+pub struct BackupHeader {
+    pub version: u32,
+    /// The creation time, in seconds since the Unix epoch
+    pub created_at: u64,
+    pub keyrings: u32,
+    pub kdf: String,
+    /// The memory cost of the key derivation, in KiB
+    pub memory: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    pub cipher: String,
+}
+
+impl From<BackupKeyring> for Keyring {
+    fn from(keyring: BackupKeyring) -> Self {
+        Keyring {
+            kind: match keyring.kind {
+                BackupKeyringKind::Mnemonic => KeyringKind::Mnemonic,
+                BackupKeyringKind::PrivateKey => KeyringKind::PrivateKey,
+            },
+            secret: keyring.secret,
+            passphrase: keyring.passphrase,
+            hd_path: keyring.hd_path,
+            number_of_accounts: keyring.number_of_accounts,
+            labels: keyring
+                .labels
+                .into_iter()
+                .map(|label| Label { index: label.index, name: label.name })
+                .collect(),
+            source: keyring.source,
+        }
+    }
+}
+
+impl From<Keyring> for BackupKeyring {
+    fn from(mut keyring: Keyring) -> Self {
+        // The keyring zeroizes its secrets on drop, so they are taken rather than moved out
+        BackupKeyring {
+            kind: match keyring.kind {
+                KeyringKind::Mnemonic => BackupKeyringKind::Mnemonic,
+                KeyringKind::PrivateKey => BackupKeyringKind::PrivateKey,
+            },
+            secret: mem::take(&mut keyring.secret),
+            passphrase: keyring.passphrase.take(),
+            hd_path: keyring.hd_path.take(),
+            number_of_accounts: keyring.number_of_accounts,
+            labels: mem::take(&mut keyring.labels)
+                .into_iter()
+                .map(|label| BackupLabel { index: label.index, name: label.name })
+                .collect(),
+            source: keyring.source.take(),
+        }
+    }
+}
+
+/// Encrypts keyrings into a backup under a password.
+pub fn backup_create(
+    keyrings: Vec<BackupKeyring>,
+    password: String,
+) -> Result<String, WalletError> {
+    let keyrings: Vec<Keyring> = keyrings.into_iter().map(Keyring::from).collect();
+    Ok(create(&keyrings, &password, KdfParams::default())?)
+}
+
+/// Returns the metadata of a backup, without decrypting it.
+pub fn backup_inspect(backup: String) -> Result<BackupHeader, WalletError> {
+    let header = inspect(&backup)?;
+    Ok(BackupHeader {
+        version: header.version,
+        created_at: header.created_at,
+        keyrings: header.keyrings,
+        kdf: header.kdf.algorithm,
+        memory: header.kdf.memory,
+        iterations: header.kdf.iterations,
+        parallelism: header.kdf.parallelism,
+        cipher: header.cipher.algorithm,
+    })
+}
+
+/// Decrypts the keyrings of a backup.
+pub fn backup_restore(backup: String, password: String) -> Result<Vec<BackupKeyring>, WalletError> {
+    Ok(restore(&backup, &password)?.into_iter().map(BackupKeyring::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_backup_round_trip() -> Result<()> {
+        let keyring = BackupKeyring {
+            kind: BackupKeyringKind::Mnemonic,
+            secret: "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                     abandon abandon about"
+                .to_string(),
+            passphrase: None,
+            hd_path: Some("m/44'/60'/0'/0".to_string()),
+            number_of_accounts: Some(2),
+            labels: vec![BackupLabel { index: 1, name: "Savings".to_string() }],
+            source: None,
+        };
+        let backup = backup_create(vec![keyring], "password".to_string())?;
+
+        let header = backup_inspect(backup.clone())?;
+        assert_eq!((header.version, header.keyrings), (1, 1));
+        assert_eq!(header.kdf, "argon2id");
+
+        let keyrings = backup_restore(backup.clone(), "password".to_string())?;
+        assert_eq!(keyrings.len(), 1);
+        assert_eq!(keyrings[0].number_of_accounts, Some(2));
+        assert_eq!(keyrings[0].labels[0].name, "Savings");
+        assert!(matches!(
+            backup_restore(backup, "wrong".to_string()),
+            Err(WalletError::Backup(wallet_signer::backup::Error::Decrypt))
+        ));
+        Ok(())
+    }
+}
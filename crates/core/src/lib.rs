@@ -2,6 +2,30 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+mod backup;
+mod metamask;
+mod slip39;
+
+pub use backup::{
+    backup_create, backup_inspect, backup_restore, BackupHeader, BackupKeyring, BackupKeyringKind,
+    BackupLabel,
+};
+pub use metamask::{
+    metamask_decrypt, metamask_locate, metamask_vaults, MetaMaskKeyring, MetaMaskVault,
+};
+pub use slip39::{slip39_combine, slip39_generate, Slip39Group};
+
+/// Errors thrown to the bindings
+#[derive(Debug, thiserror::Error)]
+pub enum WalletError {
+    #[error(transparent)]
+    Slip39(#[from] wallet_signer::slip39::Error),
+    #[error(transparent)]
+    Backup(#[from] wallet_signer::backup::Error),
+    #[error("MetaMask: {0}")]
+    MetaMask(String),
+}
+
 pub fn rust_greeting(to: String) -> String {
     format!("Hello World, {}!", to)
 }
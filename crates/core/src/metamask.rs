@@ -0,0 +1,109 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// MetaMask vaults of the local browsers, see [wallet_metamask::source].
+use crate::WalletError;
+use wallet_metamask::{
+    source::MetaMaskSource,
+    types::{KeyMetadata, Vault},
+};
+
+/// An encrypted vault found in a local MetaMask install
+pub struct MetaMaskVault {
+    /// The name of the source, e.g. `MetaMask Chrome`
+    pub source: String,
+    pub path: String,
+    pub data: String,
+    pub iv: String,
+    pub salt: Option<String>,
+    /// The PBKDF2 iterations of the newer vaults
+    pub iterations: Option<u32>,
+}
+
+/// The HD keyring of a decrypted vault
+pub struct MetaMaskKeyring {
+    pub mnemonic: String,
+    pub hd_path: Option<String>,
+    pub number_of_accounts: Option<u32>,
+}
+
+fn metamask_error(e: Box<dyn std::error::Error>) -> WalletError {
+    WalletError::MetaMask(e.to_string())
+}
+
+/// Returns the paths of the MetaMask stores on the machine.
+pub fn metamask_locate() -> Result<Vec<String>, WalletError> {
+    let mut paths = vec![];
+    for source in MetaMaskSource::all() {
+        let found = source.locate().map_err(metamask_error)?;
+        paths.extend(found.iter().map(|path| path.display().to_string()));
+    }
+    Ok(paths)
+}
+
+/// Returns the encrypted vaults of the MetaMask stores on the machine.
+pub fn metamask_vaults() -> Result<Vec<MetaMaskVault>, WalletError> {
+    let mut vaults = vec![];
+    for source in MetaMaskSource::all() {
+        for found in source.vaults().map_err(metamask_error)? {
+            vaults.push(MetaMaskVault {
+                source: source.name.to_string(),
+                path: found.path.display().to_string(),
+                data: found.vault.data,
+                iv: found.vault.iv,
+                salt: found.vault.salt,
+                iterations: found.vault.key_metadata.map(|metadata| metadata.params.iterations),
+            });
+        }
+    }
+    Ok(vaults)
+}
+
+/// Decrypts a vault with the decryptor of its source.
+pub fn metamask_decrypt(
+    vault: MetaMaskVault,
+    password: String,
+) -> Result<MetaMaskKeyring, WalletError> {
+    let source = MetaMaskSource::all()
+        .into_iter()
+        .find(|source| source.name == vault.source)
+        .ok_or_else(|| WalletError::MetaMask(format!("unknown source {}", vault.source)))?;
+    let key_metadata = vault.iterations.map(KeyMetadata::pbkdf2);
+    let decrypted = source
+        .decrypt(
+            &Vault { data: vault.data, iv: vault.iv, salt: vault.salt, key_metadata },
+            &password,
+        )
+        .map_err(metamask_error)?;
+    Ok(MetaMaskKeyring {
+        mnemonic: decrypted.data.mnemonic.to_string(),
+        hd_path: decrypted.data.hd_path,
+        number_of_accounts: decrypted.data.number_of_accounts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_metamask_decrypt() -> Result<()> {
+        // From the chromium-108.0_5359.98_4.10.24.2 fixture of wallet-metamask
+        let vault = MetaMaskVault {
+            source: "MetaMask Chrome".to_string(),
+            path: "000003.log".to_string(),
+            data: "\"8w0Wn8LaR3kMTp++Crr/JMCd6/xrfI1xWJsBgZXIdaKvPHCpjK/o1d6drEvQ7/ThtCynS5jP5F2T5esc0cin6E+2g3zcHRIpYp1Ut3Zn4Gw5Of8yxEk+Whq5eV2O8kbxfeurqTBx3b377e9Jd4N39QFF9kyE3cr8j6fETQvKjOC6irIGL0vI+TkUUylKISZ2OksbQJEooWPW3S1O8xdazL32j7dOnLbkrq1Xan0EIC7sg41oWUyMuS5eVopigxJ0ehueZsFlkvcBb+9zp6eMW5rw+CHC8KHXZdWGU45Ag85PaO5smtkOzb+WrQbufpQgsgKY23SsM8I1uTK6738/IHQ7kzFYImX0AJdF60xiUpihA/iUdWn6lr+kS4uyp7NhMLb4D5fHQi7pDb29TIDj1267rCD3w1N9M1nwWUjcG0gw5AMdf4bwYjpKOeQv2M5dGiX41+iQ9Rs5R6t3qZTNZpNu/czZaCUU8Bbr/je6Z7Milwl3b5NMfO7u2GID7aSG8s8RQ6/D5PjmtJN3a5BY6WLm1IzV\"".to_string(),
+            iv: "\"SCr2xR/hqI6qqJQese4E9Q==\"".to_string(),
+            salt: Some("\"HQnH0ArgfCWp86acfYN5Kr9wCWFKE3uw0fwUQafJHMY=\"".to_string()),
+            iterations: None,
+        };
+        let keyring = metamask_decrypt(vault, "JooXegoodowu8mohf2ietah5kohgah5".to_string())?;
+        assert_eq!(
+            keyring.mnemonic,
+            "harvest afraid useful nose electric swift various man boil diagram confirm ahead"
+        );
+        Ok(())
+    }
+}
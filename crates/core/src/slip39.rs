@@ -0,0 +1,55 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// SLIP-39 seed backups, see [wallet_signer::slip39].
+use crate::WalletError;
+use wallet_signer::slip39::{combine, generate, Group};
+
+/// The member threshold and member count of a group of shares
+pub struct Slip39Group {
+    pub threshold: u8,
+    pub count: u8,
+}
+
+/// Splits a master secret into the mnemonics of every group.
+pub fn slip39_generate(
+    group_threshold: u8,
+    groups: Vec<Slip39Group>,
+    master_secret: Vec<u8>,
+    passphrase: String,
+    iteration_exponent: u8,
+    extendable: bool,
+) -> Result<Vec<Vec<String>>, WalletError> {
+    let groups: Vec<Group> =
+        groups.iter().map(|g| Group { threshold: g.threshold, count: g.count }).collect();
+    Ok(generate(
+        group_threshold,
+        &groups,
+        &master_secret,
+        &passphrase,
+        iteration_exponent,
+        extendable,
+    )?)
+}
+
+/// Recovers a master secret from the mnemonics of enough groups.
+pub fn slip39_combine(mnemonics: Vec<String>, passphrase: String) -> Result<Vec<u8>, WalletError> {
+    Ok(combine(&mnemonics, &passphrase)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_slip39_round_trip() -> Result<()> {
+        let secret = vec![7u8; 16];
+        let groups = vec![Slip39Group { threshold: 2, count: 3 }];
+        let shares = slip39_generate(1, groups, secret.clone(), "".to_string(), 0, true)?;
+        assert_eq!(slip39_combine(shares[0][1..].to_vec(), "".to_string())?, secret);
+        assert!(slip39_combine(shares[0][..1].to_vec(), "".to_string()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Brave Wallet is built into the browser and keeps its mnemonic in the `Preferences` file of
+/// the profile, encrypted with AES-256-GCM-SIV under a PBKDF2-SHA256 key.
+///
+/// From:
+/// https://github.com/brave/brave-core/blob/master/components/brave_wallet/browser/keyring_service.cc
+/// https://github.com/brave/brave-core/blob/master/components/brave_wallet/browser/password_encryptor.cc
+use crate::{
+    crypto::{aes_256_gcm_siv_open, pbkdf2, Digest},
+    types::{Error, Secret},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use wallet_metamask::interactive::Environment;
+
+/// The PBKDF2 iterations of the current wallets
+const ITERATIONS: u32 = 310_000;
+
+/// The PBKDF2 iterations of the wallets created before the iterations were raised
+const LEGACY_ITERATIONS: u32 = 100_000;
+
+#[derive(Deserialize)]
+struct Vault {
+    encrypted_mnemonic: String,
+    password_encryptor_nonce: String,
+    password_encryptor_salt: String,
+}
+
+/// Returns the `Preferences` file of a browser profile, if it is a profile of Brave.
+pub fn stores(env: &impl Environment, profile: &Path) -> Vec<PathBuf> {
+    if !profile.components().any(|c| c.as_os_str() == "BraveSoftware") {
+        return vec![];
+    }
+    env.read_dir(profile)
+        .into_iter()
+        .filter(|entry| !entry.is_dir && entry.path.file_name() == Some("Preferences".as_ref()))
+        .map(|entry| entry.path)
+        .collect()
+}
+
+/// Returns the vault in the bytes of a `Preferences` file.
+pub fn extract(data: &[u8]) -> Vec<String> {
+    let Ok(preferences) = serde_json::from_slice::<Value>(data) else { return vec![] };
+    let keyring = &preferences["brave"]["wallet"]["keyrings"]["default"];
+    match keyring.get("encrypted_mnemonic") {
+        Some(_) => vec![keyring.to_string()],
+        None => vec![],
+    }
+}
+
+/// Decrypts a vault into its mnemonic.
+pub fn decrypt(vault: &str, password: &str) -> Result<Vec<Secret>, Error> {
+    let vault: Vault = serde_json::from_str(vault).map_err(|e| Error::Vault(e.to_string()))?;
+    let decode = |s: &str| STANDARD.decode(s).map_err(|e| Error::Vault(e.to_string()));
+    let (salt, nonce) =
+        (decode(&vault.password_encryptor_salt)?, decode(&vault.password_encryptor_nonce)?);
+    let data = decode(&vault.encrypted_mnemonic)?;
+
+    let open = |iterations| {
+        let key = pbkdf2(password.as_bytes(), &salt, iterations, Digest::Sha256, 32);
+        aes_256_gcm_siv_open(&key, &nonce, &data)
+    };
+    let plain = match open(ITERATIONS) {
+        Err(Error::Password) => open(LEGACY_ITERATIONS)?,
+        plain => plain?,
+    };
+    let phrase = String::from_utf8(plain).map_err(|e| Error::Vault(e.to_string()))?;
+    Ok(vec![Secret::Mnemonic { phrase, hd_path: None }])
+}
@@ -0,0 +1,91 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Ciphers and key derivations of the extension vaults.
+use crate::types::Error;
+use aes::Aes256;
+use aes_gcm::{
+    aead::{generic_array::GenericArray, Aead, KeyInit},
+    AesGcm,
+};
+use aes_gcm_siv::Aes256GcmSiv;
+use sha2::{Sha256, Sha512};
+
+/// The digest of a PBKDF2 key derivation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Digest {
+    Sha256,
+    Sha512,
+}
+
+/// Derives a key of `len` bytes with PBKDF2-HMAC.
+pub fn pbkdf2(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    digest: Digest,
+    len: usize,
+) -> Vec<u8> {
+    let mut key = vec![0u8; len];
+    match digest {
+        Digest::Sha256 => pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, iterations, &mut key),
+        Digest::Sha512 => pbkdf2::pbkdf2_hmac::<Sha512>(password, salt, iterations, &mut key),
+    }
+    key
+}
+
+/// Decrypts AES-256-GCM, with the tag appended to the ciphertext and a 12 or 16 bytes nonce.
+pub fn aes_256_gcm_open(key: &[u8], nonce: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+    if key.len() != 32 {
+        return Err(Error::Vault(format!("invalid key length {}", key.len())));
+    }
+    let key = GenericArray::from_slice(key);
+    let plain = match nonce.len() {
+        12 => AesGcm::<Aes256, aes_gcm::aead::consts::U12>::new(key)
+            .decrypt(GenericArray::from_slice(nonce), data),
+        16 => AesGcm::<Aes256, aes_gcm::aead::consts::U16>::new(key)
+            .decrypt(GenericArray::from_slice(nonce), data),
+        len => return Err(Error::Vault(format!("invalid nonce length {}", len))),
+    };
+    plain.map_err(|_| Error::Password)
+}
+
+/// Decrypts AES-256-GCM-SIV without associated data, with the tag appended to the ciphertext.
+pub fn aes_256_gcm_siv_open(key: &[u8], nonce: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+    if key.len() != 32 || nonce.len() != 12 {
+        return Err(Error::Vault("invalid AES-GCM-SIV key or nonce length".to_string()));
+    }
+    Aes256GcmSiv::new(GenericArray::from_slice(key))
+        .decrypt(GenericArray::from_slice(nonce), data)
+        .map_err(|_| Error::Password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use ethers_core::utils::hex;
+
+    #[test]
+    fn test_aes_256_gcm_siv_vectors() -> Result<()> {
+        // From: https://www.rfc-editor.org/rfc/rfc8452#appendix-C.2
+        let key = hex::decode("0100000000000000000000000000000000000000000000000000000000000000")?;
+        let nonce = hex::decode("030000000000000000000000")?;
+        let vectors = [
+            ("", "07f5f4169bbf55a8400cd47ea6fd400f"),
+            ("0100000000000000", "c2ef328e5c71c83b843122130f7364b761e0b97427e3df28"),
+            (
+                "010000000000000000000000",
+                "9aab2aeb3faa0a34aea8e2b18ca50da9ae6559e48fd10f6e5c9ca17e",
+            ),
+        ];
+        for (plaintext, result) in vectors {
+            let plain = aes_256_gcm_siv_open(&key, &nonce, &hex::decode(result)?)?;
+            assert_eq!(hex::encode(plain), plaintext);
+        }
+        assert_eq!(aes_256_gcm_siv_open(&key, &nonce, &[0; 16]), Err(Error::Password));
+        assert_eq!(aes_256_gcm_siv_open(&key, &nonce, &[0; 8]), Err(Error::Password));
+        Ok(())
+    }
+}
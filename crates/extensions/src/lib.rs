@@ -0,0 +1,10 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+pub mod brave;
+pub mod crypto;
+pub mod rabby;
+pub mod storage;
+pub mod types;
+pub mod wallet;
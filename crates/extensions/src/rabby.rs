@@ -0,0 +1,94 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Rabby keeps a MetaMask-style keyring vault, encrypted by `browser-passworder` with
+/// PBKDF2-SHA256 and AES-256-GCM, under the `keyringState` key of its storage.
+///
+/// From:
+/// https://github.com/RabbyHub/Rabby/blob/develop/src/background/service/keyring/index.ts
+use crate::{
+    crypto::{aes_256_gcm_open, pbkdf2, Digest},
+    storage::{json_values, unstring},
+    types::{string_or_bytes, Error, Secret},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use wallet_metamask::interactive::{files_with_extensions, Environment};
+
+/// The id of the extension in the Chrome Web Store
+pub const EXTENSION_ID: &str = "acmacodkjbdgmoleebolmdjonilkdbch";
+
+/// The storage key of the keyring state
+const STORAGE_KEY: &str = "keyringState";
+
+/// The PBKDF2 iterations of `browser-passworder`
+const ITERATIONS: u32 = 10_000;
+
+#[derive(Deserialize)]
+struct Vault {
+    data: String,
+    iv: String,
+    salt: String,
+}
+
+#[derive(Deserialize)]
+struct Keyring {
+    #[serde(rename = "type")]
+    kind: String,
+    data: Value,
+}
+
+#[derive(Deserialize)]
+struct HdKeyring {
+    #[serde(deserialize_with = "string_or_bytes")]
+    mnemonic: String,
+    #[serde(rename = "hdPath")]
+    hd_path: Option<String>,
+}
+
+/// Returns the LevelDB files of the extension in a browser profile.
+pub fn stores(env: &impl Environment, profile: &Path) -> Vec<PathBuf> {
+    let dir = profile.join("Local Extension Settings").join(EXTENSION_ID);
+    files_with_extensions(env, &dir, &["log", "ldb"])
+}
+
+/// Returns the vaults in the bytes of a storage file.
+pub fn extract(data: &[u8]) -> Vec<String> {
+    json_values(data, STORAGE_KEY)
+        .iter()
+        .filter_map(|state| unstring(&state["vault"]))
+        .filter(|vault| vault.get("data").is_some())
+        .map(|vault| vault.to_string())
+        .collect()
+}
+
+/// Decrypts a vault into the mnemonics and private keys of its keyrings.
+pub fn decrypt(vault: &str, password: &str) -> Result<Vec<Secret>, Error> {
+    let vault: Vault = serde_json::from_str(vault).map_err(|e| Error::Vault(e.to_string()))?;
+    let decode = |s: &str| STANDARD.decode(s).map_err(|e| Error::Vault(e.to_string()));
+    let key = pbkdf2(password.as_bytes(), &decode(&vault.salt)?, ITERATIONS, Digest::Sha256, 32);
+    let plain = aes_256_gcm_open(&key, &decode(&vault.iv)?, &decode(&vault.data)?)?;
+
+    let keyrings: Vec<Keyring> =
+        serde_json::from_slice(&plain).map_err(|e| Error::Vault(e.to_string()))?;
+    let mut secrets = vec![];
+    for keyring in keyrings {
+        let invalid = |e: serde_json::Error| Error::Vault(e.to_string());
+        match keyring.kind.as_str() {
+            "HD Key Tree" => {
+                let hd: HdKeyring = serde_json::from_value(keyring.data).map_err(invalid)?;
+                secrets.push(Secret::Mnemonic { phrase: hd.mnemonic, hd_path: hd.hd_path });
+            }
+            "Simple Key Pair" => {
+                let keys: Vec<String> = serde_json::from_value(keyring.data).map_err(invalid)?;
+                secrets.extend(keys.into_iter().map(Secret::PrivateKey));
+            }
+            // Hardware and watch-only keyrings hold no secrets
+            _ => {}
+        }
+    }
+    Ok(secrets)
+}
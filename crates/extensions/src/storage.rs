@@ -0,0 +1,168 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Locating and scanning the storage of the browser extensions, on the [Environment] and
+/// [VaultLocator] of the MetaMask vaults.
+use crate::wallet::Wallet;
+use serde_json::Value;
+use std::{error::Error, path::PathBuf};
+use tracing::debug;
+use wallet_metamask::{
+    interactive::{chromium_profiles, Environment, SystemEnvironment},
+    source::VaultLocator,
+};
+
+/// The maximum number of bytes between a LevelDB key and its value
+const VALUE_OFFSET: usize = 8;
+
+/// Locates the stores of a wallet in the profiles of the Chromium browsers
+#[derive(Clone, Debug)]
+pub struct ExtensionLocator<E = SystemEnvironment> {
+    pub wallet: Wallet,
+    /// The user data directories of the browsers, the platform ones when empty
+    pub user_data_dirs: Vec<PathBuf>,
+    pub env: E,
+}
+
+impl ExtensionLocator {
+    /// Locates the stores of a wallet in the environment of the running process.
+    pub fn new(wallet: Wallet) -> Self {
+        Self { wallet, user_data_dirs: vec![], env: SystemEnvironment }
+    }
+}
+
+impl<E: Environment> VaultLocator for ExtensionLocator<E> {
+    fn locate(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        Ok(chromium_profiles(&self.env, &self.user_data_dirs)
+            .into_iter()
+            .flat_map(|profile| {
+                debug!("Looking for {} in: {:?}", self.wallet, profile);
+                self.wallet.stores(&self.env, &profile)
+            })
+            .collect())
+    }
+}
+
+/// Returns the JSON values stored under a key in the bytes of a LevelDB file, which do not have
+/// to be valid UTF-8.
+pub fn json_values(data: &[u8], key: &str) -> Vec<Value> {
+    let key = key.as_bytes();
+    data.windows(key.len())
+        .enumerate()
+        .filter(|(_, window)| *window == key)
+        .filter_map(|(i, _)| {
+            let rest = &data[i + key.len()..];
+            let start = rest.iter().take(VALUE_OFFSET).position(|b| b"{[\"".contains(b))?;
+            serde_json::Deserializer::from_slice(&rest[start..]).into_iter::<Value>().next()?.ok()
+        })
+        .collect()
+}
+
+/// Returns a JSON value, parsing it first if it is a string of JSON.
+pub fn unstring(value: &Value) -> Option<Value> {
+    match value {
+        Value::String(s) => serde_json::from_str(s).ok(),
+        value => Some(value.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rabby;
+    use anyhow::{anyhow, Result};
+    use std::{collections::BTreeSet, ffi::OsString, path::Path};
+    use wallet_metamask::interactive::{DirEntry, MockEnvironment, Os};
+
+    /// Returns an environment of an operating system, a variable and a fake tree of files.
+    fn fake_environment(
+        os: Os,
+        var: (&'static str, &'static str),
+        files: &[String],
+    ) -> MockEnvironment {
+        let files: BTreeSet<PathBuf> = files.iter().map(PathBuf::from).collect();
+        let mut env = MockEnvironment::new();
+        env.expect_os().return_const(os);
+        env.expect_var_os().returning(move |key| (key == var.0).then(|| OsString::from(var.1)));
+        env.expect_read_dir().returning(move |dir: &Path| {
+            let children: BTreeSet<DirEntry> = files
+                .iter()
+                .filter_map(|path| {
+                    let mut rest = path.strip_prefix(dir).ok()?.components();
+                    let child = dir.join(rest.next()?);
+                    Some(DirEntry { is_dir: rest.next().is_some(), path: child })
+                })
+                .collect();
+            children.into_iter().collect()
+        });
+        env.expect_read_to_string().returning(|_| None);
+        env
+    }
+
+    #[test]
+    fn test_locate_platforms() -> Result<()> {
+        let platforms = [
+            (
+                Os::Windows,
+                ("LOCALAPPDATA", "C:/Users/satoshi/AppData/Local"),
+                "C:/Users/satoshi/AppData/Local/Microsoft/Edge/User Data",
+                "C:/Users/satoshi/AppData/Local/BraveSoftware/Brave-Browser/User Data",
+            ),
+            (
+                Os::MacOs,
+                ("HOME", "/Users/satoshi"),
+                "/Users/satoshi/Library/Application Support/Google/Chrome",
+                "/Users/satoshi/Library/Application Support/BraveSoftware/Brave-Browser",
+            ),
+            (
+                Os::Linux,
+                ("HOME", "/home/satoshi"),
+                "/home/satoshi/.config/chromium",
+                "/home/satoshi/.config/BraveSoftware/Brave-Browser",
+            ),
+        ];
+        for (os, var, chromium, brave) in platforms {
+            let rabby = format!(
+                "{}/Profile 1/Local Extension Settings/{}/000003.log",
+                chromium,
+                rabby::EXTENSION_ID
+            );
+            let preferences = format!("{}/Default/Preferences", brave);
+            let files = [
+                rabby.clone(),
+                format!(
+                    "{}/Profile 1/Local Extension Settings/{}/LOCK",
+                    chromium,
+                    rabby::EXTENSION_ID
+                ),
+                // Only Brave keeps a wallet in its preferences
+                format!("{}/Default/Preferences", chromium),
+                preferences.clone(),
+                format!("{}/System Profile/Preferences", brave),
+            ];
+
+            let locate = |wallet| {
+                let env = fake_environment(os, var, &files);
+                ExtensionLocator { wallet, user_data_dirs: vec![], env }
+                    .locate()
+                    .map_err(|e| anyhow!("{}", e))
+            };
+            assert_eq!(locate(Wallet::Rabby)?, vec![PathBuf::from(rabby)], "{:?}", os);
+            assert_eq!(locate(Wallet::Brave)?, vec![PathBuf::from(preferences)], "{:?}", os);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_values() {
+        let data = b"\x00\x01keyringState\x05{\"vault\":\"{\\\"data\\\":\\\"a\\\"}\"}\xff\xfejunk\
+                     keyringState\x02{\"vault\":1}{\"other\":2}keyringState\x00not json\
+                     keyringState\x00{\"vault\":\"\xff\"}";
+        let values = json_values(data, "keyringState");
+        assert_eq!(values.len(), 2);
+        assert_eq!(unstring(&values[0]["vault"]), Some(serde_json::json!({"data": "a"})));
+        assert_eq!(values[1]["vault"], 1);
+        assert!(json_values(b"keyring", "keyringState").is_empty());
+    }
+}
@@ -0,0 +1,70 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use crate::wallet::Wallet;
+use serde::{Deserialize, Deserializer};
+use wallet_signer::{
+    account::Account,
+    mnemonic::{Language, DEFAULT_HD_PATH},
+};
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("Could not read {path}: {message}")]
+    Io { path: String, message: String },
+    #[error("Invalid vault: {0}")]
+    Vault(String),
+    #[error("Wrong password")]
+    Password,
+    #[error(transparent)]
+    Account(#[from] wallet_signer::account::Error),
+}
+
+/// A vault found in the storage of an extension, still encrypted
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EncryptedVault {
+    pub wallet: Wallet,
+    /// The JSON of the vault, whose shape depends on the wallet
+    pub data: String,
+}
+
+/// A secret of a decrypted vault
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Secret {
+    Mnemonic {
+        phrase: String,
+        hd_path: Option<String>,
+    },
+    /// A hex private key
+    PrivateKey(String),
+}
+
+impl Secret {
+    /// Returns the first `count` accounts of a mnemonic, or the account of a private key.
+    pub fn accounts(&self, count: u32) -> Result<Vec<Account>, Error> {
+        match self {
+            Secret::Mnemonic { phrase, hd_path } => (0..count)
+                .map(|i| {
+                    let path = format!("{}/{}", hd_path.as_deref().unwrap_or(DEFAULT_HD_PATH), i);
+                    Ok(Account::from_mnemonic(phrase, Language::English, None, &path)?)
+                })
+                .collect(),
+            Secret::PrivateKey(key) => Ok(vec![Account::from_private_key(key)?]),
+        }
+    }
+}
+
+/// Deserializes a mnemonic stored either as a string or as its UTF-8 bytes.
+pub(crate) fn string_or_bytes<'de, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrBytes {
+        String(String),
+        Bytes(Vec<u8>),
+    }
+    match StringOrBytes::deserialize(d)? {
+        StringOrBytes::String(s) => Ok(s),
+        StringOrBytes::Bytes(b) => String::from_utf8(b).map_err(serde::de::Error::custom),
+    }
+}
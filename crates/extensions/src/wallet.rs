@@ -0,0 +1,149 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// The supported extension wallets, dispatching to the module of each wallet.
+use crate::{
+    brave, rabby,
+    storage::ExtensionLocator,
+    types::{EncryptedVault, Error, Secret},
+};
+use std::{
+    error, fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tracing::debug;
+use wallet_metamask::{
+    interactive::Environment,
+    mmap::Mmap,
+    source::{VaultDecryptor, VaultExtractor, VaultSource},
+};
+
+/// An extension wallet source, whose vaults decrypt into secrets
+pub type ExtensionSource = VaultSource<EncryptedVault, Vec<Secret>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Wallet {
+    Rabby,
+    Brave,
+}
+
+impl Wallet {
+    pub const ALL: [Wallet; 2] = [Wallet::Rabby, Wallet::Brave];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Wallet::Rabby => "rabby",
+            Wallet::Brave => "brave",
+        }
+    }
+
+    /// Returns the storage files of the wallet in a browser profile.
+    pub fn stores(&self, env: &impl Environment, profile: &Path) -> Vec<PathBuf> {
+        match self {
+            Wallet::Rabby => rabby::stores(env, profile),
+            Wallet::Brave => brave::stores(env, profile),
+        }
+    }
+
+    /// Returns the source of the wallet in the browser profiles of the running process.
+    pub fn source(&self) -> ExtensionSource {
+        VaultSource::new(
+            self.name(),
+            Box::new(ExtensionLocator::new(*self)),
+            Box::new(*self),
+            Box::new(*self),
+        )
+    }
+
+    /// Returns the vaults of the wallet in the bytes of a storage file.
+    pub fn extract(&self, data: &[u8]) -> Vec<EncryptedVault> {
+        let vaults = match self {
+            Wallet::Rabby => rabby::extract(data),
+            Wallet::Brave => brave::extract(data),
+        };
+        vaults.into_iter().map(|data| EncryptedVault { wallet: *self, data }).collect()
+    }
+
+    /// Decrypts a vault of the wallet into its secrets.
+    pub fn decrypt(&self, vault: &str, password: &str) -> Result<Vec<Secret>, Error> {
+        match self {
+            Wallet::Rabby => rabby::decrypt(vault, password),
+            Wallet::Brave => brave::decrypt(vault, password),
+        }
+    }
+}
+
+impl fmt::Display for Wallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Wallet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Wallet::ALL
+            .into_iter()
+            .find(|wallet| wallet.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown wallet {}", s))
+    }
+}
+
+impl EncryptedVault {
+    /// Decrypts the vault into its secrets.
+    pub fn decrypt(&self, password: &str) -> Result<Vec<Secret>, Error> {
+        self.wallet.decrypt(&self.data, password)
+    }
+}
+
+impl VaultExtractor for Wallet {
+    type Vault = EncryptedVault;
+
+    fn extract(&self, data: &[u8]) -> Result<Vec<EncryptedVault>, Box<dyn error::Error>> {
+        Ok(Wallet::extract(self, data))
+    }
+}
+
+impl VaultDecryptor for Wallet {
+    type Vault = EncryptedVault;
+    type Keyrings = Vec<Secret>;
+
+    fn decrypt(
+        &self,
+        vault: &EncryptedVault,
+        password: &str,
+    ) -> Result<Vec<Secret>, Box<dyn error::Error>> {
+        Ok(vault.decrypt(password)?)
+    }
+}
+
+/// Returns the vaults of a wallet in a storage file.
+pub fn extract_vaults_from_file(wallet: Wallet, path: &Path) -> Result<Vec<EncryptedVault>, Error> {
+    let data = Mmap::open(path)
+        .map_err(|e| Error::Io { path: path.display().to_string(), message: e.to_string() })?;
+    Ok(wallet.extract(&data))
+}
+
+/// Returns the distinct vaults of every wallet found in the browser profiles.
+pub fn extract_all_vaults() -> Vec<EncryptedVault> {
+    let mut vaults: Vec<EncryptedVault> = vec![];
+    for wallet in Wallet::ALL {
+        let found = match wallet.source().vaults() {
+            Ok(found) => found,
+            Err(e) => {
+                debug!("Skipping {}: {}", wallet, e);
+                continue;
+            }
+        };
+        debug!("Found {} {} vaults", found.len(), wallet);
+        for found in found {
+            if !vaults.contains(&found.vault) {
+                vaults.push(found.vault);
+            }
+        }
+    }
+    vaults
+}
@@ -0,0 +1,85 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// The fixtures are synthetic, see the README of the crate.
+use std::path::PathBuf;
+use wallet_extensions::{
+    types::{Error, Secret},
+    wallet::{extract_vaults_from_file, Wallet},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    const MNEMONIC: &str = "test test test test test test test test test test test junk";
+    const PRIVATE_KEY: &str = "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+    const PASSWORD: &str = "correct horse battery staple";
+
+    struct Fixture<'a> {
+        wallet: Wallet,
+        path: &'a str,
+        secrets: &'a [(&'a str, &'a str)],
+    }
+
+    const FIXTURES: [Fixture; 2] = [
+        Fixture {
+            wallet: Wallet::Rabby,
+            path: "rabby/000003.log",
+            secrets: &[
+                (MNEMONIC, "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"),
+                (PRIVATE_KEY, "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"),
+            ],
+        },
+        Fixture {
+            wallet: Wallet::Brave,
+            path: "brave/Preferences",
+            secrets: &[(MNEMONIC, "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266")],
+        },
+    ];
+
+    fn fixture_path(path: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+    }
+
+    #[test]
+    fn test_fixtures() -> Result<()> {
+        for fixture in FIXTURES {
+            let vaults = extract_vaults_from_file(fixture.wallet, &fixture_path(fixture.path))?;
+            let mut secrets = vec![];
+            for vault in vaults {
+                secrets.extend(vault.decrypt(PASSWORD)?);
+            }
+            assert_eq!(secrets.len(), fixture.secrets.len(), "{}", fixture.wallet);
+            for (secret, (expected, address)) in secrets.iter().zip(fixture.secrets) {
+                match secret {
+                    Secret::Mnemonic { phrase, .. } => assert_eq!(phrase, expected),
+                    Secret::PrivateKey(key) => assert_eq!(key, expected),
+                }
+                assert_eq!(secret.accounts(1)?[0].to_string().split(' ').next(), Some(*address));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_password() -> Result<()> {
+        // Brave retries with the legacy iterations, which the fixture test covers
+        let vaults = extract_vaults_from_file(Wallet::Rabby, &fixture_path("rabby/000003.log"))?;
+        assert_eq!(vaults[0].decrypt("wrong"), Err(Error::Password));
+        Ok(())
+    }
+
+    #[test]
+    fn test_other_wallet() -> Result<()> {
+        // The vaults of a wallet are not found in the storage of another
+        let vaults = extract_vaults_from_file(Wallet::Rabby, &fixture_path("brave/Preferences"))?;
+        assert!(vaults.is_empty());
+        assert!(Wallet::Brave.extract(b"not json").is_empty());
+        assert_eq!("Brave".parse::<Wallet>(), Ok(Wallet::Brave));
+        assert!("phantom".parse::<Wallet>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Benchmarks the extraction of the vaults of the fixtures, run with `cargo bench`.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::{fs, path::PathBuf};
+use wallet_metamask::vault::{
+    extract_vault_from_bytes, extract_vault_from_file, extract_vault_from_string,
+};
+
+/// The Windows LevelDB table, the largest fixture and the last attempt of the extraction
+const LDB: &str = "chrome-windows-1/000005.ldb";
+
+/// The Linux LevelDB log
+const LOG: &str = "chromium-108.0_5359.98_4.10.24.2/000003.log";
+
+fn fixture(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+}
+
+fn bench_ldb(c: &mut Criterion) {
+    let path = fixture(LDB);
+    let data = fs::read(&path).unwrap();
+    c.bench_function("ldb_from_file", |b| {
+        b.iter(|| extract_vault_from_file(black_box(&path)).unwrap())
+    });
+    c.bench_function("ldb_from_bytes", |b| {
+        b.iter(|| extract_vault_from_bytes(black_box(&data)).unwrap())
+    });
+    c.bench_function("ldb_from_lossy_string", |b| {
+        b.iter(|| extract_vault_from_string(&String::from_utf8_lossy(black_box(&data))).unwrap())
+    });
+}
+
+fn bench_log(c: &mut Criterion) {
+    let path = fixture(LOG);
+    c.bench_function("log_from_file", |b| {
+        b.iter(|| extract_vault_from_file(black_box(&path)).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_ldb, bench_log);
+criterion_main!(benches);
@@ -0,0 +1,25 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![no_main]
+
+/// Decrypts the vaults extracted from arbitrary store contents, or parsed from arbitrary JSON, with
+/// the key derivations the `fuzzing` cfg caps to a single PBKDF2 iteration and an scrypt cost of 2.
+use libfuzzer_sys::fuzz_target;
+use wallet_metamask::{
+    types::Vault,
+    vault::{decrypt_vault, extract_vault_from_bytes},
+};
+
+/// The password of the fixtures, which the capped key derivations no longer decrypt, see the
+/// `parse_keyrings` target for the parsing of their keyrings
+const PASSWORD: &str = "JooXegoodowu8mohf2ietah5kohgah5";
+
+fuzz_target!(|data: &[u8]| {
+    let vault =
+        serde_json::from_slice::<Vault>(data).ok().or_else(|| extract_vault_from_bytes(data).ok());
+    if let Some(vault) = vault {
+        let _ = decrypt_vault(&vault, PASSWORD);
+    }
+});
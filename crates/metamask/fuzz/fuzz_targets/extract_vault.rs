@@ -0,0 +1,16 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![no_main]
+
+/// Extracts vaults from arbitrary store contents, as the bytes of a file and as a string.
+use libfuzzer_sys::fuzz_target;
+use wallet_metamask::vault::{extract_vault_from_bytes, extract_vault_from_string};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = extract_vault_from_bytes(data);
+    if let Ok(s) = std::str::from_utf8(data) {
+        let _ = extract_vault_from_string(s);
+    }
+});
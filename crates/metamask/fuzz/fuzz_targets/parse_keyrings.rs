@@ -0,0 +1,13 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![no_main]
+
+/// Parses arbitrary decrypted vaults, which the decryption of arbitrary vaults rarely reaches.
+use libfuzzer_sys::fuzz_target;
+use wallet_metamask::vault::parse_keyrings;
+
+fuzz_target!(|data: &str| {
+    let _ = parse_keyrings(data);
+});
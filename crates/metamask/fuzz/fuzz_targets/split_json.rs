@@ -0,0 +1,13 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![no_main]
+
+/// Splits arbitrary decrypted keyrings into JSON objects.
+use libfuzzer_sys::fuzz_target;
+use wallet_metamask::vault::split_json;
+
+fuzz_target!(|data: &str| {
+    let _ = split_json(data);
+});
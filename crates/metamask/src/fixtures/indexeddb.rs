@@ -0,0 +1,293 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Writers of the IndexedDB stores of Firefox: SQLite databases whose `object_data` rows hold
+/// the values as Snappy compressed structured clones.
+///
+/// The database has the `object_data` table only, as a rowid table, which is enough for the
+/// extraction that scans the bytes of the file and for SQLite to open it.
+///
+/// From:
+/// https://searchfox.org/mozilla-central/source/js/src/vm/StructuredClone.cpp
+/// https://searchfox.org/mozilla-central/source/dom/indexedDB/Key.cpp
+/// https://www.sqlite.org/fileformat2.html
+use super::snappy;
+use serde_json::Value;
+
+/// The tags of the structured clones
+const SCTAG_HEADER: u32 = 0xfff1_0000;
+const SCTAG_NULL: u32 = 0xffff_0000;
+const SCTAG_BOOLEAN: u32 = 0xffff_0002;
+const SCTAG_INT32: u32 = 0xffff_0003;
+const SCTAG_STRING: u32 = 0xffff_0004;
+const SCTAG_ARRAY_OBJECT: u32 = 0xffff_0007;
+const SCTAG_OBJECT_OBJECT: u32 = 0xffff_0008;
+const SCTAG_END_OF_KEYS: u32 = 0xffff_0013;
+
+/// The scope of the clones stored by IndexedDB
+const SCOPE_DIFFERENT_PROCESS: u32 = 2;
+
+/// The size of the pages of the database
+const PAGE_SIZE: usize = 4096;
+
+/// The schema of the object store table
+const OBJECT_DATA_SQL: &str = "CREATE TABLE object_data (object_store_id INTEGER NOT NULL, key BLOB NOT NULL, index_data_values BLOB DEFAULT NULL, file_ids TEXT, data BLOB NOT NULL)";
+
+/// Returns the structured clone of a JSON value.
+pub fn structured_clone(value: &Value) -> Vec<u8> {
+    let mut output = vec![];
+    push_pair(&mut output, SCTAG_HEADER, SCOPE_DIFFERENT_PROCESS);
+    push_value(&mut output, value);
+    output
+}
+
+fn push_pair(output: &mut Vec<u8>, tag: u32, data: u32) {
+    output.extend_from_slice(&(((tag as u64) << 32) | data as u64).to_le_bytes());
+}
+
+fn push_string(output: &mut Vec<u8>, s: &str) {
+    // Latin-1 strings take a byte per character, the others two
+    let latin1 = s.chars().all(|c| (c as u32) < 0x100);
+    if latin1 {
+        push_pair(output, SCTAG_STRING, s.chars().count() as u32 | 0x8000_0000);
+        output.extend(s.chars().map(|c| c as u8));
+    } else {
+        let units: Vec<u16> = s.encode_utf16().collect();
+        push_pair(output, SCTAG_STRING, units.len() as u32);
+        output.extend(units.iter().flat_map(|unit| unit.to_le_bytes()));
+    }
+    output.resize((output.len() + 7) / 8 * 8, 0);
+}
+
+fn push_value(output: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => push_pair(output, SCTAG_NULL, 0),
+        Value::Bool(b) => push_pair(output, SCTAG_BOOLEAN, *b as u32),
+        Value::Number(n) => match n.as_i64().and_then(|n| i32::try_from(n).ok()) {
+            Some(n) => push_pair(output, SCTAG_INT32, n as u32),
+            None => output.extend_from_slice(&n.as_f64().unwrap_or_default().to_le_bytes()),
+        },
+        Value::String(s) => push_string(output, s),
+        Value::Array(values) => {
+            push_pair(output, SCTAG_ARRAY_OBJECT, values.len() as u32);
+            for (i, value) in values.iter().enumerate() {
+                push_pair(output, SCTAG_INT32, i as u32);
+                push_value(output, value);
+            }
+            push_pair(output, SCTAG_END_OF_KEYS, 0);
+        }
+        Value::Object(map) => {
+            push_pair(output, SCTAG_OBJECT_OBJECT, 0);
+            for (key, value) in map {
+                push_string(output, key);
+                push_value(output, value);
+            }
+            push_pair(output, SCTAG_END_OF_KEYS, 0);
+        }
+    }
+}
+
+/// Returns the encoding of a string key of IndexedDB.
+fn string_key(key: &str) -> Vec<u8> {
+    let mut output = vec![0x30];
+    for unit in key.encode_utf16() {
+        match unit {
+            0..=0x7e => output.push(unit as u8 + 1),
+            0x7f..=0x407e => output.extend_from_slice(&((unit - 0x7f) | 0x8000).to_be_bytes()),
+            _ => {
+                let unit = ((unit as u32) << 6) | 0x00c0_0000;
+                output.extend_from_slice(&unit.to_be_bytes()[1..]);
+            }
+        }
+    }
+    output
+}
+
+/// Returns an IndexedDB database storing values under string keys, in the first object store.
+pub fn database(entries: &[(&str, &Value)]) -> Vec<u8> {
+    let rows: Vec<Vec<Column>> = entries
+        .iter()
+        .map(|(key, value)| {
+            vec![
+                Column::Integer(1),
+                Column::Blob(string_key(key)),
+                Column::Null,
+                Column::Null,
+                Column::Blob(snappy::compress(&structured_clone(value))),
+            ]
+        })
+        .collect();
+    sqlite("object_data", OBJECT_DATA_SQL, &rows)
+}
+
+/// A column of a SQLite record
+enum Column {
+    Null,
+    Integer(i64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// Appends a big-endian SQLite varint, for values below 2^56.
+fn push_sqlite_varint(output: &mut Vec<u8>, value: u64) {
+    let mut bytes = vec![(value & 0x7f) as u8];
+    let mut value = value >> 7;
+    while value > 0 {
+        bytes.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    output.extend(bytes.iter().rev());
+}
+
+/// Returns the record of the columns of a row.
+fn record(columns: &[Column]) -> Vec<u8> {
+    let mut types = vec![];
+    let mut body = vec![];
+    for column in columns {
+        match column {
+            Column::Null => push_sqlite_varint(&mut types, 0),
+            Column::Integer(0) => push_sqlite_varint(&mut types, 8),
+            Column::Integer(1) => push_sqlite_varint(&mut types, 9),
+            Column::Integer(n) => {
+                push_sqlite_varint(&mut types, 6);
+                body.extend_from_slice(&n.to_be_bytes());
+            }
+            Column::Text(s) => {
+                push_sqlite_varint(&mut types, 13 + 2 * s.len() as u64);
+                body.extend_from_slice(s.as_bytes());
+            }
+            Column::Blob(b) => {
+                push_sqlite_varint(&mut types, 12 + 2 * b.len() as u64);
+                body.extend_from_slice(b);
+            }
+        }
+    }
+
+    // The size of the header includes its own varint
+    let mut size = types.len() + 1;
+    while {
+        let mut varint = vec![];
+        push_sqlite_varint(&mut varint, size as u64);
+        varint.len() + types.len() != size
+    } {
+        size += 1;
+    }
+    let mut output = vec![];
+    push_sqlite_varint(&mut output, size as u64);
+    output.extend_from_slice(&types);
+    output.extend_from_slice(&body);
+    output
+}
+
+/// Returns a SQLite database with a single table, whose rows all fit its root page.
+fn sqlite(name: &str, sql: &str, rows: &[Vec<Column>]) -> Vec<u8> {
+    let mut pages = vec![vec![0u8; PAGE_SIZE], vec![0u8; PAGE_SIZE]];
+    let schema = record(&[
+        Column::Text("table".to_string()),
+        Column::Text(name.to_string()),
+        Column::Text(name.to_string()),
+        Column::Integer(2),
+        Column::Text(sql.to_string()),
+    ]);
+    let schema_cell = cell(1, &schema, &mut pages);
+    write_leaf(&mut pages[0], 100, &[schema_cell]);
+    let cells: Vec<Vec<u8>> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| cell(i as i64 + 1, &record(row), &mut pages))
+        .collect();
+    write_leaf(&mut pages[1], 0, &cells);
+
+    // The database header
+    let page_count = pages.len() as u32;
+    let header = &mut pages[0];
+    header[..16].copy_from_slice(b"SQLite format 3\0");
+    header[16..18].copy_from_slice(&(PAGE_SIZE as u16).to_be_bytes());
+    header[18..24].copy_from_slice(&[1, 1, 0, 64, 32, 32]);
+    header[24..28].copy_from_slice(&1u32.to_be_bytes());
+    header[28..32].copy_from_slice(&page_count.to_be_bytes());
+    header[40..44].copy_from_slice(&1u32.to_be_bytes());
+    header[44..48].copy_from_slice(&4u32.to_be_bytes());
+    header[56..60].copy_from_slice(&1u32.to_be_bytes());
+    header[92..96].copy_from_slice(&1u32.to_be_bytes());
+    header[96..100].copy_from_slice(&3_039_002u32.to_be_bytes());
+    pages.concat()
+}
+
+/// Returns the cell of a row of a table leaf page, spilling its payload to overflow pages.
+fn cell(rowid: i64, payload: &[u8], pages: &mut Vec<Vec<u8>>) -> Vec<u8> {
+    let usable = PAGE_SIZE;
+    let max_local = usable - 35;
+    let min_local = (usable - 12) * 32 / 255 - 23;
+    let local = match payload.len() {
+        len if len <= max_local => len,
+        len => match min_local + (len - min_local) % (usable - 4) {
+            k if k <= max_local => k,
+            _ => min_local,
+        },
+    };
+
+    let mut output = vec![];
+    push_sqlite_varint(&mut output, payload.len() as u64);
+    push_sqlite_varint(&mut output, rowid as u64);
+    output.extend_from_slice(&payload[..local]);
+    if local < payload.len() {
+        output.extend_from_slice(&(pages.len() as u32 + 1).to_be_bytes());
+        let chunks: Vec<&[u8]> = payload[local..].chunks(usable - 4).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let next = if i + 1 < chunks.len() { pages.len() as u32 + 2 } else { 0 };
+            let mut page = next.to_be_bytes().to_vec();
+            page.extend_from_slice(chunk);
+            page.resize(PAGE_SIZE, 0);
+            pages.push(page);
+        }
+    }
+    output
+}
+
+/// Writes the cells of a table leaf page, whose header starts at an offset.
+fn write_leaf(page: &mut [u8], offset: usize, cells: &[Vec<u8>]) {
+    let mut content = PAGE_SIZE;
+    let mut pointers = vec![];
+    for cell in cells {
+        content -= cell.len();
+        page[content..content + cell.len()].copy_from_slice(cell);
+        pointers.extend_from_slice(&(content as u16).to_be_bytes());
+    }
+    assert!(offset + 8 + pointers.len() <= content, "the rows do not fit a page");
+    page[offset] = 0x0d;
+    page[offset + 3..offset + 5].copy_from_slice(&(cells.len() as u16).to_be_bytes());
+    page[offset + 5..offset + 7].copy_from_slice(&(content as u16).to_be_bytes());
+    page[offset + 8..offset + 8 + pointers.len()].copy_from_slice(&pointers);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structured_clone() {
+        let clone = structured_clone(&serde_json::json!({ "vault": "{}", "n": [1] }));
+        assert_eq!(&clone[..8], &[2, 0, 0, 0, 0, 0, 0xf1, 0xff]);
+        // The keys are sorted, with latin-1 strings padded to 8 bytes
+        assert_eq!(&clone[16..24], &[1, 0, 0, 0x80, 4, 0, 0xff, 0xff]);
+        assert_eq!(&clone[24..32], b"n\0\0\0\0\0\0\0");
+        assert_eq!(clone.len() % 8, 0);
+    }
+
+    #[test]
+    fn test_string_key() {
+        assert_eq!(string_key("data"), vec![0x30, b'd' + 1, b'a' + 1, b't' + 1, b'a' + 1]);
+    }
+
+    #[test]
+    fn test_database() {
+        let value = Value::String("x".repeat(20_000));
+        let database = database(&[("data", &value)]);
+        assert_eq!(&database[..16], b"SQLite format 3\0");
+        assert_eq!(database.len() % PAGE_SIZE, 0);
+        let pages = u32::from_be_bytes(database[28..32].try_into().unwrap());
+        assert_eq!(pages as usize, database.len() / PAGE_SIZE);
+    }
+}
@@ -0,0 +1,216 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Writers of the LevelDB files of the Chromium extension storage: the write-ahead logs
+/// (`000003.log`) and the sorted tables they are compacted into (`000005.ldb`).
+///
+/// From:
+/// https://github.com/google/leveldb/blob/main/doc/log_format.md
+/// https://github.com/google/leveldb/blob/main/doc/table_format.md
+use super::snappy::{self, push_varint};
+
+/// The size of the blocks of a log
+const LOG_BLOCK_SIZE: usize = 32 * 1024;
+
+/// The size of a record header of a log: checksum, length and type
+const LOG_HEADER_SIZE: usize = 7;
+
+/// The size the data blocks of a table are cut at
+const TABLE_BLOCK_SIZE: usize = 4 * 1024;
+
+/// The magic number ending a table
+const TABLE_MAGIC: u64 = 0xdb4775248b80fb57;
+
+/// The type of the values, as opposed to deletions
+const TYPE_VALUE: u8 = 1;
+
+/// The compression of the blocks of a table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Snappy,
+}
+
+/// Returns the CRC-32C of bytes.
+pub(crate) fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0x82f63b78 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Returns the CRC-32C of bytes masked as LevelDB stores it.
+fn masked_crc32c(data: &[u8]) -> u32 {
+    let crc = crc32c(data);
+    ((crc >> 15) | (crc << 17)).wrapping_add(0xa282ead8)
+}
+
+/// Returns a log of one write batch per entry, split into records across the blocks of the
+/// log as LevelDB does.
+pub fn log(entries: &[(&[u8], &[u8])]) -> Vec<u8> {
+    let mut output = vec![];
+    for (sequence, (key, value)) in entries.iter().enumerate() {
+        // A write batch of a single put
+        let mut batch = (sequence as u64 + 1).to_le_bytes().to_vec();
+        batch.extend_from_slice(&1u32.to_le_bytes());
+        batch.push(TYPE_VALUE);
+        push_varint(&mut batch, key.len() as u64);
+        batch.extend_from_slice(key);
+        push_varint(&mut batch, value.len() as u64);
+        batch.extend_from_slice(value);
+
+        let mut rest = &batch[..];
+        let mut first = true;
+        loop {
+            // Pad the end of a block too small for a header
+            let left = LOG_BLOCK_SIZE - output.len() % LOG_BLOCK_SIZE;
+            if left < LOG_HEADER_SIZE {
+                output.resize(output.len() + left, 0);
+                continue;
+            }
+
+            let length = rest.len().min(left - LOG_HEADER_SIZE);
+            let last = length == rest.len();
+            let kind: u8 = match (first, last) {
+                (true, true) => 1,
+                (true, false) => 2,
+                (false, false) => 3,
+                (false, true) => 4,
+            };
+            let checksum = masked_crc32c(&[&[kind][..], &rest[..length]].concat());
+            output.extend_from_slice(&checksum.to_le_bytes());
+            output.extend_from_slice(&(length as u16).to_le_bytes());
+            output.push(kind);
+            output.extend_from_slice(&rest[..length]);
+
+            rest = &rest[length..];
+            first = false;
+            if last {
+                break;
+            }
+        }
+    }
+    output
+}
+
+/// Returns a table of the entries, sorted by key.
+pub fn table(entries: &[(&[u8], &[u8])], compression: Compression) -> Vec<u8> {
+    // The internal keys end with the sequence and the type, the newest first
+    let mut internal: Vec<(Vec<u8>, u64, &[u8])> = entries
+        .iter()
+        .enumerate()
+        .map(|(sequence, (key, value))| (key.to_vec(), sequence as u64 + 1, *value))
+        .collect();
+    internal.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+    let mut output = vec![];
+    let mut index = vec![];
+    let mut block = vec![];
+    for (i, (key, sequence, value)) in internal.iter().enumerate() {
+        let mut key = key.clone();
+        key.extend_from_slice(&((sequence << 8) | TYPE_VALUE as u64).to_le_bytes());
+        block.push((key, value.to_vec()));
+
+        let size: usize = block.iter().map(|(key, value)| key.len() + value.len()).sum();
+        if size >= TABLE_BLOCK_SIZE || i == internal.len() - 1 {
+            let last = block.last().map(|(key, _)| key.clone()).unwrap_or_default();
+            let handle = write_block(&mut output, &encode_block(&block), compression);
+            index.push((last, handle));
+            block.clear();
+        }
+    }
+
+    let metaindex = write_block(&mut output, &encode_block(&[]), compression);
+    let index = write_block(&mut output, &encode_block(&index), compression);
+
+    // The footer is padded to a fixed size before the magic number
+    let mut footer = metaindex;
+    footer.extend_from_slice(&index);
+    footer.resize(40, 0);
+    footer.extend_from_slice(&TABLE_MAGIC.to_le_bytes());
+    output.extend_from_slice(&footer);
+    output
+}
+
+/// Encodes the entries of a block, each one a restart point without a shared key prefix.
+fn encode_block(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+    let mut block = vec![];
+    let mut restarts = vec![];
+    for (key, value) in entries {
+        restarts.push(block.len() as u32);
+        push_varint(&mut block, 0);
+        push_varint(&mut block, key.len() as u64);
+        push_varint(&mut block, value.len() as u64);
+        block.extend_from_slice(key);
+        block.extend_from_slice(value);
+    }
+    if restarts.is_empty() {
+        restarts.push(0);
+    }
+    for restart in &restarts {
+        block.extend_from_slice(&restart.to_le_bytes());
+    }
+    block.extend_from_slice(&(restarts.len() as u32).to_le_bytes());
+    block
+}
+
+/// Writes a block with its trailer, compressed when it saves an eighth of its size, and
+/// returns its handle.
+fn write_block(output: &mut Vec<u8>, block: &[u8], compression: Compression) -> Vec<u8> {
+    let compressed = match compression {
+        Compression::Snappy => Some(snappy::compress(block)),
+        Compression::None => None,
+    };
+    let (contents, kind) = match compressed {
+        Some(compressed) if compressed.len() < block.len() - block.len() / 8 => (compressed, 1),
+        _ => (block.to_vec(), 0),
+    };
+
+    let mut handle = vec![];
+    push_varint(&mut handle, output.len() as u64);
+    push_varint(&mut handle, contents.len() as u64);
+
+    let checksum = masked_crc32c(&[&contents[..], &[kind]].concat());
+    output.extend_from_slice(&contents);
+    output.push(kind);
+    output.extend_from_slice(&checksum.to_le_bytes());
+    handle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32c() {
+        assert_eq!(crc32c(b"123456789"), 0xe3069283);
+    }
+
+    #[test]
+    fn test_log() {
+        let value = vec![b'a'; 40_000];
+        let log = log(&[(b"data", &value)]);
+
+        // The batch spans a first record filling the block and a last record
+        assert_eq!(&log[4..7], &[(LOG_BLOCK_SIZE - 7) as u16 as u8, 0x7f, 2]);
+        assert_eq!(log[LOG_BLOCK_SIZE + 6], 4);
+        let checksum = masked_crc32c(&log[6..LOG_BLOCK_SIZE]);
+        assert_eq!(&log[..4], &checksum.to_le_bytes());
+    }
+
+    #[test]
+    fn test_table() {
+        let value = br#"{"KeyringController":{"vault":"{}"},"NetworkController":{}}"#.repeat(200);
+        for compression in [Compression::None, Compression::Snappy] {
+            let table = table(&[(b"data", &value), (b"meta", b"{}")], compression);
+            assert_eq!(&table[table.len() - 8..], &TABLE_MAGIC.to_le_bytes());
+            let compressed = table.len() < value.len();
+            assert_eq!(compressed, compression == Compression::Snappy);
+        }
+    }
+}
@@ -0,0 +1,304 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Synthetic MetaMask stores for tests: vaults of arbitrary keyrings, passwords and PBKDF2
+/// iterations, laid out as the MetaMask versions do and written in the storage formats of the
+/// browsers, so that the locate, extract and decrypt pipeline is tested without real user data.
+///
+/// ```
+/// use wallet_metamask::fixtures::{Store, VaultFixture};
+/// use wallet_metamask::vault::{decrypt_vault, extract_vault_from_bytes};
+///
+/// let fixture = VaultFixture::new("test test test test test test test test test test test junk", "pass");
+/// let vault = extract_vault_from_bytes(&fixture.store(Store::ChromeLog)).unwrap();
+/// let decrypted = decrypt_vault(&vault, "pass").unwrap();
+/// assert_eq!(decrypted.data.mnemonic.to_string(), fixture.mnemonic().unwrap());
+/// ```
+pub mod indexeddb;
+pub mod leveldb;
+pub mod snappy;
+
+use crate::{
+    interactive::{CHROME_EXTENSION_ID, FIREFOX_EXTENSION_ID},
+    password::{key_from_password_with_iterations, Aes256Gcm, DEFAULT_ITERATIONS},
+    types::{KeyMetadata, Vault},
+};
+use aes_gcm::{aead::Aead, KeyInit, Nonce};
+use base64::{engine::general_purpose, Engine as _};
+use leveldb::Compression;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use serde_json::{json, Value};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The HD path of the MetaMask accounts
+pub const HD_PATH: &str = "m/44'/60'/0'/0";
+
+/// The UUID Firefox gives to MetaMask in the synthetic profiles
+pub const FIREFOX_EXTENSION_UUID: &str = "6d9e8b4a-3f2c-4e1a-9b7d-0c5f2a8e1d34";
+
+/// A keyring of a vault
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Keyring {
+    /// The keyring of the mnemonic, the one the vault is decrypted to
+    HdKeyTree { mnemonic: String, number_of_accounts: u32, hd_path: String },
+    /// The imported private keys, in hex
+    SimpleKeyPair { private_keys: Vec<String> },
+    /// A Ledger, whose keys are not in the vault
+    Ledger { hd_path: String },
+}
+
+impl Keyring {
+    /// The keyring of a mnemonic with a single account.
+    pub fn hd_key_tree(mnemonic: &str) -> Self {
+        Self::HdKeyTree {
+            mnemonic: mnemonic.to_string(),
+            number_of_accounts: 1,
+            hd_path: HD_PATH.to_string(),
+        }
+    }
+
+    /// Returns the keyring as MetaMask serializes it, the type first.
+    fn to_json(&self, mnemonic_bytes: bool) -> String {
+        let (r#type, data) = match self {
+            Keyring::HdKeyTree { mnemonic, number_of_accounts, hd_path } => {
+                let mnemonic = match mnemonic_bytes {
+                    true => json!(mnemonic.as_bytes()),
+                    false => json!(mnemonic),
+                };
+                let data = format!(
+                    r#"{{"mnemonic":{},"numberOfAccounts":{},"hdPath":{}}}"#,
+                    mnemonic,
+                    number_of_accounts,
+                    json!(hd_path)
+                );
+                ("HD Key Tree", data)
+            }
+            Keyring::SimpleKeyPair { private_keys } => {
+                ("Simple Key Pair", json!(private_keys).to_string())
+            }
+            Keyring::Ledger { hd_path } => {
+                let data = format!(
+                    r#"{{"hdPath":{},"accounts":[],"accountDetails":{{}},"bridgeUrl":"https://metamask.github.io/eth-ledger-bridge-keyring","implementFullBIP44":false}}"#,
+                    json!(hd_path)
+                );
+                ("Ledger Hardware", data)
+            }
+        };
+        format!(r#"{{"type":{},"data":{}}}"#, json!(r#type), data)
+    }
+}
+
+/// How a MetaMask version lays out the vault
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// The mnemonic in clear, as before MetaMask 3
+    Cleartext,
+    /// Encrypted in the state of the `KeyringController`, with the mnemonics as strings
+    Encrypted,
+    /// Encrypted, with the mnemonics as their UTF-8 bytes as the newer versions serialize them
+    EncryptedBytes,
+}
+
+/// A storage format of the vault
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Store {
+    /// The JSON of the vault alone
+    Raw,
+    /// A LevelDB log of the Chromium extension storage
+    ChromeLog,
+    /// A LevelDB table of the Chromium extension storage
+    ChromeTable(Compression),
+    /// An IndexedDB database of the Firefox extension storage
+    FirefoxIndexedDb,
+}
+
+/// A synthetic MetaMask vault
+#[derive(Clone, Debug)]
+pub struct VaultFixture {
+    pub keyrings: Vec<Keyring>,
+    pub password: String,
+    /// The PBKDF2 iterations, stored in the key metadata when set
+    pub iterations: Option<u32>,
+    pub layout: Layout,
+    /// The seed of the salt and iv, so that the stores of a fixture are reproducible
+    pub seed: u64,
+}
+
+impl VaultFixture {
+    /// A vault of a mnemonic, encrypted with the default iterations.
+    pub fn new(mnemonic: &str, password: &str) -> Self {
+        Self {
+            keyrings: vec![Keyring::hd_key_tree(mnemonic)],
+            password: password.to_string(),
+            iterations: None,
+            layout: Layout::Encrypted,
+            seed: 0,
+        }
+    }
+
+    /// Returns the mnemonic of the first HD keyring, which the vault decrypts to.
+    pub fn mnemonic(&self) -> Option<&str> {
+        self.keyrings.iter().find_map(|keyring| match keyring {
+            Keyring::HdKeyTree { mnemonic, .. } => Some(mnemonic.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Returns the vault, encrypted with the salt and iv drawn from the seed.
+    pub fn vault(&self) -> Vault {
+        if self.layout == Layout::Cleartext {
+            let data = self.mnemonic().unwrap_or_default().to_string();
+            return Vault { data, iv: "".to_string(), salt: None, key_metadata: None };
+        }
+
+        let keyrings: Vec<String> = self
+            .keyrings
+            .iter()
+            .map(|keyring| keyring.to_json(self.layout == Layout::EncryptedBytes))
+            .collect();
+        let plaintext = format!("[{}]", keyrings.join(","));
+
+        let (mut salt, mut iv) = ([0u8; 32], [0u8; 16]);
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut iv);
+        let iterations = self.iterations.unwrap_or(DEFAULT_ITERATIONS);
+        let key = key_from_password_with_iterations(&self.password, Some(&salt), iterations);
+        let data = Aes256Gcm::new(&key.into())
+            .encrypt(Nonce::from_slice(&iv), plaintext.as_bytes())
+            .expect("the plaintext fits AES-GCM");
+
+        Vault {
+            data: general_purpose::STANDARD.encode(data),
+            iv: general_purpose::STANDARD.encode(iv),
+            salt: Some(general_purpose::STANDARD.encode(salt)),
+            key_metadata: self.iterations.map(KeyMetadata::pbkdf2),
+        }
+    }
+
+    /// Returns the JSON of the vault as MetaMask stores it, the key metadata between the iv
+    /// and the salt.
+    pub fn vault_json(&self) -> String {
+        let vault = self.vault();
+        if self.layout == Layout::Cleartext {
+            // The form of the vault decryptor, with the trailing newline of the textarea
+            let seed = json!(format!("{}\n", vault.data));
+            return format!(r#"{{"wallet-seed":{},"password":""}}"#, seed);
+        }
+        // The keys of the objects are sorted, which is the order of MetaMask
+        let mut object = serde_json::Map::new();
+        object.insert("data".to_string(), json!(vault.data));
+        object.insert("iv".to_string(), json!(vault.iv));
+        if let Some(key_metadata) = vault.key_metadata {
+            object.insert("keyMetadata".to_string(), json!(key_metadata));
+        }
+        object.insert("salt".to_string(), json!(vault.salt));
+        Value::Object(object).to_string()
+    }
+
+    /// Returns the state of the controllers of MetaMask, with the vault in the keyrings.
+    pub fn state(&self) -> Value {
+        json!({
+            "AppStateController": {
+                "connectedStatusPopoverHasBeenShown": true,
+                "defaultHomeActiveTabName": null,
+                "recoveryPhraseReminderHasBeenShown": false,
+            },
+            "CurrencyController": {
+                "conversionDate": 1_672_531_200,
+                "conversionRate": 1196.35,
+                "currentCurrency": "usd",
+                "nativeCurrency": "ETH",
+            },
+            "IncomingTransactionsController": {
+                "incomingTransactions": {},
+                "incomingTxLastFetchedBlockByChainId": { "0x1": 16_308_000, "0x5": 8_240_000 },
+            },
+            "KeyringController": { "vault": self.vault_json() },
+            "NetworkController": {
+                "network": "1",
+                "provider": { "chainId": "0x1", "rpcPrefs": {}, "ticker": "ETH", "type": "mainnet" },
+            },
+            "PreferencesController": {
+                "currentLocale": "en",
+                "featureFlags": { "showIncomingTransactions": true },
+                "useBlockie": false,
+            },
+        })
+    }
+
+    /// Returns the entries of the extension storage.
+    fn entries(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        if self.layout == Layout::Cleartext {
+            // The local storage of the pages of the extension, before the extension storage
+            let key =
+                format!("_chrome-extension://{}\0\x01restore-vault-form", CHROME_EXTENSION_ID);
+            return vec![(key.into_bytes(), [&[1u8][..], self.vault_json().as_bytes()].concat())];
+        }
+        vec![
+            (b"data".to_vec(), self.state().to_string().into_bytes()),
+            (b"meta".to_vec(), br#"{"version":74}"#.to_vec()),
+        ]
+    }
+
+    /// Returns the vault in a storage format.
+    pub fn store(&self, store: Store) -> Vec<u8> {
+        let entries = self.entries();
+        let entries: Vec<(&[u8], &[u8])> =
+            entries.iter().map(|(key, value)| (&key[..], &value[..])).collect();
+        match store {
+            Store::Raw => self.vault_json().into_bytes(),
+            Store::ChromeLog => leveldb::log(&entries),
+            Store::ChromeTable(compression) => leveldb::table(&entries, compression),
+            Store::FirefoxIndexedDb => {
+                let state = match self.layout {
+                    Layout::Cleartext => Value::String(self.vault_json()),
+                    _ => self.state(),
+                };
+                indexeddb::database(&[("data", &state), ("meta", &json!({ "version": 74 }))])
+            }
+        }
+    }
+
+    /// Writes the vault in the default profile of a Chromium user data directory, as a log or
+    /// as a table, and returns the path of the store.
+    pub fn write_chrome_profile(
+        &self,
+        user_data_dir: &Path,
+        table: Option<Compression>,
+    ) -> io::Result<PathBuf> {
+        let dir = match self.layout {
+            Layout::Cleartext => user_data_dir.join("Default/Local Storage/leveldb"),
+            _ => user_data_dir.join("Default/Local Extension Settings").join(CHROME_EXTENSION_ID),
+        };
+        let (name, store) = match table {
+            Some(compression) => ("000005.ldb", Store::ChromeTable(compression)),
+            None => ("000003.log", Store::ChromeLog),
+        };
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("LOCK"), "")?;
+        fs::write(dir.join(name), self.store(store))?;
+        Ok(dir.join(name))
+    }
+
+    /// Writes the vault in a Firefox profile of a profiles directory, and returns the path of
+    /// the store.
+    pub fn write_firefox_profile(&self, profiles_dir: &Path) -> io::Result<PathBuf> {
+        let profile = profiles_dir.join("synthetic.default-release");
+        let uuids = json!({ FIREFOX_EXTENSION_ID: FIREFOX_EXTENSION_UUID }).to_string();
+        let prefs = format!("user_pref(\"extensions.webextensions.uuids\", {});\n", json!(uuids));
+        let idb = profile
+            .join("storage/default")
+            .join(format!("moz-extension+++{}^userContextId=4294967295", FIREFOX_EXTENSION_UUID))
+            .join("idb");
+        fs::create_dir_all(&idb)?;
+        fs::write(profile.join("prefs.js"), prefs)?;
+        let path = idb.join("3647222921wleabcEoxlt-eengsairo.sqlite");
+        fs::write(&path, self.store(Store::FirefoxIndexedDb))?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,168 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// A port of the Snappy compressor, so that the synthetic LevelDB tables and IndexedDB values
+/// break up the vaults the way the browsers do.
+///
+/// From:
+/// https://github.com/google/snappy/blob/main/snappy.cc
+/// https://github.com/google/snappy/blob/main/format_description.txt
+
+/// The size of the fragments compressed independently
+const BLOCK_SIZE: usize = 1 << 16;
+
+/// The largest hash table of a fragment
+const MAX_HASH_TABLE_SIZE: usize = 1 << 14;
+
+/// The bytes at the end of a fragment that are always emitted as a literal
+const INPUT_MARGIN_BYTES: usize = 15;
+
+/// Compresses bytes into the Snappy raw format.
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut output = vec![];
+    push_varint(&mut output, input.len() as u64);
+    for fragment in input.chunks(BLOCK_SIZE) {
+        compress_fragment(fragment, &mut output);
+    }
+    output
+}
+
+/// Appends a little-endian base 128 varint.
+pub(crate) fn push_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+fn load32(input: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes([input[i], input[i + 1], input[i + 2], input[i + 3]])
+}
+
+fn compress_fragment(input: &[u8], output: &mut Vec<u8>) {
+    let table_size = input.len().next_power_of_two().clamp(256, MAX_HASH_TABLE_SIZE);
+    let shift = 32 - table_size.trailing_zeros();
+    let hash = |bytes: u32| (bytes.wrapping_mul(0x1e35a7bd) >> shift) as usize;
+    let mut table = vec![0usize; table_size];
+
+    let mut next_emit = 0;
+    if input.len() >= INPUT_MARGIN_BYTES {
+        let limit = input.len() - INPUT_MARGIN_BYTES;
+        let mut ip = 1;
+        let mut next_hash = hash(load32(input, ip));
+        'fragment: loop {
+            // Look for a match, skipping faster through the incompressible data
+            let mut skip = 32;
+            let mut next_ip = ip;
+            let mut candidate;
+            loop {
+                ip = next_ip;
+                let h = next_hash;
+                let bytes_between_lookups = skip >> 5;
+                skip += bytes_between_lookups;
+                next_ip = ip + bytes_between_lookups;
+                if next_ip > limit {
+                    break 'fragment;
+                }
+                next_hash = hash(load32(input, next_ip));
+                candidate = table[h];
+                table[h] = ip;
+                if load32(input, ip) == load32(input, candidate) {
+                    break;
+                }
+            }
+
+            // Emit the bytes before the match, then copies as long as they follow each other
+            emit_literal(&input[next_emit..ip], output);
+            loop {
+                let base = ip;
+                let mut matched = 4;
+                while ip + matched < input.len() &&
+                    input[candidate + matched] == input[ip + matched]
+                {
+                    matched += 1;
+                }
+                ip += matched;
+                emit_copy(base - candidate, matched, output);
+                next_emit = ip;
+                if ip >= limit {
+                    break 'fragment;
+                }
+
+                table[hash(load32(input, ip - 1))] = ip - 1;
+                let h = hash(load32(input, ip));
+                candidate = table[h];
+                table[h] = ip;
+                if load32(input, ip) != load32(input, candidate) {
+                    break;
+                }
+            }
+            ip += 1;
+            next_hash = hash(load32(input, ip));
+        }
+    }
+
+    if next_emit < input.len() {
+        emit_literal(&input[next_emit..], output);
+    }
+}
+
+fn emit_literal(literal: &[u8], output: &mut Vec<u8>) {
+    if literal.is_empty() {
+        return;
+    }
+    let n = literal.len() - 1;
+    if n < 60 {
+        output.push((n << 2) as u8);
+    } else {
+        let bytes = &(n as u32).to_le_bytes()[..(32 - (n as u32).leading_zeros() as usize + 7) / 8];
+        output.push(((59 + bytes.len()) << 2) as u8);
+        output.extend_from_slice(bytes);
+    }
+    output.extend_from_slice(literal);
+}
+
+fn emit_copy(offset: usize, mut len: usize, output: &mut Vec<u8>) {
+    while len >= 68 {
+        emit_copy_at_most_64(offset, 64, output);
+        len -= 64;
+    }
+    if len > 64 {
+        emit_copy_at_most_64(offset, 60, output);
+        len -= 60;
+    }
+    emit_copy_at_most_64(offset, len, output);
+}
+
+fn emit_copy_at_most_64(offset: usize, len: usize, output: &mut Vec<u8>) {
+    if len < 12 && offset < 2048 {
+        output.push((((len - 4) << 2) | ((offset >> 8) << 5) | 1) as u8);
+        output.push(offset as u8);
+    } else {
+        output.push((((len - 1) << 2) | 2) as u8);
+        output.extend_from_slice(&(offset as u16).to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snappy::decompress;
+
+    #[test]
+    fn test_compress() {
+        let repeated =
+            r#"{"AppStateController":{},"CurrencyController":{"currentCurrency":"usd"}}"#;
+        let random: Vec<u8> =
+            (0..200_000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        for input in
+            [&b""[..], b"short", repeated.as_bytes(), &repeated.repeat(3000).into_bytes(), &random]
+        {
+            let compressed = compress(input);
+            assert_eq!(decompress(&compressed).unwrap(), input);
+        }
+        assert!(compress(&repeated.repeat(100).into_bytes()).len() < repeated.len() * 10);
+    }
+}
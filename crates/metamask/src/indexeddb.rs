@@ -0,0 +1,209 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Readers of the IndexedDB stores of Firefox: SQLite databases whose `object_data` rows hold
+/// the values as Snappy compressed structured clones, which have to be decompressed before the
+/// vaults in their strings can be scanned.
+///
+/// Rather than following the b-trees from the schema, every table leaf page of the file is
+/// read and every blob of its rows that decompresses is returned, which also recovers the rows
+/// of the pages SQLite has freed but not yet overwritten.
+///
+/// From:
+/// https://www.sqlite.org/fileformat2.html
+/// https://searchfox.org/mozilla-central/source/dom/indexedDB/ActorsParent.cpp
+use crate::snappy;
+
+/// The magic string the SQLite databases start with
+const HEADER: &[u8] = b"SQLite format 3\0";
+
+/// The size of the database header, at the start of the first page
+const HEADER_LEN: usize = 100;
+
+/// The type of the table leaf pages
+const TABLE_LEAF_PAGE: u8 = 0x0d;
+
+/// Returns whether the bytes are those of a SQLite database.
+pub fn is_database(data: &[u8]) -> bool {
+    data.starts_with(HEADER)
+}
+
+/// Returns the decompressed values of the rows of a database, empty if it is not one.
+pub fn values(data: &[u8]) -> Vec<Vec<u8>> {
+    let Some(database) = Database::new(data) else {
+        return vec![];
+    };
+    (1..=database.page_count())
+        .flat_map(|page| database.payloads(page))
+        .flat_map(|payload| blobs(&payload))
+        .filter_map(|blob| snappy::decompress(&blob).ok())
+        .collect()
+}
+
+/// Reads a big-endian SQLite varint, returning it and its size.
+fn read_varint(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, byte) in data.iter().take(9).enumerate() {
+        if i == 8 {
+            return Some((value << 8 | *byte as u64, 9));
+        }
+        value = value << 7 | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+/// Reads a big-endian integer of some bytes at an offset.
+fn read_be(data: &[u8], i: usize, bytes: usize) -> Option<usize> {
+    let bytes = data.get(i..)?.get(..bytes)?;
+    Some(bytes.iter().fold(0, |n, b| n << 8 | *b as usize))
+}
+
+/// The pages of a database
+struct Database<'a> {
+    data: &'a [u8],
+    page_size: usize,
+    /// The bytes of a page that are not reserved for extensions
+    usable_size: usize,
+}
+
+impl<'a> Database<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        if !is_database(data) || data.len() < HEADER_LEN {
+            return None;
+        }
+        let page_size = match read_be(data, 16, 2)? {
+            1 => 1 << 16,
+            size if (512..=32768).contains(&size) && size.is_power_of_two() => size,
+            _ => return None,
+        };
+        let usable_size = page_size.checked_sub(data[20] as usize).filter(|size| *size >= 480)?;
+        Some(Self { data, page_size, usable_size })
+    }
+
+    fn page_count(&self) -> usize {
+        self.data.len() / self.page_size
+    }
+
+    /// Returns a page by its number, from 1.
+    fn page(&self, number: usize) -> Option<&'a [u8]> {
+        let start = number.checked_sub(1)?.checked_mul(self.page_size)?;
+        self.data.get(start..)?.get(..self.page_size)
+    }
+
+    /// Returns the payloads of the rows of a page, nothing if it is not a table leaf page.
+    fn payloads(&self, number: usize) -> Vec<Vec<u8>> {
+        let Some(page) = self.page(number) else {
+            return vec![];
+        };
+        let header = if number == 1 { HEADER_LEN } else { 0 };
+        if page[header] != TABLE_LEAF_PAGE {
+            return vec![];
+        }
+        let cells = read_be(page, header + 3, 2).unwrap_or_default();
+        (0..cells)
+            .filter_map(|cell| read_be(page, header + 8 + 2 * cell, 2))
+            .filter_map(|offset| self.payload(page.get(offset..)?))
+            .collect()
+    }
+
+    /// Returns the payload of a cell, following its overflow pages.
+    fn payload(&self, cell: &[u8]) -> Option<Vec<u8>> {
+        let (len, len_size) = read_varint(cell)?;
+        let (_, rowid_size) = read_varint(cell.get(len_size..)?)?;
+        let len = usize::try_from(len).ok().filter(|len| *len <= self.data.len())?;
+        let cell = &cell[len_size + rowid_size..];
+
+        // The part of the payload stored in the cell, the rest in the overflow pages
+        let max_local = self.usable_size - 35;
+        let min_local = (self.usable_size - 12) * 32 / 255 - 23;
+        let local = match len {
+            len if len <= max_local => len,
+            len => match min_local + (len - min_local) % (self.usable_size - 4) {
+                k if k <= max_local => k,
+                _ => min_local,
+            },
+        };
+        let mut payload = cell.get(..local)?.to_vec();
+        let mut next = if local < len { read_be(cell, local, 4)? } else { 0 };
+        // A chain longer than the file loops
+        for _ in 0..self.page_count() {
+            if payload.len() >= len || next == 0 {
+                break;
+            }
+            let page = self.page(next)?;
+            let content = &page[4..self.usable_size];
+            payload.extend_from_slice(&content[..content.len().min(len - payload.len())]);
+            next = read_be(page, 0, 4)?;
+        }
+        (payload.len() == len).then_some(payload)
+    }
+}
+
+/// Returns the blob columns of a record.
+fn blobs(record: &[u8]) -> Vec<Vec<u8>> {
+    let Some((header_len, mut i)) = read_varint(record) else {
+        return vec![];
+    };
+    let header_len = (header_len as usize).min(record.len());
+    let mut body = header_len;
+    let mut blobs = vec![];
+    while i < header_len {
+        let Some((serial_type, size)) = read_varint(&record[i..header_len]) else {
+            break;
+        };
+        i += size;
+        let len = match serial_type {
+            0 | 8..=11 => 0,
+            1..=4 => serial_type as usize,
+            5 => 6,
+            6 | 7 => 8,
+            n => ((n - 12) / 2) as usize,
+        };
+        let Some(value) = record.get(body..).and_then(|rest| rest.get(..len)) else {
+            break;
+        };
+        if serial_type >= 12 && serial_type % 2 == 0 {
+            blobs.push(value.to_vec());
+        }
+        body += len;
+    }
+    blobs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::indexeddb::{database, structured_clone};
+    use serde_json::json;
+
+    /// Returns a value that Snappy cannot compress, so that it spills to overflow pages.
+    fn incompressible() -> serde_json::Value {
+        let hex: String =
+            (0..4_000u32).map(|i| format!("{:08x}", i.wrapping_mul(2654435761))).collect();
+        json!({ "vault": hex })
+    }
+
+    #[test]
+    fn test_values() {
+        let small = json!({ "vault": "{}" });
+        let large = incompressible();
+        let data = database(&[("data", &small), ("large", &large)]);
+        assert!(data.len() > 8 * 4096);
+        assert_eq!(values(&data), vec![structured_clone(&small), structured_clone(&large)]);
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(values(b"no database").is_empty());
+        let data = database(&[("data", &incompressible())]);
+        // A truncated overflow chain, then truncated headers
+        assert!(values(&data[..data.len() - 4096]).is_empty());
+        for len in 0..200 {
+            values(&data[..len]);
+        }
+    }
+}
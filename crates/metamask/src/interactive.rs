@@ -6,13 +6,28 @@
 ///
 /// From:
 /// https://support.metamask.io/hc/en-us/articles/360018766351-How-to-use-the-Vault-Decryptor-with-the-MetaMask-Vault-Data
-use crate::types::Vault;
-use crate::vault::extract_vault_from_file;
+use crate::{
+    source::{MetaMaskSource, VaultLocator},
+    types::Vault,
+};
 use inquire::{Password, PasswordDisplayMode};
-use os_info;
-use std::{error::Error, fs, path::PathBuf};
-use tracing::{debug, trace};
-use whoami;
+#[cfg(any(test, feature = "mock"))]
+use mockall::automock;
+use serde_json::Value;
+use std::{
+    env,
+    error::Error,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::debug;
+
+/// The id of MetaMask in the Chrome Web Store
+pub const CHROME_EXTENSION_ID: &str = "nkbihfbeogaeaoehlefnkodbefgpgknn";
+
+/// The id of MetaMask in Firefox Add-ons
+pub const FIREFOX_EXTENSION_ID: &str = "webextension@metamask.io";
 
 // Interactively get the password from the user
 pub fn get_password() -> Result<String, Box<dyn Error>> {
@@ -23,73 +38,424 @@ pub fn get_password() -> Result<String, Box<dyn Error>> {
     Ok(name)
 }
 
-// Find the metamask extension files on the system
-pub fn locate_metamask_extension() -> Result<Vec<PathBuf>, Box<dyn Error>> {
-    // For Windows
-    let user_name = "USER_NAME";
-    let path = format!("C:\\Users\\{}\\AppData\\Local\\Google\\Chrome\\User Data\\Default\\Local Extension Settings\\nkbihfbeogaeaoehlefnkodbefgpgknn", user_name);
-    let win_chrome_vault_path = PathBuf::from(path);
-
-    // For Mac
-    let path = "/Users/USERNAME/Library/Application Support/Google/Chrome/Default/Local Extension Settings/nkbihfbeogaeaoehlefnkodbefgpgknn";
-    let mac_chrome_vault_path = PathBuf::from(path.replace("USERNAME", &whoami::username()));
-
-    // For Ubuntu
-    let path = "/Users/USERNAME/.config/google-chrome/Default/Local Extension Settings/nkbihfbeogaeaoehlefnkodbefgpgknn";
-    let ubuntu_chrome_vault_path = PathBuf::from(path.replace("USERNAME", &whoami::username()));
-
-    // Detect if windows or mac, and set the path accordingly
-    let path = match os_info::get().os_type() {
-        os_info::Type::Windows => win_chrome_vault_path,
-        os_info::Type::Macos => mac_chrome_vault_path,
-        os_info::Type::Ubuntu => ubuntu_chrome_vault_path,
-        _ => panic!("Unsupported OS: {:?}", os_info::get().os_type()),
+/// An operating system, which lays out the profiles of the browsers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Os {
+    Windows,
+    MacOs,
+    /// Linux, and the other Unixes laying out the profiles the same way
+    Linux,
+}
+
+impl Os {
+    /// Returns the operating system this is running on.
+    pub fn current() -> Self {
+        if cfg!(target_os = "windows") {
+            Os::Windows
+        } else if cfg!(target_os = "macos") {
+            Os::MacOs
+        } else {
+            Os::Linux
+        }
+    }
+}
+
+/// An entry of a directory
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DirEntry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+/// The operating system, variables and files the stores are located in, behind a trait so that
+/// the layouts of every platform can be tested against fake directory trees
+#[cfg_attr(any(test, feature = "mock"), automock)]
+pub trait Environment {
+    /// Returns the operating system.
+    fn os(&self) -> Os;
+
+    /// Returns the value of an environment variable, if set.
+    fn var_os(&self, key: &str) -> Option<OsString>;
+
+    /// Returns the entries of a directory, empty when it cannot be read.
+    fn read_dir(&self, dir: &Path) -> Vec<DirEntry>;
+
+    /// Returns the contents of a text file, if it can be read.
+    fn read_to_string(&self, path: &Path) -> Option<String>;
+}
+
+/// The environment of the running process
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemEnvironment;
+
+impl Environment for SystemEnvironment {
+    fn os(&self) -> Os {
+        Os::current()
+    }
+
+    fn var_os(&self, key: &str) -> Option<OsString> {
+        env::var_os(key)
+    }
+
+    fn read_dir(&self, dir: &Path) -> Vec<DirEntry> {
+        let Ok(entries) = fs::read_dir(dir) else { return vec![] };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir() || path.is_file())
+            .map(|path| DirEntry { is_dir: path.is_dir(), path })
+            .collect()
+    }
+
+    fn read_to_string(&self, path: &Path) -> Option<String> {
+        fs::read_to_string(path).ok()
+    }
+}
+
+/// Returns the home directory of the user.
+fn home_dir(env: &impl Environment) -> Option<PathBuf> {
+    env.var_os("HOME").or_else(|| env.var_os("USERPROFILE")).map(PathBuf::from)
+}
+
+/// Returns the files of a directory with one of the extensions.
+pub fn files_with_extensions(
+    env: &impl Environment,
+    dir: &Path,
+    extensions: &[&str],
+) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = env
+        .read_dir(dir)
+        .into_iter()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| entry.path)
+        .filter(|path| path.extension().map_or(false, |ext| extensions.iter().any(|e| ext == *e)))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Returns the subdirectories of a directory.
+fn subdirectories(env: &impl Environment, dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = env
+        .read_dir(dir)
+        .into_iter()
+        .filter(|entry| entry.is_dir)
+        .map(|entry| entry.path)
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Locates the LevelDB stores of MetaMask in the profiles of the Chromium browsers
+#[derive(Clone, Debug, Default)]
+pub struct ChromeLocator<E = SystemEnvironment> {
+    /// The user data directories of the browsers, the platform ones when empty
+    pub user_data_dirs: Vec<PathBuf>,
+    pub env: E,
+}
+
+/// Returns the user data directories of Chrome, Chromium, Brave and Edge on the platform.
+pub fn chromium_user_data_dirs(env: &impl Environment) -> Vec<PathBuf> {
+    let (base, browsers) = match env.os() {
+        Os::Windows => (
+            env.var_os("LOCALAPPDATA").map(PathBuf::from),
+            [
+                "Google/Chrome/User Data",
+                "Chromium/User Data",
+                "BraveSoftware/Brave-Browser/User Data",
+                "Microsoft/Edge/User Data",
+            ],
+        ),
+        Os::MacOs => (
+            home_dir(env).map(|home| home.join("Library/Application Support")),
+            ["Google/Chrome", "Chromium", "BraveSoftware/Brave-Browser", "Microsoft Edge"],
+        ),
+        Os::Linux => (
+            home_dir(env).map(|home| home.join(".config")),
+            ["google-chrome", "chromium", "BraveSoftware/Brave-Browser", "microsoft-edge"],
+        ),
     };
+    base.map_or(vec![], |base| browsers.iter().map(|browser| base.join(browser)).collect())
+}
 
-    // Check if the path is a directory
-    if !path.exists() && !path.is_dir() {
-        debug!("Could not find MetaMask extension at: {:?}", path);
-        return Err("Could not find MetaMask extension".into());
+/// Returns the profiles of the user data directories of the Chromium browsers, the platform ones
+/// when empty.
+pub fn chromium_profiles(env: &impl Environment, user_data_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let user_data_dirs = match user_data_dirs.is_empty() {
+        true => chromium_user_data_dirs(env),
+        false => user_data_dirs.to_vec(),
+    };
+    user_data_dirs
+        .iter()
+        .flat_map(|dir| subdirectories(env, dir))
+        .filter(|profile| {
+            let name = profile.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            name == "Default" || name.starts_with("Profile ")
+        })
+        .collect()
+}
+
+impl<E: Environment> VaultLocator for ChromeLocator<E> {
+    fn locate(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        Ok(chromium_profiles(&self.env, &self.user_data_dirs)
+            .into_iter()
+            .flat_map(|profile| {
+                let store = profile.join("Local Extension Settings").join(CHROME_EXTENSION_ID);
+                debug!("Looking for MetaMask at: {:?}", store);
+                files_with_extensions(&self.env, &store, &["log", "ldb"])
+            })
+            .collect())
     }
+}
+
+/// Locates the IndexedDB stores of MetaMask in the Firefox profiles
+#[derive(Clone, Debug, Default)]
+pub struct FirefoxLocator<E = SystemEnvironment> {
+    /// The directory of the profiles, the platform one when none
+    pub profiles_dir: Option<PathBuf>,
+    pub env: E,
+}
 
-    // Attempt to open all files w/ the extension .log
-    let files = fs::read_dir(path).unwrap().filter_map(|entry| {
-        let path = entry.unwrap().path();
-        if path.is_file() {
-            if let Some(ext) = path.extension() {
-                if ext == "log" || ext == "ldb" {
-                    return Some(path);
-                }
-            }
+impl<E: Environment> FirefoxLocator<E> {
+    /// Returns the directory of the Firefox profiles on the platform.
+    fn platform_profiles_dir(&self) -> Option<PathBuf> {
+        match self.env.os() {
+            Os::Windows => self
+                .env
+                .var_os("APPDATA")
+                .map(|dir| PathBuf::from(dir).join("Mozilla/Firefox/Profiles")),
+            Os::MacOs => home_dir(&self.env)
+                .map(|home| home.join("Library/Application Support/Firefox/Profiles")),
+            Os::Linux => home_dir(&self.env).map(|home| home.join(".mozilla/firefox")),
         }
-        None
-    });
+    }
 
-    // Return a vec of all files of full paths
-    Ok(files.collect())
+    /// Returns the internal UUID Firefox gave to MetaMask in a profile, from its `prefs.js`.
+    fn extension_uuid(&self, profile: &Path) -> Option<String> {
+        let prefs = self.env.read_to_string(&profile.join("prefs.js"))?;
+        let line =
+            prefs.lines().find(|line| line.contains("\"extensions.webextensions.uuids\""))?;
+        // user_pref("extensions.webextensions.uuids", "{\"webextension@metamask.io\":\"...\"}");
+        let value = line.split_once(", ")?.1.trim_end_matches(");");
+        let uuids: String = serde_json::from_str(value).ok()?;
+        let uuids: Value = serde_json::from_str(&uuids).ok()?;
+        uuids[FIREFOX_EXTENSION_ID].as_str().map(str::to_string)
+    }
+}
+
+impl<E: Environment> VaultLocator for FirefoxLocator<E> {
+    fn locate(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let Some(profiles_dir) = self.profiles_dir.clone().or_else(|| self.platform_profiles_dir()) else {
+            return Ok(vec![]);
+        };
+        Ok(subdirectories(&self.env, &profiles_dir)
+            .iter()
+            .filter_map(|profile| Some((profile, self.extension_uuid(profile)?)))
+            .flat_map(|(profile, uuid)| {
+                let prefix = format!("moz-extension+++{}", uuid);
+                debug!(
+                    "Looking for MetaMask at: {:?}",
+                    profile.join("storage/default").join(&prefix)
+                );
+                subdirectories(&self.env, &profile.join("storage/default"))
+                    .into_iter()
+                    .filter(move |dir| {
+                        dir.file_name()
+                            .and_then(|name| name.to_str())
+                            .map_or(false, |name| name.starts_with(&prefix))
+                    })
+                    .flat_map(|dir| files_with_extensions(&self.env, &dir.join("idb"), &["sqlite"]))
+            })
+            .collect())
+    }
+}
+
+// Find the metamask extension files on the system
+pub fn locate_metamask_extension() -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = vec![];
+    for source in MetaMaskSource::all() {
+        files.extend(source.locate()?);
+    }
+
+    if files.is_empty() {
+        debug!("Could not find MetaMask extension");
+        return Err("Could not find MetaMask extension".into());
+    }
+    Ok(files)
 }
 
 // Extract all vaults from the extension files
 pub fn extract_all_vaults() -> Result<Vec<Vault>, Box<dyn Error>> {
-    let a = locate_metamask_extension()?;
+    let mut vaults = vec![];
+    for source in MetaMaskSource::all() {
+        vaults.extend(source.vaults()?.into_iter().map(|found| found.vault));
+    }
+    Ok(vaults)
+}
 
-    // Collect all vaults that are found
-    let vaults: Vec<Vault> = a
-        .iter()
-        .filter_map(|a| {
-            trace!("Attempting to decrypt vault: {:?}", a);
-
-            // Attempt to extract the vault from the extension
-            let vault = extract_vault_from_file(a);
-
-            // Return the vault if it exists, continue otherwise
-            if let Ok(vault) = vault {
-                Some(vault)
-            } else {
-                None
-            }
-        })
-        .collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::collections::{BTreeMap, BTreeSet};
 
-    Ok(vaults)
+    /// Returns an environment of an operating system, its variables and a fake tree of files.
+    fn fake_environment(
+        os: Os,
+        vars: &[(&str, &str)],
+        files: &[(String, &str)],
+    ) -> MockEnvironment {
+        let vars: BTreeMap<String, OsString> =
+            vars.iter().map(|(key, value)| (key.to_string(), value.into())).collect();
+        let files: BTreeMap<PathBuf, String> =
+            files.iter().map(|(path, contents)| (path.into(), contents.to_string())).collect();
+        let tree = files.clone();
+
+        let mut env = MockEnvironment::new();
+        env.expect_os().return_const(os);
+        env.expect_var_os().returning(move |key| vars.get(key).cloned());
+        env.expect_read_dir().returning(move |dir| {
+            // The children of the directory leading to the files below it
+            let children: BTreeSet<DirEntry> = tree
+                .keys()
+                .filter_map(|path| {
+                    let mut rest = path.strip_prefix(dir).ok()?.components();
+                    let child = dir.join(rest.next()?);
+                    Some(DirEntry { is_dir: rest.next().is_some(), path: child })
+                })
+                .collect();
+            children.into_iter().collect()
+        });
+        env.expect_read_to_string().returning(move |path| files.get(path).cloned());
+        env
+    }
+
+    #[test]
+    fn test_chrome_locator_platforms() -> Result<()> {
+        let platforms = [
+            (
+                Os::Windows,
+                ("LOCALAPPDATA", "C:/Users/satoshi/AppData/Local"),
+                "C:/Users/satoshi/AppData/Local/Google/Chrome/User Data",
+            ),
+            (
+                Os::MacOs,
+                ("HOME", "/Users/satoshi"),
+                "/Users/satoshi/Library/Application Support/BraveSoftware/Brave-Browser",
+            ),
+            (Os::Linux, ("HOME", "/home/satoshi"), "/home/satoshi/.config/chromium"),
+        ];
+        for (os, var, user_data_dir) in platforms {
+            let store = format!(
+                "{}/Profile 1/Local Extension Settings/{}",
+                user_data_dir, CHROME_EXTENSION_ID
+            );
+            let log = format!("{}/000003.log", store);
+            let ldb = format!("{}/000005.ldb", store);
+            let files = [
+                (log.clone(), ""),
+                (ldb.clone(), ""),
+                (format!("{}/LOCK", store), ""),
+                (format!("{}/Default/Preferences", user_data_dir), "{}"),
+                (
+                    format!(
+                        "{}/System Profile/Local Extension Settings/{}/000003.log",
+                        user_data_dir, CHROME_EXTENSION_ID
+                    ),
+                    "",
+                ),
+            ];
+            let env = fake_environment(os, &[var], &files);
+
+            let locator = ChromeLocator { user_data_dirs: vec![], env };
+            let found = locator.locate().map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(found, vec![PathBuf::from(&log), PathBuf::from(&ldb)], "{:?}", os);
+        }
+
+        // The platform directories of Linux are not the ones of macOS
+        let store =
+            "/home/satoshi/Library/Application Support/Chromium/Default/Local Extension Settings";
+        let files = [(format!("{}/{}/000003.log", store, CHROME_EXTENSION_ID), "")];
+        let env = fake_environment(Os::Linux, &[("HOME", "/home/satoshi")], &files);
+        assert!(ChromeLocator { user_data_dirs: vec![], env }.locate().unwrap().is_empty());
+
+        // Nothing is located without the variables
+        let env = fake_environment(Os::Windows, &[("HOME", "C:/Users/satoshi")], &[]);
+        assert!(ChromeLocator { user_data_dirs: vec![], env }.locate().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_firefox_locator_platforms() -> Result<()> {
+        let prefs = r#"user_pref("extensions.webextensions.uuids", "{\"webextension@metamask.io\":\"0a1b2c3d-uuid\"}");"#;
+        let platforms = [
+            (
+                Os::Windows,
+                ("APPDATA", "C:/Users/satoshi/AppData/Roaming"),
+                "C:/Users/satoshi/AppData/Roaming/Mozilla/Firefox/Profiles",
+            ),
+            (
+                Os::MacOs,
+                ("HOME", "/Users/satoshi"),
+                "/Users/satoshi/Library/Application Support/Firefox/Profiles",
+            ),
+            (Os::Linux, ("HOME", "/home/satoshi"), "/home/satoshi/.mozilla/firefox"),
+        ];
+        for (os, var, profiles_dir) in platforms {
+            let storage = format!("{}/abcd1234.default-release/storage/default", profiles_dir);
+            let sqlite = format!(
+                "{}/moz-extension+++0a1b2c3d-uuid^userContextId=4294967295/idb/3647222921wleabcEoxlt-eengsairo.sqlite",
+                storage
+            );
+            let files = [
+                (format!("{}/abcd1234.default-release/prefs.js", profiles_dir), prefs),
+                (sqlite.clone(), ""),
+                (format!("{}/moz-extension+++ffffffff-uuid/idb/1.sqlite", storage), ""),
+                // A profile without MetaMask
+                (format!("{}/efgh5678.default/prefs.js", profiles_dir), ""),
+            ];
+            let env = fake_environment(os, &[var], &files);
+
+            let locator = FirefoxLocator { profiles_dir: None, env };
+            let found = locator.locate().map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(found, vec![PathBuf::from(&sqlite)], "{:?}", os);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_chrome_locator() -> Result<()> {
+        let dir = env::temp_dir().join(format!("metamask-chrome-{}", std::process::id()));
+        let store = dir.join("Profile 2/Local Extension Settings").join(CHROME_EXTENSION_ID);
+        fs::create_dir_all(&store)?;
+        fs::write(store.join("000003.log"), "")?;
+        fs::write(store.join("LOCK"), "")?;
+        fs::create_dir_all(dir.join("System Profile"))?;
+
+        let locator = ChromeLocator { user_data_dirs: vec![dir.clone()], env: SystemEnvironment };
+        let files = locator.locate().map_err(|e| anyhow::anyhow!("{}", e))?;
+        fs::remove_dir_all(&dir)?;
+        assert_eq!(files, vec![store.join("000003.log")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_firefox_locator() -> Result<()> {
+        let dir = env::temp_dir().join(format!("metamask-firefox-{}", std::process::id()));
+        let profile = dir.join("abcd1234.default-release");
+        let idb = profile
+            .join("storage/default/moz-extension+++0a1b2c3d-uuid^userContextId=4294967295/idb");
+        fs::create_dir_all(&idb)?;
+        fs::write(idb.join("3647222921wleabcEoxlt-eengsairo.sqlite"), "")?;
+        fs::write(
+            profile.join("prefs.js"),
+            r#"user_pref("extensions.webextensions.uuids", "{\"webextension@metamask.io\":\"0a1b2c3d-uuid\"}");"#,
+        )?;
+
+        let locator = FirefoxLocator { profiles_dir: Some(dir.clone()), env: SystemEnvironment };
+        let files = locator.locate().map_err(|e| anyhow::anyhow!("{}", e))?;
+        fs::remove_dir_all(&dir)?;
+        assert_eq!(files, vec![idb.join("3647222921wleabcEoxlt-eengsairo.sqlite")]);
+        Ok(())
+    }
 }
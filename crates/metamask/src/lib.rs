@@ -2,9 +2,15 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-#![feature(array_chunks)]
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+pub mod indexeddb;
 pub mod interactive;
+pub mod lightwallet;
+pub mod mmap;
 pub mod password;
 pub mod regex;
+pub mod snappy;
+pub mod source;
 pub mod types;
 pub mod vault;
@@ -0,0 +1,224 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Rust port of the eth-lightwallet keystore decryption, the vaults of MetaMask before v3.
+///
+/// The key is derived from the password with scrypt and decrypts the NaCl secretboxes of the
+/// keystore, the seed left padded with spaces.
+///
+/// From:
+/// https://github.com/ConsenSys/eth-lightwallet/blob/v2.5.6/lib/keystore.js
+use crate::types::{
+    DecryptedVault, LightwalletCiphertext, LightwalletKeystore, LightwalletPathData, MnemoicData,
+    StringOrBytes,
+};
+use base64::{engine::general_purpose, Engine as _};
+use crypto_secretbox::{
+    aead::{Aead, KeyInit},
+    Nonce, XSalsa20Poly1305,
+};
+use rand::{thread_rng, RngCore};
+use scrypt::{scrypt, Params};
+use sha2::{Digest, Sha512};
+use std::error::Error;
+
+/// The salt of the key derivation of the keystores before v3
+pub const LIGHTWALLET_SALT: &str = "lightwalletSalt";
+
+/// The HD path of the accounts of the keystores before v3
+pub const LIGHTWALLET_HD_PATH: &str = "m/0'/0'/0'";
+
+/// The scrypt cost of the key derivation, as a power of two
+#[cfg(not(fuzzing))]
+const LOG_N: u8 = 14;
+
+/// The scrypt cost the fuzzing lowers the key derivation to, so that decrypting an input costs no
+/// more than parsing it
+#[cfg(fuzzing)]
+const LOG_N: u8 = 1;
+
+/// The length the seed is left padded to before its encryption
+const SEED_LENGTH: usize = 120;
+
+/// Derives the key of a keystore from a password and its salt, if any.
+///
+/// From:
+/// https://github.com/ConsenSys/eth-lightwallet/blob/v2.5.6/lib/keystore.js#L507
+pub fn key_from_password(password: &str, salt: Option<&str>) -> Result<[u8; 32], Box<dyn Error>> {
+    let params = Params::new(LOG_N, 8, 1).map_err(|e| e.to_string())?;
+    let salt = salt.unwrap_or(LIGHTWALLET_SALT);
+    let mut key = [0u8; 32];
+    scrypt(password.as_bytes(), salt.as_bytes(), &params, &mut key).map_err(|e| e.to_string())?;
+    Ok(key)
+}
+
+/// Returns the hash of a key the keystores before v3 keep to tell a wrong password.
+pub fn key_hash(key: &[u8; 32]) -> String {
+    general_purpose::STANDARD.encode(Sha512::digest(key))
+}
+
+/// Encrypts a string into a secretbox under a random nonce.
+///
+/// From:
+/// https://github.com/ConsenSys/eth-lightwallet/blob/v2.5.6/lib/keystore.js#L40
+pub fn encrypt_string(
+    string: &str,
+    key: &[u8; 32],
+) -> Result<LightwalletCiphertext, Box<dyn Error>> {
+    let mut nonce = [0u8; 24];
+    thread_rng().fill_bytes(&mut nonce);
+    let cipher = XSalsa20Poly1305::new(key.into());
+    let data =
+        cipher.encrypt(Nonce::from_slice(&nonce), string.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(LightwalletCiphertext {
+        enc_str: general_purpose::STANDARD.encode(data),
+        nonce: general_purpose::STANDARD.encode(nonce),
+    })
+}
+
+/// Decrypts a secretbox into a string.
+///
+/// From:
+/// https://github.com/ConsenSys/eth-lightwallet/blob/v2.5.6/lib/keystore.js#L50
+pub fn decrypt_string(
+    ciphertext: &LightwalletCiphertext,
+    key: &[u8; 32],
+) -> Result<String, Box<dyn Error>> {
+    let data = general_purpose::STANDARD.decode(&ciphertext.enc_str)?;
+    let nonce = general_purpose::STANDARD.decode(&ciphertext.nonce)?;
+    if nonce.len() != 24 {
+        return Err(format!("Invalid nonce length {}", nonce.len()).into());
+    }
+    let cipher = XSalsa20Poly1305::new(key.into());
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), data.as_ref())
+        .map_err(|_| "Incorrect password")?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+/// Encrypts a seed into a keystore of the version of MetaMask before v3, deriving its
+/// accounts along the default HD path.
+pub fn encrypt_keystore(
+    seed: &str,
+    password: &str,
+    number_of_accounts: u32,
+) -> Result<LightwalletKeystore, Box<dyn Error>> {
+    let key = key_from_password(password, None)?;
+    let padded = format!("{:>width$}", seed, width = SEED_LENGTH);
+    let path = LightwalletPathData { addresses: vec![], hd_index: Some(number_of_accounts) };
+    Ok(LightwalletKeystore {
+        enc_seed: encrypt_string(&padded, &key)?,
+        enc_hd_root_priv: None,
+        key_hash: Some(key_hash(&key)),
+        salt: None,
+        ks_data: [(LIGHTWALLET_HD_PATH.to_string(), path)].into_iter().collect(),
+        hd_path_string: None,
+        version: 2,
+    })
+}
+
+/// Decrypts the seed of a keystore, with the HD path and the number of its accounts.
+///
+/// From:
+/// https://github.com/ConsenSys/eth-lightwallet/blob/v2.5.6/lib/keystore.js#L381
+pub fn decrypt_keystore(
+    keystore: &LightwalletKeystore,
+    password: &str,
+) -> Result<DecryptedVault, Box<dyn Error>> {
+    let key = key_from_password(password, keystore.salt.as_deref())?;
+    if keystore.key_hash.as_ref().map_or(false, |hash| *hash != key_hash(&key)) {
+        return Err("Incorrect password".into());
+    }
+    let seed = decrypt_string(&keystore.enc_seed, &key)?;
+
+    // The v3 keystores name their path, the older ones key their accounts by it
+    let (hd_path, path_data) = match &keystore.hd_path_string {
+        Some(path) => (path.clone(), keystore.ks_data.get(path)),
+        None => match keystore.ks_data.iter().next() {
+            Some((path, data)) => (path.clone(), Some(data)),
+            None => (LIGHTWALLET_HD_PATH.to_string(), None),
+        },
+    };
+    let number_of_accounts = path_data
+        .and_then(|data| data.hd_index.or(Some(data.addresses.len() as u32)))
+        .filter(|n| *n > 0);
+
+    let data = MnemoicData {
+        mnemonic: StringOrBytes::String(seed.trim().to_string()),
+        number_of_accounts,
+        hd_path: Some(hd_path),
+    };
+    Ok(DecryptedVault { r#type: Some("HD Key Tree".to_string()), data, skipped: vec![] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    const SEED: &str = "spin tomato fluid drum awkward boat stay cherry decline pulp lemon glide";
+
+    #[test]
+    fn test_string_roundtrip() -> Result<()> {
+        let key = [7u8; 32];
+        let ciphertext = encrypt_string("hello", &key).unwrap();
+
+        // The tag of the secretbox comes before the ciphertext
+        let data = general_purpose::STANDARD.decode(&ciphertext.enc_str)?;
+        assert_eq!(data.len(), 16 + 5);
+        assert_eq!(decrypt_string(&ciphertext, &key).unwrap(), "hello");
+        assert!(decrypt_string(&ciphertext, &[8u8; 32]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_from_password() {
+        // The RFC 7914 vector, with the parameters of the keystores
+        let key = key_from_password("pleaseletmein", Some("SodiumChloride")).unwrap();
+        let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "7023bdcb3afd7348461c06cd81fd38ebfda8fbba904f8e3ea9b543f6545da1f2");
+    }
+
+    #[test]
+    fn test_decrypt_keystore() -> Result<()> {
+        let keystore = encrypt_keystore(SEED, "correct horse", 3).unwrap();
+        let json = serde_json::to_string(&keystore)?;
+        assert!(json.contains(r#""version":2}"#));
+
+        let keystore: LightwalletKeystore = serde_json::from_str(&json)?;
+        let decrypted = decrypt_keystore(&keystore, "correct horse").unwrap();
+        assert_eq!(decrypted.data.mnemonic.to_string(), SEED);
+        assert_eq!(decrypted.data.number_of_accounts, Some(3));
+        assert_eq!(decrypted.data.hd_path.as_deref(), Some(LIGHTWALLET_HD_PATH));
+
+        let error = decrypt_keystore(&keystore, "wrong").unwrap_err();
+        assert_eq!(error.to_string(), "Incorrect password");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decrypt_fixture() -> Result<()> {
+        // A keystore serialized in the layout of eth-lightwallet 2.x, with its encrypted HD keys
+        // and the two accounts of the seed at m/0'/0'/0'/0 and m/0'/0'/0'/1
+        let json = include_str!("../tests/fixtures/eth-lightwallet-2.x/keystore.json");
+        let keystore: LightwalletKeystore = serde_json::from_str(json)?;
+        assert_eq!(keystore.ks_data[LIGHTWALLET_HD_PATH].addresses.len(), 2);
+
+        let decrypted = decrypt_keystore(&keystore, "Nu3Ahlie7ohque").unwrap();
+        assert_eq!(
+            decrypted.data.mnemonic.to_string(),
+            "rubber twist same simple task height always fiction visual interest ahead permit"
+        );
+        assert_eq!(decrypted.data.number_of_accounts, Some(2));
+        assert_eq!(decrypted.data.hd_path.as_deref(), Some(LIGHTWALLET_HD_PATH));
+
+        // The key hash tells the wrong password before the secretbox does
+        let mut keystore = keystore;
+        assert!(decrypt_keystore(&keystore, "nu3ahlie7ohque").is_err());
+        keystore.key_hash = None;
+        let error = decrypt_keystore(&keystore, "nu3ahlie7ohque").unwrap_err();
+        assert_eq!(error.to_string(), "Incorrect password");
+        Ok(())
+    }
+}
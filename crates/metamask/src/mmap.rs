@@ -0,0 +1,84 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Read-only memory maps of the stores, so that large LevelDB files are scanned in place
+/// rather than copied into memory.
+///
+/// A map is only sound while no other process truncates the file: reading the pages past its
+/// new end raises SIGBUS. The browser compacts its LevelDB files while it runs, so only the
+/// files at least [MIN_MAP_LEN] long are mapped, the `.ldb` tables that LevelDB never modifies
+/// in place, and the others, e.g. the `.log` files being appended to, are read into memory.
+///
+/// From:
+/// https://docs.rs/memmap2/0.6.2/memmap2/struct.Mmap.html#safety
+use std::{fs::File, io, ops::Deref, path::Path};
+
+/// The shortest file that is mapped rather than read
+pub const MIN_MAP_LEN: u64 = 1 << 20;
+
+/// The read-only contents of a file
+pub enum Mmap {
+    Mapped(memmap2::Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Mmap {
+    /// Maps a LevelDB table into memory, or reads any other file.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let table = path.extension().map_or(false, |extension| extension == "ldb");
+        if !table || len < MIN_MAP_LEN {
+            return Ok(Mmap::Owned(std::fs::read(path)?));
+        }
+
+        // SAFETY: the tables are immutable once written, LevelDB deletes them whole after a
+        // compaction, which leaves the mapped pages readable until they are unmapped.
+        Ok(Mmap::Mapped(unsafe { memmap2::Mmap::map(&file)? }))
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Mmap::Mapped(map) => map,
+            Mmap::Owned(data) => data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_mmap() -> Result<()> {
+        let path =
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/chrome-windows-1/000005.ldb");
+        let mmap = Mmap::open(path)?;
+        assert_eq!(&mmap[..], &std::fs::read(path)?[..]);
+
+        // A table long enough to be mapped, and a log of the same length that is read
+        let dir = std::env::temp_dir().join(format!("metamask-mmap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let data: Vec<u8> = (0..MIN_MAP_LEN).map(|i| i as u8).collect();
+        std::fs::write(dir.join("000001.ldb"), &data)?;
+        std::fs::write(dir.join("000002.log"), &data)?;
+        let table = Mmap::open(dir.join("000001.ldb"))?;
+        assert!(matches!(table, Mmap::Mapped(_)));
+        assert_eq!(&table[..], &data[..]);
+        assert!(matches!(Mmap::open(dir.join("000002.log"))?, Mmap::Owned(_)));
+
+        File::create(dir.join("empty.ldb"))?;
+        assert!(Mmap::open(dir.join("empty.ldb"))?.is_empty());
+        std::fs::remove_dir_all(&dir)?;
+
+        assert!(Mmap::open("/missing").is_err());
+        Ok(())
+    }
+}
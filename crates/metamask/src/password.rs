@@ -57,6 +57,7 @@ pub fn encrypt(
         data: general_purpose::STANDARD.encode(data),
         iv: general_purpose::STANDARD.encode(nonce),
         salt,
+        key_metadata: None,
     };
 
     Ok(serde_json::to_string(&text).unwrap())
@@ -74,7 +75,10 @@ pub fn decrypt(
     // Decode the nonce and encrypted data.
     let data = general_purpose::STANDARD.decode(ciphertext.data.as_bytes())?;
     let nonce_bytes = general_purpose::STANDARD.decode(ciphertext.iv.as_bytes())?;
-    let nonce_slice: [u8; 16] = *nonce_bytes.as_slice().array_chunks::<16>().next().unwrap();
+    let nonce_slice: [u8; 16] = nonce_bytes
+        .get(..16)
+        .and_then(|nonce| nonce.try_into().ok())
+        .ok_or("The iv must be at least 16 bytes")?;
 
     // Create a key from the password and salt
     let salt = ciphertext.salt.as_ref().map(|s| s.as_bytes());
@@ -89,9 +93,21 @@ pub fn decrypt(
     let data = cipher.decrypt(nonce, data.as_ref()).map_err(|e| e.to_string())?;
 
     // Return the decrypted data.
-    Ok(String::from_utf8(data).unwrap())
+    Ok(String::from_utf8(data)?)
 }
 
+/// The PBKDF2 iterations of the vaults without key metadata.
+pub const DEFAULT_ITERATIONS: u32 = 10_000;
+
+/// The most PBKDF2 iterations of the key metadata of a vault that are attempted, since they are
+/// read from untrusted stores: MetaMask uses 600,000
+pub const MAX_ITERATIONS: u32 = 5_000_000;
+
+/// The PBKDF2 iterations the fuzzing caps the key derivations to, so that decrypting an input
+/// costs no more than parsing it
+#[cfg(fuzzing)]
+const FUZZING_ITERATIONS: u32 = 1;
+
 /// Derives a key from a password and random salt.
 ///
 /// The key is derived using PBKDF2_HMAC_SHA256 with 10,000 iterations.
@@ -99,12 +115,24 @@ pub fn decrypt(
 /// From:
 /// https://github.com/MetaMask/browser-passworder/blob/a8574c40d1e42b2bc2c2b3d330b0ea50aa450017/src/index.ts#L214
 pub fn key_from_password(password: &str, salt: Option<&[u8]>) -> [u8; 32] {
+    key_from_password_with_iterations(password, salt, DEFAULT_ITERATIONS)
+}
+
+/// Derives a key from a password and random salt with the iterations of the key metadata of a
+/// vault.
+pub fn key_from_password_with_iterations(
+    password: &str,
+    salt: Option<&[u8]>,
+    iterations: u32,
+) -> [u8; 32] {
     let password = password.as_bytes();
     let random = generate_salt();
     let salt = salt.unwrap_or(&random);
+    #[cfg(fuzzing)]
+    let iterations = iterations.min(FUZZING_ITERATIONS);
 
     let mut buf = [0u8; 32];
-    pbkdf2::<Hmac<Sha256>>(password, salt, 10_000, &mut buf)
+    pbkdf2::<Hmac<Sha256>>(password, salt, iterations, &mut buf)
         .expect("HMAC can be initialized with any key length");
     buf
 }
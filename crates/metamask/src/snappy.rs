@@ -0,0 +1,121 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// A decompressor of the Snappy raw format, the compression of the IndexedDB values of
+/// Firefox.
+///
+/// The input comes from the disk, so the lengths and offsets it declares are checked rather
+/// than trusted: a malformed input is an error, never a panic or an unbounded allocation.
+///
+/// From:
+/// https://github.com/google/snappy/blob/main/format_description.txt
+use std::error::Error;
+
+/// The longest output decompressed, well above the states of MetaMask
+pub const MAX_DECOMPRESSED_LEN: usize = 1 << 26;
+
+/// Reads the little-endian base 128 varint of the output length, returning it and its size.
+fn read_len(input: &[u8]) -> Option<(usize, usize)> {
+    let mut len = 0usize;
+    for (i, byte) in input.iter().take(5).enumerate() {
+        len |= ((byte & 0x7f) as usize) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((len, i + 1));
+        }
+    }
+    None
+}
+
+/// Reads the little-endian integer of some bytes at an offset.
+fn read_le(input: &[u8], i: usize, bytes: usize) -> Option<usize> {
+    let bytes = input.get(i..)?.get(..bytes)?;
+    Some(bytes.iter().rev().fold(0, |n, b| n << 8 | *b as usize))
+}
+
+/// Decompresses the Snappy raw format.
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (len, mut i) = read_len(input).ok_or("Invalid snappy length")?;
+    if len > MAX_DECOMPRESSED_LEN {
+        return Err(format!("Unsupported snappy length {}", len).into());
+    }
+
+    let mut output: Vec<u8> = Vec::with_capacity(len);
+    while i < input.len() {
+        let tag = input[i] as usize;
+        i += 1;
+        let (offset, n) = match tag & 3 {
+            0 => {
+                let mut n = tag >> 2;
+                if n >= 60 {
+                    n = read_le(input, i, n - 59).ok_or("Truncated snappy literal")?;
+                    i += (tag >> 2) - 59;
+                }
+                let literal = input
+                    .get(i..)
+                    .and_then(|rest| rest.get(..n.checked_add(1)?))
+                    .filter(|literal| output.len() + literal.len() <= len)
+                    .ok_or("Invalid snappy literal")?;
+                output.extend_from_slice(literal);
+                i += literal.len();
+                continue;
+            }
+            1 => {
+                let low = read_le(input, i, 1).ok_or("Truncated snappy copy")?;
+                i += 1;
+                (((tag >> 5) << 8) | low, ((tag >> 2) & 7) + 4)
+            }
+            2 => {
+                let offset = read_le(input, i, 2).ok_or("Truncated snappy copy")?;
+                i += 2;
+                (offset, (tag >> 2) + 1)
+            }
+            _ => {
+                let offset = read_le(input, i, 4).ok_or("Truncated snappy copy")?;
+                i += 4;
+                (offset, (tag >> 2) + 1)
+            }
+        };
+        if offset == 0 || offset > output.len() || output.len() + n > len {
+            return Err("Invalid snappy copy".into());
+        }
+        // The copies may overlap their own output, so they go byte by byte
+        for _ in 0..n {
+            output.push(output[output.len() - offset]);
+        }
+    }
+
+    if output.len() != len {
+        return Err("Truncated snappy input".into());
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decompress() {
+        // A literal, then a copy overlapping its own output
+        assert_eq!(decompress(b"\x0a\x08abc\x0d\x03").unwrap(), b"abcabcabca");
+        assert_eq!(decompress(b"\x00").unwrap(), b"");
+    }
+
+    #[test]
+    fn test_malformed() {
+        for input in [
+            &b""[..],
+            b"\xff\xff\xff\xff\xff",
+            b"\xff\xff\xff\xff\x0f",
+            b"\x04\x0cab",
+            b"\x02\x08abc",
+            b"\x0a\x08abc\x0d\x04",
+            b"\x0a\x08abc\x0d\x00",
+            b"\x04\xf0\xff\xff\xff\xff",
+            b"\x05\x08abc",
+        ] {
+            assert!(decompress(input).is_err(), "{:?}", input);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// The stages of unlocking a wallet vault, each behind a trait so that it can be tested in
+/// isolation and new wallets can be added without touching the flow:
+///
+/// 1. [VaultLocator] finds the candidate stores of a wallet on the machine.
+/// 2. [VaultExtractor] turns the bytes of a store into encrypted vault records.
+/// 3. [VaultDecryptor] decrypts a record with a password into its keyrings.
+use crate::{
+    interactive::{ChromeLocator, FirefoxLocator},
+    mmap::Mmap,
+    types::{DecryptedVault, Vault},
+    vault::{ChromeExtractor, FirefoxExtractor, MetaMaskDecryptor},
+};
+#[cfg(any(test, feature = "mock"))]
+use mockall::automock;
+use std::{error::Error, path::PathBuf};
+use tracing::{debug, trace};
+
+/// Finds the candidate stores of a wallet
+#[cfg_attr(any(test, feature = "mock"), automock)]
+pub trait VaultLocator {
+    /// Returns the paths of the stores, empty when the wallet is not installed.
+    fn locate(&self) -> Result<Vec<PathBuf>, Box<dyn Error>>;
+}
+
+/// Turns the bytes of a store into encrypted vault records
+#[cfg_attr(any(test, feature = "mock"), automock(type Vault = Vault;))]
+pub trait VaultExtractor {
+    type Vault;
+
+    /// Returns the vaults found in the contents of a store.
+    fn extract(&self, data: &[u8]) -> Result<Vec<Self::Vault>, Box<dyn Error>>;
+}
+
+/// Decrypts a vault record into its keyrings
+#[cfg_attr(any(test, feature = "mock"), automock(type Vault = Vault; type Keyrings = DecryptedVault;))]
+pub trait VaultDecryptor {
+    type Vault;
+    type Keyrings;
+
+    /// Returns the keyrings of a vault, or an error if the password is wrong.
+    fn decrypt(
+        &self,
+        vault: &Self::Vault,
+        password: &str,
+    ) -> Result<Self::Keyrings, Box<dyn Error>>;
+}
+
+/// A vault found in a store
+#[derive(Clone, Debug)]
+pub struct Found<V> {
+    pub path: PathBuf,
+    pub vault: V,
+}
+
+/// A wallet, made of the three stages of unlocking its vaults
+pub struct VaultSource<V, K> {
+    pub name: &'static str,
+    locator: Box<dyn VaultLocator + Send + Sync>,
+    extractor: Box<dyn VaultExtractor<Vault = V> + Send + Sync>,
+    decryptor: Box<dyn VaultDecryptor<Vault = V, Keyrings = K> + Send + Sync>,
+}
+
+impl<V, K> VaultSource<V, K> {
+    pub fn new(
+        name: &'static str,
+        locator: Box<dyn VaultLocator + Send + Sync>,
+        extractor: Box<dyn VaultExtractor<Vault = V> + Send + Sync>,
+        decryptor: Box<dyn VaultDecryptor<Vault = V, Keyrings = K> + Send + Sync>,
+    ) -> Self {
+        Self { name, locator, extractor, decryptor }
+    }
+
+    /// Returns the paths of the stores of the wallet.
+    pub fn locate(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        self.locator.locate()
+    }
+
+    /// Returns the vaults of a store.
+    pub fn extract(&self, data: &[u8]) -> Result<Vec<V>, Box<dyn Error>> {
+        self.extractor.extract(data)
+    }
+
+    /// Returns the vaults of every store, skipping the stores that can not be read.
+    pub fn vaults(&self) -> Result<Vec<Found<V>>, Box<dyn Error>> {
+        let mut found = vec![];
+        for path in self.locate()? {
+            trace!("Extracting {} vaults from: {:?}", self.name, path);
+            let vaults = Mmap::open(&path)
+                .map_err(Box::<dyn Error>::from)
+                .and_then(|data| self.extract(&data));
+            match vaults {
+                Ok(vaults) => found
+                    .extend(vaults.into_iter().map(|vault| Found { path: path.clone(), vault })),
+                Err(e) => debug!("Skipping {:?}: {}", path, e),
+            }
+        }
+        Ok(found)
+    }
+
+    /// Decrypts a vault of the wallet.
+    pub fn decrypt(&self, vault: &V, password: &str) -> Result<K, Box<dyn Error>> {
+        self.decryptor.decrypt(vault, password)
+    }
+}
+
+/// A MetaMask vault source
+pub type MetaMaskSource = VaultSource<Vault, DecryptedVault>;
+
+impl MetaMaskSource {
+    /// MetaMask in the Chromium browsers, stored in LevelDB.
+    pub fn chrome() -> Self {
+        Self::new(
+            "MetaMask Chrome",
+            Box::<ChromeLocator>::default(),
+            Box::new(ChromeExtractor),
+            Box::new(MetaMaskDecryptor),
+        )
+    }
+
+    /// MetaMask in Firefox, stored in IndexedDB.
+    pub fn firefox() -> Self {
+        Self::new(
+            "MetaMask Firefox",
+            Box::<FirefoxLocator>::default(),
+            Box::new(FirefoxExtractor),
+            Box::new(MetaMaskDecryptor),
+        )
+    }
+
+    /// Every supported MetaMask install.
+    pub fn all() -> Vec<Self> {
+        vec![Self::chrome(), Self::firefox()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{MnemoicData, StringOrBytes};
+    use anyhow::Result;
+
+    fn decrypted(phrase: &str) -> DecryptedVault {
+        let mnemonic = StringOrBytes::String(phrase.to_string());
+        let data = MnemoicData { mnemonic, number_of_accounts: None, hd_path: None };
+        DecryptedVault { r#type: None, data, skipped: vec![] }
+    }
+
+    #[test]
+    fn test_vaults() -> Result<()> {
+        let fixture =
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/chrome-windows-1/000005.ldb");
+
+        // The unreadable store is skipped
+        let mut locator = MockVaultLocator::new();
+        locator
+            .expect_locate()
+            .returning(move || Ok(vec![PathBuf::from(fixture), PathBuf::from("/missing")]));
+        let mut extractor = MockVaultExtractor::new();
+        extractor.expect_extract().times(1).returning(|data| {
+            assert!(!data.is_empty());
+            Ok(vec![Vault {
+                data: "a".to_string(),
+                iv: "b".to_string(),
+                salt: None,
+                key_metadata: None,
+            }])
+        });
+        let mut decryptor = MockVaultDecryptor::new();
+        decryptor
+            .expect_decrypt()
+            .withf(|vault, password| vault.data == "a" && password == "password")
+            .returning(|_, _| Ok(decrypted("test")));
+
+        let source =
+            VaultSource::new("mock", Box::new(locator), Box::new(extractor), Box::new(decryptor));
+        let found = source.vaults().map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, PathBuf::from(fixture));
+        let keyrings =
+            source.decrypt(&found[0].vault, "password").map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(keyrings.data.mnemonic.to_string(), "test");
+        Ok(())
+    }
+
+    #[test]
+    fn test_locator_error() {
+        let mut locator = MockVaultLocator::new();
+        locator.expect_locate().returning(|| Err("no profile".into()));
+        let source = VaultSource::new(
+            "mock",
+            Box::new(locator),
+            Box::new(MockVaultExtractor::new()),
+            Box::new(MockVaultDecryptor::new()),
+        );
+        assert!(source.vaults().is_err());
+    }
+}
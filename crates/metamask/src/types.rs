@@ -3,12 +3,80 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Vault {
     pub data: String,
     pub iv: String,
     pub salt: Option<String>,
+    /// The key derivation of the newer vaults, the older ones use the default iterations
+    #[serde(rename = "keyMetadata", default, skip_serializing_if = "Option::is_none")]
+    pub key_metadata: Option<KeyMetadata>,
+}
+
+/// The key derivation of a vault
+///
+/// From:
+/// https://github.com/MetaMask/browser-passworder/blob/main/src/index.ts
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyMetadata {
+    pub algorithm: String,
+    pub params: KeyDerivationParams,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyDerivationParams {
+    pub iterations: u32,
+}
+
+impl KeyMetadata {
+    /// PBKDF2 with a number of iterations, the only algorithm of MetaMask.
+    pub fn pbkdf2(iterations: u32) -> Self {
+        Self { algorithm: "PBKDF2".to_string(), params: KeyDerivationParams { iterations } }
+    }
+}
+
+/// The eth-lightwallet keystore of the vaults before v3, which MetaMask serialized as a string
+///
+/// From:
+/// https://github.com/ConsenSys/eth-lightwallet/blob/v2.5.6/lib/keystore.js
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LightwalletKeystore {
+    pub enc_seed: LightwalletCiphertext,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enc_hd_root_priv: Option<LightwalletCiphertext>,
+    /// The base64 SHA-512 of the derived key, to tell a wrong password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_hash: Option<String>,
+    /// The salt of the key derivation of the v3 keystores, the older ones use a constant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    /// The accounts of the keystores before v3, by HD path
+    #[serde(default)]
+    pub ks_data: BTreeMap<String, LightwalletPathData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hd_path_string: Option<String>,
+    pub version: u32,
+}
+
+/// A NaCl secretbox of a keystore, the tag before the ciphertext
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LightwalletCiphertext {
+    pub enc_str: String,
+    pub nonce: String,
+}
+
+/// The accounts derived along an HD path of a keystore
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LightwalletPathData {
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hd_index: Option<u32>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -39,6 +107,9 @@ pub struct MnemoicData {
 pub struct DecryptedVault {
     pub r#type: Option<String>,
     pub data: MnemoicData,
+    /// The types of the other keyrings of the vault, which are not decrypted with it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<String>,
 }
 
 #[cfg(test)]
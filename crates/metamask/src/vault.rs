@@ -3,29 +3,42 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 /// Code from: https://github.com/MetaMask/vault-decryptor/blob/master/app/lib.js
-use crate::password::{decrypt, key_from_password};
+use crate::password::{
+    decrypt, key_from_password_with_iterations, DEFAULT_ITERATIONS, MAX_ITERATIONS,
+};
 use crate::{
-    regex::{get_regex, RegexEnum},
-    types::{DecryptedVault, MnemoicData, StringOrBytes, Vault},
+    indexeddb,
+    lightwallet::decrypt_keystore,
+    mmap::Mmap,
+    regex::{
+        detect_version, get_compiled_regex, get_field_regex, patterns, Browser, Capture, Field,
+        Version,
+    },
+    source::{VaultDecryptor, VaultExtractor},
+    types::{DecryptedVault, KeyMetadata, LightwalletKeystore, MnemoicData, StringOrBytes, Vault},
 };
 use base64::{engine::general_purpose, Engine as _};
 use itertools::Itertools;
+use lazy_static::lazy_static;
+use regex::{bytes, Regex};
 use serde_json::Value;
-use std::{error::Error, fs::File, io::Read, path::Path};
+use std::{error::Error, path::Path};
 use tracing::{info, warn};
 
-/// Extracts the vault from a file.
-pub fn extract_vault_from_file<P: AsRef<Path>>(path: P) -> Result<Vault, Box<dyn Error>> {
-    let mut file = File::open(path).unwrap();
-    let mut data = Vec::new();
-    file.read_to_end(&mut data).unwrap();
-    let data = String::from_utf8_lossy(&data);
+lazy_static! {
+    /// The escaped newlines of a pre-v3 mnemonic
+    static ref NEWLINES: bytes::Regex = bytes::Regex::new(r"\\n*").unwrap();
+    /// A BIP39 mnemonic phrase
+    static ref MNEMONIC: Regex = Regex::new(r"^(?:\w{3,}\s+){11,}\w{3,}$").unwrap();
+}
 
-    extract_vault_from_string(&data)
+/// Extracts the vault from a file, scanning the large LevelDB tables in place.
+pub fn extract_vault_from_file<P: AsRef<Path>>(path: P) -> Result<Vault, Box<dyn Error>> {
+    extract_vault_from_bytes(&Mmap::open(path)?)
 }
 
 /// Splits a string with JSON objects into a vector of JSON objects.
-fn split_json(s: &str) -> Vec<Value> {
+pub fn split_json(s: &str) -> Vec<Value> {
     s.split(r#"}},"#)
         .flat_map(|s| {
             serde_json::from_str::<Value>(&format!("{}{}", s, r#"}}"#))
@@ -48,16 +61,16 @@ fn decrypt_vault_result(res: &str) -> Result<DecryptedVault, Box<dyn Error>> {
                     number_of_accounts: vault.data.number_of_accounts,
                     hd_path: vault.data.hd_path,
                 };
-                let vault = DecryptedVault { r#type: vault.r#type, data };
+                let vault = DecryptedVault { r#type: vault.r#type, data, skipped: vec![] };
                 return Ok(vault);
             }
             StringOrBytes::Bytes(b) => {
                 let data = MnemoicData {
-                    mnemonic: StringOrBytes::String(std::str::from_utf8(&b).unwrap().to_string()),
+                    mnemonic: StringOrBytes::String(String::from_utf8(b)?),
                     number_of_accounts: vault.data.number_of_accounts,
                     hd_path: vault.data.hd_path,
                 };
-                let vault = DecryptedVault { r#type: vault.r#type, data };
+                let vault = DecryptedVault { r#type: vault.r#type, data, skipped: vec![] };
                 return Ok(vault);
             }
         }
@@ -71,111 +84,140 @@ fn decrypt_vault_result(res: &str) -> Result<DecryptedVault, Box<dyn Error>> {
 /// From:
 /// https://github.com/MetaMask/vault-decryptor/blob/master/app/lib.js#L22
 pub fn extract_vault_from_string(data: &str) -> Result<Vault, Box<dyn Error>> {
+    extract_vault_from_bytes(data.as_bytes())
+}
+
+/// Returns the lossy string of the bytes of a match.
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Extracts the vault from the bytes of a file, which do not have to be valid UTF-8, with the
+/// patterns of the MetaMask version found in them, see [detect_version].
+///
+/// From:
+/// https://github.com/MetaMask/vault-decryptor/blob/master/app/lib.js#L22
+pub fn extract_vault_from_bytes(data: &[u8]) -> Result<Vault, Box<dyn Error>> {
+    extract_vault_with_patterns(data, Browser::Chromium, detect_version(data))
+}
+
+/// Extracts the vault from the bytes of a store of a browser, the raw vault or the first one the
+/// patterns match, see [extract_vaults_with_patterns].
+pub fn extract_vault_with_patterns(
+    data: &[u8],
+    browser: Browser,
+    version: Option<Version>,
+) -> Result<Vault, Box<dyn Error>> {
     // Attempt 1:
     // Try to parse as a JSON object
     // This is the case for objects that have not been encrypted
-    if let Ok(vault) = serde_json::from_str::<Vault>(data) {
+    if let Ok(vault) = serde_json::from_slice::<Vault>(data) {
         info!("Found raw vault");
         return Ok(vault);
     }
 
-    // Attempt 2: pre-v3 cleartext
-    // If this is a pre-v3 vault, it will be a JSON object with a single key
-    // Warns that the vault is not encrypted
-    let matches = regex::Regex::new(&get_regex(RegexEnum::WalletSeed)).unwrap().captures(data);
-    if let Some(m) = matches {
-        info!("Found pre-v3 vault");
-
-        // Extract the mnemonic and parse it
-        let mnemonic = m.get(1).map_or("", |m| m.as_str());
-        let re = regex::Regex::new(r"\\n*").unwrap();
-        let mnemonic = re.replace_all(mnemonic, "");
-
-        // Extract the vault if it exists
-        let vault_matches =
-            regex::Regex::new(&get_regex(RegexEnum::WalletV2)).unwrap().captures(data);
-        let vault: Option<Vault> = vault_matches
-            .and_then(|m| serde_json::from_str::<Vault>(m.get(1).unwrap().as_str()).ok());
-
-        // Return the vault if it exists, otherwise return the mnemonic
-        warn!("Your mnemonic is not encrypted");
-        if let Some(vault) = vault {
-            return Ok(vault);
-        }
-        return Ok(Vault { data: mnemonic.to_string(), iv: "".to_string(), salt: None });
-    }
-
-    // Attempt 3: chromium 000003.log file on linux
-    let matches = regex::Regex::new(&get_regex(RegexEnum::Keyring)).unwrap().captures(data);
-    if let Some(m) = matches {
-        info!("Found chromium vault");
-
-        // Extract the vault
-        // Extra replace is to remove the extra backslashes
-        // Also remove the first and last character
-        //
-        // Ref: https://github.com/MetaMask/vault-decryptor/blob/6cebd223816c80c3d879024aa385cb91fb49de0b/app/lib.js#L53
-        let vault_body_data = &m[0][29..].replace(r#"\""#, r#"""#);
-        let vault_body_data = vault_body_data[1..vault_body_data.len() - 1].to_string();
-
-        // Parse the vault as json value
-        let vault_body = serde_json::from_str::<Value>(&vault_body_data);
-        if vault_body.is_err() {
-            return Err(Box::new(vault_body.err().unwrap()));
-        }
-
-        // Return the vault
-        let vault_value = vault_body.unwrap();
-        return Ok(Vault {
-            data: vault_value["data"].to_string(),
-            iv: vault_value["iv"].to_string(),
-            salt: Some(vault_value["salt"].to_string()),
-        });
-    }
+    extract_vaults_with_patterns(data, browser, version)
+        .next()
+        .ok_or_else(|| "Could not extract vault".into())
+}
 
-    // Attempt 4: chromium 000005.ldb on windows
-    // Attempts to match globaly
-    let match_regex = regex::Regex::new(&get_regex(RegexEnum::MatchRegex)).unwrap();
-    let capture_regex = regex::Regex::new(&get_regex(RegexEnum::CaptureRegex)).unwrap();
-    let iv_regex = regex::Regex::new(&get_regex(RegexEnum::IVRegex)).unwrap();
-    let data_regex = regex::Regex::new(&get_regex(RegexEnum::DataRegex)).unwrap();
-    let salt_regex = regex::Regex::new(&get_regex(RegexEnum::SaltRegex)).unwrap();
-
-    // Iterate over all matches and extract vaults
-    let matches = match_regex.find_iter(data);
-    let col: Vec<Vault> = matches
-        .filter_map(|m| {
-            let catches = capture_regex.captures(m.as_str());
-            if let Some(m) = catches {
-                let a = m.get(1).map_or("", |m| m.as_str());
-                let iv = iv_regex.captures(a);
-                let data = data_regex.captures(a);
-                let salt = salt_regex.captures(a);
-
-                if let (Some(i), Some(d), Some(s)) = (iv, data, salt) {
-                    // Return with redundant quotes added
-                    Some(Vault {
-                        data: format!("\"{}\"", d.get(1).unwrap().as_str()),
-                        iv: format!("\"{}\"", i.get(1).unwrap().as_str()),
-                        salt: Some(format!("\"{}\"", s.get(1).unwrap().as_str())),
-                    })
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        })
-        .unique_by(|v| (v.iv.clone(), v.data.clone(), v.salt.clone()))
-        .collect();
+/// Returns the vaults in the bytes of a store of a browser, matched by the patterns of the
+/// registry applying to the browser and, if known, the MetaMask version that wrote the store, in
+/// the order of the registry, see [patterns]. The vaults are extracted from the groups of every
+/// match as the [Capture] of its pattern declares them.
+pub fn extract_vaults_with_patterns(
+    data: &[u8],
+    browser: Browser,
+    version: Option<Version>,
+) -> impl Iterator<Item = Vault> + '_ {
+    patterns(browser, version).filter(|pattern| pattern.capture.is_vault()).flat_map(
+        move |pattern| {
+            get_compiled_regex(pattern.kind.clone()).captures_iter(data).filter_map(move |m| {
+                let vault = extract_capture(pattern.capture, &m, browser, version)?;
+                info!("Found {:?} vault", pattern.kind);
+                Some(vault)
+            })
+        },
+    )
+}
 
-    // Return the first vault
-    if !col.is_empty() {
-        info!("Found chromium ldb vault");
-        return Ok(col[0].clone());
+/// Extracts the vault of the groups of a match, none if they do not hold one.
+fn extract_capture(
+    capture: Capture,
+    m: &bytes::Captures,
+    browser: Browser,
+    version: Option<Version>,
+) -> Option<Vault> {
+    let group = m.get(1).map(|m| m.as_bytes());
+    match capture {
+        // The pre-v3 vaults kept the mnemonic in clear
+        Capture::Mnemonic => {
+            let mnemonic = NEWLINES.replace_all(group?, &b""[..]);
+            warn!("Your mnemonic is not encrypted");
+            Some(Vault {
+                data: lossy(&mnemonic),
+                iv: "".to_string(),
+                salt: None,
+                key_metadata: None,
+            })
+        }
+        // Which the decryption tells apart from the eth-lightwallet keystores
+        Capture::Keystore => {
+            let keystore = serde_json::from_slice::<String>(group?)
+                .ok()
+                .filter(|s| serde_json::from_str::<LightwalletKeystore>(s).is_ok())?;
+            Some(Vault { data: keystore, iv: "".to_string(), salt: None, key_metadata: None })
+        }
+        Capture::Vault => {
+            let vault = serde_json::from_slice::<String>(group?).ok()?;
+            let vault = serde_json::from_str::<Value>(&vault).ok()?;
+            Some(Vault {
+                data: vault["data"].to_string(),
+                iv: vault["iv"].to_string(),
+                salt: Some(vault["salt"].to_string()),
+                key_metadata: serde_json::from_value(vault["keyMetadata"].clone()).ok(),
+            })
+        }
+        Capture::Fragments => {
+            let group = group?;
+            let field = |field| get_field_regex(field, browser, version)?.captures(group);
+            let (d, i, s) = (field(Field::Data)?, field(Field::Iv)?, field(Field::Salt)?);
+
+            // The patterns also match the backslash escaping the closing quote of an unpadded
+            // value
+            let base64 = |m: &[u8]| lossy(m.strip_suffix(b"\\").unwrap_or(m));
+
+            // Return with redundant quotes added
+            Some(Vault {
+                data: format!("\"{}\"", base64(&d[1])),
+                iv: format!("\"{}\"", base64(&i[1])),
+                salt: Some(format!("\"{}\"", base64(&s[1]))),
+                key_metadata: parse_key_metadata(group, browser, version),
+            })
+        }
+        Capture::Fields => Some(Vault {
+            data: format!("\"{}\"", lossy(m.name("data")?.as_bytes())),
+            iv: format!("\"{}\"", lossy(m.name("iv")?.as_bytes())),
+            salt: Some(format!("\"{}\"", lossy(m.name("salt")?.as_bytes()))),
+            key_metadata: m
+                .name("key_metadata")
+                .and_then(|m| parse_key_metadata(m.as_bytes(), browser, version)),
+        }),
+        Capture::Field(_) => None,
     }
+}
 
-    Err("Could not extract vault".into())
+/// Parses the key metadata of a vault, or its iterations alone when the compression of the store
+/// broke up its keys.
+fn parse_key_metadata(
+    bytes: &[u8],
+    browser: Browser,
+    version: Option<Version>,
+) -> Option<KeyMetadata> {
+    serde_json::from_slice(bytes).ok().or_else(|| {
+        let iterations = get_field_regex(Field::Iterations, browser, version)?.captures(bytes)?;
+        lossy(&iterations[1]).parse().ok().map(KeyMetadata::pbkdf2)
+    })
 }
 
 /// Attempts to decrypt a vault.
@@ -184,48 +226,91 @@ pub fn extract_vault_from_string(data: &str) -> Result<Vault, Box<dyn Error>> {
 /// From:
 /// https://github.com/MetaMask/vault-decryptor/blob/master/app/lib.js#L92
 pub fn decrypt_vault(vault: &Vault, password: &str) -> Result<DecryptedVault, Box<dyn Error>> {
-    // Define a regular expression that matches a BIP39 mnemonic phrase.
-    let re = regex::Regex::new(r"^(?:\w{3,}\s+){11,}\w{3,}$").unwrap();
+    // Decrypt the eth-lightwallet keystores of the pre-v3 vaults.
+    if vault.salt.is_none() {
+        if let Ok(keystore) = serde_json::from_str::<LightwalletKeystore>(&vault.data) {
+            return decrypt_keystore(&keystore, password);
+        }
+    }
 
     // Return the vault data if it is not encrypted.
-    if re.is_match(&vault.data) || vault.salt.is_none() {
+    if MNEMONIC.is_match(&vault.data) || vault.salt.is_none() {
         let str = StringOrBytes::String(vault.data.to_string());
         let data = MnemoicData { mnemonic: str, number_of_accounts: None, hd_path: None };
-        let vault = DecryptedVault { r#type: None, data };
+        let vault = DecryptedVault { r#type: None, data, skipped: vec![] };
         return Ok(vault);
     }
 
-    // Remove redundant quotes from the vault data.
-    fn remove_redundant_quotes(s: &str) -> String {
-        if s.len() < 2 {
-            return s.to_string();
-        }
-
-        let s = &s[1..s.len() - 1];
-        s.to_string()
+    // Remove the quotes of the extracted vault data, the raw vaults have none.
+    fn unquote(s: &str) -> String {
+        s.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(s).to_string()
     }
 
     // Decode the vault data.
-    let data = remove_redundant_quotes(&vault.data);
-    let iv = remove_redundant_quotes(&vault.iv);
-    let salt = &vault.salt.clone().map_or("".to_string(), |s| remove_redundant_quotes(&s));
+    let data = unquote(&vault.data);
+    let iv = unquote(&vault.iv);
+    let salt = &vault.salt.clone().map_or("".to_string(), |s| unquote(&s));
 
     // Create a vault object.
-    let cyphertext = Vault { data, iv, salt: Some(salt.to_string()) };
+    let key_metadata = vault.key_metadata.clone();
+    let cyphertext = Vault { data, iv, salt: Some(salt.to_string()), key_metadata };
+
+    // The iterations of the key metadata of the newer vaults
+    let iterations = match &cyphertext.key_metadata {
+        Some(metadata) if metadata.algorithm != "PBKDF2" => {
+            return Err(format!("Unsupported key derivation {}", metadata.algorithm).into())
+        }
+        Some(metadata) if !(1..=MAX_ITERATIONS).contains(&metadata.params.iterations) => {
+            return Err(format!("Unsupported iterations {}", metadata.params.iterations).into())
+        }
+        Some(metadata) => metadata.params.iterations,
+        None => DEFAULT_ITERATIONS,
+    };
 
     // Attempt to decrypt the vault.
     let salt = general_purpose::STANDARD.decode(cyphertext.salt.clone().unwrap().as_bytes())?;
-    let key = key_from_password(password, Some(&salt));
+    let key = key_from_password_with_iterations(password, Some(&salt), iterations);
     let res = decrypt(password, &cyphertext, Some(&key))?;
+    parse_keyrings(&res)
+}
 
-    // Attempt to decrypt the vault.
-    let r = decrypt_vault_result(&remove_redundant_quotes(&res));
+/// Parses the keyrings of a decrypted vault, returning the first HD keyring with the types of the
+/// others.
+pub fn parse_keyrings(res: &str) -> Result<DecryptedVault, Box<dyn Error>> {
+    // Remove redundant quotes from the vault data.
+    fn remove_redundant_quotes(s: &str) -> String {
+        // Drop the first and last characters, which are not always ASCII in malformed vaults
+        let mut chars = s.chars();
+        match (chars.next(), chars.next_back()) {
+            (Some(_), Some(_)) => chars.as_str().to_string(),
+            _ => s.to_string(),
+        }
+    }
+
+    // Find the first HD keyring of the decrypted keyrings, the others are skipped.
+    if let Ok(keyrings) = serde_json::from_str::<Vec<Value>>(res) {
+        let found = keyrings.iter().enumerate().find_map(|(index, keyring)| {
+            Some((index, decrypt_vault_result(&keyring.to_string()).ok()?))
+        });
+        if let Some((index, mut vault)) = found {
+            vault.skipped = keyrings
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .map(|(_, keyring)| keyring["type"].as_str().unwrap_or("Unknown").to_string())
+                .collect();
+            return Ok(vault);
+        }
+    }
+
+    // Attempt to parse the keyrings as a single object.
+    let r = decrypt_vault_result(&remove_redundant_quotes(res));
     if r.is_ok() {
         return r;
     }
 
     // Split the vault data into multiple json objects, and attempt to decrypt each one.
-    let json_vec = split_json(&remove_redundant_quotes(&res));
+    let json_vec = split_json(&remove_redundant_quotes(res));
     for json_obj in json_vec {
         let res = decrypt_vault_result(&json_obj.to_string());
         if res.is_ok() {
@@ -236,9 +321,67 @@ pub fn decrypt_vault(vault: &Vault, password: &str) -> Result<DecryptedVault, Bo
     Err("Could not decrypt vault".into())
 }
 
+/// Extracts the vaults of the Chromium LevelDB stores.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChromeExtractor;
+
+impl VaultExtractor for ChromeExtractor {
+    type Vault = Vault;
+
+    fn extract(&self, data: &[u8]) -> Result<Vec<Vault>, Box<dyn Error>> {
+        Ok(vec![extract_vault_from_bytes(data)?])
+    }
+}
+
+/// Extracts the vaults of the Firefox IndexedDB stores, where they are stored unescaped in the
+/// Snappy compressed values of the database, see [indexeddb::values].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FirefoxExtractor;
+
+impl VaultExtractor for FirefoxExtractor {
+    type Vault = Vault;
+
+    fn extract(&self, data: &[u8]) -> Result<Vec<Vault>, Box<dyn Error>> {
+        // The values of a database are decompressed, any other bytes are scanned as they are
+        let values;
+        let scanned: Vec<&[u8]> = match indexeddb::is_database(data) {
+            true => {
+                values = indexeddb::values(data);
+                values.iter().map(Vec::as_slice).collect()
+            }
+            false => vec![data],
+        };
+        let version = scanned.iter().filter_map(|value| detect_version(value)).min();
+        let vaults: Vec<Vault> = scanned
+            .into_iter()
+            .flat_map(|value| extract_vaults_with_patterns(value, Browser::Firefox, version))
+            .unique_by(|v| (v.iv.clone(), v.data.clone(), v.salt.clone()))
+            .collect();
+
+        if vaults.is_empty() {
+            return Err("Could not extract vault".into());
+        }
+        Ok(vaults)
+    }
+}
+
+/// Decrypts the vaults of MetaMask, see [decrypt_vault].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetaMaskDecryptor;
+
+impl VaultDecryptor for MetaMaskDecryptor {
+    type Vault = Vault;
+    type Keyrings = DecryptedVault;
+
+    fn decrypt(&self, vault: &Vault, password: &str) -> Result<DecryptedVault, Box<dyn Error>> {
+        decrypt_vault(vault, password)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::regex::RegexEnum;
     use anyhow::Result;
 
     #[test]
@@ -251,6 +394,110 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_firefox_extractor() -> Result<()> {
+        // The chromium-108.0_5359.98_4.10.24.2 vault, unescaped in the snappy literals of a
+        // structured clone as Firefox stores it
+        let vault = r#"{"data":"8w0Wn8LaR3kMTp++Crr/JMCd6/xrfI1xWJsBgZXIdaKvPHCpjK/o1d6drEvQ7/ThtCynS5jP5F2T5esc0cin6E+2g3zcHRIpYp1Ut3Zn4Gw5Of8yxEk+Whq5eV2O8kbxfeurqTBx3b377e9Jd4N39QFF9kyE3cr8j6fETQvKjOC6irIGL0vI+TkUUylKISZ2OksbQJEooWPW3S1O8xdazL32j7dOnLbkrq1Xan0EIC7sg41oWUyMuS5eVopigxJ0ehueZsFlkvcBb+9zp6eMW5rw+CHC8KHXZdWGU45Ag85PaO5smtkOzb+WrQbufpQgsgKY23SsM8I1uTK6738/IHQ7kzFYImX0AJdF60xiUpihA/iUdWn6lr+kS4uyp7NhMLb4D5fHQi7pDb29TIDj1267rCD3w1N9M1nwWUjcG0gw5AMdf4bwYjpKOeQv2M5dGiX41+iQ9Rs5R6t3qZTNZpNu/czZaCUU8Bbr/je6Z7Milwl3b5NMfO7u2GID7aSG8s8RQ6/D5PjmtJN3a5BY6WLm1IzV","iv":"SCr2xR/hqI6qqJQese4E9Q==","salt":"HQnH0ArgfCWp86acfYN5Kr9wCWFKE3uw0fwUQafJHMY="}"#;
+        let data = [
+            &b"\x00\x05\xff\xf1\x00vault\x04\x00\xff\xff\x9c\x05"[..],
+            vault.as_bytes(),
+            b"\x00\x00",
+        ]
+        .concat();
+        let vaults = FirefoxExtractor.extract(&data).map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(vaults.len(), 1);
+        let decrypted = MetaMaskDecryptor
+            .decrypt(&vaults[0], "JooXegoodowu8mohf2ietah5kohgah5")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(
+            decrypted.data.mnemonic.to_string(),
+            "harvest afraid useful nose electric swift various man boil diagram confirm ahead"
+        );
+        assert!(FirefoxExtractor.extract(b"no vault").is_err());
+
+        // In a database, where the compression turns the repeated parts of the vault into copies
+        let state = serde_json::json!({ "a": r#"","iv":"","salt":""#, "vault": vault });
+        let database = crate::fixtures::indexeddb::database(&[("data", &state)]);
+        assert!(get_compiled_regex(RegexEnum::FirefoxVault).captures(&database).is_none());
+        let vaults = FirefoxExtractor.extract(&database).map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(vaults.len(), 1);
+        assert!(MetaMaskDecryptor.decrypt(&vaults[0], "JooXegoodowu8mohf2ietah5kohgah5").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pre_v3_keystore() -> Result<()> {
+        // The localStorage of MetaMask 2, with the keystore serialized as a string
+        let mnemonic = "spin tomato fluid drum awkward boat stay cherry decline pulp lemon glide";
+        let keystore = crate::lightwallet::encrypt_keystore(mnemonic, "password", 2).unwrap();
+        let wallet = Value::String(serde_json::to_string(&keystore)?);
+        let state =
+            format!(r#"{{"meta":{{"version":1}},"data":{{"config":{{}},"wallet":{}}}}}"#, wallet);
+
+        let vault = extract_vault_from_bytes(state.as_bytes()).unwrap();
+        let decrypted = decrypt_vault(&vault, "password").unwrap();
+        assert_eq!(decrypted.data.mnemonic.to_string(), mnemonic);
+        assert_eq!(decrypted.data.hd_path.as_deref(), Some("m/0'/0'/0'"));
+        assert!(decrypt_vault(&vault, "wrong").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_with_patterns() -> Result<()> {
+        let vault = r#"{"data":"AAAA","iv":"AAAA","salt":"AAAA"}"#;
+        let v3 = format!(r#""KeyringController":{{"vault":{}}}"#, Value::String(vault.into()));
+        let pre_v3 = r#"{"wallet-seed":"spin tomato fluid"}"#;
+
+        // The patterns of the other versions and browsers are not tried
+        let extract = |data: &str, browser, version| {
+            extract_vault_with_patterns(data.as_bytes(), browser, version).ok()
+        };
+        assert_eq!(extract(&v3, Browser::Chromium, None).map(|v| v.iv), Some("\"AAAA\"".into()));
+        assert!(extract(&v3, Browser::Chromium, Some((10, 25, 0))).is_some());
+        assert!(extract(&v3, Browser::Chromium, Some((2, 14, 1))).is_none());
+        assert!(extract(pre_v3, Browser::Chromium, Some((2, 14, 1))).is_some());
+        assert!(extract(pre_v3, Browser::Chromium, Some((10, 25, 0))).is_none());
+        assert!(extract(&v3, Browser::Firefox, None).is_none());
+        assert!(extract(&format!("\0{}\0", vault), Browser::Firefox, None).is_some());
+        assert!(extract(&format!("\0{}\0", vault), Browser::Chromium, None).is_none());
+
+        // Unless they are given, the patterns are those of the version found in the store
+        let upgraded =
+            format!(r#"{}"AppMetadataController":{{"currentAppVersion":"11.16.0"}}"#, pre_v3);
+        assert!(extract_vault_from_bytes(pre_v3.as_bytes()).is_ok());
+        assert!(extract_vault_from_bytes(upgraded.as_bytes()).is_err());
+        let upgraded = format!(r#"{}"currentAppVersion":"11.16.0""#, v3);
+        assert!(extract_vault_from_bytes(upgraded.as_bytes()).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed() {
+        // Inputs the fuzz targets found panicking
+        let vault = |data: &str, iv: &str, salt: Option<&str>| Vault {
+            data: data.to_string(),
+            iv: iv.to_string(),
+            salt: salt.map(str::to_string),
+            key_metadata: None,
+        };
+        assert!(decrypt_vault(&vault("\"AAAA\"", "\"AAAA\"", Some("\"AAAA\"")), "p").is_err());
+        assert!(decrypt_vault(&vault("é", "é", Some("é")), "p").is_err());
+        assert!(decrypt_vault(&vault("\"AAAA\"", "\"\"", Some("\"\"")), "p").is_err());
+        assert!(extract_vault_from_bytes(br#""KeyringController":{"vault":"{\xff}""#).is_err());
+
+        // Key metadata of iterations too costly to attempt
+        for iterations in [0, MAX_ITERATIONS + 1, u32::MAX] {
+            let vault = Vault {
+                key_metadata: Some(KeyMetadata::pbkdf2(iterations)),
+                ..vault("\"AAAA\"", "\"AAAA\"", Some("\"AAAA\""))
+            };
+            let error = decrypt_vault(&vault, "p").unwrap_err().to_string();
+            assert_eq!(error, format!("Unsupported iterations {}", iterations));
+        }
+        assert!(split_json("}},é}},").is_empty());
+    }
+
     #[test]
     fn split_json_multiple() -> Result<()> {
         let s = r#"{"name":"Alice","sed":{}},{"name":"Bob","sed":{}},{"name":"Charlie","sed":{}}"#;
@@ -2,36 +2,184 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-/// Adds interactive test on the local machine
-use wallet_metamask::interactive::{extract_all_vaults, get_password};
-use wallet_metamask::vault::decrypt_vault;
+/// Tests of the discovery of the MetaMask stores on every platform, against fake directory
+/// trees leading to the fixtures.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+use wallet_metamask::{
+    fixtures::{Store, VaultFixture, FIREFOX_EXTENSION_UUID},
+    interactive::{
+        ChromeLocator, DirEntry, FirefoxLocator, MockEnvironment, Os, CHROME_EXTENSION_ID,
+        FIREFOX_EXTENSION_ID,
+    },
+    source::MetaMaskSource,
+    vault::{ChromeExtractor, FirefoxExtractor, MetaMaskDecryptor},
+};
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use anyhow::Result;
+    use anyhow::{anyhow, Result};
 
-    /// How to use the Vault Decryptor with the MetaMask Vault Data
-    ///
-    /// https://support.metamask.io/hc/en-us/articles/360018766351-How-to-use-the-Vault-Decryptor-with-the-MetaMask-Vault-Data
-    #[allow(unused_attributes)]
-    #[ignore = "This test can only be run on the local machine"]
-    #[test]
-    fn test_open_local() -> Result<()> {
-        // Collect all vaults that are found locally
-        let vaults = extract_all_vaults().unwrap();
-        // Print the vault count
-        println!("Found {} vaults", vaults.len());
+    const CHROME_FIXTURE: &str = "tests/fixtures/chromium-108.0_5359.98_4.10.24.2/000003.log";
+    const CHROME_PASSWORD: &str = "JooXegoodowu8mohf2ietah5kohgah5";
 
-        vaults.iter().for_each(|vault| {
-            // Ask for password from user interactively
-            let pwd = get_password().unwrap();
+    const FIREFOX_MNEMONIC: &str = "test test test test test test test test test test test junk";
+    const FIREFOX_PASSWORD: &str = "correct horse battery staple";
 
-            // Attempt to decrypt the vault
-            let res = decrypt_vault(vault, &pwd);
+    /// Returns an environment of an operating system and a variable, whose fake tree lists the
+    /// stores at their paths on the platform and points them at the real files.
+    fn environment(
+        os: Os,
+        var: (&'static str, &'static str),
+        stores: &[(String, PathBuf)],
+        texts: &[(String, String)],
+    ) -> MockEnvironment {
+        let stores: BTreeMap<PathBuf, PathBuf> =
+            stores.iter().map(|(path, file)| (path.into(), file.clone())).collect();
+        let texts: BTreeMap<PathBuf, String> =
+            texts.iter().map(|(path, text)| (path.into(), text.clone())).collect();
 
-            println!("sucess! {}", res.is_ok());
+        let mut env = MockEnvironment::new();
+        env.expect_os().return_const(os);
+        env.expect_var_os().returning(move |key| (key == var.0).then(|| OsString::from(var.1)));
+        env.expect_read_dir().returning(move |dir| {
+            let children: BTreeSet<DirEntry> = stores
+                .iter()
+                .filter_map(|(path, file)| {
+                    let mut rest = path.strip_prefix(dir).ok()?.components();
+                    let child = rest.next()?;
+                    Some(match rest.next() {
+                        Some(_) => DirEntry { path: dir.join(child), is_dir: true },
+                        None => DirEntry { path: file.clone(), is_dir: false },
+                    })
+                })
+                .collect();
+            children.into_iter().collect()
         });
+        env.expect_read_to_string().returning(move |path: &Path| texts.get(path).cloned());
+        env
+    }
+
+    /// Returns the sources of MetaMask in the fake environments of a platform.
+    fn sources(os: Os, firefox: &Path) -> Vec<MetaMaskSource> {
+        let chrome = Path::new(env!("CARGO_MANIFEST_DIR")).join(CHROME_FIXTURE);
+        let (chrome_var, user_data_dir, firefox_var, profiles_dir) = match os {
+            Os::Windows => (
+                ("LOCALAPPDATA", "C:/Users/satoshi/AppData/Local"),
+                "C:/Users/satoshi/AppData/Local/Google/Chrome/User Data",
+                ("APPDATA", "C:/Users/satoshi/AppData/Roaming"),
+                "C:/Users/satoshi/AppData/Roaming/Mozilla/Firefox/Profiles",
+            ),
+            Os::MacOs => (
+                ("HOME", "/Users/satoshi"),
+                "/Users/satoshi/Library/Application Support/BraveSoftware/Brave-Browser",
+                ("HOME", "/Users/satoshi"),
+                "/Users/satoshi/Library/Application Support/Firefox/Profiles",
+            ),
+            Os::Linux => (
+                ("HOME", "/home/satoshi"),
+                "/home/satoshi/.config/google-chrome",
+                ("HOME", "/home/satoshi"),
+                "/home/satoshi/.mozilla/firefox",
+            ),
+        };
+
+        let store = format!(
+            "{}/Profile 1/Local Extension Settings/{}/000003.log",
+            user_data_dir, CHROME_EXTENSION_ID
+        );
+        let chrome_env = environment(os, chrome_var, &[(store, chrome)], &[]);
+
+        let profile = format!("{}/abcd1234.default-release", profiles_dir);
+        let uuids = serde_json::json!({ FIREFOX_EXTENSION_ID: FIREFOX_EXTENSION_UUID });
+        let prefs = format!(
+            "user_pref(\"extensions.webextensions.uuids\", {});\n",
+            serde_json::json!(uuids.to_string())
+        );
+        let store = format!(
+            "{}/storage/default/moz-extension+++{}^userContextId=4294967295/idb/3647222921wleabcEoxlt-eengsairo.sqlite",
+            profile, FIREFOX_EXTENSION_UUID
+        );
+        let texts = [(format!("{}/prefs.js", profile), prefs)];
+        let firefox_env = environment(os, firefox_var, &[(store, firefox.into())], &texts);
+
+        vec![
+            MetaMaskSource::new(
+                "MetaMask Chrome",
+                Box::new(ChromeLocator { user_data_dirs: vec![], env: chrome_env }),
+                Box::new(ChromeExtractor),
+                Box::new(MetaMaskDecryptor),
+            ),
+            MetaMaskSource::new(
+                "MetaMask Firefox",
+                Box::new(FirefoxLocator { profiles_dir: None, env: firefox_env }),
+                Box::new(FirefoxExtractor),
+                Box::new(MetaMaskDecryptor),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_discover_platforms() -> Result<()> {
+        let firefox =
+            env::temp_dir().join(format!("metamask-discover-{}.sqlite", std::process::id()));
+        let fixture = VaultFixture::new(FIREFOX_MNEMONIC, FIREFOX_PASSWORD);
+        fs::write(&firefox, fixture.store(Store::FirefoxIndexedDb))?;
+
+        for os in [Os::Windows, Os::MacOs, Os::Linux] {
+            let [chrome, firefox_source] = &sources(os, &firefox)[..] else { unreachable!() };
+
+            let found = chrome.vaults().map_err(|e| anyhow!("{}", e))?;
+            assert_eq!(found.len(), 1, "{:?}", os);
+            assert!(found[0].path.ends_with(CHROME_FIXTURE), "{:?}", os);
+            let decrypted =
+                chrome.decrypt(&found[0].vault, CHROME_PASSWORD).map_err(|e| anyhow!("{}", e))?;
+            assert_eq!(
+                decrypted.data.mnemonic.to_string(),
+                "harvest afraid useful nose electric swift various man boil diagram confirm ahead"
+            );
+
+            let found = firefox_source.vaults().map_err(|e| anyhow!("{}", e))?;
+            assert_eq!(found.len(), 1, "{:?}", os);
+            assert_eq!(found[0].path, firefox, "{:?}", os);
+            let decrypted = firefox_source
+                .decrypt(&found[0].vault, FIREFOX_PASSWORD)
+                .map_err(|e| anyhow!("{}", e))?;
+            assert_eq!(decrypted.data.mnemonic.to_string(), FIREFOX_MNEMONIC);
+            assert!(firefox_source.decrypt(&found[0].vault, CHROME_PASSWORD).is_err());
+        }
+        fs::remove_file(&firefox)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_discover_other_platform() -> Result<()> {
+        // The stores at the paths of Windows are not found on Linux, even with the variables
+        let env = environment(
+            Os::Linux,
+            ("LOCALAPPDATA", "C:/Users/satoshi/AppData/Local"),
+            &[(
+                format!(
+                    "C:/Users/satoshi/AppData/Local/Google/Chrome/User Data/Default/Local Extension Settings/{}/000003.log",
+                    CHROME_EXTENSION_ID
+                ),
+                Path::new(env!("CARGO_MANIFEST_DIR")).join(CHROME_FIXTURE),
+            )],
+            &[],
+        );
+        let source = MetaMaskSource::new(
+            "MetaMask Chrome",
+            Box::new(ChromeLocator { user_data_dirs: vec![], env }),
+            Box::new(ChromeExtractor),
+            Box::new(MetaMaskDecryptor),
+        );
+        assert!(source.vaults().map_err(|e| anyhow!("{}", e))?.is_empty());
         Ok(())
     }
 }
@@ -0,0 +1,187 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Property tests of the locate, extract and decrypt pipeline over synthetic stores.
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde_json::Value;
+use std::{env, fs};
+use wallet_metamask::{
+    fixtures::{leveldb::Compression, Keyring, Layout, Store, VaultFixture, HD_PATH},
+    interactive::{ChromeLocator, FirefoxLocator, SystemEnvironment},
+    source::VaultSource,
+    vault::{
+        decrypt_vault, extract_vault_from_bytes, ChromeExtractor, FirefoxExtractor,
+        MetaMaskDecryptor,
+    },
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Result};
+
+    const WORDS: [&str; 16] = [
+        "abandon", "ability", "harvest", "afraid", "useful", "nose", "electric", "swift",
+        "dolphin", "peanut", "amateur", "party", "speed", "accuse", "odor", "junk",
+    ];
+
+    const PASSWORD_CHARS: &[char] = &['a', 'Z', '0', ' ', '"', '\\', '{', '}', 'é', '鍵', '🦊'];
+
+    /// Returns a random vault, with the HD keyring among other keyrings.
+    fn random_fixture(rng: &mut StdRng) -> VaultFixture {
+        let words = if rng.gen_bool(0.5) { 12 } else { 24 };
+        let mnemonic: Vec<&str> = (0..words).map(|_| *WORDS.choose(rng).unwrap()).collect();
+        let mut keyrings = vec![Keyring::HdKeyTree {
+            mnemonic: mnemonic.join(" "),
+            number_of_accounts: rng.gen_range(1..10),
+            hd_path: HD_PATH.to_string(),
+        }];
+        for _ in 0..rng.gen_range(0..3) {
+            let keyring = match rng.gen_bool(0.5) {
+                true => Keyring::SimpleKeyPair {
+                    private_keys: vec![format!("{:064x}", rng.gen::<u128>())],
+                },
+                false => Keyring::Ledger { hd_path: "m/44'/60'/0'".to_string() },
+            };
+            keyrings.insert(rng.gen_range(0..=keyrings.len()), keyring);
+        }
+
+        let password: String =
+            (0..rng.gen_range(0..16)).map(|_| *PASSWORD_CHARS.choose(rng).unwrap()).collect();
+        let layout =
+            *[Layout::Cleartext, Layout::Encrypted, Layout::EncryptedBytes].choose(rng).unwrap();
+        let iterations = rng.gen_bool(0.5).then(|| rng.gen_range(1..2_000));
+        VaultFixture { keyrings, password, iterations, layout, seed: rng.gen() }
+    }
+
+    /// Extracts and decrypts the vault of a store.
+    fn unlock(fixture: &VaultFixture, store: &[u8]) -> Result<String> {
+        let vault = extract_vault_from_bytes(store).map_err(|e| anyhow!("{}", e))?;
+        let decrypted = decrypt_vault(&vault, &fixture.password).map_err(|e| anyhow!("{}", e))?;
+        Ok(decrypted.data.mnemonic.to_string())
+    }
+
+    /// Returns whether a store holds the vault whole, as the copies of Snappy break it up.
+    fn is_whole(fixture: &VaultFixture, store: &[u8]) -> bool {
+        let vault = match fixture.layout {
+            Layout::Cleartext => fixture.vault_json(),
+            _ => Value::String(fixture.vault_json()).to_string(),
+        };
+        store.windows(vault.len()).any(|window| window == vault.as_bytes())
+    }
+
+    #[test]
+    fn test_synthetic_stores() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(0x6d65_7461);
+        let (mut broken, mut recovered) = (0, 0);
+        for case in 0..32 {
+            let fixture = random_fixture(&mut rng);
+            let stores = [
+                Store::Raw,
+                Store::ChromeLog,
+                Store::ChromeTable(Compression::None),
+                Store::ChromeTable(Compression::Snappy),
+            ];
+            for store in stores {
+                let bytes = fixture.store(store);
+                let mnemonic = unlock(&fixture, &bytes);
+                let unlocked = mnemonic.as_ref().ok().map(String::as_str) == fixture.mnemonic();
+                if is_whole(&fixture, &bytes) {
+                    assert!(unlocked, "case {} {:?} {:?}: {:?}", case, store, fixture, mnemonic);
+                } else if fixture.layout != Layout::Cleartext {
+                    // The patterns recover most of the encrypted vaults Snappy broke up, while
+                    // the words of a mnemonic in clear are lost
+                    broken += 1;
+                    recovered += unlocked as usize;
+                }
+            }
+        }
+        assert!(recovered * 10 >= broken * 9, "{} of {} recovered", recovered, broken);
+        Ok(())
+    }
+
+    #[test]
+    fn test_synthetic_skipped_keyrings() -> Result<()> {
+        let mnemonic = WORDS[..12].join(" ");
+        let mut fixture = VaultFixture::new(&mnemonic, "correct");
+        fixture.iterations = Some(100);
+        fixture.keyrings = vec![
+            Keyring::SimpleKeyPair { private_keys: vec![format!("{:064x}", 1)] },
+            Keyring::hd_key_tree(&mnemonic),
+            Keyring::hd_key_tree(&WORDS[12..].join(" ")),
+            Keyring::Ledger { hd_path: "m/44'/60'/0'".to_string() },
+        ];
+        let vault = extract_vault_from_bytes(&fixture.store(Store::ChromeLog)).unwrap();
+        let decrypted = decrypt_vault(&vault, "correct").map_err(|e| anyhow!("{}", e))?;
+        assert_eq!(decrypted.data.mnemonic.to_string(), mnemonic);
+        assert_eq!(decrypted.skipped, ["Simple Key Pair", "HD Key Tree", "Ledger Hardware"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_synthetic_wrong_password() {
+        let mut fixture = VaultFixture::new(&WORDS[..12].join(" "), "correct");
+        fixture.iterations = Some(100);
+        let vault = extract_vault_from_bytes(&fixture.store(Store::ChromeLog)).unwrap();
+        assert!(decrypt_vault(&vault, "wrong").is_err());
+    }
+
+    #[test]
+    fn test_synthetic_profiles() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(0x6d61_736b);
+        let dir = env::temp_dir().join(format!("metamask-synthetic-{}", std::process::id()));
+        for case in 0..8 {
+            let mut fixture = random_fixture(&mut rng);
+            if fixture.layout == Layout::Cleartext {
+                fixture.layout = Layout::Encrypted;
+            }
+            let case_dir = dir.join(case.to_string());
+
+            // Chromium, as a log or a table
+            let table = [None, Some(Compression::None), Some(Compression::Snappy)].choose(&mut rng);
+            let path = fixture.write_chrome_profile(&case_dir.join("chrome"), *table.unwrap())?;
+            let locator = ChromeLocator {
+                user_data_dirs: vec![case_dir.join("chrome")],
+                env: SystemEnvironment,
+            };
+            let chrome = VaultSource::new(
+                "synthetic Chrome",
+                Box::new(locator),
+                Box::new(ChromeExtractor),
+                Box::new(MetaMaskDecryptor),
+            );
+
+            // Firefox
+            fixture.write_firefox_profile(&case_dir.join("firefox"))?;
+            let locator = FirefoxLocator {
+                profiles_dir: Some(case_dir.join("firefox")),
+                env: SystemEnvironment,
+            };
+            let firefox = VaultSource::new(
+                "synthetic Firefox",
+                Box::new(locator),
+                Box::new(FirefoxExtractor),
+                Box::new(MetaMaskDecryptor),
+            );
+
+            for source in [chrome, firefox] {
+                let found = source.vaults().map_err(|e| anyhow!("{}", e))?;
+                assert_eq!(found.len(), 1, "case {} {} {:?}", case, source.name, fixture);
+                let decrypted = source
+                    .decrypt(&found[0].vault, &fixture.password)
+                    .map_err(|e| anyhow!("case {} {} {:?}: {}", case, source.name, fixture, e))?;
+                assert_eq!(Some(decrypted.data.mnemonic.to_string().as_str()), fixture.mnemonic());
+            }
+            assert_eq!(
+                path,
+                case_dir
+                    .join("chrome/Default/Local Extension Settings")
+                    .join(wallet_metamask::interactive::CHROME_EXTENSION_ID)
+                    .join(path.file_name().unwrap())
+            );
+        }
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,460 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Accounts derived from a mnemonic or imported from keystores, presale wallets and raw keys.
+///
+/// From:
+/// https://ethereum.org/en/developers/docs/data-structures-and-encoding/web3-secret-storage/
+/// https://github.com/ethereum/go-ethereum/blob/master/accounts/keystore/presale.go
+use crate::mnemonic::{master_key, Language};
+use aes::{
+    cipher::{generic_array::GenericArray, BlockDecrypt, KeyInit, KeyIvInit, StreamCipher},
+    Aes128,
+};
+use coins_bip32::{ecdsa::SigningKey, enc::decode_b58_check};
+use ethers_core::{
+    types::Address,
+    utils::{hex, keccak256, secret_key_to_address, to_checksum},
+};
+use ethers_signers::LocalWallet;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::fmt;
+use subtle::ConstantTimeEq;
+
+/// The PBKDF2 iterations of the presale wallets
+const PRESALE_ITERATIONS: u32 = 2000;
+
+/// The costliest key derivations of the keystores that are attempted, since their parameters
+/// come from the untrusted file: 2^20 scrypt rounds with r·p of 16 take up to 2 GiB and a few
+/// seconds, as do 10M PBKDF2 iterations
+const MAX_SCRYPT_LOG_N: u32 = 20;
+const MAX_SCRYPT_R_P: u32 = 16;
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+/// The length of the derived keys, the AES-128 key and the MAC key
+const DKLEN: usize = 32;
+
+/// The version byte of mainnet WIF keys
+const WIF_VERSION: u8 = 0x80;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Mnemonic(#[from] crate::mnemonic::Error),
+    #[error("Unrecognized key format, expected a keystore, a presale wallet, a hex or WIF key")]
+    Format,
+    #[error("Invalid private key")]
+    PrivateKey,
+    #[error("Invalid WIF key: {0}")]
+    Wif(String),
+    #[error("Invalid JSON: {0}")]
+    Json(String),
+    #[error("Unsupported {0}")]
+    Unsupported(String),
+    #[error("A password is needed to decrypt the {0}")]
+    PasswordRequired(Source),
+    #[error("Wrong password")]
+    Password,
+    #[error("The key of address {actual} does not match the address {expected}")]
+    Address { expected: String, actual: String },
+    #[error("Failed to derive the account: {0}")]
+    Derive(String),
+}
+
+/// Where the key of an account comes from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// A mnemonic, at an HD path
+    Mnemonic { path: String },
+    /// A Web3 Secret Storage v3 keystore
+    Keystore,
+    /// A geth presale wallet
+    Presale,
+    /// A hex private key
+    PrivateKey,
+    /// A private key in the wallet import format
+    Wif,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Mnemonic { path } => write!(f, "mnemonic at {}", path),
+            Source::Keystore => write!(f, "keystore"),
+            Source::Presale => write!(f, "presale wallet"),
+            Source::PrivateKey => write!(f, "private key"),
+            Source::Wif => write!(f, "WIF key"),
+        }
+    }
+}
+
+/// An account and its private key
+#[derive(Clone)]
+pub struct Account {
+    pub address: Address,
+    pub source: Source,
+    key: SigningKey,
+}
+
+impl fmt::Debug for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Account")
+            .field("address", &self.address)
+            .field("source", &self.source)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", to_checksum(&self.address, None), self.source)
+    }
+}
+
+#[derive(Deserialize)]
+struct Keystore {
+    #[serde(alias = "Crypto")]
+    crypto: KeystoreCrypto,
+    version: u32,
+    address: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct KeystoreCrypto {
+    cipher: String,
+    cipherparams: CipherParams,
+    ciphertext: String,
+    kdf: String,
+    kdfparams: KdfParams,
+    mac: String,
+}
+
+#[derive(Deserialize)]
+struct CipherParams {
+    iv: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KdfParams {
+    Scrypt { dklen: usize, n: u64, r: u32, p: u32, salt: String },
+    Pbkdf2 { dklen: usize, c: u32, prf: String, salt: String },
+}
+
+#[derive(Deserialize)]
+struct Presale {
+    encseed: String,
+    ethaddr: String,
+}
+
+/// Decodes hex, with or without a 0x prefix.
+fn decode_hex(s: &str) -> Result<Vec<u8>, Error> {
+    hex::decode(s.trim().trim_start_matches("0x")).map_err(|e| Error::Json(e.to_string()))
+}
+
+/// Decrypts AES-128-CBC with PKCS#7 padding.
+fn decrypt_cbc(key: &[u8], iv: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+    if data.is_empty() || data.len() % 16 != 0 {
+        return Err(Error::Password);
+    }
+    let cipher = Aes128::new(GenericArray::from_slice(key));
+    let mut previous = iv;
+    let mut plain = Vec::with_capacity(data.len());
+    for chunk in data.chunks(16) {
+        let mut block = GenericArray::clone_from_slice(chunk);
+        cipher.decrypt_block(&mut block);
+        plain.extend(block.iter().zip(previous).map(|(b, p)| b ^ p));
+        previous = chunk;
+    }
+    let pad = *plain.last().expect("not empty") as usize;
+    if pad == 0 || pad > 16 || !plain[plain.len() - pad..].iter().all(|b| *b as usize == pad) {
+        return Err(Error::Password);
+    }
+    plain.truncate(plain.len() - pad);
+    Ok(plain)
+}
+
+/// Checks that the key of an account is the one of an expected address.
+fn check_address(account: Account, expected: Option<&str>) -> Result<Account, Error> {
+    let Some(expected) = expected else { return Ok(account) };
+    let actual = hex::encode(account.address);
+    match expected.trim_start_matches("0x").eq_ignore_ascii_case(&actual) {
+        true => Ok(account),
+        false => Err(Error::Address { expected: expected.to_string(), actual }),
+    }
+}
+
+/// Decrypts the secret of a Web3 Secret Storage v3 keystore, e.g. a private key or a mnemonic.
+pub fn decrypt_keystore(json: &str, password: &str) -> Result<Vec<u8>, Error> {
+    let keystore: Keystore = serde_json::from_str(json).map_err(|e| Error::Json(e.to_string()))?;
+    let crypto = keystore.crypto;
+    if keystore.version != 3 {
+        return Err(Error::Unsupported(format!("keystore version {}", keystore.version)));
+    }
+    if crypto.cipher != "aes-128-ctr" {
+        return Err(Error::Unsupported(format!("cipher {}", crypto.cipher)));
+    }
+
+    let key = match (crypto.kdf.as_str(), &crypto.kdfparams) {
+        ("scrypt", KdfParams::Scrypt { dklen, n, r, p, salt }) => {
+            if !n.is_power_of_two() || n.trailing_zeros() > MAX_SCRYPT_LOG_N {
+                return Err(Error::Unsupported(format!("scrypt n {}", n)));
+            }
+            if r.checked_mul(*p).map_or(true, |rp| rp > MAX_SCRYPT_R_P) {
+                return Err(Error::Unsupported(format!("scrypt r {} and p {}", r, p)));
+            }
+            if *dklen != DKLEN {
+                return Err(Error::Unsupported(format!("dklen {}", dklen)));
+            }
+            let params = scrypt::Params::new(n.trailing_zeros() as u8, *r, *p)
+                .map_err(|e| Error::Unsupported(format!("scrypt parameters: {}", e)))?;
+            let mut key = vec![0u8; DKLEN];
+            scrypt::scrypt(password.as_bytes(), &decode_hex(salt)?, &params, &mut key)
+                .map_err(|e| Error::Unsupported(format!("scrypt: {}", e)))?;
+            key
+        }
+        ("pbkdf2", KdfParams::Pbkdf2 { dklen, c, prf, salt }) => {
+            if prf != "hmac-sha256" {
+                return Err(Error::Unsupported(format!("pbkdf2 prf {}", prf)));
+            }
+            if *c > MAX_PBKDF2_ITERATIONS {
+                return Err(Error::Unsupported(format!("pbkdf2 iterations {}", c)));
+            }
+            if *dklen != DKLEN {
+                return Err(Error::Unsupported(format!("dklen {}", dklen)));
+            }
+            let mut key = vec![0u8; DKLEN];
+            pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), &decode_hex(salt)?, *c, &mut key);
+            key
+        }
+        (kdf, _) => return Err(Error::Unsupported(format!("kdf {}", kdf))),
+    };
+
+    let mut ciphertext = decode_hex(&crypto.ciphertext)?;
+    let mac = keccak256([&key[16..32], &ciphertext].concat());
+    if !bool::from(mac.as_slice().ct_eq(&decode_hex(&crypto.mac)?)) {
+        return Err(Error::Password);
+    }
+    let iv = decode_hex(&crypto.cipherparams.iv)?;
+    if iv.len() != 16 {
+        return Err(Error::Json(format!("invalid iv length {}", iv.len())));
+    }
+    ctr::Ctr128BE::<Aes128>::new(GenericArray::from_slice(&key[..16]), iv[..].into())
+        .apply_keystream(&mut ciphertext);
+    Ok(ciphertext)
+}
+
+impl Account {
+    pub fn new(key: SigningKey, source: Source) -> Self {
+        Account { address: secret_key_to_address(&key), source, key }
+    }
+
+    fn from_bytes(bytes: &[u8], source: Source) -> Result<Self, Error> {
+        let key = SigningKey::from_slice(bytes).map_err(|_| Error::PrivateKey)?;
+        Ok(Account::new(key, source))
+    }
+
+    pub fn signing_key(&self) -> &SigningKey {
+        &self.key
+    }
+
+    pub fn wallet(&self) -> LocalWallet {
+        LocalWallet::from(self.key.clone())
+    }
+
+    /// Derives the account at `path` of a mnemonic, e.g. the mnemonic of a vault.
+    pub fn from_mnemonic(
+        phrase: &str,
+        language: Language,
+        passphrase: Option<&str>,
+        path: &str,
+    ) -> Result<Self, Error> {
+        let root = master_key(phrase, language, passphrase)?;
+        let key = root.derive_path(path).map_err(|e| Error::Derive(e.to_string()))?;
+        let key: &SigningKey = key.as_ref();
+        Ok(Account::new(key.clone(), Source::Mnemonic { path: path.to_string() }))
+    }
+
+    /// Parses a hex private key, with or without a 0x prefix.
+    pub fn from_private_key(key: &str) -> Result<Self, Error> {
+        let key = key.trim().trim_start_matches("0x");
+        if key.len() != 64 {
+            return Err(Error::PrivateKey);
+        }
+        let bytes = hex::decode(key).map_err(|_| Error::PrivateKey)?;
+        Account::from_bytes(&bytes, Source::PrivateKey)
+    }
+
+    /// Parses a mainnet WIF private key, compressed or not.
+    pub fn from_wif(wif: &str) -> Result<Self, Error> {
+        let wif = wif.trim();
+        // The decoder of coins-bip32 panics on keys shorter than their checksum
+        if !(51..=52).contains(&wif.len()) {
+            return Err(Error::Wif("expected 51 or 52 characters".to_string()));
+        }
+        let bytes = decode_b58_check(wif).map_err(|e| Error::Wif(e.to_string()))?;
+        match bytes.as_slice() {
+            [WIF_VERSION, key @ ..] if key.len() == 32 => Account::from_bytes(key, Source::Wif),
+            [WIF_VERSION, key @ .., 0x01] if key.len() == 32 => {
+                Account::from_bytes(key, Source::Wif)
+            }
+            _ => Err(Error::Wif("expected a mainnet private key".to_string())),
+        }
+    }
+
+    /// Decrypts a Web3 Secret Storage v3 keystore, with a scrypt or PBKDF2 key derivation.
+    pub fn from_keystore(json: &str, password: &str) -> Result<Self, Error> {
+        let keystore: Keystore =
+            serde_json::from_str(json).map_err(|e| Error::Json(e.to_string()))?;
+        let account = Account::from_bytes(&decrypt_keystore(json, password)?, Source::Keystore)?;
+        check_address(account, keystore.address.as_deref())
+    }
+
+    /// Decrypts a geth presale wallet.
+    pub fn from_presale(json: &str, password: &str) -> Result<Self, Error> {
+        let presale: Presale =
+            serde_json::from_str(json).map_err(|e| Error::Json(e.to_string()))?;
+        let encseed = decode_hex(&presale.encseed)?;
+        if encseed.len() < 32 {
+            return Err(Error::Json(format!("invalid encseed length {}", encseed.len())));
+        }
+        let mut key = [0u8; 16];
+        pbkdf2::pbkdf2_hmac::<Sha256>(
+            password.as_bytes(),
+            password.as_bytes(),
+            PRESALE_ITERATIONS,
+            &mut key,
+        );
+        let seed = decrypt_cbc(&key, &encseed[..16], &encseed[16..])?;
+        let account = Account::from_bytes(&keccak256(seed), Source::Presale)?;
+        // A wrong password may still unpad, the address tells
+        check_address(account, Some(&presale.ethaddr)).map_err(|_| Error::Password)
+    }
+
+    /// Imports a keystore, a presale wallet, a hex or a WIF private key.
+    pub fn import(input: &str, password: Option<&str>) -> Result<Self, Error> {
+        let input = input.trim();
+        if input.starts_with('{') {
+            let value: serde_json::Value =
+                serde_json::from_str(input).map_err(|e| Error::Json(e.to_string()))?;
+            let source = match value.get("encseed") {
+                Some(_) => Source::Presale,
+                None => Source::Keystore,
+            };
+            let password = password.ok_or_else(|| Error::PasswordRequired(source.clone()))?;
+            return match source {
+                Source::Presale => Account::from_presale(input, password),
+                _ => Account::from_keystore(input, password),
+            };
+        }
+        let digits = input.trim_start_matches("0x");
+        if digits.len() == 64 && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Account::from_private_key(input);
+        }
+        if (51..=52).contains(&input.len()) {
+            return Account::from_wif(input);
+        }
+        Err(Error::Format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    // From: https://ethereum.org/en/developers/docs/data-structures-and-encoding/web3-secret-storage/
+    const KEY: &str = "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d";
+    const ADDRESS: &str = "0x008AeEda4D805471dF9b2A5B0f38A0C3bCBA786b";
+
+    #[test]
+    fn test_keystores() -> Result<()> {
+        for json in [
+            include_str!("../tests/fixtures/import/keystore-scrypt.json"),
+            include_str!("../tests/fixtures/import/keystore-pbkdf2.json"),
+        ] {
+            let account = Account::import(json, Some("testpassword"))?;
+            assert_eq!(account.source, Source::Keystore);
+            assert_eq!(account.address, ADDRESS.parse()?);
+            assert_eq!(hex::encode(account.signing_key().to_bytes()), KEY);
+        }
+
+        let json = include_str!("../tests/fixtures/import/keystore-scrypt.json");
+        assert_eq!(Account::from_keystore(json, "wrong").unwrap_err(), Error::Password);
+        assert_eq!(
+            Account::import(json, None).unwrap_err(),
+            Error::PasswordRequired(Source::Keystore)
+        );
+        let json = json.replace("aes-128-ctr", "aes-128-cbc");
+        assert!(matches!(Account::from_keystore(&json, ""), Err(Error::Unsupported(_))));
+
+        // Key derivations too costly to attempt, and keys of another length
+        let json = include_str!("../tests/fixtures/import/keystore-scrypt.json");
+        for (from, to) in [
+            ("\"n\": 1024", "\"n\": 2097152"),
+            ("\"r\": 8", "\"r\": 4294967295"),
+            ("\"p\": 1", "\"p\": 3"),
+            ("\"dklen\": 32", "\"dklen\": 1000000000"),
+        ] {
+            let json = json.replace(from, to);
+            assert!(matches!(decrypt_keystore(&json, ""), Err(Error::Unsupported(_))), "{}", to);
+        }
+        let json = include_str!("../tests/fixtures/import/keystore-pbkdf2.json");
+        for (from, to) in
+            [("\"c\": 262144", "\"c\": 4294967295"), ("\"dklen\": 32", "\"dklen\": 16")]
+        {
+            let json = json.replace(from, to);
+            assert!(matches!(decrypt_keystore(&json, ""), Err(Error::Unsupported(_))), "{}", to);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_presale() -> Result<()> {
+        let json = include_str!("../tests/fixtures/import/presale.json");
+        let account = Account::import(json, Some("presalepassword"))?;
+        assert_eq!(account.source, Source::Presale);
+        assert_eq!(account.address, "0x6079dc322c2d0427837262fac6349b2699d8d319".parse()?);
+        assert_eq!(Account::from_presale(json, "wrong").unwrap_err(), Error::Password);
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_keys() -> Result<()> {
+        let account = Account::import(&format!("0x{}", KEY), None)?;
+        assert_eq!((account.address, account.source), (ADDRESS.parse()?, Source::PrivateKey));
+
+        // From: https://en.bitcoin.it/wiki/Wallet_import_format
+        let key = "0c28fca386c7a227600b2fe50b7cae11ec86d3bf1fbe471be89827e19d72aa1d";
+        for wif in [
+            "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ",
+            "KwdMAjGmerYanjeui5SHS7JkmpZvVipYvB2LJGU1ZxJwYvP98617",
+        ] {
+            let account = Account::import(wif, None)?;
+            assert_eq!(account.source, Source::Wif);
+            assert_eq!(hex::encode(account.signing_key().to_bytes()), key);
+        }
+
+        assert_eq!(Account::import("0x1234", None).unwrap_err(), Error::Format);
+        assert!(matches!(
+            Account::from_wif("5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTj"),
+            Err(Error::Wif(_))
+        ));
+        assert_eq!(Account::from_private_key(&"0".repeat(64)).unwrap_err(), Error::PrivateKey);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mnemonic() -> Result<()> {
+        let phrase = "test test test test test test test test test test test junk";
+        let account = Account::from_mnemonic(phrase, Language::English, None, "m/44'/60'/0'/0/0")?;
+        assert_eq!(account.address, "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse()?);
+        assert_eq!(
+            account.to_string(),
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266 (mnemonic at m/44'/60'/0'/0/0)"
+        );
+        Ok(())
+    }
+}
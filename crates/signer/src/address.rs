@@ -0,0 +1,272 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Ethereum address checksums, validation and vanity generation.
+///
+/// From:
+/// https://eips.ethereum.org/EIPS/eip-55
+/// https://eips.ethereum.org/EIPS/eip-1191
+use ethers_core::{
+    k256::ecdsa::SigningKey,
+    types::{Address, H256},
+    utils::{get_create2_address_from_hash, hex, keccak256, secret_key_to_address},
+};
+use rand::{thread_rng, RngCore};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// The number of attempts between two progress reports of a thread
+const PROGRESS_INTERVAL: u64 = 1 << 14;
+
+/// The number of hex characters of an address
+const ADDRESS_LEN: usize = 40;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid address `{0}`, expected 0x and 40 hex characters")]
+    Invalid(String),
+    #[error("Invalid checksum, expected {0}")]
+    Checksum(String),
+    #[error("Invalid pattern `{0}`, expected hex characters")]
+    Pattern(String),
+    #[error("The prefix and the suffix are longer than an address")]
+    PatternLength,
+}
+
+/// Returns the EIP-55 checksummed address, or the EIP-1191 one of a chain.
+pub fn checksum(address: &Address, chain_id: Option<u64>) -> String {
+    let lower = hex::encode(address);
+    let hash = match chain_id {
+        Some(chain_id) => keccak256(format!("{}0x{}", chain_id, lower)),
+        None => keccak256(&lower),
+    };
+    let checksummed: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = hash[i / 2] >> (4 * (1 - i % 2)) & 0xf;
+            match nibble >= 8 {
+                true => c.to_ascii_uppercase(),
+                false => c,
+            }
+        })
+        .collect();
+    format!("0x{}", checksummed)
+}
+
+/// Parses an address, checking its checksum unless it is all lowercase or all uppercase.
+pub fn validate(address: &str, chain_id: Option<u64>) -> Result<Address, Error> {
+    let digits = address.strip_prefix("0x").unwrap_or(address);
+    if digits.len() != ADDRESS_LEN || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::Invalid(address.to_string()));
+    }
+    let parsed: Address = digits.parse().map_err(|_| Error::Invalid(address.to_string()))?;
+    let mixed = digits.chars().any(|c| c.is_ascii_lowercase()) &&
+        digits.chars().any(|c| c.is_ascii_uppercase());
+    let expected = checksum(&parsed, chain_id);
+    match mixed && expected[2..] != *digits {
+        true => Err(Error::Checksum(expected)),
+        false => Ok(parsed),
+    }
+}
+
+/// The hex characters a vanity address starts and ends with
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pattern {
+    prefix: String,
+    suffix: String,
+    /// Match the case of the EIP-55 checksummed address
+    case_sensitive: bool,
+}
+
+impl Pattern {
+    pub fn new(prefix: &str, suffix: &str, case_sensitive: bool) -> Result<Self, Error> {
+        let prefix = prefix.strip_prefix("0x").unwrap_or(prefix);
+        for part in [prefix, suffix] {
+            if !part.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(Error::Pattern(part.to_string()));
+            }
+        }
+        if prefix.len() + suffix.len() > ADDRESS_LEN {
+            return Err(Error::PatternLength);
+        }
+        let case = |s: &str| match case_sensitive {
+            true => s.to_string(),
+            false => s.to_ascii_lowercase(),
+        };
+        Ok(Pattern { prefix: case(prefix), suffix: case(suffix), case_sensitive })
+    }
+
+    /// Returns whether an address matches the pattern.
+    pub fn matches(&self, address: &Address) -> bool {
+        let digits = match self.case_sensitive {
+            true => checksum(address, None)[2..].to_string(),
+            false => hex::encode(address),
+        };
+        digits.starts_with(&self.prefix) && digits.ends_with(&self.suffix)
+    }
+
+    /// Returns the expected number of attempts to find a matching address.
+    pub fn difficulty(&self) -> f64 {
+        let chars = self.prefix.chars().chain(self.suffix.chars());
+        chars
+            .map(|c| match self.case_sensitive && c.is_ascii_alphabetic() {
+                true => 32.0,
+                false => 16.0,
+            })
+            .product()
+    }
+}
+
+/// The progress of a vanity search
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub attempts: u64,
+}
+
+/// Runs `attempt` on `threads` threads until one returns a result.
+fn search<T: Send>(
+    threads: usize,
+    attempt: impl Fn(u64) -> Option<T> + Sync,
+    progress: impl Fn(Progress) + Sync,
+) -> T {
+    let attempts = AtomicU64::new(0);
+    let stop = AtomicBool::new(false);
+    let found = Mutex::new(None);
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let (attempts, stop, found, attempt, progress) =
+                (&attempts, &stop, &found, &attempt, &progress);
+            scope.spawn(move || {
+                let mut n = 0;
+                while !stop.load(Ordering::Relaxed) {
+                    if let Some(result) = attempt(n) {
+                        found.lock().unwrap().get_or_insert(result);
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                    let total = attempts.fetch_add(1, Ordering::Relaxed) + 1;
+                    if total % PROGRESS_INTERVAL == 0 {
+                        progress(Progress { attempts: total });
+                    }
+                    n += 1;
+                }
+            });
+        }
+    });
+    progress(Progress { attempts: attempts.into_inner() });
+    found.into_inner().unwrap().expect("the search stops once found")
+}
+
+/// Generates a private key whose address matches a pattern.
+pub fn vanity_key(
+    pattern: &Pattern,
+    threads: usize,
+    progress: impl Fn(Progress) + Sync,
+) -> (SigningKey, Address) {
+    search(
+        threads,
+        |_| {
+            let key = SigningKey::random(&mut thread_rng());
+            let address = secret_key_to_address(&key);
+            pattern.matches(&address).then_some((key, address))
+        },
+        progress,
+    )
+}
+
+/// Mines a CREATE2 salt whose contract address matches a pattern.
+///
+/// Every thread starts at a random salt, whose last 8 bytes are the attempt counter.
+pub fn vanity_salt(
+    pattern: &Pattern,
+    deployer: Address,
+    init_code_hash: H256,
+    threads: usize,
+    progress: impl Fn(Progress) + Sync,
+) -> (H256, Address) {
+    thread_local! {
+        static SALT: [u8; 24] = {
+            let mut salt = [0u8; 24];
+            thread_rng().fill_bytes(&mut salt);
+            salt
+        };
+    }
+    search(
+        threads,
+        |n| {
+            let salt = SALT.with(|base| {
+                let mut salt = [0u8; 32];
+                salt[..24].copy_from_slice(base);
+                salt[24..].copy_from_slice(&n.to_be_bytes());
+                H256(salt)
+            });
+            let address = get_create2_address_from_hash(deployer, salt, init_code_hash);
+            pattern.matches(&address).then_some((salt, address))
+        },
+        progress,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_checksum_vectors() -> Result<()> {
+        // From: https://eips.ethereum.org/EIPS/eip-55
+        for expected in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            let address = validate(&expected.to_lowercase(), None)?;
+            assert_eq!(checksum(&address, None), expected);
+            assert_eq!(validate(expected, None)?, address);
+        }
+
+        // From: https://eips.ethereum.org/EIPS/eip-1191
+        let address = validate("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed", None)?;
+        assert_eq!(checksum(&address, Some(30)), "0x5aaEB6053f3e94c9b9a09f33669435E7ef1bEAeD");
+        assert_eq!(checksum(&address, Some(31)), "0x5aAeb6053F3e94c9b9A09F33669435E7EF1BEaEd");
+        assert!(validate("0x5aaEB6053f3e94c9b9a09f33669435E7ef1bEAeD", Some(30)).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_errors() {
+        assert_eq!(
+            validate("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD", None),
+            Err(Error::Checksum("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".to_string()))
+        );
+        assert!(matches!(validate("0x5aAeb6053F", None), Err(Error::Invalid(_))));
+        assert!(matches!(validate(&format!("0x{}", "g".repeat(40)), None), Err(Error::Invalid(_))));
+    }
+
+    #[test]
+    fn test_vanity() -> Result<()> {
+        assert_eq!(Pattern::new("xyz", "", false), Err(Error::Pattern("xyz".to_string())));
+        assert_eq!(Pattern::new(&"0".repeat(41), "", false), Err(Error::PatternLength));
+        assert_eq!(Pattern::new("0xab", "C", true)?.difficulty(), 32.0 * 32.0 * 32.0);
+
+        let pattern = Pattern::new("0xA", "b", false)?;
+        let (key, address) = vanity_key(&pattern, 2, |_| {});
+        assert_eq!(secret_key_to_address(&key), address);
+        let hex = hex::encode(address);
+        assert!(hex.starts_with('a') && hex.ends_with('b'));
+
+        let pattern = Pattern::new("", "Ab", true)?;
+        let (deployer, init_code_hash) = (Address::repeat_byte(1), H256::repeat_byte(2));
+        let (salt, address) = vanity_salt(&pattern, deployer, init_code_hash, 2, |_| {});
+        assert_eq!(get_create2_address_from_hash(deployer, salt, init_code_hash), address);
+        assert!(checksum(&address, None).ends_with("Ab"));
+        Ok(())
+    }
+}
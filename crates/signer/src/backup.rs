@@ -0,0 +1,583 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Encrypted backups of keyrings, with their account labels and derivation metadata.
+///
+/// A backup is a JSON document of a header in clear and the ciphertext of the keyrings. The
+/// key is derived from the password with Argon2id and encrypts the keyrings with
+/// XChaCha20-Poly1305, authenticating the header as associated data so that its metadata can
+/// be inspected without the password but not modified. The associated data is the header as
+/// written in the backup, byte for byte, rather than its serialization by this version.
+///
+/// From:
+/// https://www.rfc-editor.org/rfc/rfc9106
+/// https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-xchacha
+use crate::{
+    account::Account,
+    mnemonic::{validate, Language},
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use rand::{thread_rng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::{Zeroize, Zeroizing};
+
+/// The name of the format, to tell a backup from other JSON documents
+pub const FORMAT: &str = "wallet-rs-backup";
+
+/// The version of the format
+pub const VERSION: u32 = 1;
+
+/// The key derivation and the cipher of the version
+const KDF_ALGORITHM: &str = "argon2id";
+const CIPHER_ALGORITHM: &str = "xchacha20-poly1305";
+
+/// The lengths of the salt, the nonce and the key
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+
+/// The largest costs accepted when restoring: 2 GiB of memory, and iterations and lanes well
+/// above the recommended ones, so that a crafted header cannot stall the restore
+const MAX_MEMORY: u32 = 1 << 21;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid backup: {0}")]
+    Json(String),
+    #[error("Unsupported backup {0}")]
+    Unsupported(String),
+    #[error("Invalid key derivation parameters: {0}")]
+    Params(String),
+    #[error("Wrong password, or the backup was modified")]
+    Decrypt,
+    #[error("Invalid keyring {index}: {reason}")]
+    Keyring { index: usize, reason: String },
+}
+
+/// The kind of the secret of a keyring
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyringKind {
+    /// A BIP-39 mnemonic, deriving accounts along an HD path
+    Mnemonic,
+    /// A hex private key
+    PrivateKey,
+}
+
+/// The label of an account of a keyring
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Label {
+    /// The index of the account along the HD path, 0 for a private key
+    pub index: u32,
+    pub name: String,
+}
+
+/// A keyring of a backup
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Keyring {
+    pub kind: KeyringKind,
+    /// The mnemonic, or the hex private key
+    pub secret: String,
+    /// The BIP-39 passphrase of a mnemonic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase: Option<String>,
+    /// The HD path of the accounts of a mnemonic, without their index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hd_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number_of_accounts: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<Label>,
+    /// Where the keyring comes from, e.g. `MetaMask Chrome`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl Drop for Keyring {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+        self.passphrase.zeroize();
+    }
+}
+
+impl Keyring {
+    /// A keyring of a mnemonic, deriving accounts along an HD path.
+    pub fn mnemonic(phrase: &str, hd_path: Option<&str>, number_of_accounts: Option<u32>) -> Self {
+        Keyring {
+            kind: KeyringKind::Mnemonic,
+            secret: phrase.to_string(),
+            passphrase: None,
+            hd_path: hd_path.map(str::to_string),
+            number_of_accounts,
+            labels: vec![],
+            source: None,
+        }
+    }
+
+    /// A keyring of a hex private key.
+    pub fn private_key(key: &str) -> Self {
+        Keyring {
+            kind: KeyringKind::PrivateKey,
+            secret: key.to_string(),
+            passphrase: None,
+            hd_path: None,
+            number_of_accounts: None,
+            labels: vec![],
+            source: None,
+        }
+    }
+
+    /// Checks the secret of the keyring, and that a private key has no derivation metadata.
+    pub fn validate(&self) -> Result<(), String> {
+        match self.kind {
+            KeyringKind::Mnemonic => {
+                if !Language::ALL.iter().any(|language| validate(&self.secret, *language).is_ok()) {
+                    return Err("invalid mnemonic".to_string());
+                }
+            }
+            KeyringKind::PrivateKey => {
+                Account::from_private_key(&self.secret).map_err(|e| e.to_string())?;
+                let derived = self.passphrase.is_some() ||
+                    self.hd_path.is_some() ||
+                    self.number_of_accounts.is_some();
+                if derived || self.labels.iter().any(|label| label.index != 0) {
+                    return Err("a private key has a single account, without a path".to_string());
+                }
+            }
+        }
+        let mut indices: Vec<u32> = self.labels.iter().map(|label| label.index).collect();
+        indices.sort_unstable();
+        if indices.windows(2).any(|w| w[0] == w[1]) {
+            return Err("duplicate account label".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The Argon2id costs of the key derivation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// The memory cost, in KiB
+    pub memory: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// The second recommended option of RFC 9106, for memory constrained environments.
+    fn default() -> Self {
+        KdfParams { memory: 64 * 1024, iterations: 3, parallelism: 4 }
+    }
+}
+
+/// The key derivation of a backup
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Kdf {
+    pub algorithm: String,
+    pub memory: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    /// The base64 salt
+    pub salt: String,
+}
+
+/// The cipher of a backup
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Cipher {
+    pub algorithm: String,
+    /// The base64 nonce
+    pub nonce: String,
+}
+
+/// The metadata of a backup, in clear but authenticated
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Header {
+    pub format: String,
+    pub version: u32,
+    /// The creation time, in seconds since the Unix epoch
+    pub created_at: u64,
+    pub kdf: Kdf,
+    pub cipher: Cipher,
+    /// The number of keyrings
+    pub keyrings: u32,
+}
+
+/// An encrypted backup
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Backup {
+    pub header: Header,
+    /// The JSON of the header as written in the backup, which the cipher authenticates
+    raw_header: String,
+    /// The base64 ciphertext of the keyrings
+    pub ciphertext: String,
+}
+
+/// The JSON document of a backup, keeping the header as written
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Document<'a> {
+    #[serde(borrow)]
+    header: &'a RawValue,
+    ciphertext: &'a str,
+}
+
+/// The encrypted contents of a backup
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Contents {
+    keyrings: Vec<Keyring>,
+}
+
+fn decode(field: &str, value: &str, len: usize) -> Result<Vec<u8>, Error> {
+    match general_purpose::STANDARD.decode(value) {
+        Ok(bytes) if bytes.len() == len => Ok(bytes),
+        _ => Err(Error::Json(format!("invalid {}", field))),
+    }
+}
+
+/// Derives the key of a backup from the password.
+fn derive_key(
+    password: &str,
+    salt: &[u8],
+    params: KdfParams,
+) -> Result<Zeroizing<[u8; KEY_LEN]>, Error> {
+    let params = Params::new(params.memory, params.iterations, params.parallelism, Some(KEY_LEN))
+        .map_err(|e| Error::Params(e.to_string()))?;
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, key.as_mut())
+        .map_err(|e| Error::Params(e.to_string()))?;
+    Ok(key)
+}
+
+impl Backup {
+    /// Parses a backup, checking its format, version and algorithms.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let document: Document =
+            serde_json::from_str(json).map_err(|e| Error::Json(e.to_string()))?;
+        let raw_header = document.header.get().to_string();
+        let header: Header =
+            serde_json::from_str(&raw_header).map_err(|e| Error::Json(e.to_string()))?;
+        if header.format != FORMAT {
+            return Err(Error::Unsupported(format!("format {}", header.format)));
+        }
+        if header.version != VERSION {
+            return Err(Error::Unsupported(format!("version {}", header.version)));
+        }
+        if header.kdf.algorithm != KDF_ALGORITHM {
+            return Err(Error::Unsupported(format!("key derivation {}", header.kdf.algorithm)));
+        }
+        if header.cipher.algorithm != CIPHER_ALGORITHM {
+            return Err(Error::Unsupported(format!("cipher {}", header.cipher.algorithm)));
+        }
+        decode("salt", &header.kdf.salt, SALT_LEN)?;
+        decode("nonce", &header.cipher.nonce, NONCE_LEN)?;
+        Ok(Backup { header, raw_header, ciphertext: document.ciphertext.to_string() })
+    }
+
+    /// Serializes the backup, with the header as it was written.
+    pub fn to_json(&self) -> String {
+        let header = RawValue::from_string(self.raw_header.clone()).expect("a header is JSON");
+        let document = Document { header: &header, ciphertext: &self.ciphertext };
+        serde_json::to_string_pretty(&document).expect("a backup serializes")
+    }
+
+    /// Encrypts keyrings under a password, with the salt and nonce given.
+    fn seal(
+        keyrings: &[Keyring],
+        password: &str,
+        params: KdfParams,
+        salt: [u8; SALT_LEN],
+        nonce: [u8; NONCE_LEN],
+        created_at: u64,
+    ) -> Result<Self, Error> {
+        for (index, keyring) in keyrings.iter().enumerate() {
+            keyring.validate().map_err(|reason| Error::Keyring { index, reason })?;
+        }
+        let header = Header {
+            format: FORMAT.to_string(),
+            version: VERSION,
+            created_at,
+            kdf: Kdf {
+                algorithm: KDF_ALGORITHM.to_string(),
+                memory: params.memory,
+                iterations: params.iterations,
+                parallelism: params.parallelism,
+                salt: general_purpose::STANDARD.encode(salt),
+            },
+            cipher: Cipher {
+                algorithm: CIPHER_ALGORITHM.to_string(),
+                nonce: general_purpose::STANDARD.encode(nonce),
+            },
+            keyrings: keyrings.len() as u32,
+        };
+
+        let key = derive_key(password, &salt, params)?;
+        let contents = Zeroizing::new(
+            serde_json::to_vec(&Contents { keyrings: keyrings.to_vec() })
+                .expect("keyrings serialize"),
+        );
+        let raw_header = serde_json::to_string(&header).expect("a header serializes");
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(key.as_slice()))
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload { msg: &contents, aad: raw_header.as_bytes() },
+            )
+            .map_err(|_| Error::Decrypt)?;
+        Ok(Backup { header, raw_header, ciphertext: general_purpose::STANDARD.encode(ciphertext) })
+    }
+
+    /// Decrypts the keyrings of the backup.
+    pub fn open(&self, password: &str) -> Result<Vec<Keyring>, Error> {
+        let header = &self.header;
+        let params = KdfParams {
+            memory: header.kdf.memory,
+            iterations: header.kdf.iterations,
+            parallelism: header.kdf.parallelism,
+        };
+        if params.memory > MAX_MEMORY {
+            return Err(Error::Params(format!("memory cost {} KiB is too large", params.memory)));
+        }
+        if params.iterations > MAX_ITERATIONS {
+            return Err(Error::Params(format!("{} iterations are too many", params.iterations)));
+        }
+        if params.parallelism > MAX_PARALLELISM {
+            return Err(Error::Params(format!("{} lanes are too many", params.parallelism)));
+        }
+        let salt = decode("salt", &header.kdf.salt, SALT_LEN)?;
+        let nonce = decode("nonce", &header.cipher.nonce, NONCE_LEN)?;
+        let ciphertext = general_purpose::STANDARD
+            .decode(&self.ciphertext)
+            .map_err(|_| Error::Json("invalid ciphertext".to_string()))?;
+
+        let key = derive_key(password, &salt, params)?;
+        let aad = self.raw_header.as_bytes();
+        let contents = XChaCha20Poly1305::new(Key::from_slice(key.as_slice()))
+            .decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad })
+            .map(Zeroizing::new)
+            .map_err(|_| Error::Decrypt)?;
+
+        // The contents are authenticated, but written by another version maybe
+        let contents: Contents =
+            serde_json::from_slice(&contents).map_err(|e| Error::Json(e.to_string()))?;
+        if contents.keyrings.len() != header.keyrings as usize {
+            return Err(Error::Json("the header does not match the keyrings".to_string()));
+        }
+        for (index, keyring) in contents.keyrings.iter().enumerate() {
+            keyring.validate().map_err(|reason| Error::Keyring { index, reason })?;
+        }
+        Ok(contents.keyrings)
+    }
+}
+
+/// Creates the backup of keyrings under a password.
+pub fn create(keyrings: &[Keyring], password: &str, params: KdfParams) -> Result<String, Error> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    thread_rng().fill_bytes(&mut salt);
+    thread_rng().fill_bytes(&mut nonce);
+    let created_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    Ok(Backup::seal(keyrings, password, params, salt, nonce, created_at)?.to_json())
+}
+
+/// Returns the metadata of a backup, without decrypting it.
+pub fn inspect(json: &str) -> Result<Header, Error> {
+    Ok(Backup::from_json(json)?.header)
+}
+
+/// Decrypts the keyrings of a backup.
+pub fn restore(json: &str, password: &str) -> Result<Vec<Keyring>, Error> {
+    Backup::from_json(json)?.open(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_json::Value;
+
+    const PHRASE: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    const KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    /// The cheapest costs, so that the tests are fast
+    const PARAMS: KdfParams = KdfParams { memory: 64, iterations: 1, parallelism: 1 };
+
+    fn keyrings() -> Vec<Keyring> {
+        let mut hd = Keyring::mnemonic(PHRASE, Some("m/44'/60'/0'/0"), Some(3));
+        hd.passphrase = Some("TREZOR".to_string());
+        hd.labels = vec![
+            Label { index: 0, name: "Main".to_string() },
+            Label { index: 2, name: "Cold \"storage\" 🧊".to_string() },
+        ];
+        hd.source = Some("MetaMask Chrome".to_string());
+        let mut key = Keyring::private_key(KEY);
+        key.labels = vec![Label { index: 0, name: "Imported".to_string() }];
+        vec![hd, key, Keyring::mnemonic(PHRASE, None, None)]
+    }
+
+    fn seal(keyrings: &[Keyring], password: &str) -> Result<Backup> {
+        Ok(Backup::seal(keyrings, password, PARAMS, [1; SALT_LEN], [2; NONCE_LEN], 1_700_000_000)?)
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        for keyrings in [keyrings(), vec![]] {
+            for password in ["", "correct horse battery staple", "パスワード"] {
+                let json = seal(&keyrings, password)?.to_json();
+                assert_eq!(restore(&json, password)?, keyrings);
+                assert_eq!(Backup::from_json(&json)?.to_json(), json);
+            }
+        }
+
+        let json = create(&keyrings(), "password", PARAMS)?;
+        assert_eq!(restore(&json, "password")?, keyrings());
+        Ok(())
+    }
+
+    #[test]
+    fn test_deterministic() -> Result<()> {
+        // The same salt and nonce encrypt the same way, and the others differently
+        let json = seal(&keyrings(), "password")?.to_json();
+        assert_eq!(seal(&keyrings(), "password")?.to_json(), json);
+        assert_ne!(
+            create(&keyrings(), "password", PARAMS)?,
+            create(&keyrings(), "password", PARAMS)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspect() -> Result<()> {
+        let json = seal(&keyrings(), "password")?.to_json();
+        let header = inspect(&json)?;
+        assert_eq!(header.keyrings, 3);
+        assert_eq!(header.created_at, 1_700_000_000);
+        assert_eq!(header.kdf.memory, 64);
+        assert_eq!(header.kdf.salt, general_purpose::STANDARD.encode([1; SALT_LEN]));
+
+        // The header holds no secret
+        assert!(!json.contains("abandon") && !json.contains("Main") && !json.contains(KEY));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_password() -> Result<()> {
+        let json = seal(&keyrings(), "password")?.to_json();
+        assert_eq!(restore(&json, "Password"), Err(Error::Decrypt));
+        assert_eq!(restore(&json, ""), Err(Error::Decrypt));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tampered() -> Result<()> {
+        let json = seal(&keyrings(), "password")?.to_json();
+        let tamper = |f: &dyn Fn(&mut Value)| {
+            let mut value: Value = serde_json::from_str(&json).unwrap();
+            f(&mut value);
+            restore(&value.to_string(), "password")
+        };
+
+        // The header is authenticated
+        assert_eq!(tamper(&|v| v["header"]["createdAt"] = 1.into()), Err(Error::Decrypt));
+        assert_eq!(tamper(&|v| v["header"]["keyrings"] = 2.into()), Err(Error::Decrypt));
+        assert_eq!(tamper(&|v| v["header"]["kdf"]["iterations"] = 2.into()), Err(Error::Decrypt));
+        let nonce = general_purpose::STANDARD.encode([3; NONCE_LEN]);
+        assert_eq!(
+            tamper(&|v| v["header"]["cipher"]["nonce"] = nonce.clone().into()),
+            Err(Error::Decrypt)
+        );
+
+        // So is the ciphertext
+        let mut ciphertext = general_purpose::STANDARD
+            .decode(serde_json::from_str::<Value>(&json)?["ciphertext"].as_str().unwrap())?;
+        ciphertext[0] ^= 1;
+        let ciphertext = general_purpose::STANDARD.encode(ciphertext);
+        assert_eq!(tamper(&|v| v["ciphertext"] = ciphertext.clone().into()), Err(Error::Decrypt));
+        assert!(tamper(&|v| v["ciphertext"] = "AAAA".into()).is_err());
+
+        // Unknown fields and versions are rejected
+        assert!(matches!(tamper(&|v| v["header"]["extra"] = 1.into()), Err(Error::Json(_))));
+        assert!(matches!(tamper(&|v| v["extra"] = 1.into()), Err(Error::Json(_))));
+        assert!(matches!(
+            tamper(&|v| v["header"]["version"] = 2.into()),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            tamper(&|v| v["header"]["format"] = "x".into()),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            tamper(&|v| v["header"]["kdf"]["algorithm"] = "scrypt".into()),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            tamper(&|v| v["header"]["kdf"]["salt"] = "AAAA".into()),
+            Err(Error::Json(_))
+        ));
+        assert!(matches!(
+            tamper(&|v| v["header"]["kdf"]["memory"] = (MAX_MEMORY + 1).into()),
+            Err(Error::Params(_))
+        ));
+        assert!(matches!(
+            tamper(&|v| v["header"]["kdf"]["iterations"] = u32::MAX.into()),
+            Err(Error::Params(_))
+        ));
+        assert!(matches!(
+            tamper(&|v| v["header"]["kdf"]["parallelism"] = (MAX_PARALLELISM + 1).into()),
+            Err(Error::Params(_))
+        ));
+        assert!(matches!(restore("{}", "password"), Err(Error::Json(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_header() -> Result<()> {
+        // The header is authenticated as written, so that it cannot be reformatted either
+        let json = seal(&keyrings(), "password")?.to_json();
+        assert_eq!(restore(&json, "password")?, keyrings());
+        let reformatted = json.replacen(r#""keyrings":3"#, r#""keyrings": 3"#, 1);
+        assert_ne!(reformatted, json);
+        assert_eq!(inspect(&reformatted)?, inspect(&json)?);
+        assert_eq!(restore(&reformatted, "password"), Err(Error::Decrypt));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_keyrings() {
+        let invalid = |keyring: Keyring| {
+            matches!(
+                seal(&[keyring], "password").unwrap_err().downcast_ref(),
+                Some(Error::Keyring { index: 0, .. })
+            )
+        };
+        assert!(invalid(Keyring::mnemonic("abandon abandon", None, None)));
+        assert!(invalid(Keyring::private_key("0x1234")));
+        let mut key = Keyring::private_key(KEY);
+        key.hd_path = Some("m/44'/60'/0'/0".to_string());
+        assert!(invalid(key));
+        let mut hd = Keyring::mnemonic(PHRASE, None, None);
+        hd.labels = vec![
+            Label { index: 1, name: "a".to_string() },
+            Label { index: 1, name: "b".to_string() },
+        ];
+        assert!(invalid(hd));
+        let params = KdfParams { memory: 1, iterations: 1, parallelism: 1 };
+        assert!(matches!(create(&keyrings(), "password", params), Err(Error::Params(_))));
+    }
+}
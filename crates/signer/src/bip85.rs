@@ -0,0 +1,273 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// BIP-85 deterministic entropy of child mnemonics, keys and passwords from a root key.
+///
+/// From:
+/// https://github.com/bitcoin/bips/blob/master/bip-0085.mediawiki
+use crate::mnemonic::{entropy_len, from_entropy, Language};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use coins_bip32::{
+    ecdsa::SigningKey,
+    enc::{encode_b58_check, MainnetEncoder, XKeyEncoder},
+    primitives::{ChainCode, Hint, KeyFingerprint, XKeyInfo},
+    xkeys::XPriv,
+};
+use ethers_core::utils::hex;
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+use std::fmt;
+
+/// The purpose of the BIP-85 derivation paths
+pub const PURPOSE: u32 = 83696968;
+
+/// The HMAC key of the entropy of a derived key
+const HMAC_KEY: &[u8] = b"bip-entropy-from-k";
+
+/// The characters of the base85 passwords, from RFC 1924
+const BASE85: &[u8; 85] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!#$%&()*+-;<=>?@^_`{|}~";
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Mnemonic(#[from] crate::mnemonic::Error),
+    #[error("Language {0} has no BIP-85 code")]
+    Language(Language),
+    #[error("Invalid {name} length {len}, expected {min} to {max}")]
+    Length { name: &'static str, len: usize, min: usize, max: usize },
+    #[error("Invalid extended private key: {0}")]
+    Xprv(String),
+    #[error("Failed to derive the key: {0}")]
+    Derive(String),
+}
+
+/// What the entropy of a derivation is used for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Application {
+    /// A BIP-39 mnemonic
+    Bip39 { language: Language, words: usize },
+    /// A compressed private key in the wallet import format
+    Wif,
+    /// A BIP-32 root key
+    Xprv,
+    /// Hex entropy of 16 to 64 bytes
+    Hex { bytes: usize },
+    /// A base64 password of 20 to 86 characters
+    Base64 { length: usize },
+    /// A base85 password of 10 to 80 characters
+    Base85 { length: usize },
+}
+
+impl fmt::Display for Application {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Application::Bip39 { language, words } => write!(f, "{} words {}", words, language),
+            Application::Wif => write!(f, "WIF"),
+            Application::Xprv => write!(f, "XPRV"),
+            Application::Hex { bytes } => write!(f, "{} bytes hex", bytes),
+            Application::Base64 { length } => write!(f, "{} characters base64 password", length),
+            Application::Base85 { length } => write!(f, "{} characters base85 password", length),
+        }
+    }
+}
+
+/// Returns the BIP-85 code of a language.
+fn language_code(language: Language) -> Result<u32, Error> {
+    match language {
+        Language::English => Ok(0),
+        Language::Japanese => Ok(1),
+        Language::Korean => Ok(2),
+        Language::Spanish => Ok(3),
+        Language::ChineseSimplified => Ok(4),
+        Language::ChineseTraditional => Ok(5),
+        Language::French => Ok(6),
+        Language::Italian => Ok(7),
+        Language::Czech => Ok(8),
+        Language::Portuguese => Err(Error::Language(language)),
+    }
+}
+
+/// Checks that a length is within `min..=max`.
+fn check_length(name: &'static str, len: usize, min: usize, max: usize) -> Result<usize, Error> {
+    match (min..=max).contains(&len) {
+        true => Ok(len),
+        false => Err(Error::Length { name, len, min, max }),
+    }
+}
+
+impl Application {
+    /// Returns the derivation path of the child at `index`.
+    pub fn path(&self, index: u32) -> Result<String, Error> {
+        let path = match *self {
+            Application::Bip39 { language, words } => {
+                entropy_len(words)?;
+                format!("39'/{}'/{}'/{}'", language_code(language)?, words, index)
+            }
+            Application::Wif => format!("2'/{}'", index),
+            Application::Xprv => format!("32'/{}'", index),
+            Application::Hex { bytes } => {
+                format!("128169'/{}'/{}'", check_length("hex", bytes, 16, 64)?, index)
+            }
+            Application::Base64 { length } => {
+                format!("707764'/{}'/{}'", check_length("password", length, 20, 86)?, index)
+            }
+            Application::Base85 { length } => {
+                format!("707785'/{}'/{}'", check_length("password", length, 10, 80)?, index)
+            }
+        };
+        Ok(format!("m/{}'/{}", PURPOSE, path))
+    }
+}
+
+/// The length of a base58 extended key
+const XKEY_LEN: usize = 111;
+
+/// Parses a base58 extended private key.
+pub fn parse_xprv(xprv: &str) -> Result<XPriv, Error> {
+    // The decoder of coins-bip32 panics on keys shorter than their checksum
+    if xprv.len() != XKEY_LEN {
+        return Err(Error::Xprv(format!("expected {} characters", XKEY_LEN)));
+    }
+    MainnetEncoder::xpriv_from_base58(xprv).map_err(|e| Error::Xprv(e.to_string()))
+}
+
+/// Returns the 64 bytes of entropy of the key at `path`.
+pub fn entropy(root: &XPriv, path: &str) -> Result<[u8; 64], Error> {
+    let key = root.derive_path(path).map_err(|e| Error::Derive(e.to_string()))?;
+    let signing_key: &SigningKey = key.as_ref();
+    let mut mac = Hmac::<Sha512>::new_from_slice(HMAC_KEY).expect("HMAC takes keys of any size");
+    mac.update(&signing_key.to_bytes());
+    Ok(mac.finalize().into_bytes().into())
+}
+
+/// Encodes the entropy as a base85 string.
+fn base85(entropy: &[u8]) -> String {
+    entropy
+        .chunks(4)
+        .flat_map(|chunk| {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            let mut n = u32::from_be_bytes(word);
+            let mut chars = [0u8; 5];
+            for c in chars.iter_mut().rev() {
+                *c = BASE85[(n % 85) as usize];
+                n /= 85;
+            }
+            chars
+        })
+        .map(char::from)
+        .collect()
+}
+
+/// Derives the child of an application at `index`.
+pub fn derive(root: &XPriv, application: Application, index: u32) -> Result<String, Error> {
+    let entropy = entropy(root, &application.path(index)?)?;
+    match application {
+        Application::Bip39 { language, words } => {
+            Ok(from_entropy(&entropy[..entropy_len(words)?], language)?)
+        }
+        Application::Wif => {
+            let key = [&[0x80], &entropy[..32], &[0x01]].concat();
+            Ok(encode_b58_check(&key))
+        }
+        Application::Xprv => {
+            let key =
+                SigningKey::from_slice(&entropy[32..]).map_err(|e| Error::Derive(e.to_string()))?;
+            let info = XKeyInfo {
+                depth: 0,
+                parent: KeyFingerprint([0; 4]),
+                index: 0,
+                chain_code: ChainCode(entropy[..32].try_into().expect("32 bytes")),
+                hint: Hint::Legacy,
+            };
+            MainnetEncoder::xpriv_to_base58(&XPriv::new(key, info))
+                .map_err(|e| Error::Derive(e.to_string()))
+        }
+        Application::Hex { bytes } => Ok(hex::encode(&entropy[..bytes])),
+        Application::Base64 { length } => Ok(STANDARD.encode(entropy)[..length].to_string()),
+        Application::Base85 { length } => Ok(base85(&entropy)[..length].to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    // From: https://github.com/bitcoin/bips/blob/master/bip-0085.mediawiki#test-vectors
+    const ROOT: &str = "xprv9s21ZrQH143K2LBWUUQRFXhucrQqBpKdRRxNVq2zBqsx8HVqFk2uYo8kmbaLLHRdqtQpUm98uKfu3vca1LqdGhUtyoFnCNkfmXRyPXLjbKb";
+
+    #[test]
+    fn test_entropy_vectors() -> Result<()> {
+        let root = parse_xprv(ROOT)?;
+        let vectors = [
+            (
+                "m/83696968'/0'/0'",
+                "efecfbccffea313214232d29e71563d941229afb4338c21f9517c41aaa0d16f00b83d2a09ef747e7a64e8e2bd5a14869e693da66ce94ac2da570ab7ee48618f7",
+            ),
+            (
+                "m/83696968'/0'/1'",
+                "70c6e3e8ebee8dc4c0dbba66076819bb8c09672527c4277ca8729532ad711872218f826919f6b67218adde99018a6df9095ab2b58d803b5b93ec9802085a690e",
+            ),
+        ];
+        for (path, expected) in vectors {
+            assert_eq!(hex::encode(entropy(&root, path)?), expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_application_vectors() -> Result<()> {
+        let root = parse_xprv(ROOT)?;
+        let english = |words| Application::Bip39 { language: Language::English, words };
+        let vectors = [
+            (english(12), "girl mad pet galaxy egg matter matrix prison refuse sense ordinary nose"),
+            (
+                english(18),
+                "near account window bike charge season chef number sketch tomorrow excuse sniff \
+                 circle vital hockey outdoor supply token",
+            ),
+            (
+                english(24),
+                "puppy ocean match cereal symbol another shed magic wrap hammer bulb intact gadget \
+                 divorce twin tonight reason outdoor destroy simple truth cigar social volcano",
+            ),
+            (Application::Wif, "Kzyv4uF39d4Jrw2W7UryTHwZr1zQVNk4dAFyqE6BuMrMh1Za7uhp"),
+            (
+                Application::Xprv,
+                "xprv9s21ZrQH143K2srSbCSg4m4kLvPMzcWydgmKEnMmoZUurYuBuYG46c6P71UGXMzmriLzCCBvKQWBUv3vPB3m1SATMhp3uEjXHJ42jFg7myX",
+            ),
+            (
+                Application::Hex { bytes: 64 },
+                "492db4698cf3b73a5a24998aa3e9d7fa96275d85724a91e71aa2d645442f878555d078fd1f1f67e368976f04137b1f7a0d19232136ca50c44614af72b5582a5c",
+            ),
+            (Application::Base64 { length: 21 }, "dKLoepugzdVJvdL56ogNV"),
+            (Application::Base85 { length: 12 }, "_s`{TW89)i4`"),
+        ];
+        for (application, expected) in vectors {
+            assert_eq!(derive(&root, application, 0)?, expected, "{}", application);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_applications() -> Result<()> {
+        let root = parse_xprv(ROOT)?;
+        assert_eq!(
+            derive(&root, Application::Hex { bytes: 8 }, 0),
+            Err(Error::Length { name: "hex", len: 8, min: 16, max: 64 })
+        );
+        assert_eq!(
+            derive(&root, Application::Bip39 { language: Language::Portuguese, words: 12 }, 0),
+            Err(Error::Language(Language::Portuguese))
+        );
+        assert!(matches!(
+            derive(&root, Application::Bip39 { language: Language::English, words: 13 }, 0),
+            Err(Error::Mnemonic(_))
+        ));
+        assert!(parse_xprv("xprv").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,572 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Decoding of transaction calldata into a typed tree, for human-readable previews.
+///
+/// Methods are looked up by selector in a [Registry], built from JSON ABIs. The [builtin]
+/// registry knows the common token standards, Permit2, the Uniswap routers and multicall.
+/// Calldata that does not match a known method falls back to [Decoded::Raw].
+use ethers_core::{
+    abi::{decode as abi_decode, param_type::Reader, ParamType, RawAbi, Token},
+    types::{Address, I256, U256},
+    utils::{hex, id, to_checksum},
+};
+use lazy_static::lazy_static;
+use serde::{Serialize, Serializer};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+};
+
+/// How deep calls nested in `bytes` arguments, like multicalls, are decoded
+const MAX_DEPTH: usize = 4;
+
+/// The JSON ABIs of the builtin registry, in priority order for shared selectors.
+///
+/// ERC-20 and ERC-721 share `approve` and `transferFrom`, whose arguments are labelled with
+/// the names of both, e.g. `amount/tokenId`.
+const BUILTIN_ABIS: [&str; 6] = [
+    include_str!("../abi/erc20.json"),
+    include_str!("../abi/erc721.json"),
+    include_str!("../abi/erc1155.json"),
+    include_str!("../abi/permit2.json"),
+    include_str!("../abi/uniswap.json"),
+    include_str!("../abi/multicall.json"),
+];
+
+lazy_static! {
+    static ref BUILTIN: Registry = {
+        let mut registry = Registry::default();
+        for abi in BUILTIN_ABIS {
+            registry.add_abi_json(abi).expect("builtin ABIs are valid");
+        }
+        registry
+    };
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid JSON ABI: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid type `{0}`")]
+    Type(String),
+}
+
+/// A named parameter of a method, with the field names of its tuples
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub kind: ParamType,
+    /// The fields of the tuple, or of the tuples of the array
+    pub components: Vec<Param>,
+}
+
+/// A method of a registry
+#[derive(Clone, Debug, PartialEq)]
+pub struct Method {
+    pub name: String,
+    pub inputs: Vec<Param>,
+}
+
+impl Method {
+    /// Returns the canonical signature, e.g. `transfer(address,uint256)`.
+    pub fn signature(&self) -> String {
+        let types: Vec<String> = self.inputs.iter().map(|p| p.kind.to_string()).collect();
+        format!("{}({})", self.name, types.join(","))
+    }
+
+    pub fn selector(&self) -> [u8; 4] {
+        id(self.signature())
+    }
+}
+
+/// Methods indexed by selector
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    methods: HashMap<[u8; 4], Method>,
+}
+
+/// Returns the registry of the common methods.
+pub fn builtin() -> &'static Registry {
+    &BUILTIN
+}
+
+/// Decodes calldata with the [builtin] registry.
+pub fn decode(data: &[u8]) -> Decoded {
+    builtin().decode(data)
+}
+
+/// Builds the parameter of an ABI component, resolving the types of its tuples.
+fn param(component: &ethers_core::abi::Component) -> Result<Param, Error> {
+    let components = component.components.iter().map(param).collect::<Result<Vec<_>, Error>>()?;
+    let kind = match component.type_field.strip_prefix("tuple") {
+        Some(suffix) => {
+            let types: Vec<String> = components.iter().map(|c| c.kind.to_string()).collect();
+            format!("({}){}", types.join(","), suffix)
+        }
+        None => component.type_field.clone(),
+    };
+    Ok(Param {
+        name: component.name.clone(),
+        kind: Reader::read(&kind).map_err(|_| Error::Type(component.type_field.clone()))?,
+        components,
+    })
+}
+
+/// Appends the names of other parameters of the same types, e.g. `amount/tokenId`.
+fn merge_names(params: &mut [Param], others: &[Param]) {
+    for (param, other) in params.iter_mut().zip(others) {
+        let known = param.name.split('/').any(|name| name == other.name);
+        if !known && !other.name.is_empty() {
+            param.name = match param.name.is_empty() {
+                true => other.name.clone(),
+                false => format!("{}/{}", param.name, other.name),
+            };
+        }
+        merge_names(&mut param.components, &other.components);
+    }
+}
+
+impl Registry {
+    /// Builds a registry from the functions of a JSON ABI.
+    pub fn from_abi_json(json: &str) -> Result<Self, Error> {
+        let mut registry = Registry::default();
+        registry.add_abi_json(json)?;
+        Ok(registry)
+    }
+
+    /// Adds the functions of a JSON ABI, either an array or an object with an `abi` key.
+    ///
+    /// Selectors already known are kept, with the parameter names of both methods when they
+    /// share the signature, returns the number of methods added.
+    pub fn add_abi_json(&mut self, json: &str) -> Result<usize, Error> {
+        let abi: RawAbi = serde_json::from_str(json)?;
+        let mut added = 0;
+        for item in abi.into_iter().filter(|item| item.type_field == "function") {
+            let method = Method {
+                name: item.name.unwrap_or_default(),
+                inputs: item.inputs.iter().map(param).collect::<Result<_, _>>()?,
+            };
+            match self.methods.entry(method.selector()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(method);
+                    added += 1;
+                }
+                Entry::Occupied(mut entry) => {
+                    if entry.get().signature() == method.signature() {
+                        merge_names(&mut entry.get_mut().inputs, &method.inputs);
+                    }
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    pub fn get(&self, selector: &[u8; 4]) -> Option<&Method> {
+        self.methods.get(selector)
+    }
+
+    pub fn len(&self) -> usize {
+        self.methods.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.methods.is_empty()
+    }
+
+    /// Decodes calldata, falling back to [Decoded::Raw] for unknown or malformed calls.
+    pub fn decode(&self, data: &[u8]) -> Decoded {
+        if data.is_empty() {
+            return Decoded::Empty;
+        }
+        match self.decode_call(data, 0) {
+            Some(call) => Decoded::Call(call),
+            None => Decoded::Raw {
+                selector: data.get(..4).map(|s| s.try_into().unwrap()),
+                data: data.to_vec(),
+            },
+        }
+    }
+
+    fn decode_call(&self, data: &[u8], depth: usize) -> Option<Call> {
+        let selector: [u8; 4] = data.get(..4)?.try_into().ok()?;
+        let method = self.get(&selector)?;
+        let types: Vec<ParamType> = method.inputs.iter().map(|p| p.kind.clone()).collect();
+        let tokens = abi_decode(&types, &data[4..]).ok()?;
+
+        Some(Call {
+            selector,
+            name: method.name.clone(),
+            signature: method.signature(),
+            arguments: self.arguments(&method.inputs, tokens, depth),
+        })
+    }
+
+    fn arguments(&self, params: &[Param], tokens: Vec<Token>, depth: usize) -> Vec<Argument> {
+        params
+            .iter()
+            .zip(tokens)
+            .map(|(param, token)| Argument {
+                name: param.name.clone(),
+                kind: param.kind.to_string(),
+                value: self.value(param, token, depth),
+            })
+            .collect()
+    }
+
+    fn value(&self, param: &Param, token: Token, depth: usize) -> Value {
+        match token {
+            Token::Address(a) => Value::Address(a),
+            Token::Uint(u) => Value::Uint(u),
+            Token::Int(i) => Value::Int(I256::from_raw(i)),
+            Token::Bool(b) => Value::Bool(b),
+            Token::String(s) => Value::String(s),
+            Token::FixedBytes(b) => Value::FixedBytes(b),
+            Token::Bytes(b) => match depth < MAX_DEPTH {
+                true => match self.decode_call(&b, depth + 1) {
+                    Some(call) => Value::Call(Box::new(call)),
+                    None => Value::Bytes(b),
+                },
+                false => Value::Bytes(b),
+            },
+            Token::Array(tokens) | Token::FixedArray(tokens) => {
+                // The components describe the tuples of the array
+                let item = Param {
+                    name: String::new(),
+                    kind: match &param.kind {
+                        ParamType::Array(kind) | ParamType::FixedArray(kind, _) => *kind.clone(),
+                        kind => kind.clone(),
+                    },
+                    components: param.components.clone(),
+                };
+                Value::Array(tokens.into_iter().map(|t| self.value(&item, t, depth)).collect())
+            }
+            Token::Tuple(tokens) => {
+                // Fields of a JSON ABI tuple without components are named by position
+                let fields = match (&param.kind, param.components.is_empty()) {
+                    (ParamType::Tuple(kinds), true) => kinds
+                        .iter()
+                        .enumerate()
+                        .map(|(i, kind)| Param {
+                            name: i.to_string(),
+                            kind: kind.clone(),
+                            components: vec![],
+                        })
+                        .collect(),
+                    _ => param.components.clone(),
+                };
+                Value::Tuple(self.arguments(&fields, tokens, depth))
+            }
+        }
+    }
+}
+
+/// A decoded value
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum Value {
+    #[serde(serialize_with = "serialize_address")]
+    Address(Address),
+    #[serde(serialize_with = "serialize_display")]
+    Uint(U256),
+    #[serde(serialize_with = "serialize_display")]
+    Int(I256),
+    Bool(bool),
+    String(String),
+    #[serde(serialize_with = "serialize_hex")]
+    Bytes(Vec<u8>),
+    #[serde(serialize_with = "serialize_hex")]
+    FixedBytes(Vec<u8>),
+    Array(Vec<Value>),
+    Tuple(Vec<Argument>),
+    /// `bytes` holding a call to a known method, e.g. the calls of a multicall
+    Call(Box<Call>),
+}
+
+/// A decoded argument of a call
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Argument {
+    pub name: String,
+    /// The canonical type, e.g. `uint256` or `(address,bytes)[]`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub value: Value,
+}
+
+/// A call to a known method
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Call {
+    #[serde(serialize_with = "serialize_hex")]
+    pub selector: [u8; 4],
+    pub name: String,
+    pub signature: String,
+    pub arguments: Vec<Argument>,
+}
+
+/// Decoded calldata
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Decoded {
+    /// No calldata, a plain transfer of ether
+    Empty,
+    /// A call to a known method
+    Call(Call),
+    /// Calldata that could not be decoded
+    Raw {
+        #[serde(serialize_with = "serialize_option_hex")]
+        selector: Option<[u8; 4]>,
+        #[serde(serialize_with = "serialize_hex")]
+        data: Vec<u8>,
+    },
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn serialize_address<S: Serializer>(address: &Address, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&to_checksum(address, None))
+}
+
+fn serialize_display<T: fmt::Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn serialize_hex<T: AsRef<[u8]>, S: Serializer>(
+    bytes: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&to_hex(bytes.as_ref()))
+}
+
+fn serialize_option_hex<S: Serializer>(
+    bytes: &Option<[u8; 4]>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match bytes {
+        Some(bytes) => serialize_hex(bytes, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Address(a) => write!(f, "{}", to_checksum(a, None)),
+            Value::Uint(u) => write!(f, "{}", u),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::Bytes(b) | Value::FixedBytes(b) => write!(f, "{}", to_hex(b)),
+            Value::Array(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", values.join(", "))
+            }
+            Value::Tuple(fields) => {
+                let fields: Vec<String> =
+                    fields.iter().map(|a| format!("{}: {}", a.name, a.value)).collect();
+                write!(f, "({})", fields.join(", "))
+            }
+            Value::Call(call) => write!(f, "{}", call),
+        }
+    }
+}
+
+impl fmt::Display for Call {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arguments: Vec<String> =
+            self.arguments.iter().map(|a| format!("{}: {}", a.name, a.value)).collect();
+        write!(f, "{}({})", self.name, arguments.join(", "))
+    }
+}
+
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decoded::Empty => write!(f, "(no calldata)"),
+            Decoded::Call(call) => write!(f, "{}", call),
+            Decoded::Raw { data, .. } => write!(f, "{}", to_hex(data)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use ethers_core::abi::encode;
+
+    fn calldata(selector: [u8; 4], tokens: &[Token]) -> Vec<u8> {
+        [selector.to_vec(), encode(tokens)].concat()
+    }
+
+    fn address(s: &str) -> Address {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_builtin_selectors() {
+        let selectors = [
+            ("transfer", "a9059cbb"),
+            ("approve", "095ea7b3"),
+            ("transferFrom", "23b872dd"),
+            ("safeTransferFrom", "42842e0e"),
+            ("safeTransferFrom", "b88d4fde"),
+            ("setApprovalForAll", "a22cb465"),
+            ("safeTransferFrom", "f242432a"),
+            ("safeBatchTransferFrom", "2eb2c2d6"),
+            ("approve", "87517c45"),
+            ("permit", "2b67b570"),
+            ("permit", "2a2d80d1"),
+            ("transferFrom", "36c78516"),
+            ("swapExactTokensForTokens", "38ed1739"),
+            ("swapExactETHForTokens", "7ff36ab5"),
+            ("exactInputSingle", "414bf389"),
+            ("exactInputSingle", "04e45aaf"),
+            ("exactInput", "c04b8d59"),
+            ("exactInput", "b858183f"),
+            ("execute", "3593564c"),
+            ("execute", "24856bc3"),
+            ("multicall", "ac9650d8"),
+            ("multicall", "5ae401dc"),
+            ("aggregate", "252dba42"),
+            ("aggregate3", "82ad56cb"),
+        ];
+        for (name, selector) in selectors {
+            let selector: [u8; 4] = hex::decode(selector).unwrap().try_into().unwrap();
+            assert_eq!(builtin().get(&selector).map(|m| m.name.as_str()), Some(name));
+        }
+    }
+
+    #[test]
+    fn test_decode_erc20_transfer() {
+        let to = address("0x70997970C51812dc3A010C7d01b50e0d17dc79C8");
+        let data = calldata(
+            [0xa9, 0x05, 0x9c, 0xbb],
+            &[Token::Address(to), Token::Uint(U256::from(1_000_000u64))],
+        );
+
+        let Decoded::Call(call) = decode(&data) else { panic!("not decoded") };
+        assert_eq!(call.signature, "transfer(address,uint256)");
+        assert_eq!(
+            call.arguments,
+            vec![
+                Argument {
+                    name: "to".to_string(),
+                    kind: "address".to_string(),
+                    value: Value::Address(to)
+                },
+                Argument {
+                    name: "amount".to_string(),
+                    kind: "uint256".to_string(),
+                    value: Value::Uint(U256::from(1_000_000u64))
+                },
+            ]
+        );
+        assert_eq!(
+            call.to_string(),
+            "transfer(to: 0x70997970C51812dc3A010C7d01b50e0d17dc79C8, amount: 1000000)"
+        );
+    }
+
+    #[test]
+    fn test_decode_shared_selector() {
+        // ERC-20 and ERC-721 share the selector of transferFrom, the amount is a token ID
+        let from = address("0x70997970C51812dc3A010C7d01b50e0d17dc79C8");
+        let to = address("0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC");
+        let data = calldata(
+            [0x23, 0xb8, 0x72, 0xdd],
+            &[Token::Address(from), Token::Address(to), Token::Uint(42.into())],
+        );
+        assert_eq!(
+            decode(&data).to_string(),
+            "transferFrom(from: 0x70997970C51812dc3A010C7d01b50e0d17dc79C8, \
+             to: 0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC, amount/tokenId: 42)"
+        );
+
+        let data = calldata([0x09, 0x5e, 0xa7, 0xb3], &[Token::Address(to), Token::Uint(7.into())]);
+        assert_eq!(
+            decode(&data).to_string(),
+            "approve(spender/to: 0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC, amount/tokenId: 7)"
+        );
+    }
+
+    #[test]
+    fn test_decode_nested_multicall() -> Result<()> {
+        let token = address("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+        let recipient = address("0x70997970C51812dc3A010C7d01b50e0d17dc79C8");
+        let swap = calldata(
+            [0x04, 0xe4, 0x5a, 0xaf],
+            &[Token::Tuple(vec![
+                Token::Address(token),
+                Token::Address(recipient),
+                Token::Uint(500.into()),
+                Token::Address(recipient),
+                Token::Uint(U256::exp10(18)),
+                Token::Uint(0.into()),
+                Token::Uint(0.into()),
+            ])],
+        );
+        let data = calldata(
+            [0x5a, 0xe4, 0x01, 0xdc],
+            &[Token::Uint(1_700_000_000u64.into()), Token::Array(vec![Token::Bytes(swap)])],
+        );
+
+        let Decoded::Call(call) = decode(&data) else { panic!("not decoded") };
+        assert_eq!(call.signature, "multicall(uint256,bytes[])");
+        let Value::Array(calls) = &call.arguments[1].value else { panic!("not an array") };
+        let Value::Call(swap) = &calls[0] else { panic!("not a call") };
+        assert_eq!(swap.name, "exactInputSingle");
+        let Value::Tuple(fields) = &swap.arguments[0].value else { panic!("not a tuple") };
+        assert_eq!(fields[2].name, "fee");
+        assert_eq!(fields[2].value, Value::Uint(500.into()));
+
+        let json = serde_json::to_value(&decode(&data))?;
+        assert_eq!(json["kind"], "call");
+        assert_eq!(json["arguments"][0]["value"]["value"], "1700000000");
+        assert_eq!(json["arguments"][1]["value"]["value"][0]["value"]["name"], "exactInputSingle");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_raw() {
+        assert_eq!(decode(&[]), Decoded::Empty);
+        assert_eq!(decode(&[0xde, 0xad]), Decoded::Raw { selector: None, data: vec![0xde, 0xad] });
+        // Known selector with truncated arguments
+        assert_eq!(
+            decode(&[0xa9, 0x05, 0x9c, 0xbb, 0x01]),
+            Decoded::Raw {
+                selector: Some([0xa9, 0x05, 0x9c, 0xbb]),
+                data: vec![0xa9, 0x05, 0x9c, 0xbb, 0x01]
+            }
+        );
+        assert_eq!(decode(&[0xde, 0xad]).to_string(), "0xdead");
+    }
+
+    #[test]
+    fn test_registry_from_abi_json() -> Result<()> {
+        let registry = Registry::from_abi_json(
+            r#"{"abi": [
+                {"type": "event", "name": "Deposit", "inputs": []},
+                {"type": "function", "name": "deposit", "inputs": [], "outputs": []},
+                {"type": "function", "name": "setValue", "inputs": [
+                    {"name": "value", "type": "int8"},
+                    {"name": "label", "type": "string"}
+                ], "outputs": []}
+            ]}"#,
+        )?;
+        assert_eq!(registry.len(), 2);
+
+        let data = calldata(
+            id("setValue(int8,string)"),
+            &[Token::Int(I256::from(-1).into_raw()), Token::String("hi".to_string())],
+        );
+        assert_eq!(registry.decode(&data).to_string(), "setValue(value: -1, label: \"hi\")");
+        assert!(matches!(Registry::from_abi_json("[{]"), Err(Error::Json(_))));
+        Ok(())
+    }
+}
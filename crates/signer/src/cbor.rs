@@ -0,0 +1,322 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// The subset of CBOR used by Uniform Resources: integers, byte and text strings, arrays,
+/// maps, tags and the simple values, of definite lengths.
+///
+/// Values are encoded deterministically, with the shortest heads and the map entries in their
+/// given order.
+///
+/// From:
+/// https://www.rfc-editor.org/rfc/rfc8949
+/// https://github.com/BlockchainCommons/Research/blob/master/papers/bcr-2020-005-ur.md
+use std::str;
+
+/// The deepest nesting of arrays, maps and tags that is decoded
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("Unexpected end of the CBOR data")]
+    Eof,
+    #[error("{0} trailing bytes after the CBOR value")]
+    Trailing(usize),
+    #[error("Unsupported CBOR item {0:#04x}")]
+    Unsupported(u8),
+    #[error("The CBOR value is nested too deeply")]
+    Depth,
+    #[error("Invalid UTF-8 in a CBOR text string")]
+    Utf8,
+    #[error("Expected {0}")]
+    Type(&'static str),
+}
+
+/// A CBOR data item
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Unsigned(u64),
+    /// The negative integer `-1 - n`
+    Negative(u64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Tag(u64, Box<Value>),
+    Bool(bool),
+    Null,
+}
+
+/// Writes the head of an item, its major type and its argument in the fewest bytes.
+fn write_head(output: &mut Vec<u8>, major: u8, argument: u64) {
+    let major = major << 5;
+    match argument {
+        0..=23 => output.push(major | argument as u8),
+        24..=0xff => output.extend([major | 24, argument as u8]),
+        0x100..=0xffff => {
+            output.push(major | 25);
+            output.extend((argument as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            output.push(major | 26);
+            output.extend((argument as u32).to_be_bytes());
+        }
+        _ => {
+            output.push(major | 27);
+            output.extend(argument.to_be_bytes());
+        }
+    }
+}
+
+impl Value {
+    /// Encodes the value.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut output = vec![];
+        self.write(&mut output);
+        output
+    }
+
+    fn write(&self, output: &mut Vec<u8>) {
+        match self {
+            Value::Unsigned(n) => write_head(output, 0, *n),
+            Value::Negative(n) => write_head(output, 1, *n),
+            Value::Bytes(bytes) => {
+                write_head(output, 2, bytes.len() as u64);
+                output.extend(bytes);
+            }
+            Value::Text(text) => {
+                write_head(output, 3, text.len() as u64);
+                output.extend(text.as_bytes());
+            }
+            Value::Array(items) => {
+                write_head(output, 4, items.len() as u64);
+                items.iter().for_each(|item| item.write(output));
+            }
+            Value::Map(entries) => {
+                write_head(output, 5, entries.len() as u64);
+                for (key, value) in entries {
+                    key.write(output);
+                    value.write(output);
+                }
+            }
+            Value::Tag(tag, value) => {
+                write_head(output, 6, *tag);
+                value.write(output);
+            }
+            Value::Bool(b) => output.push(if *b { 0xf5 } else { 0xf4 }),
+            Value::Null => output.push(0xf6),
+        }
+    }
+
+    /// Decodes a value that spans all of `data`.
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader { data, position: 0 };
+        let value = reader.value(0)?;
+        match data.len() - reader.position {
+            0 => Ok(value),
+            trailing => Err(Error::Trailing(trailing)),
+        }
+    }
+
+    pub fn as_u64(&self) -> Result<u64, Error> {
+        match self {
+            Value::Unsigned(n) => Ok(*n),
+            _ => Err(Error::Type("an unsigned integer")),
+        }
+    }
+
+    pub fn as_bytes(&self) -> Result<&[u8], Error> {
+        match self {
+            Value::Bytes(bytes) => Ok(bytes),
+            _ => Err(Error::Type("a byte string")),
+        }
+    }
+
+    pub fn as_text(&self) -> Result<&str, Error> {
+        match self {
+            Value::Text(text) => Ok(text),
+            _ => Err(Error::Type("a text string")),
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, Error> {
+        match self {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(Error::Type("a boolean")),
+        }
+    }
+
+    pub fn as_array(&self) -> Result<&[Value], Error> {
+        match self {
+            Value::Array(items) => Ok(items),
+            _ => Err(Error::Type("an array")),
+        }
+    }
+
+    pub fn as_map(&self) -> Result<&[(Value, Value)], Error> {
+        match self {
+            Value::Map(entries) => Ok(entries),
+            _ => Err(Error::Type("a map")),
+        }
+    }
+
+    /// Returns the content of a tagged value, which must carry `tag`.
+    pub fn untag(&self, tag: u64) -> Result<&Value, Error> {
+        match self {
+            Value::Tag(t, value) if *t == tag => Ok(value),
+            _ => Err(Error::Type("a tagged value")),
+        }
+    }
+
+    /// Returns the value of an integer key of a map.
+    pub fn get(&self, key: u64) -> Result<Option<&Value>, Error> {
+        Ok(self.as_map()?.iter().find(|(k, _)| *k == Value::Unsigned(key)).map(|(_, value)| value))
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self.position.checked_add(len).filter(|end| *end <= self.data.len());
+        let bytes = &self.data[self.position..end.ok_or(Error::Eof)?];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    /// Reads the head of an item, its major type, its additional information and its argument.
+    fn head(&mut self) -> Result<(u8, u8, u64), Error> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let argument = match info {
+            0..=23 => info as u64,
+            24..=27 => {
+                let bytes = self.take(1 << (info - 24))?;
+                bytes.iter().fold(0, |n, b| n << 8 | *b as u64)
+            }
+            _ => return Err(Error::Unsupported(initial)),
+        };
+        Ok((major, info, argument))
+    }
+
+    fn len(&self, argument: u64) -> Result<usize, Error> {
+        // Every item takes at least a byte, so no length can exceed what is left
+        match usize::try_from(argument) {
+            Ok(len) if len <= self.data.len() - self.position => Ok(len),
+            _ => Err(Error::Eof),
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::Depth);
+        }
+        let (major, info, argument) = self.head()?;
+        Ok(match major {
+            0 => Value::Unsigned(argument),
+            1 => Value::Negative(argument),
+            2 => Value::Bytes(self.take(self.len(argument)?)?.to_vec()),
+            3 => {
+                let bytes = self.take(self.len(argument)?)?;
+                Value::Text(str::from_utf8(bytes).map_err(|_| Error::Utf8)?.to_string())
+            }
+            4 => Value::Array(
+                (0..self.len(argument)?)
+                    .map(|_| self.value(depth + 1))
+                    .collect::<Result<_, _>>()?,
+            ),
+            5 => Value::Map(
+                (0..self.len(argument)?)
+                    .map(|_| Ok((self.value(depth + 1)?, self.value(depth + 1)?)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            6 => Value::Tag(argument, Box::new(self.value(depth + 1)?)),
+            _ => match info {
+                20 => Value::Bool(false),
+                21 => Value::Bool(true),
+                22 => Value::Null,
+                _ => return Err(Error::Unsupported(0xe0 | info)),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use ethers_core::utils::hex;
+
+    #[test]
+    fn test_rfc_vectors() -> Result<()> {
+        // From: https://www.rfc-editor.org/rfc/rfc8949#appendix-A
+        let vectors = [
+            (Value::Unsigned(0), "00"),
+            (Value::Unsigned(23), "17"),
+            (Value::Unsigned(24), "1818"),
+            (Value::Unsigned(1000), "1903e8"),
+            (Value::Unsigned(1000000), "1a000f4240"),
+            (Value::Unsigned(1000000000000), "1b000000e8d4a51000"),
+            (Value::Negative(999), "3903e7"),
+            (Value::Bytes(vec![1, 2, 3, 4]), "4401020304"),
+            (Value::Text("\u{6c34}".to_string()), "63e6b0b4"),
+            (Value::Bool(true), "f5"),
+            (Value::Null, "f6"),
+            (Value::Tag(1, Box::new(Value::Unsigned(1363896240))), "c11a514b67b0"),
+            (
+                Value::Array(vec![
+                    Value::Unsigned(1),
+                    Value::Array(vec![Value::Unsigned(2), Value::Unsigned(3)]),
+                ]),
+                "8201820203",
+            ),
+            (
+                Value::Map(vec![
+                    (Value::Text("a".to_string()), Value::Unsigned(1)),
+                    (
+                        Value::Text("b".to_string()),
+                        Value::Array(vec![Value::Unsigned(2), Value::Unsigned(3)]),
+                    ),
+                ]),
+                "a26161016162820203",
+            ),
+        ];
+        for (value, encoded) in vectors {
+            assert_eq!(hex::encode(value.encode()), encoded);
+            assert_eq!(Value::decode(&hex::decode(encoded)?)?, value);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(Value::decode(&[]), Err(Error::Eof));
+        assert_eq!(Value::decode(&[0x00, 0x00]), Err(Error::Trailing(1)));
+        // A byte string longer than the data, an indefinite array and a float
+        assert_eq!(
+            Value::decode(&[0x5b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            Err(Error::Eof)
+        );
+        assert_eq!(Value::decode(&[0x9f, 0xff]), Err(Error::Unsupported(0x9f)));
+        assert_eq!(Value::decode(&[0xf9, 0x3c, 0x00]), Err(Error::Unsupported(0xf9)));
+        assert_eq!(Value::decode(&[0x62, 0xff, 0xfe]), Err(Error::Utf8));
+        assert_eq!(Value::decode(&[0x81; 64]), Err(Error::Depth));
+    }
+
+    #[test]
+    fn test_accessors() -> Result<()> {
+        let value = Value::Map(vec![
+            (Value::Unsigned(1), Value::Bytes(vec![0xab])),
+            (Value::Unsigned(2), Value::Tag(37, Box::new(Value::Text("x".to_string())))),
+        ]);
+        assert_eq!(value.get(1)?.unwrap().as_bytes()?, [0xab]);
+        assert_eq!(value.get(2)?.unwrap().untag(37)?.as_text()?, "x");
+        assert!(value.get(2)?.unwrap().untag(304).is_err());
+        assert_eq!(value.get(3)?, None);
+        assert!(Value::Null.get(1).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,650 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// The ERC-4527 registry of URs for air-gapped signing: the `crypto-hdkey` of the accounts
+/// that a wallet connects to, its `eth-sign-request`s, and the `eth-signature`s sent back.
+///
+/// Top level values are untagged, as their UR type names them, and nested ones carry their
+/// tags.
+///
+/// From:
+/// https://eips.ethereum.org/EIPS/eip-4527
+/// https://github.com/BlockchainCommons/Research/blob/master/papers/bcr-2020-007-hdkey.md
+/// https://github.com/KeystoneHQ/keystone-sdk-base/tree/master/packages/ur-registry-eth
+use crate::{
+    cbor::{self, Value},
+    ur,
+};
+use coins_bip32::{
+    ecdsa::{SigningKey, VerifyingKey},
+    primitives::XKeyInfo,
+    xkeys::XPriv,
+};
+use ethers_core::{
+    types::{
+        transaction::eip712::{Eip712, TypedData},
+        Address, H256,
+    },
+    utils::{hash_message, keccak256, secret_key_to_address, to_checksum},
+};
+use ethers_signers::LocalWallet;
+use std::fmt;
+
+/// The UR types
+pub const CRYPTO_HDKEY: &str = "crypto-hdkey";
+pub const ETH_SIGN_REQUEST: &str = "eth-sign-request";
+pub const ETH_SIGNATURE: &str = "eth-signature";
+
+/// The CBOR tags of the nested values
+const TAG_UUID: u64 = 37;
+const TAG_KEYPATH: u64 = 304;
+const TAG_COIN_INFO: u64 = 305;
+
+/// The SLIP-44 coin type of Ether
+const COIN_TYPE_ETH: u64 = 60;
+
+/// The bit of hardened path components
+const HARDENED: u32 = 1 << 31;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Ur(#[from] ur::Error),
+    #[error(transparent)]
+    Cbor(#[from] cbor::Error),
+    #[error("Missing {0}")]
+    Missing(&'static str),
+    #[error("Invalid {0}")]
+    Invalid(String),
+    #[error("The request is for the key of fingerprint {expected:08x}, not {actual:08x}")]
+    Fingerprint { expected: u32, actual: u32 },
+    #[error("The request is for the address {expected}, not {actual}")]
+    Address { expected: String, actual: String },
+    #[error("Failed to derive the key: {0}")]
+    Derive(String),
+    #[error("Failed to sign: {0}")]
+    Sign(String),
+}
+
+/// A component of a key path, a wildcard without an index
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathComponent {
+    pub index: Option<u32>,
+    pub hardened: bool,
+}
+
+/// A `crypto-keypath`, the derivation of a key from the master key of a fingerprint
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyPath {
+    pub components: Vec<PathComponent>,
+    pub source_fingerprint: Option<u32>,
+    pub depth: Option<u8>,
+}
+
+impl KeyPath {
+    /// Parses a path such as `m/44'/60'/0'/0/0`, or `0/*` for the children of a key.
+    pub fn parse(path: &str, source_fingerprint: Option<u32>) -> Result<Self, Error> {
+        let path = path.trim();
+        let path = path.strip_prefix("m/").or_else(|| path.strip_prefix("M/")).unwrap_or(path);
+        let components = match path {
+            "" | "m" | "M" => vec![],
+            _ => path
+                .split('/')
+                .map(|component| {
+                    let (index, hardened) = match component.strip_suffix(['\'', 'h', 'H']) {
+                        Some(index) => (index, true),
+                        None => (component, false),
+                    };
+                    let index = match index {
+                        "*" => None,
+                        _ => Some(
+                            index
+                                .parse::<u32>()
+                                .ok()
+                                .filter(|index| *index < HARDENED)
+                                .ok_or_else(|| Error::Invalid(format!("path {}", path)))?,
+                        ),
+                    };
+                    Ok(PathComponent { index, hardened })
+                })
+                .collect::<Result<_, Error>>()?,
+        };
+        Ok(KeyPath { components, source_fingerprint, depth: None })
+    }
+
+    fn to_cbor(&self) -> Value {
+        let mut components = vec![];
+        for component in &self.components {
+            components.push(match component.index {
+                Some(index) => Value::Unsigned(index as u64),
+                None => Value::Array(vec![]),
+            });
+            components.push(Value::Bool(component.hardened));
+        }
+        let mut map = vec![(Value::Unsigned(1), Value::Array(components))];
+        if let Some(fingerprint) = self.source_fingerprint.filter(|f| *f != 0) {
+            map.push((Value::Unsigned(2), Value::Unsigned(fingerprint as u64)));
+        }
+        if let Some(depth) = self.depth {
+            map.push((Value::Unsigned(3), Value::Unsigned(depth as u64)));
+        }
+        Value::Tag(TAG_KEYPATH, Box::new(Value::Map(map)))
+    }
+
+    fn from_cbor(value: &Value) -> Result<Self, Error> {
+        let value = value.untag(TAG_KEYPATH)?;
+        let items = value.get(1)?.ok_or(Error::Missing("path components"))?.as_array()?;
+        let components = items
+            .chunks(2)
+            .map(|pair| match pair {
+                [index, hardened] => {
+                    let index = match index {
+                        Value::Array(wildcard) if wildcard.is_empty() => None,
+                        _ => match u32_of(index, "path component")? {
+                            index if index < HARDENED => Some(index),
+                            _ => return Err(Error::Invalid("path component".into())),
+                        },
+                    };
+                    Ok(PathComponent { index, hardened: hardened.as_bool()? })
+                }
+                _ => Err(Error::Invalid("path components".into())),
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(KeyPath {
+            components,
+            source_fingerprint: optional(value, 2, |v| u32_of(v, "source fingerprint"))?,
+            depth: optional(value, 3, |v| {
+                u8::try_from(v.as_u64()?).map_err(|_| Error::Invalid("depth".into()))
+            })?,
+        })
+    }
+}
+
+impl fmt::Display for KeyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "m")?;
+        for component in &self.components {
+            match component.index {
+                Some(index) => write!(f, "/{}", index)?,
+                None => write!(f, "/*")?,
+            }
+            if component.hardened {
+                write!(f, "'")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the value of an optional key of a map, converted.
+fn optional<T>(
+    map: &Value,
+    key: u64,
+    convert: impl FnOnce(&Value) -> Result<T, Error>,
+) -> Result<Option<T>, Error> {
+    map.get(key)?.map(convert).transpose()
+}
+
+fn u32_of(value: &Value, name: &str) -> Result<u32, Error> {
+    u32::try_from(value.as_u64()?).map_err(|_| Error::Invalid(name.into()))
+}
+
+fn uuid_of(value: &Value) -> Result<[u8; 16], Error> {
+    value.untag(TAG_UUID)?.as_bytes()?.try_into().map_err(|_| Error::Invalid("request id".into()))
+}
+
+fn text_of(value: &Value) -> Result<String, Error> {
+    Ok(value.as_text()?.to_string())
+}
+
+/// A `crypto-hdkey`, the extended public key of the accounts of a wallet
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CryptoHdKey {
+    /// The compressed public key
+    pub key_data: Vec<u8>,
+    pub chain_code: Option<[u8; 32]>,
+    /// The derivation of the key, and of the accounts from it
+    pub origin: Option<KeyPath>,
+    pub children: Option<KeyPath>,
+    pub parent_fingerprint: Option<u32>,
+    pub name: Option<String>,
+    pub note: Option<String>,
+}
+
+impl CryptoHdKey {
+    /// Returns the extended public key at `path` of a master key, e.g. `m/44'/60'/0'` with its
+    /// accounts at `0/*`.
+    pub fn from_master(master: &XPriv, path: &str, children: &str) -> Result<Self, Error> {
+        let key = master.derive_path(path).map_err(|e| Error::Derive(e.to_string()))?;
+        let info: &XKeyInfo = key.as_ref();
+        let xpub = key.verify_key();
+        let public_key: &VerifyingKey = xpub.as_ref();
+        let mut origin = KeyPath::parse(path, Some(fingerprint(master)))?;
+        origin.depth = Some(info.depth);
+        Ok(CryptoHdKey {
+            key_data: public_key.to_sec1_bytes().to_vec(),
+            chain_code: Some(info.chain_code.0),
+            origin: Some(origin),
+            children: Some(KeyPath::parse(children, None)?),
+            parent_fingerprint: Some(u32::from_be_bytes(info.parent.0)).filter(|f| *f != 0),
+            name: None,
+            note: None,
+        })
+    }
+
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut map = vec![(Value::Unsigned(3), Value::Bytes(self.key_data.clone()))];
+        if let Some(chain_code) = self.chain_code {
+            map.push((Value::Unsigned(4), Value::Bytes(chain_code.to_vec())));
+        }
+        let coin_info = Value::Map(vec![(Value::Unsigned(1), Value::Unsigned(COIN_TYPE_ETH))]);
+        map.push((Value::Unsigned(5), Value::Tag(TAG_COIN_INFO, Box::new(coin_info))));
+        if let Some(origin) = &self.origin {
+            map.push((Value::Unsigned(6), origin.to_cbor()));
+        }
+        if let Some(children) = &self.children {
+            map.push((Value::Unsigned(7), children.to_cbor()));
+        }
+        if let Some(fingerprint) = self.parent_fingerprint {
+            map.push((Value::Unsigned(8), Value::Unsigned(fingerprint as u64)));
+        }
+        if let Some(name) = &self.name {
+            map.push((Value::Unsigned(9), Value::Text(name.clone())));
+        }
+        if let Some(note) = &self.note {
+            map.push((Value::Unsigned(10), Value::Text(note.clone())));
+        }
+        Value::Map(map).encode()
+    }
+
+    pub fn from_cbor(data: &[u8]) -> Result<Self, Error> {
+        let value = Value::decode(data)?;
+        if optional(&value, 2, |v| Ok(v.as_bool()?))? == Some(true) {
+            return Err(Error::Invalid("hdkey, a private key".into()));
+        }
+        let key_data = value.get(3)?.ok_or(Error::Missing("key data"))?.as_bytes()?.to_vec();
+        if key_data.len() != 33 {
+            return Err(Error::Invalid("key data".into()));
+        }
+        Ok(CryptoHdKey {
+            key_data,
+            chain_code: optional(&value, 4, |v| {
+                v.as_bytes()?.try_into().map_err(|_| Error::Invalid("chain code".into()))
+            })?,
+            origin: optional(&value, 6, KeyPath::from_cbor)?,
+            children: optional(&value, 7, KeyPath::from_cbor)?,
+            parent_fingerprint: optional(&value, 8, |v| u32_of(v, "parent fingerprint"))?,
+            name: optional(&value, 9, text_of)?,
+            note: optional(&value, 10, text_of)?,
+        })
+    }
+
+    pub fn to_ur(&self) -> String {
+        ur::encode(CRYPTO_HDKEY, &self.to_cbor())
+    }
+}
+
+/// What an `eth-sign-request` signs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataType {
+    /// The RLP of a legacy transaction
+    Transaction = 1,
+    /// The JSON of EIP-712 typed data
+    TypedData = 2,
+    /// A message, signed with the EIP-191 prefix
+    PersonalMessage = 3,
+    /// An EIP-2718 typed transaction, its type and payload
+    TypedTransaction = 4,
+}
+
+impl TryFrom<u64> for DataType {
+    type Error = Error;
+
+    fn try_from(value: u64) -> Result<Self, Error> {
+        match value {
+            1 => Ok(DataType::Transaction),
+            2 => Ok(DataType::TypedData),
+            3 => Ok(DataType::PersonalMessage),
+            4 => Ok(DataType::TypedTransaction),
+            _ => Err(Error::Invalid(format!("data type {}", value))),
+        }
+    }
+}
+
+/// An `eth-sign-request`, the data that a wallet asks the air-gapped signer to sign
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EthSignRequest {
+    pub request_id: Option<[u8; 16]>,
+    pub sign_data: Vec<u8>,
+    pub data_type: DataType,
+    pub chain_id: Option<u64>,
+    pub derivation_path: KeyPath,
+    pub address: Option<Address>,
+    /// The wallet that asks
+    pub origin: Option<String>,
+}
+
+impl EthSignRequest {
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut map = vec![];
+        if let Some(id) = self.request_id {
+            let id = Value::Tag(TAG_UUID, Box::new(Value::Bytes(id.to_vec())));
+            map.push((Value::Unsigned(1), id));
+        }
+        map.push((Value::Unsigned(2), Value::Bytes(self.sign_data.clone())));
+        map.push((Value::Unsigned(3), Value::Unsigned(self.data_type as u64)));
+        if let Some(chain_id) = self.chain_id {
+            map.push((Value::Unsigned(4), Value::Unsigned(chain_id)));
+        }
+        map.push((Value::Unsigned(5), self.derivation_path.to_cbor()));
+        if let Some(address) = self.address {
+            map.push((Value::Unsigned(6), Value::Bytes(address.as_bytes().to_vec())));
+        }
+        if let Some(origin) = &self.origin {
+            map.push((Value::Unsigned(7), Value::Text(origin.clone())));
+        }
+        Value::Map(map).encode()
+    }
+
+    pub fn from_cbor(data: &[u8]) -> Result<Self, Error> {
+        let value = Value::decode(data)?;
+        let data_type = value.get(3)?.ok_or(Error::Missing("data type"))?.as_u64()?;
+        Ok(EthSignRequest {
+            request_id: optional(&value, 1, uuid_of)?,
+            sign_data: value.get(2)?.ok_or(Error::Missing("sign data"))?.as_bytes()?.to_vec(),
+            data_type: DataType::try_from(data_type)?,
+            chain_id: optional(&value, 4, |v| Ok(v.as_u64()?))?,
+            derivation_path: KeyPath::from_cbor(
+                value.get(5)?.ok_or(Error::Missing("derivation path"))?,
+            )?,
+            address: optional(&value, 6, |v| match v.as_bytes()? {
+                bytes if bytes.len() == 20 => Ok(Address::from_slice(bytes)),
+                _ => Err(Error::Invalid("address".into())),
+            })?,
+            origin: optional(&value, 7, text_of)?,
+        })
+    }
+
+    pub fn to_ur(&self) -> String {
+        ur::encode(ETH_SIGN_REQUEST, &self.to_cbor())
+    }
+
+    /// Decodes the type and payload of a complete `eth-sign-request` UR.
+    pub fn from_ur(ur_type: &str, payload: &[u8]) -> Result<Self, Error> {
+        match ur_type {
+            ETH_SIGN_REQUEST => Self::from_cbor(payload),
+            _ => {
+                Err(ur::Error::Type { expected: ETH_SIGN_REQUEST.into(), actual: ur_type.into() })?
+            }
+        }
+    }
+
+    /// Returns the hash that is signed.
+    pub fn signing_hash(&self) -> Result<H256, Error> {
+        Ok(match self.data_type {
+            DataType::Transaction | DataType::TypedTransaction => H256(keccak256(&self.sign_data)),
+            DataType::PersonalMessage => hash_message(&self.sign_data),
+            DataType::TypedData => {
+                let typed_data: TypedData = serde_json::from_slice(&self.sign_data)
+                    .map_err(|e| Error::Invalid(format!("typed data: {}", e)))?;
+                H256(typed_data.encode_eip712().map_err(|e| Error::Invalid(e.to_string()))?)
+            }
+        })
+    }
+
+    /// Signs the request with the key of its path, derived from the master key that its
+    /// fingerprint names.
+    ///
+    /// The recovery id of a legacy transaction is encoded as in EIP-155, of a typed one as its
+    /// parity, and of a message as 27 or 28.
+    pub fn sign(&self, master: &XPriv) -> Result<EthSignature, Error> {
+        let actual = fingerprint(master);
+        match self.derivation_path.source_fingerprint {
+            Some(expected) if expected != actual && expected != 0 => {
+                return Err(Error::Fingerprint { expected, actual })
+            }
+            _ => {}
+        }
+        if self.derivation_path.components.iter().any(|c| c.index.is_none()) {
+            return Err(Error::Invalid(format!("derivation path {}", self.derivation_path)));
+        }
+        let key = master
+            .derive_path(self.derivation_path.to_string().as_str())
+            .map_err(|e| Error::Derive(e.to_string()))?;
+        let key: &SigningKey = key.as_ref();
+        let address = secret_key_to_address(key);
+        match self.address {
+            Some(expected) if expected != address => {
+                return Err(Error::Address {
+                    expected: to_checksum(&expected, None),
+                    actual: to_checksum(&address, None),
+                })
+            }
+            _ => {}
+        }
+
+        let signature = LocalWallet::from(key.clone())
+            .sign_hash(self.signing_hash()?)
+            .map_err(|e| Error::Sign(e.to_string()))?;
+        let parity = signature.v - 27;
+        let v = match (self.data_type, self.chain_id) {
+            (DataType::Transaction, Some(chain_id)) => parity + 35 + 2 * chain_id,
+            (DataType::TypedTransaction, _) => parity,
+            _ => signature.v,
+        };
+        let mut bytes = [0; 64];
+        signature.r.to_big_endian(&mut bytes[..32]);
+        signature.s.to_big_endian(&mut bytes[32..]);
+        let mut signature = bytes.to_vec();
+        let v = v.to_be_bytes();
+        let leading = v.iter().take(7).take_while(|b| **b == 0).count();
+        signature.extend(&v[leading..]);
+        Ok(EthSignature { request_id: self.request_id, signature, origin: None })
+    }
+}
+
+/// An `eth-signature`, the answer of the air-gapped signer: r, s and v
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EthSignature {
+    pub request_id: Option<[u8; 16]>,
+    pub signature: Vec<u8>,
+    /// The signer that answers
+    pub origin: Option<String>,
+}
+
+impl EthSignature {
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut map = vec![];
+        if let Some(id) = self.request_id {
+            let id = Value::Tag(TAG_UUID, Box::new(Value::Bytes(id.to_vec())));
+            map.push((Value::Unsigned(1), id));
+        }
+        map.push((Value::Unsigned(2), Value::Bytes(self.signature.clone())));
+        if let Some(origin) = &self.origin {
+            map.push((Value::Unsigned(3), Value::Text(origin.clone())));
+        }
+        Value::Map(map).encode()
+    }
+
+    pub fn from_cbor(data: &[u8]) -> Result<Self, Error> {
+        let value = Value::decode(data)?;
+        let signature = value.get(2)?.ok_or(Error::Missing("signature"))?.as_bytes()?.to_vec();
+        if signature.len() < 65 {
+            return Err(Error::Invalid("signature".into()));
+        }
+        Ok(EthSignature {
+            request_id: optional(&value, 1, uuid_of)?,
+            signature,
+            origin: optional(&value, 3, text_of)?,
+        })
+    }
+
+    pub fn to_ur(&self) -> String {
+        ur::encode(ETH_SIGNATURE, &self.to_cbor())
+    }
+}
+
+/// Returns the fingerprint of a master key, as a number.
+pub fn fingerprint(master: &XPriv) -> u32 {
+    u32::from_be_bytes(master.fingerprint().0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mnemonic::{master_key, Language};
+    use anyhow::Result;
+    use ethers_core::{
+        types::{RecoveryMessage, Signature},
+        utils::hex,
+    };
+
+    const PHRASE: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    const ADDRESS: &str = "0x9858EfFD232B4033E47d90003D41EC34EcaEda94";
+
+    fn request(data_type: DataType, sign_data: &[u8], chain_id: Option<u64>) -> EthSignRequest {
+        EthSignRequest {
+            request_id: Some([7; 16]),
+            sign_data: sign_data.to_vec(),
+            data_type,
+            chain_id,
+            derivation_path: KeyPath::parse("m/44'/60'/0'/0/0", Some(0x73c5da0a)).unwrap(),
+            address: Some(ADDRESS.parse().unwrap()),
+            origin: Some("MetaMask".to_string()),
+        }
+    }
+
+    /// Recovers the signer of a signature, with its recovery id in its last bytes.
+    fn recover(request: &EthSignRequest, signature: &EthSignature) -> Result<Address> {
+        let bytes = &signature.signature;
+        let v = bytes[64..].iter().fold(0, |v, b| v << 8 | *b as u64);
+        let signature = Signature {
+            r: bytes[..32].into(),
+            s: bytes[32..64].into(),
+            v: if v < 2 { v + 27 } else { v },
+        };
+        Ok(signature.recover(RecoveryMessage::Hash(request.signing_hash()?))?)
+    }
+
+    #[test]
+    fn test_key_path() -> Result<()> {
+        let path = KeyPath::parse("m/44'/1'/1'/0/1", Some(0xe9181cf3))?;
+        assert_eq!(path.to_string(), "m/44'/1'/1'/0/1");
+        assert_eq!(
+            hex::encode(path.to_cbor().encode()),
+            "d90130a2018a182cf501f501f500f401f4021ae9181cf3"
+        );
+        assert_eq!(KeyPath::from_cbor(&path.to_cbor())?, path);
+
+        let children = KeyPath::parse("0/*", None)?;
+        assert_eq!(children.components[1], PathComponent { index: None, hardened: false });
+        assert_eq!(KeyPath::from_cbor(&children.to_cbor())?, children);
+        assert_eq!(KeyPath::parse("m/44h/60H", None)?.to_string(), "m/44'/60'");
+        assert!(KeyPath::parse("m/44'/x", None).is_err());
+        assert!(KeyPath::parse("m/2147483648", None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_crypto_hdkey() -> Result<()> {
+        let master = master_key(PHRASE, Language::English, None)?;
+        assert_eq!(fingerprint(&master), 0x73c5da0a);
+
+        let mut hdkey = CryptoHdKey::from_master(&master, "m/44'/60'/0'", "0/*")?;
+        hdkey.name = Some("wallet-rs".to_string());
+        hdkey.note = Some("account.standard".to_string());
+        let origin = hdkey.origin.as_ref().unwrap();
+        assert_eq!((origin.source_fingerprint, origin.depth), (Some(0x73c5da0a), Some(3)));
+        assert_eq!(hdkey.key_data.len(), 33);
+        assert!(hdkey.parent_fingerprint.is_some());
+        assert_eq!(CryptoHdKey::from_cbor(&hdkey.to_cbor())?, hdkey);
+
+        let (ur_type, payload) = ur::decode(&hdkey.to_ur())?;
+        assert_eq!((ur_type.as_str(), CryptoHdKey::from_cbor(&payload)?), (CRYPTO_HDKEY, hdkey));
+
+        // A private key is never accepted
+        let private = Value::Map(vec![
+            (Value::Unsigned(2), Value::Bool(true)),
+            (Value::Unsigned(3), Value::Bytes(vec![0; 33])),
+        ]);
+        assert!(CryptoHdKey::from_cbor(&private.encode()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_request_cbor() -> Result<()> {
+        let request = request(DataType::PersonalMessage, b"Hello", Some(1));
+        assert_eq!(EthSignRequest::from_cbor(&request.to_cbor())?, request);
+        let (ur_type, payload) = ur::decode(&request.to_ur())?;
+        assert_eq!(EthSignRequest::from_ur(&ur_type, &payload)?, request);
+        assert!(EthSignRequest::from_ur(ETH_SIGNATURE, &payload).is_err());
+
+        // The data type is checked, and the sign data and the path are required
+        let Value::Map(mut entries) = Value::decode(&request.to_cbor())? else { unreachable!() };
+        entries[2].1 = Value::Unsigned(5);
+        assert!(EthSignRequest::from_cbor(&Value::Map(entries.clone()).encode()).is_err());
+        entries.remove(1);
+        let missing = EthSignRequest::from_cbor(&Value::Map(entries).encode());
+        assert_eq!(missing, Err(Error::Missing("sign data")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign() -> Result<()> {
+        let master = master_key(PHRASE, Language::English, None)?;
+        let address: Address = ADDRESS.parse()?;
+
+        let message = request(DataType::PersonalMessage, b"Hello", None);
+        let signature = message.sign(&master)?;
+        assert_eq!(signature.request_id, Some([7; 16]));
+        assert!(matches!(signature.signature[64], 27 | 28));
+        assert_eq!(recover(&message, &signature)?, address);
+        assert_eq!(EthSignature::from_cbor(&signature.to_cbor())?, signature);
+
+        // A legacy transaction of chain 1, its v as in EIP-155
+        let legacy = hex::decode("ec098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a764000080018080")?;
+        let transaction = request(DataType::Transaction, &legacy, Some(1));
+        let signature = transaction.sign(&master)?;
+        assert!(matches!(signature.signature[64], 37 | 38));
+        assert_eq!(recover(&transaction, &signature)?, address);
+
+        // A typed transaction, its v as the parity of y
+        let typed = request(DataType::TypedTransaction, &[0x02, 0xc0], Some(1));
+        let signature = typed.sign(&master)?;
+        assert!(matches!(signature.signature[64], 0 | 1));
+        assert_eq!(recover(&typed, &signature)?, address);
+
+        let typed_data = r#"{
+            "types": {
+                "EIP712Domain": [{"name": "name", "type": "string"}, {"name": "chainId", "type": "uint256"}],
+                "Mail": [{"name": "contents", "type": "string"}]
+            },
+            "primaryType": "Mail",
+            "domain": {"name": "Mail", "chainId": 1},
+            "message": {"contents": "Hello"}
+        }"#;
+        let data = request(DataType::TypedData, typed_data.as_bytes(), Some(1));
+        assert_eq!(recover(&data, &data.sign(&master)?)?, address);
+        assert!(request(DataType::TypedData, b"{}", None).sign(&master).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_mismatch() -> Result<()> {
+        let master = master_key(PHRASE, Language::English, None)?;
+        let mut message = request(DataType::PersonalMessage, b"Hello", None);
+        message.derivation_path.source_fingerprint = Some(0x12345678);
+        assert!(matches!(message.sign(&master), Err(Error::Fingerprint { .. })));
+
+        let mut message = request(DataType::PersonalMessage, b"Hello", None);
+        message.derivation_path = KeyPath::parse("m/44'/60'/0'/0/1", Some(0x73c5da0a))?;
+        assert!(matches!(message.sign(&master), Err(Error::Address { .. })));
+
+        message.derivation_path = KeyPath::parse("m/44'/60'/0'/0/*", None)?;
+        assert!(matches!(message.sign(&master), Err(Error::Invalid(_))));
+        Ok(())
+    }
+}
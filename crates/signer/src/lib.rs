@@ -2,6 +2,19 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+pub mod account;
+pub mod address;
+pub mod backup;
+pub mod bip85;
+pub mod calldata;
+pub mod cbor;
+pub mod erc4527;
+pub mod mnemonic;
+pub mod recovery;
+pub mod siwe;
+pub mod slip39;
+pub mod ur;
+
 #[no_mangle]
 pub extern "C" fn hello_world() {
     println!("Hello, world!");
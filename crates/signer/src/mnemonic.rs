@@ -0,0 +1,530 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// BIP-39 mnemonic generation, validation and address derivation.
+///
+/// The phrases and passphrases are NFKD normalized before their words are looked up and their
+/// seed is derived, so that the composed and decomposed forms of the accented and kana words
+/// are the same mnemonic.
+///
+/// From:
+/// https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+use coins_bip32::xkeys::XPriv;
+use ethers_core::{
+    k256::ecdsa::SigningKey,
+    types::Address,
+    utils::{secret_key_to_address, to_checksum},
+};
+use ethers_signers::coins_bip39::{
+    ChineseSimplified, ChineseTraditional, Czech, English, French, Italian, Japanese, Korean,
+    Portuguese, Spanish, Wordlist,
+};
+use pbkdf2::pbkdf2_hmac;
+use rand::Rng;
+use sha2::{Digest, Sha256, Sha512};
+use std::{fmt, str::FromStr};
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
+
+/// The number of words of a valid mnemonic
+pub const WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// The default HD path of Ethereum accounts, without the account index
+pub const DEFAULT_HD_PATH: &str = "m/44'/60'/0'/0";
+
+/// The maximum edit distance of the suggestions for a misspelled word
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// The maximum number of suggestions for a misspelled word
+const MAX_SUGGESTIONS: usize = 5;
+
+/// The PBKDF2 rounds of the seed of a mnemonic
+const SEED_ROUNDS: u32 = 2048;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid word count {0}, expected 12, 15, 18, 21 or 24")]
+    WordCount(usize),
+    #[error("Invalid entropy length {0}, expected 16, 20, 24, 28 or 32 bytes")]
+    EntropyLength(usize),
+    #[error("Unknown words: {}", .0.iter().map(|w| w.to_string()).collect::<Vec<_>>().join(", "))]
+    UnknownWords(Vec<UnknownWord>),
+    #[error("Invalid checksum")]
+    Checksum,
+    #[error("Invalid dice roll `{0}`, expected 1 to 6")]
+    DiceRoll(char),
+    #[error("Unknown language `{0}`")]
+    Language(String),
+    #[error("Failed to derive the account: {0}")]
+    Derive(String),
+}
+
+/// A word missing from the wordlist, and the closest words of the wordlist
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownWord {
+    /// The position of the word in the mnemonic, starting at 1
+    pub position: usize,
+    pub word: String,
+    pub suggestions: Vec<&'static str>,
+}
+
+impl fmt::Display for UnknownWord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} `{}`", self.position, self.word)?;
+        if !self.suggestions.is_empty() {
+            write!(f, " (did you mean {}?)", self.suggestions.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The languages of the BIP-39 wordlists
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    ChineseSimplified,
+    ChineseTraditional,
+    Czech,
+    French,
+    Italian,
+    Japanese,
+    Korean,
+    Portuguese,
+    Spanish,
+}
+
+/// Runs `$f::<W>($args)` with the wordlist `W` of `$language`.
+macro_rules! with_wordlist {
+    ($language:expr, $f:ident($($args:expr),*)) => {
+        match $language {
+            Language::English => $f::<English>($($args),*),
+            Language::ChineseSimplified => $f::<ChineseSimplified>($($args),*),
+            Language::ChineseTraditional => $f::<ChineseTraditional>($($args),*),
+            Language::Czech => $f::<Czech>($($args),*),
+            Language::French => $f::<French>($($args),*),
+            Language::Italian => $f::<Italian>($($args),*),
+            Language::Japanese => $f::<Japanese>($($args),*),
+            Language::Korean => $f::<Korean>($($args),*),
+            Language::Portuguese => $f::<Portuguese>($($args),*),
+            Language::Spanish => $f::<Spanish>($($args),*),
+        }
+    };
+}
+
+fn all_words<W: Wordlist>() -> &'static [&'static str] {
+    W::get_all()
+}
+
+impl Language {
+    pub const ALL: [Language; 10] = [
+        Language::English,
+        Language::ChineseSimplified,
+        Language::ChineseTraditional,
+        Language::Czech,
+        Language::French,
+        Language::Italian,
+        Language::Japanese,
+        Language::Korean,
+        Language::Portuguese,
+        Language::Spanish,
+    ];
+
+    /// Returns the 2048 words of the wordlist, in the order of their indices, which is not the
+    /// alphabetical order of every language.
+    pub fn words(&self) -> &'static [&'static str] {
+        with_wordlist!(self, all_words())
+    }
+
+    /// Returns the index of `word` in the wordlist, comparing their NFKD forms.
+    pub fn index_of(&self, word: &str) -> Option<usize> {
+        let words = self.words();
+        words
+            .iter()
+            .position(|w| *w == word)
+            .or_else(|| words.iter().position(|w| w.nfkd().eq(word.nfkd())))
+    }
+
+    /// Returns the separator of the words of a phrase, the ideographic space in Japanese.
+    pub fn separator(&self) -> &'static str {
+        match self {
+            Language::Japanese => "\u{3000}",
+            _ => " ",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::English => "english",
+            Language::ChineseSimplified => "chinese-simplified",
+            Language::ChineseTraditional => "chinese-traditional",
+            Language::Czech => "czech",
+            Language::French => "french",
+            Language::Italian => "italian",
+            Language::Japanese => "japanese",
+            Language::Korean => "korean",
+            Language::Portuguese => "portuguese",
+            Language::Spanish => "spanish",
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Language {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Language::ALL
+            .into_iter()
+            .find(|language| language.name() == s.to_lowercase())
+            .ok_or_else(|| Error::Language(s.to_string()))
+    }
+}
+
+/// Returns the entropy length in bytes of a mnemonic of `word_count` words.
+pub fn entropy_len(word_count: usize) -> Result<usize, Error> {
+    match WORD_COUNTS.contains(&word_count) {
+        true => Ok(word_count / 3 * 4),
+        false => Err(Error::WordCount(word_count)),
+    }
+}
+
+/// Splits a phrase into its words, ignoring extra whitespace, including the ideographic spaces.
+pub fn split(phrase: &str) -> Vec<&str> {
+    phrase.split_whitespace().collect()
+}
+
+/// Encodes entropy as a mnemonic, appending its checksum.
+pub fn from_entropy(entropy: &[u8], language: Language) -> Result<String, Error> {
+    if ![16, 20, 24, 28, 32].contains(&entropy.len()) {
+        return Err(Error::EntropyLength(entropy.len()));
+    }
+    let checksum = Sha256::digest(entropy)[0];
+    let bits: Vec<bool> = entropy
+        .iter()
+        .chain(std::iter::once(&checksum))
+        .flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+        .take(entropy.len() * 8 + entropy.len() / 4)
+        .collect();
+
+    let words = language.words();
+    Ok(bits
+        .chunks(11)
+        .map(|chunk| words[chunk.iter().fold(0, |index, bit| index << 1 | *bit as usize)])
+        .collect::<Vec<_>>()
+        .join(language.separator()))
+}
+
+/// Decodes the word indices of a mnemonic into its entropy, verifying its checksum.
+pub fn indices_to_entropy(indices: &[usize]) -> Result<Vec<u8>, Error> {
+    let len = entropy_len(indices.len())?;
+    let bits: Vec<bool> =
+        indices.iter().flat_map(|index| (0..11).rev().map(move |i| index >> i & 1 == 1)).collect();
+    let entropy: Vec<u8> = bits[..len * 8]
+        .chunks(8)
+        .map(|chunk| chunk.iter().fold(0, |byte, bit| byte << 1 | *bit as u8))
+        .collect();
+
+    let checksum = Sha256::digest(&entropy)[0];
+    let expected = (0..len / 4).all(|i| bits[len * 8 + i] == (checksum >> (7 - i) & 1 == 1));
+    match expected {
+        true => Ok(entropy),
+        false => Err(Error::Checksum),
+    }
+}
+
+/// Validates the word count, the words and the checksum of a mnemonic, and returns its entropy.
+///
+/// Words missing from the wordlist are reported with the closest words of the wordlist.
+pub fn validate(phrase: &str, language: Language) -> Result<Vec<u8>, Error> {
+    let words = split(phrase);
+    entropy_len(words.len())?;
+
+    let mut indices = vec![];
+    let mut unknown = vec![];
+    for (i, word) in words.iter().enumerate() {
+        match language.index_of(word) {
+            Some(index) => indices.push(index),
+            None => unknown.push(UnknownWord {
+                position: i + 1,
+                word: word.to_string(),
+                suggestions: suggestions(word, language),
+            }),
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(Error::UnknownWords(unknown));
+    }
+    indices_to_entropy(&indices)
+}
+
+/// Returns the Levenshtein distance between two words.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + (ca != *cb) as usize;
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Returns the words of the wordlist within `max_distance` edits of `word`, closest first.
+pub fn nearest_words(word: &str, language: Language, max_distance: usize) -> Vec<&'static str> {
+    let mut words: Vec<(usize, &'static str)> = language
+        .words()
+        .iter()
+        .map(|w| (levenshtein(word, w), *w))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    words.sort();
+    words.into_iter().map(|(_, w)| w).collect()
+}
+
+/// Returns the closest words of the wordlist for a misspelled word.
+///
+/// Every word of the English wordlist is uniquely identified by its first four letters, so a
+/// matching prefix is suggested first.
+pub fn suggestions(word: &str, language: Language) -> Vec<&'static str> {
+    let prefix: String = word.chars().take(4).collect();
+    let mut suggestions: Vec<&'static str> = match prefix.chars().count() == 4 {
+        true => language.words().iter().filter(|w| w.starts_with(&prefix)).copied().collect(),
+        false => vec![],
+    };
+    for w in nearest_words(word, language, MAX_SUGGESTION_DISTANCE) {
+        if !suggestions.contains(&w) {
+            suggestions.push(w);
+        }
+    }
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+/// Converts dice rolls, e.g. `"3615..."`, into entropy.
+///
+/// Each roll is worth log2(6) ≈ 2.58 bits, 50 rolls are needed for 128 bits of entropy.
+pub fn dice_entropy(rolls: &str) -> Result<Vec<u8>, Error> {
+    let rolls: Vec<char> = rolls.chars().filter(|c| !c.is_whitespace()).collect();
+    if let Some(c) = rolls.iter().find(|c| !('1'..='6').contains(*c)) {
+        return Err(Error::DiceRoll(*c));
+    }
+    Ok(rolls.into_iter().map(|c| c as u8).collect())
+}
+
+/// Generates a mnemonic of `word_count` words from the system RNG.
+///
+/// When `extra_entropy` is not empty, e.g. from [dice_entropy], it is hashed together with the
+/// random bytes so that it can only add entropy.
+pub fn generate(
+    word_count: usize,
+    language: Language,
+    extra_entropy: &[u8],
+) -> Result<String, Error> {
+    let len = entropy_len(word_count)?;
+    let mut entropy = [0u8; 32];
+    rand::thread_rng().fill(&mut entropy);
+    if !extra_entropy.is_empty() {
+        entropy = Sha256::new().chain_update(entropy).chain_update(extra_entropy).finalize().into();
+    }
+    from_entropy(&entropy[..len], language)
+}
+
+/// An account derived from a mnemonic
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DerivedAccount {
+    pub path: String,
+    pub address: Address,
+}
+
+impl fmt::Display for DerivedAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.path, to_checksum(&self.address, None))
+    }
+}
+
+/// Returns the BIP-39 seed of a mnemonic, validating it first.
+///
+/// The seed is derived from the NFKD forms of the phrase, its words separated by spaces, and of
+/// the passphrase.
+pub fn seed(
+    phrase: &str,
+    language: Language,
+    passphrase: Option<&str>,
+) -> Result<Zeroizing<[u8; 64]>, Error> {
+    validate(phrase, language)?;
+    let phrase: Zeroizing<String> = Zeroizing::new(split(phrase).join(" ").nfkd().collect());
+    let salt: Zeroizing<String> =
+        Zeroizing::new(format!("mnemonic{}", passphrase.unwrap_or_default()).nfkd().collect());
+    let mut seed = Zeroizing::new([0u8; 64]);
+    pbkdf2_hmac::<Sha512>(phrase.as_bytes(), salt.as_bytes(), SEED_ROUNDS, seed.as_mut());
+    Ok(seed)
+}
+
+/// Returns the BIP-32 root key of a mnemonic.
+pub fn master_key(
+    phrase: &str,
+    language: Language,
+    passphrase: Option<&str>,
+) -> Result<XPriv, Error> {
+    let seed = seed(phrase, language, passphrase)?;
+    XPriv::root_from_seed(seed.as_ref(), None).map_err(|e| Error::Derive(e.to_string()))
+}
+
+/// Derives the address at `path` of a mnemonic.
+pub fn derive_address(
+    phrase: &str,
+    language: Language,
+    passphrase: Option<&str>,
+    path: &str,
+) -> Result<Address, Error> {
+    let key = master_key(phrase, language, passphrase)?
+        .derive_path(path)
+        .map_err(|e| Error::Derive(e.to_string()))?;
+    let key: &SigningKey = key.as_ref();
+    Ok(secret_key_to_address(key))
+}
+
+/// Derives the accounts at `hd_path/i` of a mnemonic, for `i` in `indices`.
+pub fn derive_accounts(
+    phrase: &str,
+    language: Language,
+    passphrase: Option<&str>,
+    hd_path: &str,
+    indices: impl IntoIterator<Item = u32>,
+) -> Result<Vec<DerivedAccount>, Error> {
+    indices
+        .into_iter()
+        .map(|index| {
+            let path = format!("{}/{}", hd_path.trim_end_matches('/'), index);
+            let address = derive_address(phrase, language, passphrase, &path)?;
+            Ok(DerivedAccount { path, address })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use ethers_core::utils::hex;
+
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn test_entropy_vectors() -> Result<()> {
+        // From: https://github.com/trezor/python-mnemonic/blob/master/vectors.json
+        let vectors = [
+            (
+                "00000000000000000000000000000000",
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                 abandon about",
+            ),
+            (
+                "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+                "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            ),
+            (
+                "8080808080808080808080808080808080808080808080808080808080808080",
+                "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd \
+                 amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic \
+                 bless",
+            ),
+            (
+                "9e885d952ad362caeb4efe34a8e91bd2",
+                "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic",
+            ),
+        ];
+        for (entropy, phrase) in vectors {
+            let entropy = hex::decode(entropy)?;
+            assert_eq!(from_entropy(&entropy, Language::English)?, phrase);
+            assert_eq!(validate(phrase, Language::English)?, entropy);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_errors() {
+        assert_eq!(validate("abandon about", Language::English), Err(Error::WordCount(2)));
+        assert_eq!(validate(&"abandon ".repeat(12), Language::English), Err(Error::Checksum));
+
+        let Err(Error::UnknownWords(unknown)) =
+            validate(&PHRASE.replace("junk", "junkk"), Language::English)
+        else {
+            panic!("expected unknown words")
+        };
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(unknown[0].position, 12);
+        assert_eq!(unknown[0].suggestions[0], "junk");
+    }
+
+    #[test]
+    fn test_suggestions() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(suggestions("abandn", Language::English)[0], "abandon");
+        // Unique prefix of the English wordlist
+        assert_eq!(suggestions("conceal", Language::English)[0], "concert");
+    }
+
+    #[test]
+    fn test_generate() -> Result<()> {
+        for word_count in WORD_COUNTS {
+            for language in [Language::English, Language::Japanese, Language::Spanish] {
+                let phrase = generate(word_count, language, &dice_entropy("1 2 3 4 5 6")?)?;
+                assert_eq!(split(&phrase).len(), word_count);
+                assert!(validate(&phrase, language).is_ok());
+            }
+        }
+        assert_eq!(generate(13, Language::English, &[]), Err(Error::WordCount(13)));
+        assert_eq!(dice_entropy("1237"), Err(Error::DiceRoll('7')));
+        assert_eq!("Japanese".parse::<Language>()?, Language::Japanese);
+        Ok(())
+    }
+
+    #[test]
+    fn test_japanese_vector() -> Result<()> {
+        // From: https://github.com/bip32JP/bip32JP.github.io/blob/master/test_JP_BIP39.json
+        let phrase = "あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　\
+                      あいこくしん　あいこくしん　あいこくしん　あいこくしん　あいこくしん　\
+                      あいこくしん　あおぞら";
+        let passphrase = "㍍ガバヴァぱばぐゞちぢ十人十色";
+        let expected = "a262d6fb6122ecf45be09c50492b31f92e9beb7d9a845987a02cefda57a15f9c\
+                        467a17872029a9e92299b5cbdf306e3a0ee620245cbd508959b6cb7ca637bd55";
+
+        assert_eq!(from_entropy(&[0; 16], Language::Japanese)?.nfc().collect::<String>(), phrase);
+        assert_eq!(hex::encode(*seed(phrase, Language::Japanese, Some(passphrase))?), expected);
+
+        // The decomposed forms and the ASCII spaces are the same mnemonic
+        let decomposed: String = phrase.nfd().collect::<String>().replace('　', " ");
+        assert_eq!(validate(&decomposed, Language::Japanese)?, vec![0; 16]);
+        let passphrase: String = passphrase.nfkc().collect();
+        assert_eq!(
+            hex::encode(*seed(&decomposed, Language::Japanese, Some(&passphrase))?),
+            expected
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_derive_accounts() -> Result<()> {
+        let accounts = derive_accounts(PHRASE, Language::English, None, DEFAULT_HD_PATH, 0..2)?;
+        assert_eq!(
+            accounts.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+            vec![
+                "m/44'/60'/0'/0/0 0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+                "m/44'/60'/0'/0/1 0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Recovery of a mnemonic with missing, misspelled or swapped words.
+///
+/// The candidates are the combinations of the possible words of every position, with `?` for
+/// an unreadable word and the nearest words of the wordlist for a misspelled one. Each
+/// combination is also tried with adjacent words swapped and, optionally, with a valid word
+/// replaced by a near one. A phrase one word short is tried with the missing word at every
+/// position.
+///
+/// Candidates are filtered by checksum, then by the address derived at the target path if any.
+use crate::mnemonic::{
+    derive_address, entropy_len, indices_to_entropy, nearest_words, split, Language, WORD_COUNTS,
+};
+use ethers_core::types::Address;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// The marker of an unreadable word
+pub const UNKNOWN_WORD: &str = "?";
+
+/// The number of candidates checked between two progress reports of a thread
+const PROGRESS_INTERVAL: u64 = 1 << 12;
+
+/// The largest search space accepted, about a day of checksum checks
+const MAX_CANDIDATES: u128 = 1 << 40;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Mnemonic(#[from] crate::mnemonic::Error),
+    #[error("Too many candidates ({0}), mark fewer words as unknown")]
+    SearchSpace(u128),
+}
+
+/// The account a recovered mnemonic must derive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Target {
+    pub address: Address,
+    /// The full HD path of the account, e.g. `m/44'/60'/0'/0/0`
+    pub path: String,
+    pub passphrase: Option<String>,
+}
+
+/// What to try while recovering a mnemonic
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub language: Language,
+    /// The maximum edit distance of the replacements of a misspelled word
+    pub max_distance: usize,
+    /// Try swapping adjacent words
+    pub swaps: bool,
+    /// Try replacing a valid word by a near one, in case it was misread as another word
+    pub near_valid_words: bool,
+    pub target: Option<Target>,
+    pub threads: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            language: Language::English,
+            max_distance: 2,
+            swaps: true,
+            near_valid_words: false,
+            target: None,
+            threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        }
+    }
+}
+
+/// The progress of a recovery
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub checked: u64,
+    pub total: u64,
+    pub found: u64,
+}
+
+/// A change applied to every combination of words
+#[derive(Clone, Copy, Debug)]
+enum Transform {
+    Identity,
+    /// Swaps the words at `i` and `i + 1`
+    Swap(usize),
+    /// Replaces the word at `i` by the word at index `word`
+    Replace(usize, usize),
+}
+
+/// The possible word indices of every position
+type Layout = Vec<Vec<usize>>;
+
+/// Returns the possible word indices of a position.
+fn candidates(word: &str, options: &Options) -> Vec<usize> {
+    let language = options.language;
+    if word == UNKNOWN_WORD {
+        return (0..language.words().len()).collect();
+    }
+    if let Some(index) = language.index_of(word) {
+        return vec![index];
+    }
+    let near: Vec<usize> = nearest_words(word, language, options.max_distance)
+        .into_iter()
+        .filter_map(|w| language.index_of(w))
+        .collect();
+    // A word too far from the wordlist is as good as unreadable
+    match near.is_empty() {
+        true => (0..language.words().len()).collect(),
+        false => near,
+    }
+}
+
+/// Returns the layouts to search, with the missing word at every position if one is missing.
+fn layouts(words: &[&str], options: &Options) -> Result<Vec<Layout>, Error> {
+    let positions: Layout = words.iter().map(|word| candidates(word, options)).collect();
+    if entropy_len(words.len()).is_ok() {
+        return Ok(vec![positions]);
+    }
+    if !WORD_COUNTS.contains(&(words.len() + 1)) {
+        return Err(crate::mnemonic::Error::WordCount(words.len()).into());
+    }
+    let all: Vec<usize> = (0..options.language.words().len()).collect();
+    Ok((0..=positions.len())
+        .map(|at| {
+            let mut layout = positions.clone();
+            layout.insert(at, all.clone());
+            layout
+        })
+        .collect())
+}
+
+/// Returns the transforms to try on the combinations of a layout.
+fn transforms(layout: &Layout, options: &Options) -> Vec<Transform> {
+    let mut transforms = vec![Transform::Identity];
+    if options.swaps {
+        transforms.extend((0..layout.len() - 1).map(Transform::Swap));
+    }
+    if options.near_valid_words {
+        let words = options.language.words();
+        for (i, position) in layout.iter().enumerate() {
+            if let [index] = position[..] {
+                transforms.extend(
+                    nearest_words(words[index], options.language, options.max_distance)
+                        .into_iter()
+                        .filter_map(|w| options.language.index_of(w))
+                        .filter(|near| *near != index)
+                        .map(|near| Transform::Replace(i, near)),
+                );
+            }
+        }
+    }
+    transforms
+}
+
+/// Returns the number of combinations of a layout.
+fn combinations(layout: &Layout) -> u128 {
+    layout.iter().fold(1, |n: u128, position| n.saturating_mul(position.len() as u128))
+}
+
+/// Writes the combination `n` of a layout into `indices`.
+fn combination(layout: &Layout, mut n: u64, indices: &mut [usize]) {
+    for (i, position) in layout.iter().enumerate().rev() {
+        let len = position.len() as u64;
+        indices[i] = position[(n % len) as usize];
+        n /= len;
+    }
+}
+
+/// Returns whether the word indices form a valid mnemonic deriving the target, if any.
+fn matches(indices: &[usize], options: &Options) -> Option<String> {
+    indices_to_entropy(indices).ok()?;
+    let words = options.language.words();
+    let phrase = indices.iter().map(|i| words[*i]).collect::<Vec<_>>().join(" ");
+    match &options.target {
+        Some(target) => {
+            let address = derive_address(
+                &phrase,
+                options.language,
+                target.passphrase.as_deref(),
+                &target.path,
+            )
+            .ok()?;
+            (address == target.address).then_some(phrase)
+        }
+        None => Some(phrase),
+    }
+}
+
+/// Recovers the candidate mnemonics of a damaged phrase, with `?` for unreadable words.
+///
+/// The search runs on [Options::threads] threads and calls `progress` periodically. With a
+/// target, it stops at the first mnemonic deriving the target address.
+pub fn recover(
+    phrase: &str,
+    options: &Options,
+    progress: impl Fn(Progress) + Sync,
+) -> Result<Vec<String>, Error> {
+    let words = split(phrase);
+    let layouts: Vec<(Layout, Vec<Transform>)> = layouts(&words, options)?
+        .into_iter()
+        .map(|layout| {
+            let transforms = transforms(&layout, options);
+            (layout, transforms)
+        })
+        .collect();
+
+    let total: u128 = layouts.iter().fold(0, |n: u128, (layout, transforms)| {
+        n.saturating_add(combinations(layout).saturating_mul(transforms.len() as u128))
+    });
+    if total > MAX_CANDIDATES {
+        return Err(Error::SearchSpace(total));
+    }
+    let total = total as u64;
+
+    let checked = AtomicU64::new(0);
+    let stop = AtomicBool::new(false);
+    let found = Mutex::new(Vec::<String>::new());
+    let report = || Progress {
+        checked: checked.load(Ordering::Relaxed),
+        total,
+        found: found.lock().unwrap().len() as u64,
+    };
+
+    for (layout, transforms) in layouts.iter() {
+        let count = combinations(layout) as u64 * transforms.len() as u64;
+        let threads = options.threads.max(1) as u64;
+        thread::scope(|scope| {
+            for thread in 0..threads {
+                let (checked, stop, found, progress) = (&checked, &stop, &found, &progress);
+                scope.spawn(move || {
+                    let mut indices = vec![0; layout.len()];
+                    let mut n = thread;
+                    while n < count && !stop.load(Ordering::Relaxed) {
+                        combination(layout, n / transforms.len() as u64, &mut indices);
+                        match transforms[(n % transforms.len() as u64) as usize] {
+                            Transform::Identity => {}
+                            Transform::Swap(i) => indices.swap(i, i + 1),
+                            Transform::Replace(i, word) => indices[i] = word,
+                        }
+                        if let Some(phrase) = matches(&indices, options) {
+                            let mut found = found.lock().unwrap();
+                            if !found.contains(&phrase) {
+                                found.push(phrase);
+                            }
+                            if options.target.is_some() {
+                                stop.store(true, Ordering::Relaxed);
+                            }
+                        }
+                        if checked.fetch_add(1, Ordering::Relaxed) % PROGRESS_INTERVAL == 0 {
+                            progress(report());
+                        }
+                        n += threads;
+                    }
+                });
+            }
+        });
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+
+    progress(report());
+    let mut found = found.into_inner().unwrap();
+    found.sort();
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    const PHRASE: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn test_recover_missing_word_with_target() -> Result<()> {
+        let options = Options {
+            target: Some(Target {
+                address: "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".parse()?,
+                path: "m/44'/60'/0'/0/0".to_string(),
+                passphrase: None,
+            }),
+            swaps: false,
+            ..Default::default()
+        };
+        let last = Mutex::new(None);
+        let found = recover(&PHRASE.replace("junk", "?"), &options, |p| {
+            *last.lock().unwrap() = Some(p);
+        })?;
+
+        assert_eq!(found, vec![PHRASE]);
+        assert_eq!(last.into_inner().unwrap().map(|p| p.found), Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_recover_swapped_and_misspelled() -> Result<()> {
+        let options = Options::default();
+        let swapped = "test test test test test test test test test test junk test";
+        assert_eq!(recover(swapped, &options, |_| {})?, vec![PHRASE]);
+
+        let misspelled = PHRASE.replace("junk", "jumk");
+        assert!(recover(&misspelled, &options, |_| {})?.contains(&PHRASE.to_string()));
+
+        // Every candidate is a valid mnemonic
+        let options = Options { near_valid_words: true, swaps: false, ..options };
+        let found = recover(&PHRASE.replace("junk", "just"), &options, |_| {})?;
+        assert!(found.contains(&PHRASE.to_string()));
+        assert!(found.iter().all(|p| crate::mnemonic::validate(p, Language::English).is_ok()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_recover_missing_position() -> Result<()> {
+        let options = Options { swaps: false, ..Default::default() };
+        let found = recover(&PHRASE.replacen("test ", "", 1), &options, |_| {})?;
+        assert!(found.contains(&PHRASE.to_string()));
+
+        assert_eq!(
+            recover("test test", &options, |_| {}),
+            Err(Error::Mnemonic(crate::mnemonic::Error::WordCount(2)))
+        );
+        assert!(matches!(recover(&"? ".repeat(12), &options, |_| {}), Err(Error::SearchSpace(_))));
+        Ok(())
+    }
+}
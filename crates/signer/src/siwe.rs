@@ -0,0 +1,618 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Sign-In with Ethereum messages: parsing by the EIP-4361 grammar, validation against the
+/// domain, nonce, chain and time of a session, and EIP-191 signatures.
+///
+/// The signatures of contract wallets are verified with EIP-1271, by calling the wallet
+/// through an [Rpc] that the caller provides.
+///
+/// From:
+/// https://eips.ethereum.org/EIPS/eip-4361
+/// https://eips.ethereum.org/EIPS/eip-1271
+/// https://github.com/spruceid/siwe-rs
+use crate::address::checksum;
+use chrono::{DateTime, FixedOffset, Utc};
+use coins_bip32::ecdsa::SigningKey;
+use ethers_core::{
+    abi::{encode, Token},
+    types::{Address, Signature, H256},
+    utils::{hash_message, secret_key_to_address},
+};
+use ethers_signers::LocalWallet;
+#[cfg(any(test, feature = "mock"))]
+use mockall::automock;
+use rand::{distributions::Alphanumeric, Rng};
+use std::{fmt, str::FromStr, time::SystemTime};
+use url::Url;
+
+/// The end of the first line, after the domain
+const PREAMBLE: &str = " wants you to sign in with your Ethereum account:";
+
+/// The only version of the messages
+const VERSION: &str = "1";
+
+/// The shortest nonce, and the length of the generated ones
+const MIN_NONCE_LEN: usize = 8;
+const NONCE_LEN: usize = 17;
+
+/// The selector of `isValidSignature(bytes32,bytes)`, and what valid signatures return
+const IS_VALID_SIGNATURE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid message, expected {0}")]
+    Format(String),
+    #[error("Invalid {field} `{value}`")]
+    Field { field: &'static str, value: String },
+    #[error("The message is for {actual}, not {expected}")]
+    Domain { expected: String, actual: String },
+    #[error("The message is for the URI {actual}, not {expected}")]
+    Uri { expected: String, actual: String },
+    #[error("The nonce of the message does not match the session")]
+    Nonce,
+    #[error("The message is for the chain {actual}, not {expected}")]
+    ChainId { expected: u64, actual: u64 },
+    #[error("The message expired at {0}")]
+    Expired(String),
+    #[error("The message is not valid before {0}")]
+    NotYetValid(String),
+    #[error("The message is signed by {actual}, not {expected}")]
+    Signer { expected: String, actual: String },
+    #[error("Invalid signature: {0}")]
+    Signature(String),
+    #[error("Failed to call the contract wallet: {0}")]
+    Rpc(String),
+}
+
+/// An Ethereum node, to call the contract wallets that sign with EIP-1271
+#[cfg_attr(any(test, feature = "mock"), automock)]
+pub trait Rpc {
+    /// Returns the output of an `eth_call` of `data` to `to`, at the latest block of a chain.
+    fn call(&self, chain_id: u64, to: Address, data: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+/// An RFC 3339 date-time, kept as written since the message is signed as text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimeStamp {
+    text: String,
+    time: DateTime<FixedOffset>,
+}
+
+impl TimeStamp {
+    pub fn time(&self) -> DateTime<Utc> {
+        self.time.with_timezone(&Utc)
+    }
+}
+
+impl FromStr for TimeStamp {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let time = DateTime::parse_from_rfc3339(s)
+            .map_err(|_| Error::Field { field: "date-time", value: s.to_string() })?;
+        Ok(TimeStamp { text: s.to_string(), time })
+    }
+}
+
+impl From<DateTime<Utc>> for TimeStamp {
+    fn from(time: DateTime<Utc>) -> Self {
+        TimeStamp { text: time.to_rfc3339(), time: time.into() }
+    }
+}
+
+impl fmt::Display for TimeStamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// A Sign-In with Ethereum message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    /// The scheme of the origin, e.g. `https`, when the message names it
+    pub scheme: Option<String>,
+    /// The authority that asks for the sign in, e.g. `example.com` or `localhost:3000`
+    pub domain: String,
+    pub address: Address,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub nonce: String,
+    pub issued_at: TimeStamp,
+    pub expiration_time: Option<TimeStamp>,
+    pub not_before: Option<TimeStamp>,
+    pub request_id: Option<String>,
+    pub resources: Vec<String>,
+}
+
+/// What the verifier of a message expects of it, each check skipped when omitted
+#[derive(Clone, Debug, Default)]
+pub struct VerifyOptions {
+    pub domain: Option<String>,
+    pub uri: Option<String>,
+    pub nonce: Option<String>,
+    pub chain_id: Option<u64>,
+    /// The time of the verification, now when omitted
+    pub time: Option<DateTime<Utc>>,
+}
+
+/// Returns a random nonce of alphanumeric characters.
+pub fn generate_nonce() -> String {
+    rand::thread_rng().sample_iter(&Alphanumeric).take(NONCE_LEN).map(char::from).collect()
+}
+
+fn field(field: &'static str, value: &str) -> Error {
+    Error::Field { field, value: value.to_string() }
+}
+
+/// Checks an RFC 3986 authority, a host with optional user info and port.
+fn check_domain(domain: &str) -> Result<(), Error> {
+    match Url::parse(&format!("https://{}", domain)) {
+        Ok(url)
+            if url.host_str().is_some() &&
+                url.path() == "/" &&
+                !domain.contains(['/', '?', '#']) =>
+        {
+            Ok(())
+        }
+        _ => Err(field("domain", domain)),
+    }
+}
+
+fn check_uri(name: &'static str, uri: &str) -> Result<(), Error> {
+    Url::parse(uri).map(|_| ()).map_err(|_| field(name, uri))
+}
+
+/// The lines of a message, read field by field
+struct Lines<'a> {
+    lines: std::iter::Peekable<std::str::Split<'a, char>>,
+}
+
+impl<'a> Lines<'a> {
+    fn next(&mut self, expected: &str) -> Result<&'a str, Error> {
+        self.lines.next().ok_or_else(|| Error::Format(expected.to_string()))
+    }
+
+    /// Reads a field of a tag.
+    fn tagged(&mut self, tag: &str) -> Result<&'a str, Error> {
+        let line = self.next(tag)?;
+        line.strip_prefix(tag).ok_or_else(|| Error::Format(format!("`{}`", tag.trim())))
+    }
+
+    /// Reads an optional field of a tag.
+    fn optional(&mut self, tag: &str) -> Option<&'a str> {
+        let value = self.lines.peek()?.strip_prefix(tag)?;
+        self.lines.next();
+        Some(value)
+    }
+}
+
+impl FromStr for Message {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let mut lines = Lines { lines: s.split('\n').peekable() };
+
+        // The origin that asks, then the account
+        let origin = lines
+            .next("the domain")?
+            .strip_suffix(PREAMBLE)
+            .ok_or_else(|| Error::Format(format!("`<domain>{}`", PREAMBLE)))?;
+        let (scheme, domain) = match origin.split_once("://") {
+            Some((scheme, domain)) => (Some(scheme.to_string()), domain),
+            None => (None, origin),
+        };
+        check_domain(domain)?;
+        let address_line = lines.next("the address")?;
+        let address: Address = address_line.parse().map_err(|_| field("address", address_line))?;
+        if !address_line.starts_with("0x") || checksum(&address, None) != address_line {
+            return Err(field("EIP-55 address", address_line));
+        }
+
+        // The statement between empty lines, the second one alone without a statement
+        if !lines.next("an empty line")?.is_empty() {
+            return Err(Error::Format("an empty line after the address".into()));
+        }
+        let statement = match lines.next("the statement")? {
+            "" if lines.lines.peek().map_or(false, |line| line.starts_with("URI: ")) => None,
+            statement => {
+                if !lines.next("an empty line")?.is_empty() {
+                    return Err(Error::Format("an empty line after the statement".into()));
+                }
+                Some(statement.to_string())
+            }
+        };
+
+        let uri = lines.tagged("URI: ")?.to_string();
+        check_uri("URI", &uri)?;
+        let version = lines.tagged("Version: ")?.to_string();
+        if version != VERSION {
+            return Err(field("version", &version));
+        }
+        let chain_id = lines.tagged("Chain ID: ")?;
+        let chain_id = chain_id.parse().map_err(|_| field("chain ID", chain_id))?;
+        let nonce = lines.tagged("Nonce: ")?.to_string();
+        if nonce.len() < MIN_NONCE_LEN || !nonce.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(field("nonce", &nonce));
+        }
+        let issued_at = lines.tagged("Issued At: ")?.parse()?;
+        let expiration_time = lines.optional("Expiration Time: ").map(str::parse).transpose()?;
+        let not_before = lines.optional("Not Before: ").map(str::parse).transpose()?;
+        let request_id = lines.optional("Request ID: ").map(str::to_string);
+
+        let mut resources = vec![];
+        if lines.optional("Resources:").is_some() {
+            while let Some(resource) = lines.optional("- ") {
+                check_uri("resource", resource)?;
+                resources.push(resource.to_string());
+            }
+        }
+        if let Some(line) = lines.lines.next() {
+            return Err(Error::Format(format!("the end of the message, not `{}`", line)));
+        }
+
+        Ok(Message {
+            scheme,
+            domain: domain.to_string(),
+            address,
+            statement,
+            uri,
+            version,
+            chain_id,
+            nonce,
+            issued_at,
+            expiration_time,
+            not_before,
+            request_id,
+            resources,
+        })
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(scheme) = &self.scheme {
+            write!(f, "{}://", scheme)?;
+        }
+        writeln!(f, "{}{}", self.domain, PREAMBLE)?;
+        writeln!(f, "{}", checksum(&self.address, None))?;
+        writeln!(f)?;
+        if let Some(statement) = &self.statement {
+            writeln!(f, "{}", statement)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "URI: {}", self.uri)?;
+        writeln!(f, "Version: {}", self.version)?;
+        writeln!(f, "Chain ID: {}", self.chain_id)?;
+        writeln!(f, "Nonce: {}", self.nonce)?;
+        write!(f, "Issued At: {}", self.issued_at)?;
+        if let Some(expiration_time) = &self.expiration_time {
+            write!(f, "\nExpiration Time: {}", expiration_time)?;
+        }
+        if let Some(not_before) = &self.not_before {
+            write!(f, "\nNot Before: {}", not_before)?;
+        }
+        if let Some(request_id) = &self.request_id {
+            write!(f, "\nRequest ID: {}", request_id)?;
+        }
+        if !self.resources.is_empty() {
+            write!(f, "\nResources:")?;
+            for resource in &self.resources {
+                write!(f, "\n- {}", resource)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Message {
+    /// Returns the EIP-191 hash of the message, the one signed.
+    pub fn hash(&self) -> H256 {
+        hash_message(self.to_string())
+    }
+
+    /// Checks that the message is valid at a time, between its not before and expiration times.
+    pub fn valid_at(&self, time: DateTime<Utc>) -> Result<(), Error> {
+        if let Some(not_before) = &self.not_before {
+            if time < not_before.time() {
+                return Err(Error::NotYetValid(not_before.to_string()));
+            }
+        }
+        match &self.expiration_time {
+            Some(expiration) if time >= expiration.time() => {
+                Err(Error::Expired(expiration.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the message against what a verifier expects of it.
+    pub fn validate(&self, options: &VerifyOptions) -> Result<(), Error> {
+        match &options.domain {
+            Some(domain) if *domain != self.domain => {
+                return Err(Error::Domain { expected: domain.clone(), actual: self.domain.clone() })
+            }
+            _ => {}
+        }
+        match &options.uri {
+            Some(uri) if *uri != self.uri => {
+                return Err(Error::Uri { expected: uri.clone(), actual: self.uri.clone() })
+            }
+            _ => {}
+        }
+        match &options.nonce {
+            Some(nonce) if *nonce != self.nonce => return Err(Error::Nonce),
+            _ => {}
+        }
+        match options.chain_id {
+            Some(chain_id) if chain_id != self.chain_id => {
+                return Err(Error::ChainId { expected: chain_id, actual: self.chain_id })
+            }
+            _ => {}
+        }
+        self.valid_at(options.time.unwrap_or_else(|| SystemTime::now().into()))
+    }
+
+    /// Signs the message with the key of its address, as r, s and v of 27 or 28.
+    pub fn sign(&self, key: &SigningKey) -> Result<[u8; 65], Error> {
+        let address = secret_key_to_address(key);
+        if address != self.address {
+            return Err(Error::Signer {
+                expected: checksum(&self.address, None),
+                actual: checksum(&address, None),
+            });
+        }
+        let signature = LocalWallet::from(key.clone())
+            .sign_hash(self.hash())
+            .map_err(|e| Error::Signature(e.to_string()))?;
+        Ok(signature.into())
+    }
+
+    /// Returns the address that signed the message with EIP-191.
+    pub fn recover(&self, signature: &[u8]) -> Result<Address, Error> {
+        let signature =
+            Signature::try_from(signature).map_err(|e| Error::Signature(e.to_string()))?;
+        signature.recover(self.hash()).map_err(|e| Error::Signature(e.to_string()))
+    }
+
+    /// Verifies the signature of the message by its address, recovering the signer of an
+    /// EIP-191 signature, or asking the contract wallet at the address with EIP-1271 when an
+    /// [Rpc] is given.
+    pub fn verify_signature(&self, signature: &[u8], rpc: Option<&dyn Rpc>) -> Result<(), Error> {
+        let recovered = self.recover(signature);
+        if recovered.as_ref().ok() == Some(&self.address) {
+            return Ok(());
+        }
+        let Some(rpc) = rpc else {
+            return match recovered {
+                Ok(actual) => Err(Error::Signer {
+                    expected: checksum(&self.address, None),
+                    actual: checksum(&actual, None),
+                }),
+                Err(e) => Err(e),
+            };
+        };
+
+        let mut data = IS_VALID_SIGNATURE.to_vec();
+        data.extend(encode(&[
+            Token::FixedBytes(self.hash().as_bytes().to_vec()),
+            Token::Bytes(signature.to_vec()),
+        ]));
+        let output = rpc.call(self.chain_id, self.address, &data).map_err(Error::Rpc)?;
+        match output.get(..4) == Some(&IS_VALID_SIGNATURE) {
+            true => Ok(()),
+            false => Err(Error::Signature(format!(
+                "rejected by the contract wallet {}",
+                checksum(&self.address, None)
+            ))),
+        }
+    }
+
+    /// Validates the message and verifies its signature.
+    pub fn verify(
+        &self,
+        signature: &[u8],
+        options: &VerifyOptions,
+        rpc: Option<&dyn Rpc>,
+    ) -> Result<(), Error> {
+        self.validate(options)?;
+        self.verify_signature(signature, rpc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::Account;
+    use anyhow::Result;
+    use mockall::predicate::eq;
+
+    // From: https://eips.ethereum.org/EIPS/eip-4361#example-message
+    const MESSAGE: &str = "service.invalid wants you to sign in with your Ethereum account:
+0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2
+
+I accept the ServiceOrg Terms of Service: https://service.invalid/tos
+
+URI: https://service.invalid/login
+Version: 1
+Chain ID: 1
+Nonce: 32891756
+Issued At: 2021-09-30T16:25:24Z
+Resources:
+- ipfs://bafybeiemxf5abjwjbikoz4mc3a3dla6ual3jsgpdr4cjr3oz3evfyavhwq/
+- https://example.com/my-web2-claim.json";
+
+    /// The first account of the Hardhat and Anvil nodes
+    const KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    fn time(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().into()
+    }
+
+    fn message_for(address: Address) -> Message {
+        Message {
+            scheme: Some("https".to_string()),
+            domain: "localhost:3000".to_string(),
+            address,
+            statement: None,
+            uri: "https://localhost:3000/".to_string(),
+            version: VERSION.to_string(),
+            chain_id: 5,
+            nonce: generate_nonce(),
+            issued_at: time("2023-06-01T00:00:00Z").into(),
+            expiration_time: Some("2023-06-01T03:00:00.000+02:00".parse().unwrap()),
+            not_before: None,
+            request_id: Some("42".to_string()),
+            resources: vec![],
+        }
+    }
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let message: Message = MESSAGE.parse()?;
+        assert_eq!((message.scheme.as_deref(), message.domain.as_str()), (None, "service.invalid"));
+        assert_eq!(message.address, "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".parse()?);
+        assert_eq!(
+            message.statement.as_deref(),
+            Some("I accept the ServiceOrg Terms of Service: https://service.invalid/tos")
+        );
+        assert_eq!((message.chain_id, message.nonce.as_str()), (1, "32891756"));
+        assert_eq!(message.issued_at.time(), time("2021-09-30T16:25:24Z"));
+        assert_eq!(message.expiration_time, None);
+        assert_eq!(message.resources.len(), 2);
+        assert_eq!(message.to_string(), MESSAGE);
+
+        // Without a statement, and with the scheme and the optional fields
+        let message = message_for(message.address);
+        let text = message.to_string();
+        assert!(text.starts_with("https://localhost:3000 wants you"));
+        assert!(text.contains("Cc2\n\n\nURI: "));
+        assert!(text.ends_with("Expiration Time: 2023-06-01T03:00:00.000+02:00\nRequest ID: 42"));
+        assert_eq!(text.parse::<Message>()?, message);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let invalid = |from: &str, to: &str| MESSAGE.replacen(from, to, 1).parse::<Message>();
+        assert!(matches!(invalid(" wants", " needs"), Err(Error::Format(_))));
+        assert!(matches!(
+            invalid("service.invalid ", "service/invalid "),
+            Err(Error::Field { .. })
+        ));
+        assert_eq!(
+            invalid("0xC02aaA39", "0xc02aaa39").unwrap_err(),
+            Error::Field {
+                field: "EIP-55 address",
+                value: "0xc02aaa39b223FE8D0A0e5C4F27eAD9083C756Cc2".to_string()
+            }
+        );
+        assert!(matches!(invalid("Version: 1", "Version: 2"), Err(Error::Field { .. })));
+        assert!(matches!(invalid("Chain ID: 1", "Chain ID: x"), Err(Error::Field { .. })));
+        assert!(matches!(invalid("32891756", "3289175"), Err(Error::Field { .. })));
+        assert!(matches!(invalid("32891756", "3289175-6"), Err(Error::Field { .. })));
+        assert!(matches!(invalid("16:25:24Z", "16:25:24"), Err(Error::Field { .. })));
+        assert!(matches!(invalid("\nURI", "\nUri"), Err(Error::Format(_))));
+        assert!(matches!(invalid("Issued At", "Expiration Time"), Err(Error::Format(_))));
+        assert!(matches!(invalid("- https", "https"), Err(Error::Format(_))));
+        assert!(matches!(format!("{}\n", MESSAGE).parse::<Message>(), Err(Error::Format(_))));
+        assert!(matches!(MESSAGE.replace('\n', "\r\n").parse::<Message>(), Err(_)));
+    }
+
+    #[test]
+    fn test_validate() -> Result<()> {
+        let message = message_for(Address::zero());
+        let options = VerifyOptions {
+            domain: Some("localhost:3000".to_string()),
+            uri: Some("https://localhost:3000/".to_string()),
+            nonce: Some(message.nonce.clone()),
+            chain_id: Some(5),
+            time: Some(time("2023-06-01T00:30:00Z")),
+        };
+        message.validate(&options)?;
+
+        let check = |options: VerifyOptions| message.validate(&options).unwrap_err();
+        let domain = Some("example.com".to_string());
+        assert!(matches!(check(VerifyOptions { domain, ..options.clone() }), Error::Domain { .. }));
+        let nonce = Some(generate_nonce());
+        assert_eq!(check(VerifyOptions { nonce, ..options.clone() }), Error::Nonce);
+        let chain_id = Some(1);
+        assert_eq!(
+            check(VerifyOptions { chain_id, ..options.clone() }),
+            Error::ChainId { expected: 1, actual: 5 }
+        );
+
+        // Expired at 01:00 UTC
+        let time = Some(super::tests::time("2023-06-01T01:00:00Z"));
+        assert!(matches!(check(VerifyOptions { time, ..options.clone() }), Error::Expired(_)));
+        let mut message = message.clone();
+        message.expiration_time = None;
+        message.not_before = Some("2023-06-02T00:00:00Z".parse()?);
+        assert!(matches!(message.validate(&options), Err(Error::NotYetValid(_))));
+        message.not_before = None;
+        message.validate(&VerifyOptions::default())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_verify() -> Result<()> {
+        let account = Account::from_private_key(KEY)?;
+        let message = message_for(account.address);
+        let signature = message.sign(account.signing_key())?;
+        assert!(signature[64] == 27 || signature[64] == 28);
+        assert_eq!(message.recover(&signature)?, account.address);
+        let options =
+            VerifyOptions { time: Some(time("2023-06-01T00:30:00Z")), ..Default::default() };
+        message.verify(&signature, &options, None)?;
+
+        // A message of another address, and a tampered one
+        let other = message_for(Address::zero());
+        assert!(matches!(other.sign(account.signing_key()), Err(Error::Signer { .. })));
+        assert!(matches!(other.verify(&signature, &options, None), Err(Error::Signer { .. })));
+        assert!(matches!(
+            message.verify(&signature[..64], &options, None),
+            Err(Error::Signature(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_eip1271() -> Result<()> {
+        let account = Account::from_private_key(KEY)?;
+        let wallet: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".parse()?;
+        let message = message_for(wallet);
+        let signature =
+            LocalWallet::from(account.signing_key().clone()).sign_hash(message.hash())?.to_vec();
+
+        let mut data = IS_VALID_SIGNATURE.to_vec();
+        data.extend(encode(&[
+            Token::FixedBytes(message.hash().as_bytes().to_vec()),
+            Token::Bytes(signature.clone()),
+        ]));
+        let mut rpc = MockRpc::new();
+        rpc.expect_call()
+            .with(eq(5), eq(wallet), eq(data))
+            .times(1)
+            .returning(|_, _, _| Ok([IS_VALID_SIGNATURE.to_vec(), vec![0; 28]].concat()));
+        message.verify_signature(&signature, Some(&rpc))?;
+
+        // Rejected by the wallet, and a failing node
+        let mut rpc = MockRpc::new();
+        rpc.expect_call().times(1).returning(|_, _, _| Ok(vec![0xff; 32]));
+        assert!(matches!(
+            message.verify_signature(&signature, Some(&rpc)),
+            Err(Error::Signature(_))
+        ));
+        let mut rpc = MockRpc::new();
+        rpc.expect_call().times(1).returning(|_, _, _| Err("connection refused".to_string()));
+        assert_eq!(
+            message.verify_signature(&signature, Some(&rpc)),
+            Err(Error::Rpc("connection refused".to_string()))
+        );
+        assert!(matches!(message.verify_signature(&signature, None), Err(Error::Signer { .. })));
+        Ok(())
+    }
+}
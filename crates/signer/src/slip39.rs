@@ -0,0 +1,687 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// SLIP-39 Shamir's secret sharing of a master secret, in two levels of groups and members.
+///
+/// The master secret is encrypted with the passphrase, split into group shares with the group
+/// threshold, and each group share is split into member shares with the member threshold of
+/// its group. Each member share is encoded as a mnemonic of the SLIP-39 wordlist.
+///
+/// From:
+/// https://github.com/satoshilabs/slips/blob/master/slip-0039.md
+/// https://github.com/trezor/python-shamir-mnemonic
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use rand::{Rng, RngCore};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+
+/// The customization string of the checksum, and salt prefix of the encryption, of
+/// non-extendable backups
+const CUSTOMIZATION_STRING: &[u8] = b"shamir";
+
+/// The customization string of the checksum of extendable backups
+const CUSTOMIZATION_STRING_EXTENDABLE: &[u8] = b"shamir_extendable";
+
+/// The number of bits encoded by a word
+const RADIX_BITS: usize = 10;
+
+/// The number of words of the share parameters, and of the checksum
+const PREFIX_WORDS: usize = 4;
+const CHECKSUM_WORDS: usize = 3;
+
+/// The minimum length of a master secret, in bytes
+const MIN_SECRET_LEN: usize = 16;
+
+/// The maximum number of groups, and of members of a group
+const MAX_SHARE_COUNT: u8 = 16;
+
+/// The x coordinates of the digest and the secret in the shared polynomial
+const DIGEST_INDEX: u8 = 254;
+const SECRET_INDEX: u8 = 255;
+const DIGEST_LEN: usize = 4;
+
+/// The PBKDF2 iterations of the encryption, spread across its Feistel rounds
+const BASE_ITERATION_COUNT: u32 = 10000;
+const ROUND_COUNT: u8 = 4;
+
+lazy_static! {
+    static ref WORDLIST: Vec<&'static str> =
+        include_str!("../wordlists/slip39.txt").split_whitespace().collect();
+
+    /// The exponent and logarithm tables of GF(256) with the Rijndael polynomial, generator 3
+    static ref TABLES: ([u8; 255], [u8; 256]) = {
+        let (mut exp, mut log) = ([0u8; 255], [0u8; 256]);
+        let mut poly: u16 = 1;
+        for (i, e) in exp.iter_mut().enumerate() {
+            *e = poly as u8;
+            log[poly as usize] = i as u8;
+            poly = (poly << 1) ^ poly;
+            if poly & 0x100 != 0 {
+                poly ^= 0x11b;
+            }
+        }
+        (exp, log)
+    };
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("Unknown word `{0}`")]
+    UnknownWord(String),
+    #[error("Invalid mnemonic length {0}")]
+    Length(usize),
+    #[error("Invalid mnemonic checksum")]
+    Checksum,
+    #[error("Invalid mnemonic padding")]
+    Padding,
+    #[error("The master secret must be at least 16 bytes long, with an even length")]
+    SecretLength,
+    #[error("Invalid threshold {threshold} of {count}")]
+    Threshold { threshold: u8, count: u8 },
+    #[error("A threshold of 1 requires a single share, use a threshold of 1 of 1")]
+    SingleThreshold,
+    #[error("The iteration exponent must be below 16")]
+    IterationExponent,
+    #[error("The mnemonics are not shares of the same secret")]
+    Mismatch,
+    #[error("Duplicate share {0}")]
+    Duplicate(u8),
+    #[error("Insufficient shares, {0} more needed")]
+    Insufficient(u8),
+    #[error("Invalid digest, the shares or the passphrase are wrong")]
+    Digest,
+}
+
+/// A share of a master secret
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Share {
+    pub identifier: u16,
+    pub extendable: bool,
+    pub iteration_exponent: u8,
+    pub group_index: u8,
+    pub group_threshold: u8,
+    pub group_count: u8,
+    pub member_index: u8,
+    pub member_threshold: u8,
+    pub value: Vec<u8>,
+}
+
+/// The member threshold and member count of a group
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Group {
+    pub threshold: u8,
+    pub count: u8,
+}
+
+fn customization(extendable: bool) -> &'static [u8] {
+    match extendable {
+        true => CUSTOMIZATION_STRING_EXTENDABLE,
+        false => CUSTOMIZATION_STRING,
+    }
+}
+
+/// The Reed-Solomon checksum of the words, over GF(1024).
+fn rs1024_polymod(values: impl IntoIterator<Item = u16>) -> u32 {
+    const GEN: [u32; 10] = [
+        0xe0e040, 0x1c1c080, 0x3838100, 0x7070200, 0xe0e0009, 0x1c0c2412, 0x38086c24, 0x3090fc48,
+        0x21b1f890, 0x3f3f120,
+    ];
+    let mut chk: u32 = 1;
+    for value in values {
+        let b = chk >> 20;
+        chk = (chk & 0xfffff) << 10 ^ value as u32;
+        for (i, gen) in GEN.iter().enumerate() {
+            if b >> i & 1 == 1 {
+                chk ^= gen;
+            }
+        }
+    }
+    chk
+}
+
+fn rs1024_checksum(extendable: bool, data: &[u16]) -> [u16; CHECKSUM_WORDS] {
+    let values = customization(extendable).iter().map(|b| *b as u16).chain(data.iter().copied());
+    let polymod = rs1024_polymod(values.chain([0; CHECKSUM_WORDS])) ^ 1;
+    [2, 1, 0].map(|i| (polymod >> (RADIX_BITS * i) & 1023) as u16)
+}
+
+fn rs1024_verify(extendable: bool, data: &[u16]) -> bool {
+    rs1024_polymod(customization(extendable).iter().map(|b| *b as u16).chain(data.iter().copied())) ==
+        1
+}
+
+impl Share {
+    /// Decodes a share from its mnemonic, verifying its checksum.
+    pub fn from_mnemonic(mnemonic: &str) -> Result<Self, Error> {
+        let indices = mnemonic
+            .split_whitespace()
+            .map(|word| {
+                WORDLIST
+                    .iter()
+                    .position(|w| *w == word.to_lowercase())
+                    .map(|i| i as u16)
+                    .ok_or_else(|| Error::UnknownWord(word.to_string()))
+            })
+            .collect::<Result<Vec<u16>, Error>>()?;
+
+        let value_words = indices.len().saturating_sub(PREFIX_WORDS + CHECKSUM_WORDS);
+        if value_words * RADIX_BITS < MIN_SECRET_LEN * 8 {
+            return Err(Error::Length(indices.len()));
+        }
+        let padding = value_words * RADIX_BITS % 16;
+        if padding > 8 {
+            return Err(Error::Length(indices.len()));
+        }
+
+        // Identifier (15 bits), extendable (1), iteration exponent (4), group index (4), group
+        // threshold - 1 (4), group count - 1 (4), member index (4), member threshold - 1 (4)
+        let prefix = indices[..PREFIX_WORDS].iter().fold(0u64, |bits, i| bits << 10 | *i as u64);
+        let extendable = prefix >> 24 & 1 == 1;
+        if !rs1024_verify(extendable, &indices) {
+            return Err(Error::Checksum);
+        }
+
+        let bits: Vec<bool> = indices[PREFIX_WORDS..indices.len() - CHECKSUM_WORDS]
+            .iter()
+            .flat_map(|index| (0..RADIX_BITS).rev().map(move |i| index >> i & 1 == 1))
+            .collect();
+        if bits[..padding].iter().any(|bit| *bit) {
+            return Err(Error::Padding);
+        }
+        let value = bits[padding..]
+            .chunks(8)
+            .map(|chunk| chunk.iter().fold(0u8, |byte, bit| byte << 1 | *bit as u8))
+            .collect();
+
+        let share = Share {
+            identifier: (prefix >> 25) as u16,
+            extendable,
+            iteration_exponent: (prefix >> 20 & 15) as u8,
+            group_index: (prefix >> 16 & 15) as u8,
+            group_threshold: (prefix >> 12 & 15) as u8 + 1,
+            group_count: (prefix >> 8 & 15) as u8 + 1,
+            member_index: (prefix >> 4 & 15) as u8,
+            member_threshold: (prefix & 15) as u8 + 1,
+            value,
+        };
+        if share.group_threshold > share.group_count {
+            return Err(Error::Threshold {
+                threshold: share.group_threshold,
+                count: share.group_count,
+            });
+        }
+        Ok(share)
+    }
+
+    /// Encodes the share as a mnemonic.
+    pub fn to_mnemonic(&self) -> String {
+        let prefix = (self.identifier as u64) << 25 |
+            (self.extendable as u64) << 24 |
+            (self.iteration_exponent as u64) << 20 |
+            (self.group_index as u64) << 16 |
+            (self.group_threshold as u64 - 1) << 12 |
+            (self.group_count as u64 - 1) << 8 |
+            (self.member_index as u64) << 4 |
+            (self.member_threshold as u64 - 1);
+        let mut indices: Vec<u16> =
+            (0..PREFIX_WORDS).rev().map(|i| (prefix >> (RADIX_BITS * i) & 1023) as u16).collect();
+
+        // Left-pad the value with zero bits to a multiple of the word size
+        let value_bits = self.value.len() * 8;
+        let padding = (RADIX_BITS - value_bits % RADIX_BITS) % RADIX_BITS;
+        let bits: Vec<bool> = std::iter::repeat(false)
+            .take(padding)
+            .chain(self.value.iter().flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1)))
+            .collect();
+        indices.extend(
+            bits.chunks(RADIX_BITS)
+                .map(|chunk| chunk.iter().fold(0u16, |index, bit| index << 1 | *bit as u16)),
+        );
+        indices.extend(rs1024_checksum(self.extendable, &indices));
+
+        indices.iter().map(|i| WORDLIST[*i as usize]).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Evaluates at `x` the polynomial of GF(256) going through the shares, bytewise.
+fn interpolate(shares: &[(u8, Vec<u8>)], x: u8) -> Vec<u8> {
+    if let Some((_, value)) = shares.iter().find(|(share_x, _)| *share_x == x) {
+        return value.clone();
+    }
+    let (exp, log) = &*TABLES;
+    let log_prod: usize =
+        shares.iter().map(|(share_x, _)| log[(share_x ^ x) as usize] as usize).sum();
+
+    let mut result = vec![0u8; shares[0].1.len()];
+    for (share_x, value) in shares {
+        // The logarithm of the Lagrange basis polynomial of the share, evaluated at x
+        let log_others: usize = shares
+            .iter()
+            .filter(|(other_x, _)| other_x != share_x)
+            .map(|(other_x, _)| log[(share_x ^ other_x) as usize] as usize)
+            .sum();
+        let log_basis =
+            (log_prod + 255 * shares.len() - log[(share_x ^ x) as usize] as usize - log_others) %
+                255;
+        for (r, v) in result.iter_mut().zip(value) {
+            if *v != 0 {
+                *r ^= exp[(log[*v as usize] as usize + log_basis) % 255];
+            }
+        }
+    }
+    result
+}
+
+fn digest(random: &[u8], secret: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(random).expect("HMAC accepts any key length");
+    mac.update(secret);
+    mac.finalize().into_bytes()[..DIGEST_LEN].to_vec()
+}
+
+/// Splits a secret into `count` shares, any `threshold` of which recover it.
+fn split_secret(threshold: u8, count: u8, secret: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, Error> {
+    if threshold < 1 || threshold > count || count > MAX_SHARE_COUNT {
+        return Err(Error::Threshold { threshold, count });
+    }
+    if threshold == 1 {
+        return Ok((0..count).map(|i| (i, secret.to_vec())).collect());
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut random = |len: usize| {
+        let mut bytes = vec![0u8; len];
+        rng.fill_bytes(&mut bytes);
+        bytes
+    };
+    let mut shares: Vec<(u8, Vec<u8>)> =
+        (0..threshold - 2).map(|i| (i, random(secret.len()))).collect();
+    let random_part = random(secret.len() - DIGEST_LEN);
+    let digest_share = [digest(&random_part, secret), random_part].concat();
+
+    let mut base = shares.clone();
+    base.push((DIGEST_INDEX, digest_share));
+    base.push((SECRET_INDEX, secret.to_vec()));
+    shares.extend((threshold - 2..count).map(|i| (i, interpolate(&base, i))));
+    Ok(shares)
+}
+
+/// Recovers a secret from `threshold` of its shares, verifying its digest.
+fn recover_secret(threshold: u8, shares: &[(u8, Vec<u8>)]) -> Result<Vec<u8>, Error> {
+    if threshold == 1 {
+        return Ok(shares[0].1.clone());
+    }
+    let secret = interpolate(shares, SECRET_INDEX);
+    let digest_share = interpolate(shares, DIGEST_INDEX);
+    match digest_share[..DIGEST_LEN] == digest(&digest_share[DIGEST_LEN..], &secret) {
+        true => Ok(secret),
+        false => Err(Error::Digest),
+    }
+}
+
+/// The Feistel cipher of the master secret, decrypting when `rounds` is reversed.
+fn feistel(
+    secret: &[u8],
+    passphrase: &str,
+    iteration_exponent: u8,
+    identifier: u16,
+    extendable: bool,
+    rounds: impl Iterator<Item = u8>,
+) -> Vec<u8> {
+    let salt = match extendable {
+        true => vec![],
+        false => [CUSTOMIZATION_STRING, &identifier.to_be_bytes()].concat(),
+    };
+    let iterations = (BASE_ITERATION_COUNT << iteration_exponent) / ROUND_COUNT as u32;
+
+    let (l, r) = secret.split_at(secret.len() / 2);
+    let (mut l, mut r) = (l.to_vec(), r.to_vec());
+    for i in rounds {
+        let mut f = vec![0u8; r.len()];
+        let password = [&[i], passphrase.as_bytes()].concat();
+        pbkdf2::pbkdf2_hmac::<Sha256>(&password, &[&salt[..], &r].concat(), iterations, &mut f);
+        let next: Vec<u8> = l.iter().zip(&f).map(|(a, b)| a ^ b).collect();
+        (l, r) = (r, next);
+    }
+    [r, l].concat()
+}
+
+/// Splits a master secret into the mnemonics of `groups`, any `group_threshold` groups of which
+/// recover it.
+///
+/// The secret is encrypted with `passphrase`, with 10000 × 2^`iteration_exponent` iterations of
+/// PBKDF2. Returns the mnemonics of every group.
+pub fn generate(
+    group_threshold: u8,
+    groups: &[Group],
+    master_secret: &[u8],
+    passphrase: &str,
+    iteration_exponent: u8,
+    extendable: bool,
+) -> Result<Vec<Vec<String>>, Error> {
+    if master_secret.len() < MIN_SECRET_LEN || master_secret.len() % 2 != 0 {
+        return Err(Error::SecretLength);
+    }
+    if iteration_exponent > 15 {
+        return Err(Error::IterationExponent);
+    }
+    if groups.iter().any(|g| g.threshold == 1 && g.count > 1) {
+        return Err(Error::SingleThreshold);
+    }
+
+    let identifier: u16 = rand::thread_rng().gen_range(0..1 << 15);
+    let encrypted = feistel(
+        master_secret,
+        passphrase,
+        iteration_exponent,
+        identifier,
+        extendable,
+        0..ROUND_COUNT,
+    );
+
+    let group_shares = split_secret(group_threshold, groups.len() as u8, &encrypted)?;
+    groups
+        .iter()
+        .zip(group_shares)
+        .map(|(group, (group_index, group_secret))| {
+            Ok(split_secret(group.threshold, group.count, &group_secret)?
+                .into_iter()
+                .map(|(member_index, value)| {
+                    Share {
+                        identifier,
+                        extendable,
+                        iteration_exponent,
+                        group_index,
+                        group_threshold,
+                        group_count: groups.len() as u8,
+                        member_index,
+                        member_threshold: group.threshold,
+                        value,
+                    }
+                    .to_mnemonic()
+                })
+                .collect())
+        })
+        .collect()
+}
+
+/// Recovers the master secret from the mnemonics of enough groups, decrypting it with
+/// `passphrase`.
+pub fn combine(mnemonics: &[impl AsRef<str>], passphrase: &str) -> Result<Vec<u8>, Error> {
+    let shares = mnemonics
+        .iter()
+        .map(|m| Share::from_mnemonic(m.as_ref()))
+        .collect::<Result<Vec<Share>, Error>>()?;
+    let first = shares.first().ok_or(Error::Insufficient(1))?;
+
+    let mut groups: BTreeMap<u8, Vec<&Share>> = BTreeMap::new();
+    for share in shares.iter() {
+        let same = (
+            share.identifier,
+            share.extendable,
+            share.iteration_exponent,
+            share.group_threshold,
+            share.group_count,
+            share.value.len(),
+        ) == (
+            first.identifier,
+            first.extendable,
+            first.iteration_exponent,
+            first.group_threshold,
+            first.group_count,
+            first.value.len(),
+        );
+        if !same {
+            return Err(Error::Mismatch);
+        }
+        groups.entry(share.group_index).or_default().push(share);
+    }
+
+    // Recover the group shares of the complete groups
+    let mut group_shares = vec![];
+    for (group_index, members) in groups.iter() {
+        let threshold = members[0].member_threshold;
+        if members.iter().any(|m| m.member_threshold != threshold) {
+            return Err(Error::Mismatch);
+        }
+        let mut member_shares: Vec<(u8, Vec<u8>)> = vec![];
+        for member in members {
+            if member_shares.iter().any(|(i, _)| *i == member.member_index) {
+                return Err(Error::Duplicate(member.member_index));
+            }
+            member_shares.push((member.member_index, member.value.clone()));
+        }
+        if member_shares.len() >= threshold as usize {
+            member_shares.truncate(threshold as usize);
+            group_shares.push((*group_index, recover_secret(threshold, &member_shares)?));
+        }
+    }
+
+    if group_shares.len() < first.group_threshold as usize {
+        return Err(Error::Insufficient(first.group_threshold - group_shares.len() as u8));
+    }
+    group_shares.truncate(first.group_threshold as usize);
+    let encrypted = recover_secret(first.group_threshold, &group_shares)?;
+
+    Ok(feistel(
+        &encrypted,
+        passphrase,
+        first.iteration_exponent,
+        first.identifier,
+        first.extendable,
+        (0..ROUND_COUNT).rev(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use ethers_core::utils::hex;
+
+    #[test]
+    fn test_wordlist() {
+        assert_eq!(WORDLIST.len(), 1024);
+        assert!(WORDLIST.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_trezor_vectors() -> Result<()> {
+        // From: https://github.com/trezor/python-shamir-mnemonic/blob/master/vectors.json
+        let vectors: [(&[&str], &str); 3] = [
+            (
+                &["duckling enlarge academic academic agency result length solution fridge kidney \
+                   coal piece deal husband erode duke ajar critical decision keyboard"],
+                "bb54aac4b89dc868ba37d9cc21b2cece",
+            ),
+            (
+                &[
+                    "shadow pistol academic always adequate wildlife fancy gross oasis cylinder \
+                     mustang wrist rescue view short owner flip making coding armed",
+                    "shadow pistol academic acid actress prayer class unknown daughter sweater \
+                     depict flip twice unkind craft early superior advocate guest smoking",
+                ],
+                "b43ceb7e57a0ea8766221624d01b0864",
+            ),
+            (
+                &[
+                    "eraser senior decision roster beard treat identify grumpy salt index fake \
+                     aviation theater cubic bike cause research dragon emphasis counter",
+                    "eraser senior ceramic snake clay various huge numb argue hesitate auction \
+                     category timber browser greatest hanger petition script leaf pickup",
+                    "eraser senior ceramic shaft dynamic become junior wrist silver peasant force \
+                     math alto coal amazing segment yelp velvet image paces",
+                    "eraser senior ceramic round column hawk trust auction smug shame alive \
+                     greatest sheriff living perfect corner chest sled fumes adequate",
+                    "eraser senior decision smug corner ruin rescue cubic angel tackle skin skunk \
+                     program roster trash rumor slush angel flea amazing",
+                ],
+                "7c3397a292a5941682d7a4ae2d898d11",
+            ),
+        ];
+        for (mnemonics, secret) in vectors {
+            assert_eq!(hex::encode(combine(mnemonics, "TREZOR")?), secret);
+            // Shares round trip through their mnemonics
+            for mnemonic in mnemonics {
+                let mnemonic = mnemonic.split_whitespace().collect::<Vec<_>>().join(" ");
+                assert_eq!(Share::from_mnemonic(&mnemonic)?.to_mnemonic(), mnemonic);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_mnemonics() {
+        let mnemonic = "duckling enlarge academic academic agency result length solution fridge \
+                        kidney coal piece deal husband erode duke ajar critical decision kidney";
+        assert_eq!(Share::from_mnemonic(mnemonic), Err(Error::Checksum));
+        assert_eq!(Share::from_mnemonic("duckling enlarge"), Err(Error::Length(2)));
+        assert_eq!(
+            Share::from_mnemonic("duckling satoshis"),
+            Err(Error::UnknownWord("satoshis".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_trezor_invalid_vectors() {
+        // From: https://github.com/trezor/python-shamir-mnemonic/blob/master/vectors.json
+        let vectors: [(&str, &[&str], Error); 10] = [
+            (
+                "Mnemonic with invalid padding",
+                &["duckling enlarge academic academic email result length solution fridge kidney \
+                   coal piece deal husband erode duke ajar music cargo fitness"],
+                Error::Padding,
+            ),
+            (
+                "Basic sharing 2-of-3, with a single share",
+                &["shadow pistol academic always adequate wildlife fancy gross oasis cylinder \
+                   mustang wrist rescue view short owner flip making coding armed"],
+                Error::Insufficient(1),
+            ),
+            (
+                "Mnemonics with different identifiers",
+                &[
+                    "adequate smoking academic acid debut wine petition glen cluster slow rhyme \
+                     slow simple epidemic rumor junk tracks treat olympic tolerate",
+                    "adequate stay academic agency agency formal party ting frequent learn \
+                     upstairs remember smear leaf damage anatomy ladle market hush corner",
+                ],
+                Error::Mismatch,
+            ),
+            (
+                "Mnemonics with different iteration exponents",
+                &[
+                    "peasant leaves academic acid desert exact olympic math alive axle trial \
+                     tackle drug deny decent smear dominant desert bucket remind",
+                    "peasant leader academic agency cultural blessing percent network envelope \
+                     medal junk primary human pumps jacket fragment payroll ticket evoke voice",
+                ],
+                Error::Mismatch,
+            ),
+            (
+                "Mnemonics with mismatching group thresholds",
+                &[
+                    "liberty category beard echo animal fawn temple briefing math username \
+                     various wolf aviation fancy visual holy thunder yelp helpful payment",
+                    "liberty category beard email beyond should fancy romp founder easel pink \
+                     holy hairy romp loyalty material victim owner toxic custody",
+                    "liberty category academic easy being hazard crush diminish oral lizard \
+                     reaction cluster force dilemma deploy force club veteran expect photo",
+                ],
+                Error::Mismatch,
+            ),
+            (
+                "Mnemonics with mismatching group counts",
+                &[
+                    "average senior academic leaf broken teacher expect surface hour capture \
+                     obesity desire negative dynamic dominant pistol mineral mailman iris aide",
+                    "average senior academic agency curious pants blimp spew clothes slice \
+                     script dress wrap firm shaft regular slavery negative theater roster",
+                ],
+                Error::Mismatch,
+            ),
+            (
+                "Mnemonics with greater group threshold than group counts",
+                &[
+                    "music husband acrobat acid artist finance center either graduate swimming \
+                     object bike medical clothes station aspect spider maiden bulb welcome",
+                    "music husband acrobat agency advance hunting bike corner density careful \
+                     material civil evil tactics remind hawk discuss hobo voice rainbow",
+                    "music husband beard academic black tricycle clock mayor estimate level \
+                     photo episode exclude ecology papa source amazing salt verify divorce",
+                ],
+                Error::Threshold { threshold: 2, count: 1 },
+            ),
+            (
+                "Mnemonics with duplicate member indices",
+                &[
+                    "device stay academic always dive coal antenna adult black exceed stadium \
+                     herald advance soldier busy dryer daughter evaluate minister laser",
+                    "device stay academic always dwarf afraid robin gravity crunch adjust soul \
+                     branch walnut coastal dream costume scholar mortgage mountain pumps",
+                ],
+                Error::Duplicate(2),
+            ),
+            (
+                "Mnemonics giving an invalid digest",
+                &[
+                    "guilt walnut academic acid deliver remove equip listen vampire tactics \
+                     nylon rhythm failure husband fatigue alive blind enemy teaspoon rebound",
+                    "guilt walnut academic agency brave hamster hobo declare herd taste alpha \
+                     slim criminal mild arcade formal romp branch pink ambition",
+                ],
+                Error::Digest,
+            ),
+            (
+                "Insufficient number of groups",
+                &["eraser senior beard romp adorn nuclear spill corner cradle style ancient \
+                   family general leader ambition exchange unusual garlic promise voice"],
+                Error::Insufficient(1),
+            ),
+        ];
+        for (description, mnemonics, error) in vectors {
+            assert_eq!(combine(mnemonics, "TREZOR"), Err(error), "{}", description);
+        }
+    }
+
+    #[test]
+    fn test_generate_combine() -> Result<()> {
+        let secret =
+            hex::decode("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")?;
+        let groups = [
+            Group { threshold: 1, count: 1 },
+            Group { threshold: 2, count: 3 },
+            Group { threshold: 3, count: 5 },
+        ];
+        for extendable in [false, true] {
+            let shares = generate(2, &groups, &secret, "TREZOR", 0, extendable)?;
+            assert_eq!(shares.iter().map(|g| g.len()).collect::<Vec<_>>(), vec![1, 3, 5]);
+            assert!(shares.iter().flatten().all(|m| m.split(' ').count() == 33));
+
+            let mnemonics = [&shares[0][..], &shares[2][1..4]].concat();
+            assert_eq!(combine(&mnemonics, "TREZOR")?, secret);
+            let mnemonics = [&shares[1][1..], &shares[2][..3]].concat();
+            assert_eq!(combine(&mnemonics, "TREZOR")?, secret);
+
+            // A single group, or an incomplete one, is not enough
+            assert_eq!(combine(&shares[0], "TREZOR"), Err(Error::Insufficient(1)));
+            let mnemonics = [&shares[0][..], &shares[2][..2]].concat();
+            assert_eq!(combine(&mnemonics, "TREZOR"), Err(Error::Insufficient(1)));
+        }
+
+        assert_eq!(
+            generate(1, &groups[..1], &secret[..15], "", 0, false),
+            Err(Error::SecretLength)
+        );
+        assert_eq!(
+            generate(1, &[Group { threshold: 1, count: 2 }], &secret, "", 0, false),
+            Err(Error::SingleThreshold)
+        );
+        assert_eq!(
+            generate(3, &groups[..2], &secret, "", 0, false),
+            Err(Error::Threshold { threshold: 3, count: 2 })
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,761 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// Uniform Resources, CBOR payloads encoded as bytewords in `ur:` URIs for QR codes.
+///
+/// A payload too long for a single QR code is split into fragments of a fountain code. The
+/// first parts carry the fragments in order, the following ones an endless stream of
+/// pseudorandom mixes of them, so an animated QR code can be scanned from any frame and past
+/// missed ones.
+///
+/// From:
+/// https://github.com/BlockchainCommons/Research/blob/master/papers/bcr-2020-005-ur.md
+/// https://github.com/BlockchainCommons/Research/blob/master/papers/bcr-2020-012-bytewords.md
+/// https://github.com/BlockchainCommons/bc-ur
+use crate::cbor::{self, Value};
+use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+
+/// The shortest fragment of a multipart UR
+const MIN_FRAGMENT_LEN: usize = 10;
+
+/// The longest message and the most fragments of a multipart UR that are decoded, since every
+/// part allocates and shuffles as many indices as its count of fragments
+const MAX_MESSAGE_LEN: usize = 1 << 20;
+const MAX_FRAGMENT_COUNT: usize = 10_000;
+
+lazy_static! {
+    static ref BYTEWORDS: Vec<&'static str> =
+        include_str!("../wordlists/bytewords.txt").split_whitespace().collect();
+
+    /// The bytes of the words, and of their first and last letters
+    static ref BYTES: HashMap<&'static str, u8> =
+        BYTEWORDS.iter().enumerate().map(|(i, word)| (*word, i as u8)).collect();
+    static ref MINIMAL_BYTES: HashMap<String, u8> = BYTEWORDS
+        .iter()
+        .enumerate()
+        .map(|(i, word)| (minimal(word), i as u8))
+        .collect();
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid byteword `{0}`")]
+    Byteword(String),
+    #[error("Invalid checksum")]
+    Checksum,
+    #[error("Invalid UR: {0}")]
+    Invalid(String),
+    #[error("Expected a {expected} UR, not a {actual} one")]
+    Type { expected: String, actual: String },
+    #[error("The part does not belong to the same UR as the previous ones")]
+    Mismatch,
+    #[error(transparent)]
+    Cbor(#[from] cbor::Error),
+}
+
+/// How bytes are written as bytewords
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// The words, separated by spaces
+    Standard,
+    /// The words, separated by dashes
+    Uri,
+    /// The first and last letters of the words, as in URs
+    Minimal,
+}
+
+fn minimal(word: &str) -> String {
+    let bytes = word.as_bytes();
+    [bytes[0] as char, bytes[3] as char].iter().collect()
+}
+
+/// Returns the CRC-32 checksum of data, as in zlib.
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32fast::hash(data)
+}
+
+/// Encodes data as bytewords, followed by its checksum.
+pub fn encode_bytewords(data: &[u8], style: Style) -> String {
+    let bytes = data.iter().copied().chain(crc32(data).to_be_bytes());
+    let words = bytes.map(|b| BYTEWORDS[b as usize]);
+    match style {
+        Style::Standard => words.collect::<Vec<_>>().join(" "),
+        Style::Uri => words.collect::<Vec<_>>().join("-"),
+        Style::Minimal => words.map(minimal).collect(),
+    }
+}
+
+/// Decodes bytewords and checks their checksum.
+pub fn decode_bytewords(text: &str, style: Style) -> Result<Vec<u8>, Error> {
+    let text = text.to_lowercase();
+    let mut bytes = match style {
+        Style::Standard | Style::Uri => {
+            let separator = if style == Style::Standard { ' ' } else { '-' };
+            text.split(separator)
+                .map(|word| BYTES.get(word).copied().ok_or_else(|| Error::Byteword(word.into())))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        Style::Minimal => {
+            if !text.is_ascii() || text.len() % 2 != 0 {
+                return Err(Error::Byteword(text));
+            }
+            (0..text.len())
+                .step_by(2)
+                .map(|i| {
+                    let word = &text[i..i + 2];
+                    MINIMAL_BYTES.get(word).copied().ok_or_else(|| Error::Byteword(word.into()))
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+    if bytes.len() < 5 {
+        return Err(Error::Checksum);
+    }
+    let checksum = bytes.split_off(bytes.len() - 4);
+    match crc32(&bytes).to_be_bytes() == checksum[..] {
+        true => Ok(bytes),
+        false => Err(Error::Checksum),
+    }
+}
+
+/// The xoshiro256** generator of the fountain code, seeded with the SHA-256 digest of a seed.
+pub struct Xoshiro256 {
+    s: [u64; 4],
+}
+
+impl Xoshiro256 {
+    pub fn new(seed: &[u8]) -> Self {
+        let digest = Sha256::digest(seed);
+        let mut s = [0; 4];
+        for (i, chunk) in digest.chunks(8).enumerate() {
+            s[i] = u64::from_be_bytes(chunk.try_into().expect("8 bytes"));
+        }
+        Xoshiro256 { s }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let result = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.s[1] << 17;
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+        result
+    }
+
+    /// Returns a number in [0, 1).
+    pub fn next_double(&mut self) -> f64 {
+        self.next_u64() as f64 / (u64::MAX as f64 + 1.0)
+    }
+
+    /// Returns a number in [low, high].
+    pub fn next_int(&mut self, low: u64, high: u64) -> u64 {
+        (self.next_double() * (high - low + 1) as f64) as u64 + low
+    }
+
+    pub fn next_bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next_int(0, 255) as u8).collect()
+    }
+
+    /// Shuffles items, drawing them one by one.
+    fn shuffled<T>(&mut self, mut items: Vec<T>) -> Vec<T> {
+        let mut shuffled = Vec::with_capacity(items.len());
+        while !items.is_empty() {
+            let index = self.next_int(0, items.len() as u64 - 1) as usize;
+            shuffled.push(items.remove(index));
+        }
+        shuffled
+    }
+}
+
+/// Vose's alias method to sample indices by their weights.
+struct Sampler {
+    probabilities: Vec<f64>,
+    aliases: Vec<usize>,
+}
+
+impl Sampler {
+    fn new(weights: &[f64]) -> Self {
+        let n = weights.len();
+        let sum: f64 = weights.iter().sum();
+        let mut scaled: Vec<f64> = weights.iter().map(|w| w * n as f64 / sum).collect();
+        let (mut small, mut large) = (vec![], vec![]);
+        for (i, p) in scaled.iter().enumerate().rev() {
+            match *p < 1.0 {
+                true => small.push(i),
+                false => large.push(i),
+            }
+        }
+        let (mut probabilities, mut aliases) = (vec![0.0; n], vec![0; n]);
+        while !small.is_empty() && !large.is_empty() {
+            let (a, g) = (small.pop().expect("a small"), large.pop().expect("a large"));
+            probabilities[a] = scaled[a];
+            aliases[a] = g;
+            scaled[g] += scaled[a] - 1.0;
+            match scaled[g] < 1.0 {
+                true => small.push(g),
+                false => large.push(g),
+            }
+        }
+        for i in large.into_iter().chain(small) {
+            probabilities[i] = 1.0;
+        }
+        Sampler { probabilities, aliases }
+    }
+
+    fn next(&self, rng: &mut Xoshiro256) -> usize {
+        let (r1, r2) = (rng.next_double(), rng.next_double());
+        let i = (self.probabilities.len() as f64 * r1) as usize;
+        match r2 < self.probabilities[i] {
+            true => i,
+            false => self.aliases[i],
+        }
+    }
+}
+
+/// Returns the indices of the fragments mixed into a part: the fragment of the same index for
+/// the first parts, then a number of fragments drawn from the ideal soliton distribution.
+pub fn choose_fragments(sequence: u32, count: usize, checksum: u32) -> BTreeSet<usize> {
+    if sequence as usize <= count {
+        return BTreeSet::from([sequence as usize - 1]);
+    }
+    let seed: Vec<u8> = sequence.to_be_bytes().into_iter().chain(checksum.to_be_bytes()).collect();
+    let mut rng = Xoshiro256::new(&seed);
+    let weights: Vec<f64> = (1..=count).map(|i| 1.0 / i as f64).collect();
+    let degree = Sampler::new(&weights).next(&mut rng) + 1;
+    rng.shuffled((0..count).collect()).into_iter().take(degree).collect()
+}
+
+fn xor(data: &mut [u8], other: &[u8]) {
+    data.iter_mut().zip(other).for_each(|(a, b)| *a ^= b);
+}
+
+/// A part of a multipart UR
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Part {
+    /// The number of the part, from 1
+    pub sequence: u32,
+    /// The number of fragments of the message
+    pub count: usize,
+    pub message_len: usize,
+    pub checksum: u32,
+    /// The mix of the fragments
+    pub data: Vec<u8>,
+}
+
+impl Part {
+    pub fn to_cbor(&self) -> Vec<u8> {
+        Value::Array(vec![
+            Value::Unsigned(self.sequence as u64),
+            Value::Unsigned(self.count as u64),
+            Value::Unsigned(self.message_len as u64),
+            Value::Unsigned(self.checksum as u64),
+            Value::Bytes(self.data.clone()),
+        ])
+        .encode()
+    }
+
+    pub fn from_cbor(data: &[u8]) -> Result<Self, Error> {
+        let value = Value::decode(data)?;
+        let [sequence, count, message_len, checksum, data] = value.as_array()? else {
+            return Err(Error::Invalid("expected a part of 5 items".into()));
+        };
+        let invalid = |field: &str| Error::Invalid(format!("invalid {} of the part", field));
+        let part = Part {
+            sequence: u32::try_from(sequence.as_u64()?).map_err(|_| invalid("sequence"))?,
+            count: usize::try_from(count.as_u64()?).map_err(|_| invalid("count"))?,
+            message_len: usize::try_from(message_len.as_u64()?)
+                .map_err(|_| invalid("message length"))?,
+            checksum: u32::try_from(checksum.as_u64()?).map_err(|_| invalid("checksum"))?,
+            data: data.as_bytes()?.to_vec(),
+        };
+        if part.sequence == 0 || part.data.is_empty() {
+            return Err(invalid("header"));
+        }
+        if part.message_len == 0 || part.message_len > MAX_MESSAGE_LEN {
+            return Err(invalid("message length"));
+        }
+        // The message is split in as few fragments of the length of the data as it takes
+        let count = (part.message_len + part.data.len() - 1) / part.data.len();
+        if part.count != count || part.count > MAX_FRAGMENT_COUNT {
+            return Err(invalid("count"));
+        }
+        Ok(part)
+    }
+
+    /// Returns the indices of the fragments of the part.
+    pub fn indices(&self) -> BTreeSet<usize> {
+        choose_fragments(self.sequence, self.count, self.checksum)
+    }
+}
+
+/// Returns whether a UR type is lowercase letters, digits and dashes.
+fn is_valid_type(ur_type: &str) -> bool {
+    !ur_type.is_empty() &&
+        ur_type.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+/// Encodes a payload as a single part UR.
+pub fn encode(ur_type: &str, payload: &[u8]) -> String {
+    format!("ur:{}/{}", ur_type, encode_bytewords(payload, Style::Minimal))
+}
+
+/// Decodes a single part UR into its type and payload.
+pub fn decode(ur: &str) -> Result<(String, Vec<u8>), Error> {
+    let mut decoder = Decoder::default();
+    decoder.receive(ur)?;
+    match (decoder.ur_type, decoder.message) {
+        (Some(ur_type), Some(message)) => Ok((ur_type, message)),
+        _ => Err(Error::Invalid("expected a single part UR".into())),
+    }
+}
+
+/// The encoder of a payload into the parts of a UR, single or multipart.
+pub struct Encoder {
+    ur_type: String,
+    message_len: usize,
+    checksum: u32,
+    fragments: Vec<Vec<u8>>,
+    single: Option<String>,
+    sequence: u32,
+}
+
+impl Encoder {
+    /// Splits a payload into fragments of at most `max_fragment_len` bytes, or a single part
+    /// when it fits one.
+    pub fn new(ur_type: &str, payload: &[u8], max_fragment_len: usize) -> Result<Self, Error> {
+        if !is_valid_type(ur_type) {
+            return Err(Error::Invalid(format!("invalid type `{}`", ur_type)));
+        }
+        if payload.is_empty() || max_fragment_len < MIN_FRAGMENT_LEN {
+            return Err(Error::Invalid("empty payload or too short fragments".into()));
+        }
+
+        // The fewest fragments of equal length that fit the maximum, padded with zeros
+        let count = (payload.len() + max_fragment_len - 1) / max_fragment_len;
+        if payload.len() > MAX_MESSAGE_LEN || count > MAX_FRAGMENT_COUNT {
+            return Err(Error::Invalid("payload too long to decode".into()));
+        }
+        let fragment_len = (payload.len() + count - 1) / count;
+        let mut padded = payload.to_vec();
+        padded.resize(fragment_len * count, 0);
+        Ok(Encoder {
+            ur_type: ur_type.to_string(),
+            message_len: payload.len(),
+            checksum: crc32(payload),
+            fragments: padded.chunks(fragment_len).map(<[u8]>::to_vec).collect(),
+            single: (count == 1).then(|| encode(ur_type, payload)),
+            sequence: 0,
+        })
+    }
+
+    /// The number of parts that carry the fragments in order.
+    pub fn fragment_count(&self) -> usize {
+        self.fragments.len()
+    }
+
+    pub fn is_single_part(&self) -> bool {
+        self.single.is_some()
+    }
+
+    /// Returns the next part, the same one for a single part UR.
+    pub fn next_part(&mut self) -> String {
+        if let Some(single) = &self.single {
+            return single.clone();
+        }
+        self.sequence += 1;
+        let count = self.fragments.len();
+        let mut data = vec![0; self.fragments[0].len()];
+        for index in choose_fragments(self.sequence, count, self.checksum) {
+            xor(&mut data, &self.fragments[index]);
+        }
+        let part = Part {
+            sequence: self.sequence,
+            count,
+            message_len: self.message_len,
+            checksum: self.checksum,
+            data,
+        };
+        format!(
+            "ur:{}/{}-{}/{}",
+            self.ur_type,
+            self.sequence,
+            count,
+            encode_bytewords(&part.to_cbor(), Style::Minimal)
+        )
+    }
+}
+
+/// The decoder of the parts of a UR, received in any order and with duplicates.
+#[derive(Default)]
+pub struct Decoder {
+    ur_type: Option<String>,
+    /// The count, message length and checksum of the parts
+    header: Option<(usize, usize, u32)>,
+    fragment_len: usize,
+    /// The fragments recovered, and the mixes of several of them
+    fragments: HashMap<usize, Vec<u8>>,
+    mixed: Vec<(BTreeSet<usize>, Vec<u8>)>,
+    message: Option<Vec<u8>>,
+}
+
+impl Decoder {
+    /// Receives a part, single or multipart, and returns whether the message is complete.
+    pub fn receive(&mut self, part: &str) -> Result<bool, Error> {
+        let ur = part.trim().to_lowercase();
+        let path = ur.strip_prefix("ur:").ok_or_else(|| Error::Invalid("expected ur:".into()))?;
+        let components: Vec<&str> = path.split('/').collect();
+        let ur_type = components[0];
+        if !is_valid_type(ur_type) {
+            return Err(Error::Invalid(format!("invalid type `{}`", ur_type)));
+        }
+        match &self.ur_type {
+            Some(expected) if expected != ur_type => {
+                return Err(Error::Type { expected: expected.clone(), actual: ur_type.into() })
+            }
+            _ => self.ur_type = Some(ur_type.to_string()),
+        }
+        if self.message.is_some() {
+            return Ok(true);
+        }
+
+        match components[1..] {
+            [payload] => {
+                self.message = Some(decode_bytewords(payload, Style::Minimal)?);
+            }
+            [sequence, payload] => {
+                let part = Part::from_cbor(&decode_bytewords(payload, Style::Minimal)?)?;
+                if sequence != format!("{}-{}", part.sequence, part.count) {
+                    return Err(Error::Invalid(format!("sequence `{}` of the part", sequence)));
+                }
+                let header = (part.count, part.message_len, part.checksum);
+                match self.header {
+                    Some(expected)
+                        if expected != header || self.fragment_len != part.data.len() =>
+                    {
+                        return Err(Error::Mismatch)
+                    }
+                    _ => {
+                        self.header = Some(header);
+                        self.fragment_len = part.data.len();
+                    }
+                }
+                self.reduce(part.indices(), part.data);
+                self.join()?;
+            }
+            _ => return Err(Error::Invalid("expected ur:type/[sequence/]payload".into())),
+        }
+        Ok(self.message.is_some())
+    }
+
+    /// Reduces a mix by the fragments and the mixes known, until it is a fragment, a new mix or
+    /// nothing new, and reduces the mixes known by it in turn.
+    fn reduce(&mut self, indices: BTreeSet<usize>, data: Vec<u8>) {
+        let mut queue = vec![(indices, data)];
+        while let Some((mut indices, mut data)) = queue.pop() {
+            for index in indices.clone() {
+                if let Some(fragment) = self.fragments.get(&index) {
+                    xor(&mut data, fragment);
+                    indices.remove(&index);
+                }
+            }
+            for (mixed, mixed_data) in &self.mixed {
+                if mixed.is_subset(&indices) {
+                    indices = &indices - mixed;
+                    xor(&mut data, mixed_data);
+                }
+            }
+
+            if indices.is_empty() || self.mixed.iter().any(|(mixed, _)| *mixed == indices) {
+                continue;
+            }
+            let (reduced, kept) = self
+                .mixed
+                .drain(..)
+                .partition(|(mixed, _)| mixed.is_superset(&indices) && *mixed != indices);
+            self.mixed = kept;
+            queue.extend::<Vec<_>>(reduced);
+            match indices.len() {
+                1 => self.fragments.insert(*indices.iter().next().expect("an index"), data),
+                _ => {
+                    self.mixed.push((indices, data));
+                    None
+                }
+            };
+        }
+    }
+
+    /// Joins the fragments once they are all recovered, and checks the message.
+    fn join(&mut self) -> Result<(), Error> {
+        let Some((count, message_len, checksum)) = self.header else { return Ok(()) };
+        if self.fragments.len() < count {
+            return Ok(());
+        }
+        let mut message: Vec<u8> = (0..count).flat_map(|i| self.fragments[&i].clone()).collect();
+        message.truncate(message_len);
+        if crc32(&message) != checksum {
+            return Err(Error::Checksum);
+        }
+        self.message = Some(message);
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.message.is_some()
+    }
+
+    /// The share of the fragments recovered, from 0 to 1.
+    pub fn progress(&self) -> f64 {
+        match (&self.message, self.header) {
+            (Some(_), _) => 1.0,
+            (None, Some((count, _, _))) => self.fragments.len() as f64 / count as f64,
+            (None, None) => 0.0,
+        }
+    }
+
+    pub fn ur_type(&self) -> Option<&str> {
+        self.ur_type.as_deref()
+    }
+
+    /// The payload, once complete.
+    pub fn message(&self) -> Option<&[u8]> {
+        self.message.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    /// The pseudorandom messages of the reference tests
+    fn make_message(seed: &str, len: usize) -> Vec<u8> {
+        Xoshiro256::new(seed.as_bytes()).next_bytes(len)
+    }
+
+    /// The CBOR byte string of a message, the payload of a `bytes` UR
+    fn make_payload(seed: &str, len: usize) -> Vec<u8> {
+        Value::Bytes(make_message(seed, len)).encode()
+    }
+
+    #[test]
+    fn test_bytewords() -> Result<()> {
+        assert_eq!(BYTEWORDS.len(), 256);
+        assert!(BYTEWORDS.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(MINIMAL_BYTES.len(), 256);
+
+        // From: https://github.com/BlockchainCommons/Research/blob/master/papers/bcr-2020-012-bytewords.md
+        let data = [0, 1, 2, 128, 255];
+        let vectors = [
+            (Style::Standard, "able acid also lava zoom jade need echo taxi"),
+            (Style::Uri, "able-acid-also-lava-zoom-jade-need-echo-taxi"),
+            (Style::Minimal, "aeadaolazmjendeoti"),
+        ];
+        for (style, words) in vectors {
+            assert_eq!(encode_bytewords(&data, style), words);
+            assert_eq!(decode_bytewords(words, style)?, data);
+            assert_eq!(decode_bytewords(&words.to_uppercase(), style)?, data);
+        }
+        assert_eq!(decode_bytewords("aeadaolazmjendeoto", Style::Minimal), Err(Error::Checksum));
+        assert!(decode_bytewords("aeadaolazmjendeot", Style::Minimal).is_err());
+        assert_eq!(
+            decode_bytewords("able acid also lava zoom jade need echo tax", Style::Standard),
+            Err(Error::Byteword("tax".into()))
+        );
+        assert_eq!(decode_bytewords("aeadjendeoti", Style::Minimal), Err(Error::Checksum));
+        Ok(())
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"Hello, world!"), 0xebe6c6e6);
+        assert_eq!(crc32(b"Wolf"), 0x598c84dc);
+    }
+
+    #[test]
+    fn test_xoshiro256() {
+        let mut rng = Xoshiro256::new(b"Wolf");
+        let numbers: Vec<u64> = (0..100).map(|_| rng.next_u64() % 100).collect();
+        assert_eq!(
+            numbers,
+            [
+                42, 81, 85, 8, 82, 84, 76, 73, 70, 88, 2, 74, 40, 48, 77, 54, 88, 7, 5, 88, 37, 25,
+                82, 13, 69, 59, 30, 39, 11, 82, 19, 99, 45, 87, 30, 15, 32, 22, 89, 44, 92, 77, 29,
+                78, 4, 92, 44, 68, 92, 69, 1, 42, 89, 50, 37, 84, 63, 34, 32, 3, 17, 62, 40, 98,
+                82, 89, 24, 43, 85, 39, 15, 3, 99, 29, 20, 42, 27, 10, 85, 66, 50, 35, 69, 70, 70,
+                74, 30, 13, 72, 54, 11, 5, 70, 55, 91, 52, 10, 43, 43, 52
+            ]
+        );
+    }
+
+    #[test]
+    fn test_choose_fragments() {
+        // From: https://github.com/BlockchainCommons/bc-ur/blob/master/test/test.cpp
+        let checksum = crc32(&make_message("Wolf", 1024));
+        let chosen: Vec<Vec<usize>> = (1..=30)
+            .map(|sequence| choose_fragments(sequence, 11, checksum).into_iter().collect())
+            .collect();
+        let expected: [&[usize]; 30] = [
+            &[0],
+            &[1],
+            &[2],
+            &[3],
+            &[4],
+            &[5],
+            &[6],
+            &[7],
+            &[8],
+            &[9],
+            &[10],
+            &[9],
+            &[2, 5, 6, 8, 9, 10],
+            &[8],
+            &[1, 5],
+            &[1],
+            &[0, 2, 4, 5, 8, 10],
+            &[5],
+            &[2],
+            &[2],
+            &[0, 1, 3, 4, 5, 7, 9, 10],
+            &[0, 1, 2, 3, 5, 6, 8, 9, 10],
+            &[0, 2, 4, 5, 7, 8, 9, 10],
+            &[3, 5],
+            &[4],
+            &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+            &[0, 1, 3, 4, 5, 6, 7, 9, 10],
+            &[6],
+            &[5, 6],
+            &[7],
+        ];
+        assert_eq!(chosen, expected);
+    }
+
+    #[test]
+    fn test_single_part() -> Result<()> {
+        let payload = make_payload("Wolf", 50);
+        let ur = encode("bytes", &payload);
+        assert_eq!(
+            ur,
+            "ur:bytes/hdeymejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtgwdpfnsboxgwlbaawzuefywkdplrsrjynbvygabwjldapfcsdwkbrkch"
+        );
+        assert_eq!(decode(&ur)?, ("bytes".to_string(), payload.clone()));
+        assert_eq!(decode(&ur.to_uppercase())?.1, payload);
+
+        let mut encoder = Encoder::new("bytes", &payload, 100)?;
+        assert!(encoder.is_single_part());
+        assert_eq!(encoder.next_part(), ur);
+
+        assert!(decode("ur:bytes").is_err());
+        assert!(decode("ur:Bytes!/aeadaolazmjendeoti").is_err());
+        assert!(decode("bytes/aeadaolazmjendeoti").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_multipart() -> Result<()> {
+        let payload = make_payload("Wolf", 256);
+        let mut encoder = Encoder::new("bytes", &payload, 30)?;
+        assert_eq!(encoder.fragment_count(), 9);
+        let parts: Vec<String> = (0..20).map(|_| encoder.next_part()).collect();
+        assert_eq!(
+            parts[..2],
+            [
+                "ur:bytes/1-9/lpadascfadaxcywenbpljkhdcahkadaemejtswhhylkepmykhhtsytsnoyoyaxaedsuttydmmhhpktpmsrjtdkgslpgh",
+                "ur:bytes/2-9/lpaoascfadaxcywenbpljkhdcagwdpfnsboxgwlbaawzuefywkdplrsrjynbvygabwjldapfcsgmghhkhstlrdcxaefz",
+            ]
+        );
+        assert!(parts[9].starts_with("ur:bytes/10-9/"));
+
+        // In order, the fragments are enough
+        let mut decoder = Decoder::default();
+        for (i, part) in parts.iter().enumerate() {
+            assert_eq!(decoder.receive(part)?, i >= 8);
+        }
+        assert_eq!(decoder.message(), Some(&payload[..]));
+        assert_eq!(decoder.ur_type(), Some("bytes"));
+
+        // Without the first fragments, the mixes make up for them
+        let mut decoder = Decoder::default();
+        let mut received = 0;
+        for part in parts.iter().skip(3).cycle().take(200) {
+            received += 1;
+            if decoder.receive(part)? {
+                break;
+            }
+        }
+        assert!(decoder.is_complete() && received < 200);
+        assert_eq!(decoder.message(), Some(&payload[..]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fountain_recovery() -> Result<()> {
+        // Every third part is lost, as if the camera missed the frames
+        for len in [100, 1000, 4000] {
+            let payload = make_payload("Fountain", len);
+            let mut encoder = Encoder::new("bytes", &payload, 50)?;
+            let mut decoder = Decoder::default();
+            let mut sent = 0;
+            while !decoder.is_complete() {
+                let part = encoder.next_part();
+                sent += 1;
+                if sent % 3 != 0 {
+                    decoder.receive(&part)?;
+                }
+                assert!(sent < 10 * encoder.fragment_count());
+            }
+            assert_eq!(decoder.progress(), 1.0);
+            assert_eq!(decoder.message(), Some(&payload[..]));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_parts() -> Result<()> {
+        let mut encoder = Encoder::new("bytes", &make_payload("Wolf", 256), 30)?;
+        let first = encoder.next_part();
+        let mut decoder = Decoder::default();
+        decoder.receive(&first)?;
+        assert!(decoder.progress() > 0.0);
+
+        // Another type, another message, and a sequence that is not the one of the part
+        let other_type = first.replace("ur:bytes/", "ur:eth-signature/");
+        assert!(matches!(decoder.receive(&other_type), Err(Error::Type { .. })));
+        let mut other = Encoder::new("bytes", &make_payload("Fox", 256), 30)?;
+        assert_eq!(decoder.receive(&other.next_part()), Err(Error::Mismatch));
+        let wrong_sequence = first.replace("/1-9/", "/2-9/");
+        assert!(matches!(decoder.receive(&wrong_sequence), Err(Error::Invalid(_))));
+
+        assert!(Encoder::new("Bytes", b"abc", 30).is_err());
+        assert!(Encoder::new("bytes", b"", 30).is_err());
+        assert!(Encoder::new("bytes", b"abc", 5).is_err());
+        assert!(Encoder::new("bytes", &vec![0; MAX_MESSAGE_LEN + 1], 1000).is_err());
+        let part = Part { sequence: 1, count: 2, message_len: 10, checksum: 0, data: vec![0; 4] };
+        assert!(Part::from_cbor(&part.to_cbor()).is_err());
+        let part = Part { count: 3, ..part };
+        assert!(Part::from_cbor(&part.to_cbor()).is_ok());
+
+        // Counts of fragments that overflow, or that would take too long to shuffle
+        for (count, message_len, data) in [
+            (usize::MAX, 10, vec![0; 4]),
+            (usize::MAX / 2, usize::MAX, vec![0; 4]),
+            (MAX_FRAGMENT_COUNT + 1, MAX_FRAGMENT_COUNT + 1, vec![0; 1]),
+            (MAX_MESSAGE_LEN / 4 + 1, MAX_MESSAGE_LEN + 1, vec![0; 4]),
+        ] {
+            let part = Part { sequence: 1, count, message_len, checksum: 0, data };
+            assert!(matches!(Part::from_cbor(&part.to_cbor()), Err(Error::Invalid(_))));
+            let ur = format!(
+                "ur:bytes/1-{}/{}",
+                count,
+                encode_bytewords(&part.to_cbor(), Style::Minimal)
+            );
+            assert!(Decoder::default().receive(&ur).is_err());
+        }
+        Ok(())
+    }
+}
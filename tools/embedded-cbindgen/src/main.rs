@@ -2,30 +2,154 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::env;
-
-fn main() {
-    // Get the current directory
-    let current_dir = env::current_dir().unwrap();
-
-    // Get the command line arguments
-    let args: Vec<String> = env::args().collect();
-
-    // Find the index of the --output argument
-    let output_index = args.iter().position(|arg| arg == "--output");
-
-    // Get the value of the --output argument if it is present
-    let output = output_index.and_then(|i| args.get(i + 1));
-
-    // Generate the C header file using the specified output directory, or the current directory if
-    // no output directory is specified
-    if let Some(output) = output {
-        let _ = cbindgen::Builder::new()
-            .with_crate(current_dir)
-            .generate()
-            .unwrap()
-            .write_to_file(output);
-    } else {
-        let _ = cbindgen::generate(current_dir);
+//! Generates (or verifies) the C/C++ header of a crate with cbindgen.
+//!
+//! Usage:
+//! cargo cbindgen --crate crates/signer --output libwallet_signer_ffi.h
+//! cargo cbindgen --crate crates/signer --output libwallet_signer_ffi.h --verify
+use cbindgen::{Builder, Config, Language};
+use clap::{Parser, ValueEnum};
+use eyre::{bail, eyre, Result};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// The language of the generated header
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Lang {
+    /// Generate a C header
+    C,
+    /// Generate a C++ header
+    #[value(name = "c++", alias = "cpp", alias = "cxx")]
+    Cxx,
+}
+
+impl From<Lang> for Language {
+    fn from(lang: Lang) -> Self {
+        match lang {
+            Lang::C => Language::C,
+            Lang::Cxx => Language::Cxx,
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "embedded-cbindgen", about = "Generate C/C++ headers with cbindgen")]
+struct Args {
+    /// The path to the crate, defaults to the current directory
+    #[arg(long = "crate", value_name = "PATH")]
+    crate_dir: Option<PathBuf>,
+
+    /// The path to the cbindgen.toml, defaults to `<crate>/cbindgen.toml` if present
+    #[arg(short, long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Override the language set in the config
+    #[arg(short, long, value_enum)]
+    lang: Option<Lang>,
+
+    /// Override the include guard set in the config
+    #[arg(long, value_name = "NAME")]
+    include_guard: Option<String>,
+
+    /// The path of the header to write, prints to stdout if omitted
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Fail if the header at `--output` differs from the generated one, instead of writing it
+    #[arg(long, requires = "output")]
+    verify: bool,
+}
+
+impl Args {
+    /// Builds the cbindgen config from the config file and the overrides.
+    fn config(&self, crate_dir: &Path) -> Result<Config> {
+        let mut config = match &self.config {
+            Some(path) => Config::from_file(path).map_err(|e| eyre!(e))?,
+            None => Config::from_root_or_default(crate_dir),
+        };
+
+        if let Some(lang) = self.lang {
+            config.language = lang.into();
+        }
+        if let Some(guard) = &self.include_guard {
+            config.include_guard = Some(guard.clone());
+        }
+
+        Ok(config)
+    }
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    // Resolve the crate directory, defaulting to the current directory
+    let crate_dir = match &args.crate_dir {
+        Some(dir) => dir.clone(),
+        None => env::current_dir()?,
+    };
+
+    // Generate the bindings in memory
+    let bindings = Builder::new()
+        .with_config(args.config(&crate_dir)?)
+        .with_crate(&crate_dir)
+        .generate()
+        .map_err(|e| eyre!("Failed to generate bindings for {:?}: {}", crate_dir, e))?;
+    let mut generated = Vec::new();
+    bindings.write(&mut generated);
+
+    let Some(output) = &args.output else {
+        print!("{}", String::from_utf8_lossy(&generated));
+        return Ok(());
+    };
+
+    // Compare the checked-in header with the generated one
+    if args.verify {
+        let existing =
+            fs::read(output).map_err(|e| eyre!("Failed to read header at {:?}: {}", output, e))?;
+        if existing != generated {
+            bail!("Header at {:?} is out of date, regenerate it with `cargo cbindgen`", output);
+        }
+        println!("Header at {:?} is up to date", output);
+        return Ok(());
+    }
+
+    fs::write(output, generated)
+        .map_err(|e| eyre!("Failed to write header at {:?}: {}", output, e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_parse() {
+        let args = Args::parse_from([
+            "embedded-cbindgen",
+            "--crate",
+            "crates/signer",
+            "--lang",
+            "c++",
+            "--include-guard",
+            "WALLET_SIGNER_H",
+            "--output",
+            "signer.h",
+            "--verify",
+        ]);
+        assert_eq!(args.crate_dir, Some(PathBuf::from("crates/signer")));
+        assert_eq!(args.lang, Some(Lang::Cxx));
+        assert!(args.verify);
+
+        let config = args.config(Path::new("crates/signer")).unwrap();
+        assert_eq!(config.language, Language::Cxx);
+        assert_eq!(config.include_guard.as_deref(), Some("WALLET_SIGNER_H"));
+    }
+
+    #[test]
+    fn test_verify_requires_output() {
+        assert!(Args::try_parse_from(["embedded-cbindgen", "--verify"]).is_err());
     }
 }
@@ -2,6 +2,17 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-fn main() {
-    uniffi::uniffi_bindgen_main()
+mod workspace;
+
+use clap::Parser;
+use std::env;
+
+fn main() -> anyhow::Result<()> {
+    // The `workspace` command is ours, everything else is forwarded to uniffi
+    if env::args().nth(1).as_deref() == Some("workspace") {
+        return workspace::run(workspace::Args::parse_from(env::args().skip(1)));
+    }
+
+    uniffi::uniffi_bindgen_main();
+    Ok(())
 }
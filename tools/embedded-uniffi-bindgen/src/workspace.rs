@@ -0,0 +1,353 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Generates the Swift and Kotlin bindings of every UniFFI crate in the workspace, which the
+//! Android build runs through `make bindgen-kotlin` before compiling.
+//!
+//! Usage:
+//! cargo build --package wallet-rs
+//! cargo uniffi-bindgen workspace
+
+use anyhow::{bail, Context, Result};
+use cargo_metadata::{
+    camino::{Utf8Path, Utf8PathBuf},
+    MetadataCommand, Package,
+};
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+use std::{collections::HashMap, env::consts, fs};
+
+/// The languages generated for the mobile apps
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Language {
+    Swift,
+    Kotlin,
+}
+
+impl Language {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Language::Swift => "swift",
+            Language::Kotlin => "kotlin",
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "workspace", about = "Generate bindings for every UniFFI crate in the workspace")]
+pub struct Args {
+    /// Languages to generate, defaults to both Swift and Kotlin
+    #[arg(short, long, value_enum)]
+    language: Vec<Language>,
+
+    /// Use the cdylibs built with `--release`
+    #[arg(long)]
+    release: bool,
+
+    /// Directory with the built cdylibs, defaults to `target/{debug,release}`
+    #[arg(long, value_name = "DIR")]
+    lib_dir: Option<Utf8PathBuf>,
+
+    /// Directory the Swift sources are copied to, relative to the workspace root. The header and
+    /// the modulemap stay next to the UDL file, where the iOS frameworks are assembled from
+    #[arg(long, value_name = "DIR", default_value = "ios/WalletCoreSource/Sources/Generated")]
+    swift_out_dir: Utf8PathBuf,
+
+    /// Output directory of the Kotlin bindings, relative to the workspace root
+    #[arg(long, value_name = "DIR", default_value = "android/src/main/java")]
+    kotlin_out_dir: Utf8PathBuf,
+
+    /// Package every Kotlin binding must live under (the Android namespace)
+    #[arg(long, value_name = "PACKAGE", default_value = "android.WalletCore")]
+    kotlin_package_prefix: String,
+
+    /// Only check the `uniffi.toml` configs, without generating anything
+    #[arg(long)]
+    check: bool,
+
+    /// Do not try to format the generated bindings
+    #[arg(long)]
+    no_format: bool,
+}
+
+/// The subset of `uniffi.toml` checked before generating
+#[derive(Debug, Default, Deserialize)]
+struct UniffiConfig {
+    #[serde(default)]
+    bindings: BindingsConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct BindingsConfig {
+    kotlin: Option<KotlinConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KotlinConfig {
+    package_name: Option<String>,
+    cdylib_name: Option<String>,
+}
+
+/// A workspace crate exposing a UniFFI interface
+#[derive(Debug)]
+struct UniffiCrate {
+    name: String,
+    lib_name: String,
+    udl_files: Vec<Utf8PathBuf>,
+    config: UniffiConfig,
+}
+
+impl UniffiCrate {
+    /// Returns the crate if it depends on uniffi and builds a cdylib with a UDL file.
+    fn from_package(package: &Package) -> Result<Option<Self>> {
+        if !package.dependencies.iter().any(|d| d.name == "uniffi") {
+            return Ok(None);
+        }
+        let Some(lib) = package
+            .targets
+            .iter()
+            .find(|t| t.crate_types.iter().any(|c| c == "cdylib"))
+        else {
+            return Ok(None);
+        };
+
+        // Collect the UDL files in `src/`
+        let crate_root = package.manifest_path.parent().context("Manifest has no parent")?;
+        let mut udl_files: Vec<Utf8PathBuf> = fs::read_dir(crate_root.join("src"))?
+            .filter_map(|entry| Utf8PathBuf::from_path_buf(entry.ok()?.path()).ok())
+            .filter(|path| path.extension() == Some("udl"))
+            .collect();
+        if udl_files.is_empty() {
+            return Ok(None);
+        }
+        udl_files.sort();
+
+        // Parse the `uniffi.toml` next to the manifest if it exists
+        let config_path = crate_root.join("uniffi.toml");
+        let config = if config_path.is_file() {
+            toml::from_str(&fs::read_to_string(&config_path)?)
+                .with_context(|| format!("Failed to parse {}", config_path))?
+        } else {
+            UniffiConfig::default()
+        };
+
+        Ok(Some(UniffiCrate {
+            name: package.name.clone(),
+            lib_name: lib.name.replace('-', "_"),
+            udl_files,
+            config,
+        }))
+    }
+
+    /// The path of the built cdylib in `lib_dir`.
+    fn lib_file(&self, lib_dir: &Utf8Path) -> Utf8PathBuf {
+        lib_dir.join(format!("{}{}{}", consts::DLL_PREFIX, self.lib_name, consts::DLL_SUFFIX))
+    }
+}
+
+/// Checks that the Kotlin config of every crate matches the cdylib and the Android namespace, and
+/// that no two crates generate into the same package.
+fn check_configs(crates: &[UniffiCrate], kotlin_package_prefix: &str) -> Result<()> {
+    let mut packages: HashMap<&str, &str> = HashMap::new();
+
+    for c in crates {
+        let Some(kotlin) = &c.config.bindings.kotlin else {
+            bail!("{}: missing [bindings.kotlin] in uniffi.toml", c.name);
+        };
+        let Some(package_name) = kotlin.package_name.as_deref() else {
+            bail!("{}: missing bindings.kotlin.package_name in uniffi.toml", c.name);
+        };
+
+        if package_name != kotlin_package_prefix &&
+            !package_name.starts_with(&format!("{}.", kotlin_package_prefix))
+        {
+            bail!(
+                "{}: kotlin package `{}` is not under `{}`",
+                c.name,
+                package_name,
+                kotlin_package_prefix
+            );
+        }
+        if let Some(cdylib_name) = &kotlin.cdylib_name {
+            if cdylib_name != &c.lib_name {
+                bail!(
+                    "{}: cdylib_name `{}` does not match the library `{}`",
+                    c.name,
+                    cdylib_name,
+                    c.lib_name
+                );
+            }
+        }
+        if let Some(other) = packages.insert(package_name, &c.name) {
+            bail!("{} and {} both use the kotlin package `{}`", other, c.name, package_name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Declares the module of a modulemap as a framework module, as the iOS frameworks need.
+fn framework_modulemap(modulemap: &str) -> String {
+    modulemap
+        .split('\n')
+        .map(|line| match line.strip_prefix("module ") {
+            Some(rest) => format!("framework module {}", rest),
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns the files of a directory with an extension.
+fn files_with_extension(dir: &Utf8Path, extension: &str) -> Result<Vec<Utf8PathBuf>> {
+    Ok(fs::read_dir(dir)?
+        .filter_map(|entry| Utf8PathBuf::from_path_buf(entry.ok()?.path()).ok())
+        .filter(|path| path.extension() == Some(extension))
+        .collect())
+}
+
+/// Generates the Swift bindings of a UDL file next to it, as `uniffi-bindgen generate` does,
+/// makes its modulemap a framework one and copies the Swift sources to `out_dir`.
+fn generate_swift(
+    udl_file: &Utf8Path,
+    lib_file: &Utf8Path,
+    out_dir: &Utf8Path,
+    format: bool,
+) -> Result<()> {
+    let udl_dir = udl_file.parent().context("UDL file has no parent")?;
+    uniffi::generate_bindings(udl_file, None, vec!["swift"], None, Some(lib_file), format)
+        .with_context(|| format!("Failed to generate bindings for {}", udl_file))?;
+
+    for modulemap in files_with_extension(udl_dir, "modulemap")? {
+        fs::write(&modulemap, framework_modulemap(&fs::read_to_string(&modulemap)?))?;
+    }
+    for swift in files_with_extension(udl_dir, "swift")? {
+        fs::copy(&swift, out_dir.join(swift.file_name().context("Swift file has no name")?))?;
+    }
+    Ok(())
+}
+
+/// Runs the `workspace` command.
+pub fn run(args: Args) -> Result<()> {
+    let metadata = MetadataCommand::new().no_deps().exec()?;
+    let root = &metadata.workspace_root;
+
+    let crates = metadata
+        .workspace_packages()
+        .into_iter()
+        .filter_map(|p| UniffiCrate::from_package(p).transpose())
+        .collect::<Result<Vec<_>>>()?;
+    if crates.is_empty() {
+        bail!("No UniFFI crates found in the workspace");
+    }
+
+    check_configs(&crates, &args.kotlin_package_prefix)?;
+    if args.check {
+        println!("Checked {} UniFFI crate(s)", crates.len());
+        return Ok(());
+    }
+
+    let languages = if args.language.is_empty() {
+        vec![Language::Swift, Language::Kotlin]
+    } else {
+        args.language
+    };
+    let lib_dir = args.lib_dir.clone().unwrap_or_else(|| {
+        metadata.target_directory.join(if args.release { "release" } else { "debug" })
+    });
+
+    for c in crates.iter() {
+        let lib_file = c.lib_file(&lib_dir);
+        if !lib_file.is_file() {
+            bail!("{} not found, build it first with `cargo build --package {}`", lib_file, c.name);
+        }
+
+        for language in languages.iter() {
+            let out_dir = root.join(match language {
+                Language::Swift => &args.swift_out_dir,
+                Language::Kotlin => &args.kotlin_out_dir,
+            });
+            fs::create_dir_all(&out_dir)?;
+
+            for udl_file in c.udl_files.iter() {
+                println!(
+                    "Generating {} bindings for {} into {}",
+                    language.as_str(),
+                    c.name,
+                    out_dir
+                );
+                match language {
+                    Language::Swift => {
+                        generate_swift(udl_file, &lib_file, &out_dir, !args.no_format)?
+                    }
+                    Language::Kotlin => uniffi::generate_bindings(
+                        udl_file,
+                        None,
+                        vec![language.as_str()],
+                        Some(&out_dir),
+                        Some(&lib_file),
+                        !args.no_format,
+                    )
+                    .with_context(|| format!("Failed to generate bindings for {}", udl_file))?,
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniffi_crate(name: &str, config: &str) -> UniffiCrate {
+        UniffiCrate {
+            name: name.to_string(),
+            lib_name: name.replace('-', "_"),
+            udl_files: vec![],
+            config: toml::from_str(config).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_check_configs() {
+        let core = uniffi_crate(
+            "wallet_core",
+            "[bindings.kotlin]\npackage_name = \"android.WalletCore.uniffi\"\ncdylib_name = \"wallet_core\"",
+        );
+        assert!(check_configs(&[core], "android.WalletCore").is_ok());
+
+        let outside =
+            uniffi_crate("wallet_core", "[bindings.kotlin]\npackage_name = \"uniffi.core\"");
+        assert!(check_configs(&[outside], "android.WalletCore").is_err());
+
+        let wrong_lib = uniffi_crate(
+            "wallet_core",
+            "[bindings.kotlin]\npackage_name = \"android.WalletCore\"\ncdylib_name = \"core\"",
+        );
+        assert!(check_configs(&[wrong_lib], "android.WalletCore").is_err());
+
+        let missing = uniffi_crate("wallet_core", "");
+        assert!(check_configs(&[missing], "android.WalletCore").is_err());
+    }
+
+    #[test]
+    fn test_framework_modulemap() {
+        let modulemap = "// Generated\nmodule WalletCoreFFI {\n    header \"WalletCoreFFI.h\"\n}";
+        let framework = framework_modulemap(modulemap);
+        assert_eq!(
+            framework,
+            "// Generated\nframework module WalletCoreFFI {\n    header \"WalletCoreFFI.h\"\n}"
+        );
+        assert_eq!(framework_modulemap(&framework), framework);
+    }
+
+    #[test]
+    fn test_check_configs_duplicate_package() {
+        let config = "[bindings.kotlin]\npackage_name = \"android.WalletCore.uniffi\"";
+        let crates = [uniffi_crate("wallet_a", config), uniffi_crate("wallet_b", config)];
+        assert!(check_configs(&crates, "android.WalletCore").is_err());
+    }
+}
@@ -2,35 +2,206 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use glob::glob;
-use std::fs;
-
-fn main() {
-    let dirs = [
-        "bin/**/src/**/*.rs",
-        "bin/**/tests/**/*.rs",
-        "crates/**/src/**/*.rs",
-        "crates/**/tests/**/*.rs",
-        "tools/**/src/**/*.rs",
-        "tools/**/tests/**/*.rs",
-    ];
-    let mut failed = false;
-    for dir in dirs.iter() {
-        glob(dir).expect("Failed to read glob pattern").for_each(|entry| {
-            if let Ok(path) = entry {
-                let contents = fs::read_to_string(&path).expect("Failed to read file");
-                if !contents.starts_with(
-                    "// This Source Code Form is subject to the terms of the Mozilla Public\n\
-                     // License, v. 2.0. If a copy of the MPL was not distributed with this\n\
-                     // file, You can obtain one at https://mozilla.org/MPL/2.0/.\n",
-                ) {
-                    println!("License header not found in file: {:?}", path);
-                    failed = true;
-                }
-            }
-        });
-    }
-    if failed {
-        panic!("License header check failed");
+//! Checks (and optionally inserts) the MPL-2.0 header in every Rust source file.
+//!
+//! Usage:
+//! cargo mpl-license-checker
+//! cargo mpl-license-checker --fix
+//! cargo mpl-license-checker --root "crates/**/*.rs" --allow "crates/**/generated/**"
+
+use clap::Parser;
+use glob::{glob, Pattern};
+use std::{collections::BTreeSet, fs, path::PathBuf, process::ExitCode};
+
+/// The header every file is expected to start with
+const MPL_HEADER: &str = "// This Source Code Form is subject to the terms of the Mozilla Public\n\
+                          // License, v. 2.0. If a copy of the MPL was not distributed with this\n\
+                          // file, You can obtain one at https://mozilla.org/MPL/2.0/.\n";
+
+/// The SPDX tag and license accepted as an equivalent of the header
+const SPDX_TAG: &str = "SPDX-License-Identifier:";
+const SPDX_LICENSE: &str = "MPL-2.0";
+
+/// Glob patterns of the files checked by default
+const DEFAULT_ROOTS: [&str; 10] = [
+    "apps/tauri/src-tauri/build.rs",
+    "apps/tauri/src-tauri/src/**/*.rs",
+    "bin/**/src/**/*.rs",
+    "bin/**/tests/**/*.rs",
+    "crates/**/src/**/*.rs",
+    "crates/**/tests/**/*.rs",
+    "crates/*/build.rs",
+    "tools/**/src/**/*.rs",
+    "tools/**/tests/**/*.rs",
+    "tools/*/build.rs",
+];
+
+/// Glob patterns of the vendored or generated files skipped by default
+const DEFAULT_ALLOWLIST: [&str; 2] = ["**/target/**", "**/node_modules/**"];
+
+#[derive(Debug, Parser)]
+#[command(name = "mpl-license-checker", about = "Check the MPL-2.0 header of the source files")]
+struct Args {
+    /// Insert the header in the files missing it
+    #[arg(long)]
+    fix: bool,
+
+    /// Glob patterns of the files to check, replaces the defaults
+    #[arg(long, value_name = "GLOB")]
+    root: Vec<String>,
+
+    /// Glob patterns of the files to skip, in addition to the defaults
+    #[arg(long, value_name = "GLOB")]
+    allow: Vec<String>,
+}
+
+/// The outcome of the check
+#[derive(Debug, Default)]
+struct Summary {
+    checked: usize,
+    allowed: usize,
+    fixed: Vec<PathBuf>,
+    missing: Vec<PathBuf>,
+}
+
+/// Returns whether a comment line declares exactly the MPL SPDX identifier, and not one of its
+/// variants such as `MPL-2.0-no-copyleft-exception`.
+fn is_spdx_identifier(line: &str) -> bool {
+    let comment = line.trim().trim_start_matches(['/', '!']).trim_start();
+    comment.strip_prefix(SPDX_TAG).map_or(false, |license| license.trim() == SPDX_LICENSE)
+}
+
+/// Returns whether the contents start with the MPL header, or declare the MPL SPDX identifier in
+/// their leading comments, whatever their line endings.
+fn has_header(contents: &str) -> bool {
+    if contents.replace("\r\n", "\n").starts_with(MPL_HEADER) {
+        return true;
+    }
+
+    contents.lines().take_while(|line| line.trim_start().starts_with("//")).any(is_spdx_identifier)
+}
+
+/// Prepends the MPL header to the contents, with their line endings.
+fn insert_header(contents: &str) -> String {
+    let header = format!("{}\n", MPL_HEADER);
+    match contents.contains("\r\n") {
+        true => format!("{}{}", header.replace('\n', "\r\n"), contents),
+        false => format!("{}{}", header, contents),
+    }
+}
+
+/// Collects the files matching the roots, in a stable order.
+fn collect_files(roots: &[String]) -> Result<BTreeSet<PathBuf>, glob::PatternError> {
+    let mut files = BTreeSet::new();
+    for root in roots.iter() {
+        files.extend(glob(root)?.filter_map(Result::ok).filter(|path| path.is_file()));
+    }
+    Ok(files)
+}
+
+/// Checks every file, fixing them if requested.
+fn check(args: &Args) -> Result<Summary, Box<dyn std::error::Error>> {
+    let roots = if args.root.is_empty() {
+        DEFAULT_ROOTS.iter().map(|s| s.to_string()).collect()
+    } else {
+        args.root.clone()
+    };
+    let allowlist = DEFAULT_ALLOWLIST
+        .iter()
+        .map(|s| s.to_string())
+        .chain(args.allow.iter().cloned())
+        .map(|s| Pattern::new(&s))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut summary = Summary::default();
+    for path in collect_files(&roots)? {
+        if allowlist.iter().any(|p| p.matches_path(&path)) {
+            summary.allowed += 1;
+            continue;
+        }
+
+        summary.checked += 1;
+        let contents = fs::read_to_string(&path)?;
+        if has_header(&contents) {
+            continue;
+        }
+
+        if args.fix {
+            fs::write(&path, insert_header(&contents))?;
+            summary.fixed.push(path);
+        } else {
+            summary.missing.push(path);
+        }
+    }
+
+    Ok(summary)
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let summary = match check(&args) {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for path in summary.fixed.iter() {
+        println!("Inserted license header in file: {:?}", path);
+    }
+    for path in summary.missing.iter() {
+        println!("License header not found in file: {:?}", path);
+    }
+    println!(
+        "Checked {} files: {} missing, {} fixed, {} allowed",
+        summary.checked,
+        summary.missing.len(),
+        summary.fixed.len(),
+        summary.allowed
+    );
+
+    if summary.missing.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        println!("Run `cargo mpl-license-checker --fix` to insert the missing headers");
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_header() {
+        assert!(has_header(&format!("{}\nfn main() {{}}\n", MPL_HEADER)));
+        assert!(has_header("// SPDX-License-Identifier: MPL-2.0\n\nfn main() {}\n"));
+        assert!(has_header("// Copyright wallet-rs\n// SPDX-License-Identifier: MPL-2.0\n"));
+
+        assert!(!has_header("fn main() {}\n"));
+        assert!(!has_header("// SPDX-License-Identifier: MIT\n"));
+        assert!(!has_header("fn main() {}\n// SPDX-License-Identifier: MPL-2.0\n"));
+
+        // The variants of the license are not the license
+        assert!(!has_header("// SPDX-License-Identifier: MPL-2.0-no-copyleft-exception\n"));
+        assert!(!has_header("// SPDX-License-Identifier: MPL-2.0 OR MIT\n"));
+        assert!(!has_header("// Not an SPDX-License-Identifier: MPL-2.0\n"));
+
+        // Whatever the line endings
+        assert!(has_header(&format!("{}\nfn main() {{}}\n", MPL_HEADER.replace('\n', "\r\n"))));
+        assert!(has_header("// SPDX-License-Identifier: MPL-2.0\r\n\r\nfn main() {}\r\n"));
+    }
+
+    #[test]
+    fn test_insert_header() {
+        let contents = insert_header("fn main() {}\n");
+        assert!(has_header(&contents));
+        assert!(contents.ends_with("\n\nfn main() {}\n"));
+
+        let contents = insert_header("fn main() {}\r\n");
+        assert!(has_header(&contents));
+        assert!(!contents.replace("\r\n", "").contains('\n'));
     }
 }